@@ -0,0 +1,95 @@
+//! Structured reports of errors, shared by the components exposing
+//! `js-component-bindgen` and `wasm-tools` to JS, which each map them to the `error` record of their
+//! WIT interface.
+
+use anyhow::Error;
+
+/// Kind of failure, from the causes of an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Wit,
+    Wasm,
+    Io,
+    Other,
+}
+
+/// Location within a source file, with 1-based line and column numbers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Underlying causes of the error, outermost first
+    pub context: Vec<String>,
+    pub span: Option<SourceSpan>,
+}
+
+impl ErrorReport {
+    pub fn new(e: &Error) -> Self {
+        let span = e.chain().find_map(|cause| source_span(&cause.to_string()));
+        let kind = if e.chain().any(|cause| cause.is::<std::io::Error>()) {
+            ErrorKind::Io
+        } else if e
+            .chain()
+            .any(|cause| cause.is::<wasmparser::BinaryReaderError>())
+        {
+            ErrorKind::Wasm
+        } else if span.is_some() {
+            ErrorKind::Wit
+        } else {
+            ErrorKind::Other
+        };
+        ErrorReport {
+            kind,
+            message: e.to_string(),
+            context: e.chain().skip(1).map(|cause| cause.to_string()).collect(),
+            span,
+        }
+    }
+
+    /// Report an error from parsing or resolving WIT, which is a WIT error
+    /// unless caused by a more specific failure
+    pub fn wit(e: &Error) -> Self {
+        let mut report = ErrorReport::new(e);
+        if report.kind == ErrorKind::Other {
+            report.kind = ErrorKind::Wit;
+        }
+        report
+    }
+
+    /// Report an error from parsing WebAssembly text or binaries, which is a
+    /// Wasm error unless caused by an I/O failure.
+    ///
+    /// Errors from `wat` are only recognizable by their caller, as this crate
+    /// does not depend on it.
+    pub fn wasm(e: &Error) -> Self {
+        let mut report = ErrorReport::new(e);
+        if report.kind != ErrorKind::Io {
+            report.kind = ErrorKind::Wasm;
+        }
+        report
+    }
+}
+
+/// Location of the source highlighted by an error from `wit-parser` or `wat`.
+///
+/// Neither crate exposes the span of its errors, which is only available as
+/// the ` --> file:line:column` line of the highlighted source they render.
+fn source_span(message: &str) -> Option<SourceSpan> {
+    message.lines().find_map(|line| {
+        let location = line.trim_start().strip_prefix("--> ")?;
+        let (location, column) = location.rsplit_once(':')?;
+        let (file, line) = location.rsplit_once(':')?;
+        Some(SourceSpan {
+            file: file.to_string(),
+            line: line.parse().ok()?,
+            column: column.parse().ok()?,
+        })
+    })
+}
@@ -0,0 +1,7 @@
+//! Helpers shared by `js-component-bindgen` and the components exposing it
+//! and `wasm-tools` to JS, kept free of the dependencies of the bindgen
+//! itself.
+
+pub mod error_report;
+pub mod wit_display;
+pub mod wit_files;
@@ -0,0 +1,198 @@
+//! Rendering of WIT types and functions in WIT syntax, for diagnostics and
+//! for comparing types across worlds.
+
+use wit_parser::{
+    Function, FunctionKind, Handle, Resolve, Results, Type, TypeDefKind, TypeId, TypeOwner,
+};
+
+/// Renders the types of a resolve in WIT syntax
+#[derive(Clone, Copy)]
+pub struct TypeNames<'a> {
+    resolve: &'a Resolve,
+    /// Whether named types are rendered as their definitions, rather than by
+    /// name
+    expand: bool,
+    /// Whether expanded resources are qualified by the version of their
+    /// package
+    versioned: bool,
+}
+
+impl<'a> TypeNames<'a> {
+    /// Render named types by their name
+    pub fn new(resolve: &'a Resolve) -> Self {
+        TypeNames {
+            resolve,
+            expand: false,
+            versioned: true,
+        }
+    }
+
+    /// Render named types as their definitions, with aliases resolved and
+    /// resources qualified by their interface, so that types can be compared
+    /// structurally across resolves
+    pub fn expanded(resolve: &'a Resolve) -> Self {
+        TypeNames {
+            resolve,
+            expand: true,
+            versioned: true,
+        }
+    }
+
+    /// Render named types as their definitions like [`TypeNames::expanded`],
+    /// but without the package versions of resources, so that types can be
+    /// compared across versions of a package
+    pub fn unversioned(resolve: &'a Resolve) -> Self {
+        TypeNames {
+            resolve,
+            expand: true,
+            versioned: false,
+        }
+    }
+
+    /// WIT syntax of a type
+    pub fn ty(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::U16 => "u16".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::U64 => "u64".to_string(),
+            Type::S8 => "s8".to_string(),
+            Type::S16 => "s16".to_string(),
+            Type::S32 => "s32".to_string(),
+            Type::S64 => "s64".to_string(),
+            Type::F32 => "f32".to_string(),
+            Type::F64 => "f64".to_string(),
+            Type::Char => "char".to_string(),
+            Type::String => "string".to_string(),
+            Type::Id(id) => match &self.resolve.types[*id].name {
+                Some(name) if !self.expand => name.clone(),
+                _ => self.type_def(*id),
+            },
+        }
+    }
+
+    /// Definition of a type, such as `record { a: u32 }` or `list<u8>`
+    pub fn type_def(&self, id: TypeId) -> String {
+        let optional = |ty: &Option<Type>| match ty {
+            Some(ty) => self.ty(ty),
+            None => "_".to_string(),
+        };
+        let list = |items: Vec<String>| items.join(", ");
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Type(ty) => self.ty(ty),
+            TypeDefKind::Record(record) => {
+                let fields = record
+                    .fields
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name, self.ty(&field.ty)))
+                    .collect();
+                format!("record {{ {} }}", list(fields))
+            }
+            TypeDefKind::Resource => self.resource_name(id),
+            TypeDefKind::Handle(Handle::Own(id)) => self.ty(&Type::Id(*id)),
+            TypeDefKind::Handle(Handle::Borrow(id)) => {
+                format!("borrow<{}>", self.ty(&Type::Id(*id)))
+            }
+            TypeDefKind::Flags(flags) => {
+                let flags = flags.flags.iter().map(|flag| flag.name.clone()).collect();
+                format!("flags {{ {} }}", list(flags))
+            }
+            TypeDefKind::Tuple(tuple) => {
+                let types = tuple.types.iter().map(|ty| self.ty(ty)).collect();
+                format!("tuple<{}>", list(types))
+            }
+            TypeDefKind::Variant(variant) => {
+                let cases = variant
+                    .cases
+                    .iter()
+                    .map(|case| match &case.ty {
+                        Some(ty) => format!("{}({})", case.name, self.ty(ty)),
+                        None => case.name.clone(),
+                    })
+                    .collect();
+                format!("variant {{ {} }}", list(cases))
+            }
+            TypeDefKind::Enum(enum_) => {
+                let cases = enum_.cases.iter().map(|case| case.name.clone()).collect();
+                format!("enum {{ {} }}", list(cases))
+            }
+            TypeDefKind::Option(ty) => format!("option<{}>", self.ty(ty)),
+            TypeDefKind::Result(result) => match (&result.ok, &result.err) {
+                (None, None) => "result".to_string(),
+                (Some(ok), None) => format!("result<{}>", self.ty(ok)),
+                (ok, Some(err)) => format!("result<{}, {}>", optional(ok), self.ty(err)),
+            },
+            TypeDefKind::List(ty) => format!("list<{}>", self.ty(ty)),
+            TypeDefKind::Future(None) => "future".to_string(),
+            TypeDefKind::Future(Some(ty)) => format!("future<{}>", self.ty(ty)),
+            TypeDefKind::Stream(stream) => {
+                format!(
+                    "stream<{}, {}>",
+                    optional(&stream.element),
+                    optional(&stream.end)
+                )
+            }
+            TypeDefKind::Unknown => "unknown".to_string(),
+        }
+    }
+
+    /// Results of a function, as a single type or a parenthesized list of
+    /// named results
+    pub fn results(&self, results: &Results) -> String {
+        match results {
+            Results::Anon(ty) => self.ty(ty),
+            Results::Named(results) => {
+                let results = results
+                    .iter()
+                    .map(|(name, ty)| format!("{name}: {}", self.ty(ty)))
+                    .collect::<Vec<_>>();
+                format!("({})", results.join(", "))
+            }
+        }
+    }
+
+    /// Type of a function, such as `func(a: u32) -> string`
+    pub fn func(&self, func: &Function) -> String {
+        let params = func
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{name}: {}", self.ty(ty)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &func.results {
+            Results::Named(results) if results.is_empty() => format!("func({params})"),
+            results => format!("func({params}) -> {}", self.results(results)),
+        }
+    }
+
+    /// Resources are named by their interface when expanded, as they have no
+    /// structure to compare
+    fn resource_name(&self, id: TypeId) -> String {
+        let ty = &self.resolve.types[id];
+        let name = ty.name.as_deref().unwrap_or("<unnamed>");
+        match ty.owner {
+            TypeOwner::Interface(interface) if self.expand => match self.resolve.id_of(interface) {
+                Some(interface) if !self.versioned => {
+                    let interface = interface.split('@').next().unwrap();
+                    format!("{interface}#{name}")
+                }
+                Some(interface) => format!("{interface}#{name}"),
+                None => name.to_string(),
+            },
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// Display name of a function, qualified by its resource if any, such as
+/// `blob.read` or `blob.constructor`
+pub fn func_name(resolve: &Resolve, func: &Function) -> String {
+    match func.kind {
+        FunctionKind::Freestanding => func.name.clone(),
+        FunctionKind::Method(id) | FunctionKind::Static(id) | FunctionKind::Constructor(id) => {
+            let resource = resolve.types[id].name.as_deref().unwrap_or("<unnamed>");
+            format!("{resource}.{}", func.item_name())
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Resolving WIT packages from in-memory files, for environments without a
+//! filesystem.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use wit_component::DecodedWasm;
+use wit_parser::{PackageId, PackageName, Resolve, SourceMap, UnresolvedPackageGroup};
+
+/// Resolve an in-memory set of `(path, contents)` files laid out like a WIT
+/// package directory into `resolve`, returning the main package.
+///
+/// The `*.wit` files at the root form the main package, while each entry of a
+/// top-level `deps/` directory is a dependency: either a directory of `*.wit`
+/// files, a single `*.wit` file or a binary-encoded WIT package. Dependencies
+/// are pushed in the order of their package references.
+pub fn push_files(resolve: &mut Resolve, files: Vec<(String, Vec<u8>)>) -> Result<PackageId> {
+    let mut root = SourceMap::new();
+    let mut deps: BTreeMap<String, SourceMap> = BTreeMap::new();
+
+    for (path, contents) in files {
+        let path = path.replace('\\', "/");
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .collect::<Vec<_>>();
+        let source_map = match segments.as_slice() {
+            [file] if file.ends_with(".wit") => &mut root,
+            ["deps", file] if file.ends_with(".wasm") => {
+                match wit_component::decode(&contents)
+                    .with_context(|| format!("failed to decode WIT package [{path}]"))?
+                {
+                    DecodedWasm::WitPackage(decoded, _) => {
+                        resolve.merge(decoded)?;
+                    }
+                    DecodedWasm::Component(..) => {
+                        bail!(
+                            "found an actual component instead of an encoded WIT package [{path}]"
+                        )
+                    }
+                }
+                continue;
+            }
+            ["deps", file] if file.ends_with(".wit") => deps.entry(file.to_string()).or_default(),
+            ["deps", dir, file] if file.ends_with(".wit") => {
+                deps.entry(dir.to_string()).or_default()
+            }
+            // Other files are ignored, as when resolving from the filesystem
+            _ => continue,
+        };
+        let contents = String::from_utf8(contents)
+            .map_err(|_| anyhow!("input file is not valid utf-8 [{path}]"))?;
+        source_map.push(Path::new(&path), contents);
+    }
+
+    let groups = deps
+        .into_values()
+        .map(SourceMap::parse)
+        .collect::<Result<Vec<_>>>()?;
+
+    // Sort the dependencies topologically, by the packages they reference
+    let mut group_by_name = HashMap::new();
+    for (i, group) in groups.iter().enumerate() {
+        for pkg in group.nested.iter().chain([&group.main]) {
+            group_by_name.insert(pkg.name.clone(), i);
+        }
+    }
+    fn visit(
+        i: usize,
+        groups: &[UnresolvedPackageGroup],
+        group_by_name: &HashMap<PackageName, usize>,
+        visiting: &mut HashSet<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<()> {
+        if order.contains(&i) {
+            return Ok(());
+        }
+        let group = &groups[i];
+        if !visiting.insert(i) {
+            bail!("package [{}] depends on itself", group.main.name);
+        }
+        for pkg in group.nested.iter().chain([&group.main]) {
+            for dep in pkg.foreign_deps.keys() {
+                match group_by_name.get(dep) {
+                    Some(&dep) if dep != i => visit(dep, groups, group_by_name, visiting, order)?,
+                    _ => {}
+                }
+            }
+        }
+        visiting.remove(&i);
+        order.push(i);
+        Ok(())
+    }
+    let mut order = Vec::new();
+    for i in 0..groups.len() {
+        visit(i, &groups, &group_by_name, &mut HashSet::new(), &mut order)?;
+    }
+
+    let mut groups = groups.into_iter().map(Some).collect::<Vec<_>>();
+    for i in order {
+        resolve.push_group(groups[i].take().unwrap())?;
+    }
+
+    resolve.push_group(root.parse()?)
+}
@@ -0,0 +1,61 @@
+use anyhow::Context;
+use jco_shared::error_report::{ErrorKind, ErrorReport, SourceSpan};
+use wit_parser::Resolve;
+
+#[test]
+fn wit_error_span() {
+    let wit = "
+package test:errors;
+
+interface types {
+    get: func() -> missing;
+}
+";
+    let err = Resolve::default()
+        .push_str("errors.wit", wit)
+        .context("failed to resolve WIT")
+        .unwrap_err();
+    let report = ErrorReport::wit(&err);
+    assert_eq!(report.kind, ErrorKind::Wit);
+    assert_eq!(report.message, "failed to resolve WIT");
+    assert_eq!(
+        report.span,
+        Some(SourceSpan {
+            file: "errors.wit".to_string(),
+            line: 5,
+            column: 20,
+        })
+    );
+}
+
+#[test]
+fn wasm_error() {
+    let err = wat::parse_str("(module (func (result i32)))")
+        .context("failed to parse WebAssembly text")
+        .and_then(|binary| {
+            wasmparser::Validator::new().validate_all(&binary)?;
+            Ok(())
+        })
+        .unwrap_err();
+    let report = ErrorReport::new(&err);
+    assert_eq!(report.kind, ErrorKind::Wasm);
+    assert_eq!(report.span, None);
+}
+
+#[test]
+fn wat_error() {
+    let err = wat::parse_str("(module (func (result i32) i32.bogus))")
+        .context("failed to parse WebAssembly text")
+        .unwrap_err();
+    let report = ErrorReport::wasm(&err);
+    assert_eq!(report.kind, ErrorKind::Wasm);
+    assert_eq!(report.message, "failed to parse WebAssembly text");
+    assert_eq!(
+        report.span,
+        Some(SourceSpan {
+            file: "<anon>".to_string(),
+            line: 1,
+            column: 28,
+        })
+    );
+}
@@ -1,11 +1,17 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use jco_shared::{
+    error_report::{self, ErrorReport},
+    wit_files::push_files,
+};
 use js_component_bindgen::{
-    generate_types, generate_typescript_stubs,
+    generate_js_mocks, generate_lower_bindings, generate_types, generate_typescript_import_stubs,
+    generate_typescript_stubs, semver_check,
     source::wit_parser::{PackageId, Resolve},
     transpile,
 };
+use wit_component::DecodedWasm;
 
 /// Calls [`write!`] with the passed arguments and unwraps the result.
 ///
@@ -46,6 +52,25 @@ impl From<InstantiationMode> for js_component_bindgen::InstantiationMode {
     }
 }
 
+impl From<js_component_bindgen::ApiChange> for ApiChange {
+    fn from(change: js_component_bindgen::ApiChange) -> Self {
+        ApiChange {
+            kind: match change.kind {
+                js_component_bindgen::ChangeKind::Breaking => ChangeKind::Breaking,
+                js_component_bindgen::ChangeKind::Additive => ChangeKind::Additive,
+            },
+            item: match change.item {
+                js_component_bindgen::ChangedItem::Interface => ChangedItem::Interface,
+                js_component_bindgen::ChangedItem::Function => ChangedItem::Function,
+                js_component_bindgen::ChangedItem::Type => ChangedItem::Type,
+                js_component_bindgen::ChangedItem::Resource => ChangedItem::Resource,
+            },
+            path: change.path,
+            description: change.description,
+        }
+    }
+}
+
 impl From<BindingsMode> for js_component_bindgen::BindingsMode {
     fn from(value: BindingsMode) -> Self {
         match value {
@@ -57,18 +82,45 @@ impl From<BindingsMode> for js_component_bindgen::BindingsMode {
     }
 }
 
+impl From<ExportBindingsMode> for js_component_bindgen::ExportBindingsMode {
+    fn from(value: ExportBindingsMode) -> Self {
+        match value {
+            ExportBindingsMode::Js => js_component_bindgen::ExportBindingsMode::Js,
+            ExportBindingsMode::Hybrid => js_component_bindgen::ExportBindingsMode::Hybrid,
+        }
+    }
+}
+
+impl From<js_component_bindgen::TranspileWarning> for TranspileWarning {
+    fn from(warning: js_component_bindgen::TranspileWarning) -> Self {
+        let message = warning.to_string();
+        let kind = match warning {
+            js_component_bindgen::TranspileWarning::UnusedMapEntry(key) => {
+                TranspileWarningKind::UnusedMapEntry(key)
+            }
+            js_component_bindgen::TranspileWarning::AmbiguousImportMapping { import, entries } => {
+                TranspileWarningKind::AmbiguousImportMapping((import, entries))
+            }
+        };
+        TranspileWarning { kind, message }
+    }
+}
+
 struct JsComponentBindgenComponent;
 
 export!(JsComponentBindgenComponent);
 
 impl Guest for JsComponentBindgenComponent {
-    fn generate(component: Vec<u8>, options: GenerateOptions) -> Result<Transpiled, String> {
-        let component = wat::parse_bytes(&component).map_err(|e| format!("{e}"))?;
+    fn generate(component: Vec<u8>, options: GenerateOptions) -> Result<Transpiled, Error> {
+        let component = wat::parse_bytes(&component)
+            .context("failed to parse WebAssembly text")
+            .map_err(wasm_error)?;
         let opts = js_component_bindgen::TranspileOpts {
             name: options.name,
             no_typescript: options.no_typescript.unwrap_or(false),
             instantiation: options.instantiation.map(Into::into),
             map: options.map.map(|map| map.into_iter().collect()),
+            export_map: options.export_map.map(|map| map.into_iter().collect()),
             no_nodejs_compat: options.no_nodejs_compat.unwrap_or(false),
             base64_cutoff: options.base64_cutoff.unwrap_or(5000) as usize,
             tla_compat: options
@@ -78,20 +130,26 @@ impl Guest for JsComponentBindgenComponent {
             tracing: options.tracing.unwrap_or(false),
             no_namespaced_exports: options.no_namespaced_exports.unwrap_or(false),
             multi_memory: options.multi_memory.unwrap_or(false),
+            merge_memories: options.merge_memories.unwrap_or(false),
+            detect_multi_memory: options.detect_multi_memory.unwrap_or(false),
             import_bindings: options.import_bindings.map(Into::into),
+            import_bindings_map: options
+                .import_bindings_map
+                .map(|map| map.into_iter().map(|(k, v)| (k, v.into())).collect()),
+            export_bindings: options.export_bindings.map(Into::into),
         };
 
         let js_component_bindgen::Transpiled {
             files,
             imports,
             mut exports,
-        } = transpile(&component, opts)
-            .map_err(|e| format!("{:?}", e))
-            .map_err(|e| e.to_string())?;
+            warnings,
+        } = transpile(&component, opts)?;
 
         Ok(Transpiled {
             files,
             imports,
+            warnings: warnings.into_iter().map(Into::into).collect(),
             exports: exports
                 .drain(..)
                 .map(|(name, expt)| {
@@ -115,12 +173,12 @@ impl Guest for JsComponentBindgenComponent {
     fn generate_types(
         name: String,
         opts: TypeGenerationOptions,
-    ) -> Result<Vec<(String, Vec<u8>)>, String> {
+    ) -> Result<Vec<(String, Vec<u8>)>, Error> {
         let (resolve, id) = resolve_package(opts.wit, opts.features, Some(&name))?;
         let world_string = opts.world.map(|world| world.to_string());
         let world = resolve
             .select_world(id, world_string.as_deref())
-            .map_err(|e| e.to_string())?;
+            .map_err(wit_error)?;
 
         let opts = js_component_bindgen::TranspileOpts {
             name: "component".to_string(),
@@ -128,38 +186,95 @@ impl Guest for JsComponentBindgenComponent {
             no_nodejs_compat: false,
             instantiation: opts.instantiation.map(Into::into),
             map: opts.map.map(|map| map.into_iter().collect()),
+            export_map: opts.export_map.map(|map| map.into_iter().collect()),
             tla_compat: opts.tla_compat.unwrap_or(false),
             valid_lifting_optimization: false,
             base64_cutoff: 0,
             tracing: false,
             no_namespaced_exports: false,
             multi_memory: false,
+            merge_memories: false,
+            detect_multi_memory: false,
             import_bindings: None,
+            import_bindings_map: None,
+            export_bindings: None,
+        };
+
+        let files = generate_types(name, resolve, world, opts)?;
+
+        Ok(files)
+    }
+
+    fn generate_typescript_stubs(opts: TypescriptStubOptions) -> Result<Files, Error> {
+        let (resolve, id) = resolve_package(opts.wit, None, None)?;
+        let world_string = opts.world.map(|world| world.to_string());
+        let world = resolve
+            .select_world(id, world_string.as_deref())
+            .map_err(wit_error)?;
+
+        let files = if opts.imports.unwrap_or(false) {
+            let opts = js_component_bindgen::TranspileOpts {
+                map: opts.map.map(|map| map.into_iter().collect()),
+                ..Default::default()
+            };
+            generate_typescript_import_stubs(resolve, world, opts)
+        } else {
+            generate_typescript_stubs(resolve, world)
+        }?;
+
+        Ok(files)
+    }
+
+    fn generate_js_mocks(opts: JsMockOptions) -> Result<Files, Error> {
+        let (resolve, id) = resolve_package(opts.wit, opts.features, None)?;
+        let world_string = opts.world.map(|world| world.to_string());
+        let world = resolve
+            .select_world(id, world_string.as_deref())
+            .map_err(wit_error)?;
+
+        let opts = js_component_bindgen::TranspileOpts {
+            map: opts.map.map(|map| map.into_iter().collect()),
+            ..Default::default()
         };
 
-        let files = generate_types(name, resolve, world, opts).map_err(|e| e.to_string())?;
+        let files = generate_js_mocks(resolve, world, opts)?;
 
         Ok(files)
     }
 
-    fn generate_typescript_stubs(opts: TypescriptStubOptions) -> Result<Files, String> {
-        let (resolve, id) = resolve_package(opts.wit, None, None).map_err(|e| e.to_string())?;
+    fn generate_lower_bindings(opts: LowerBindingsOptions) -> Result<Files, Error> {
+        let (resolve, id) = resolve_package(opts.wit, opts.features, None)?;
         let world_string = opts.world.map(|world| world.to_string());
         let world = resolve
             .select_world(id, world_string.as_deref())
-            .map_err(|e| e.to_string())?;
+            .map_err(wit_error)?;
 
-        let files = generate_typescript_stubs(resolve, world).map_err(|e| e.to_string())?;
+        let files = generate_lower_bindings(resolve, world)?;
 
         Ok(files)
     }
+
+    fn semver_check(opts: SemverCheckOptions) -> Result<Vec<ApiChange>, Error> {
+        let (old, old_id) = resolve_package(opts.old, opts.features.clone(), Some("old"))?;
+        let old_world = old
+            .select_world(old_id, opts.old_world.as_deref())
+            .map_err(wit_error)?;
+        let (new, new_id) = resolve_package(opts.new, opts.features, Some("new"))?;
+        let new_world = new
+            .select_world(new_id, opts.new_world.as_deref())
+            .map_err(wit_error)?;
+
+        let changes = semver_check(&old, old_world, &new, new_world)?;
+
+        Ok(changes.into_iter().map(Into::into).collect())
+    }
 }
 
 fn resolve_package(
     wit: Wit,
     features: Option<EnabledFeatureSet>,
     name: Option<&str>,
-) -> Result<(Resolve, PackageId), String> {
+) -> Result<(Resolve, PackageId), Error> {
     let name = name.unwrap_or("world");
     let mut resolve = Resolve::default();
 
@@ -179,17 +294,71 @@ fn resolve_package(
     let id = match wit {
         Wit::Source(source) => resolve
             .push_str(format!("{name}.wit"), &source)
-            .map_err(|e| e.to_string())?,
+            .map_err(wit_error)?,
         Wit::Path(path) => {
             let path = PathBuf::from(path);
             if path.is_dir() {
-                resolve.push_dir(&path).map_err(|e| e.to_string())?.0
+                resolve.push_dir(&path).map_err(wit_error)?.0
             } else {
-                resolve.push_file(&path).map_err(|e| e.to_string())?
+                resolve.push_file(&path).map_err(wit_error)?
             }
         }
-        Wit::Binary(_) => todo!(),
+        Wit::Files(files) => push_files(&mut resolve, files).map_err(wit_error)?,
+        Wit::Binary(binary) => {
+            let (decoded_resolve, id) =
+                match wit_component::decode(&binary).context("failed to decode WIT binary")? {
+                    DecodedWasm::WitPackage(resolve, id) => (resolve, id),
+                    DecodedWasm::Component(resolve, world) => {
+                        let id = resolve.worlds[world].package.unwrap();
+                        (resolve, id)
+                    }
+                };
+            // The decoded resolve replaces the empty one, keeping the
+            // enabled features for feature gating during generation
+            resolve = Resolve {
+                features: resolve.features,
+                all_features: resolve.all_features,
+                ..decoded_resolve
+            };
+            id
+        }
     };
 
     Ok((resolve, id))
 }
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        ErrorReport::new(&e).into()
+    }
+}
+
+impl From<ErrorReport> for Error {
+    fn from(report: ErrorReport) -> Self {
+        Error {
+            kind: match report.kind {
+                error_report::ErrorKind::Wit => ErrorKind::Wit,
+                error_report::ErrorKind::Wasm => ErrorKind::Wasm,
+                error_report::ErrorKind::Io => ErrorKind::Io,
+                error_report::ErrorKind::Other => ErrorKind::Other,
+            },
+            message: report.message,
+            context: report.context,
+            span: report.span.map(|span| SourceSpan {
+                file: span.file,
+                line: span.line,
+                column: span.column,
+            }),
+        }
+    }
+}
+
+/// Convert an error from parsing or resolving WIT
+fn wit_error(e: anyhow::Error) -> Error {
+    ErrorReport::wit(&e).into()
+}
+
+/// Convert an error from parsing WebAssembly text
+fn wasm_error(e: anyhow::Error) -> Error {
+    ErrorReport::wasm(&e).into()
+}
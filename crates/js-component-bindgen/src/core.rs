@@ -3,18 +3,19 @@
 //!
 //! Wasmtime's implementation of adapter modules between components requires the
 //! usage of multi-memory for copying data back and forth between two
-//! components. The multi-memory proposal is, at this time, not stable in any JS
-//! engine. This module is an attempt to polyfill this until at such a time that
-//! multi-memory can be used natively.
+//! components. The multi-memory proposal is, at this time, not stable in every
+//! JS engine. This module is an attempt to polyfill this until at such a time
+//! that multi-memory can be used natively.
 //!
 //! The purpose of this module is to identify core wasms which require
-//! multi-memory coming out of Wasmtime. These wasms are rewritten to not
-//! actually use more than one memory. The implementation here is to replace all
-//! memory instructions operating on memory index 1 or greater with function
-//! calls where JS is the one that does the load/store/etc. This is not expected
-//! to be fast at runtime but is intended to be just enough to get this working
-//! in JS environments at this time. The true speed is expected to come with the
-//! multi-memory proposal.
+//! multi-memory, whether coming out of Wasmtime or written by hand. These wasms
+//! are rewritten to not actually use more than one memory. The implementation
+//! here is to keep memory index 0 in the module and to replace all instructions
+//! operating on memory index 1 or greater with function calls where JS is the
+//! one that does the load/store/etc. This is not expected to be fast at runtime
+//! but is intended to be just enough to get this working in JS environments at
+//! this time. The true speed is expected to come with the multi-memory
+//! proposal.
 //!
 //! This module exports a [`Translation`] which wraps a [`ModuleTranslation`]
 //! either as a pass-through "normal" or an "augmented" version where
@@ -23,44 +24,110 @@
 //! imports for the "augmented" module the arguments for JS functions that
 //! read/write memory are automatically injected and handled.
 //!
+//! Memories other than memory 0 live entirely in JS: imported memories are
+//! used as-is, memories defined by the module are created in JS, and exports
+//! of these memories are provided by JS too. All instructions that use them
+//! are supported:
+//!
+//! * loads and stores of every width are calls of an [`AugmentedOp`],
+//! * SIMD loads and stores are split into scalar loads and stores, as `v128`
+//!   values can't cross the JS boundary,
+//! * `memory.size`, `memory.grow`, `memory.fill`, `memory.copy` (in either
+//!   direction between any two memories) and `memory.init` are calls as well,
+//!   with JS holding a copy of the data segments used to initialize these
+//!   memories.
+//!
+//! Active data segments targeting these memories are applied from JS after
+//! instantiation, so the start function of an augmented module, if any, is
+//! deferred until then, which is taken care of by calling the
+//! [`AUGMENTS_START`] export.
+//!
 //! Callers of this module need to have an implementation in JS for all of the
-//! entries listed in [`AugmentedOp`], likely through the `DataView` class in
-//! JS.
+//! entries listed in [`AugmentedImport`], likely through the `DataView` class
+//! in JS. Atomic instructions and 64-bit memories are only supported for
+//! memory 0.
 //!
-//! Note that at this time this module is not intended to be a complete and
-//! general purpose method of compiling multiple memories to single-memory
-//! modules. This does not handle all instructions that use memory for example,
-//! but only those that Wasmtime's adapter modules emits. It's possible to add
-//! support for more instructions but such support isn't required at this time.
-//! Examples of unsupported instructions are `i64.load8_u` and `memory.copy`.
-//! Additionally core wasm sections such as data sections and tables are not
-//! supported because, again, Wasmtime doesn't use it at this time.
+//! Going through JS for every load and store is slow though, so memories can
+//! alternatively be merged into memory 0 when `merge_memories` is set. Each
+//! merged memory is given a range of pages after the maximum of memory 0,
+//! and its current size is kept in a global: loads and stores are checked
+//! against this size and their address moved to the range of the memory,
+//! while `memory.size`, `memory.grow` and the bulk memory instructions use
+//! this size and range instead, through functions added to the module.
+//! Memory 0 itself is treated the same way, with a range starting at page 0,
+//! so that its loads, stores, bulk memory instructions and active data
+//! segments, as well as copies by JS to or from it, trap past its current
+//! size rather than access the merged memories. Its active data segments are
+//! then applied by the initialization function, in order with those of the
+//! merged memories.
+//!
+//! This requires memory 0 to be defined by the module with a maximum and not
+//! to be accessed by atomic instructions or `memory.discard`, which can't be
+//! checked, and only applies to memories defined by the module with a
+//! maximum which are not exported, nor copied to or from memories in JS, such
+//! that JS never needs to know of them. Imported memories never take this
+//! path: a module importing any memory is not merged at all. This rules out
+//! the adapter modules of Wasmtime between components, which import the
+//! memories of the components they copy between, so these always go through
+//! JS.
 
 use anyhow::{bail, Result};
-use std::collections::{HashMap, HashSet};
+use indexmap::IndexSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use wasm_encoder::*;
 use wasmparser::collections::IndexMap;
 use wasmparser::*;
 use wasmtime_environ::component::CoreDef;
+use wasmtime_environ::EntityIndex;
 use wasmtime_environ::{wasmparser, ModuleTranslation};
-use wasmtime_environ::{EntityIndex, MemoryIndex, PrimaryMap};
 
-fn unimplemented_try_table() -> wasm_encoder::Instruction<'static> {
-    unimplemented!()
-}
+/// Name of the export of an augmented module which runs its deferred
+/// initialization, to be called once its memories are available in JS
+pub const AUGMENTS_START: &str = "augments start";
+
+/// Name of the export of memory 0 by an augmented module, when it is needed
+/// from JS to copy between memories
+pub const AUGMENTS_MEMORY: &str = "augments memory";
+
 pub enum Translation<'a> {
     Normal(ModuleTranslation<'a>),
     Augmented {
         original: ModuleTranslation<'a>,
         wasm: Vec<u8>,
         imports_removed: HashSet<(String, String)>,
-        imports_added: Vec<(String, String, MemoryIndex, AugmentedOp)>,
+        imports_added: Vec<(String, String, u32, AugmentedOp)>,
+        memories_defined: Vec<(u32, wasmparser::MemoryType)>,
+        memory_exports: Vec<(String, u32)>,
+        data: Vec<(u32, &'a [u8])>,
+        start: bool,
     },
 }
 
 pub enum AugmentedImport<'a> {
     CoreDef(&'a CoreDef),
-    Memory { mem: &'a CoreDef, op: AugmentedOp },
+    Memory {
+        mem: AugmentedMemory<'a>,
+        op: AugmentedOp,
+    },
+    /// Copy between two memories, at least one of which is not memory 0
+    MemoryCopy {
+        dst: AugmentedMemory<'a>,
+        src: AugmentedMemory<'a>,
+    },
+    /// Drop the JS copy of a data segment
+    DataDrop(u32),
+}
+
+/// Memory of the original module, as available in JS
+#[derive(Clone, Copy)]
+pub enum AugmentedMemory<'a> {
+    /// Memory imported by the original module
+    Import(&'a CoreDef),
+    /// Memory defined by the original module, which is created in JS instead
+    /// with the type given by [`Translation::memories_defined`]
+    Defined(u32),
+    /// Memory 0 defined by the module, exported as [`AUGMENTS_MEMORY`]
+    Module,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -71,19 +138,42 @@ pub enum AugmentedOp {
     I32Load16U,
     I32Load16S,
     I64Load,
+    I64Load8U,
+    I64Load8S,
+    I64Load16U,
+    I64Load16S,
+    I64Load32U,
+    I64Load32S,
     F32Load,
     F64Load,
     I32Store,
     I32Store8,
     I32Store16,
     I64Store,
+    I64Store8,
+    I64Store16,
+    I64Store32,
     F32Store,
     F64Store,
     MemorySize,
+    MemoryGrow,
+    MemoryFill,
+    /// Copy to this memory from the memory with the given index
+    MemoryCopy {
+        src: u32,
+    },
+    /// Initialize this memory from the data segment with the given index
+    MemoryInit(u32),
+    /// Drop the data segment with the given index
+    DataDrop(u32),
 }
 
 impl<'a> Translation<'a> {
-    pub fn new(translation: ModuleTranslation<'a>, multi_memory: bool) -> Result<Translation<'a>> {
+    pub fn new(
+        translation: ModuleTranslation<'a>,
+        multi_memory: bool,
+        merge_memories: bool,
+    ) -> Result<Translation<'a>> {
         if multi_memory {
             return Ok(Translation::Normal(translation));
         }
@@ -107,23 +197,56 @@ impl<'a> Translation<'a> {
         }
 
         let mut augmenter = Augmenter {
-            translation: &translation,
+            wasm: translation.wasm,
+            merge_memories,
             imports_removed: Default::default(),
             imports_added: Default::default(),
             imported_funcs: Default::default(),
             imported_memories: Default::default(),
+            imported_globals: Default::default(),
             imports: Default::default(),
             exports: Default::default(),
+            memory_exports: Default::default(),
             local_func_tys: Default::default(),
             local_funcs: Default::default(),
+            scratch: Default::default(),
             types: Default::default(),
+            tables: Default::default(),
+            memories: Default::default(),
+            tags: Default::default(),
+            globals: Default::default(),
+            elements: Default::default(),
+            data: Default::default(),
+            data_count: Default::default(),
+            data_dropped: Default::default(),
+            js_data: Default::default(),
+            start: Default::default(),
             augments: Default::default(),
+            merged: Default::default(),
+            helpers: Default::default(),
         };
         let wasm = augmenter.run()?;
+        let memories_defined = augmenter
+            .memories
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (augmenter.imported_memories + i as u32, *ty))
+            .filter(|(index, _)| *index > 0 && !augmenter.merged.contains_key(index))
+            .collect();
+        let data = augmenter
+            .js_data
+            .iter()
+            .map(|index| (*index, augmenter.data[*index as usize].data))
+            .collect();
+        let start = augmenter.has_init();
         Ok(Translation::Augmented {
             wasm,
             imports_removed: augmenter.imports_removed,
             imports_added: augmenter.imports_added,
+            memories_defined,
+            memory_exports: augmenter.memory_exports,
+            data,
+            start,
             original: translation,
         })
     }
@@ -138,6 +261,35 @@ impl<'a> Translation<'a> {
         }
     }
 
+    /// Returns the original wasm of a module that was augmented, which uses
+    /// multi-memory.
+    pub fn multi_memory_wasm(&self) -> Option<&[u8]> {
+        match self {
+            Translation::Normal(_) => None,
+            Translation::Augmented { original, .. } => Some(original.wasm),
+        }
+    }
+
+    /// Returns the imports of the original module, using multi-memory, for
+    /// the `args` as supplied to it.
+    pub fn multi_memory_imports<'b>(
+        &'b self,
+        args: &'b [CoreDef],
+    ) -> Vec<(&'b str, &'b str, AugmentedImport<'b>)> {
+        let (Translation::Normal(translation)
+        | Translation::Augmented {
+            original: translation,
+            ..
+        }) = self;
+        assert_eq!(translation.module.imports().len(), args.len());
+        translation
+            .module
+            .imports()
+            .zip(args)
+            .map(|((module, name, _), arg)| (module, name, AugmentedImport::CoreDef(arg)))
+            .collect()
+    }
+
     /// Returns an iterator over the imports for this module using the `args` as
     /// supplied to the original module.
     ///
@@ -148,15 +300,7 @@ impl<'a> Translation<'a> {
         args: &'b [CoreDef],
     ) -> Vec<(&'b str, &'b str, AugmentedImport<'b>)> {
         match self {
-            Translation::Normal(translation) => {
-                assert_eq!(translation.module.imports().len(), args.len());
-                translation
-                    .module
-                    .imports()
-                    .zip(args)
-                    .map(|((module, name, _), arg)| (module, name, AugmentedImport::CoreDef(arg)))
-                    .collect()
-            }
+            Translation::Normal(_) => self.multi_memory_imports(args),
             Translation::Augmented {
                 original,
                 imports_removed,
@@ -164,29 +308,55 @@ impl<'a> Translation<'a> {
                 ..
             } => {
                 let mut ret = Vec::new();
-                let mut memories: PrimaryMap<MemoryIndex, &'b CoreDef> = PrimaryMap::new();
                 for ((module, name, _), arg) in original.module.imports().zip(args) {
-                    if imports_removed.contains(&(module.to_string(), name.to_string())) {
-                        memories.push(arg);
-                    } else {
+                    if !imports_removed.contains(&(module.to_string(), name.to_string())) {
                         ret.push((module, name, AugmentedImport::CoreDef(arg)));
                     }
                 }
-                for (module, name, index, op) in imports_added {
-                    ret.push((
-                        module,
-                        name,
-                        AugmentedImport::Memory {
-                            mem: memories[*index],
+                let memories = self.memories(args);
+                for (module, name, mem, op) in imports_added {
+                    let import = match op {
+                        AugmentedOp::DataDrop(data) => AugmentedImport::DataDrop(*data),
+                        AugmentedOp::MemoryCopy { src } => AugmentedImport::MemoryCopy {
+                            dst: memories[mem],
+                            src: memories[src],
+                        },
+                        op => AugmentedImport::Memory {
+                            mem: memories[mem],
                             op: *op,
                         },
-                    ));
+                    };
+                    ret.push((module.as_str(), name.as_str(), import));
                 }
                 ret
             }
         }
     }
 
+    /// Returns the memories of the original module, by memory index, as
+    /// available in JS using the `args` supplied to the original module.
+    fn memories<'b>(&'b self, args: &'b [CoreDef]) -> HashMap<u32, AugmentedMemory<'b>> {
+        let mut memories = HashMap::new();
+        let Translation::Augmented {
+            original,
+            memories_defined,
+            ..
+        } = self
+        else {
+            return memories;
+        };
+        for ((_, _, ty), arg) in original.module.imports().zip(args) {
+            if let wasmtime_environ::EntityType::Memory(_) = ty {
+                memories.insert(memories.len() as u32, AugmentedMemory::Import(arg));
+            }
+        }
+        memories.entry(0).or_insert(AugmentedMemory::Module);
+        for (index, _) in memories_defined {
+            memories.insert(*index, AugmentedMemory::Defined(*index));
+        }
+        memories
+    }
+
     /// Returns the exports of this module, which are not modified by
     /// augmentation.
     pub fn exports(&self) -> &IndexMap<String, EntityIndex> {
@@ -195,21 +365,124 @@ impl<'a> Translation<'a> {
             Translation::Augmented { original, .. } => &original.module.exports,
         }
     }
+
+    /// Returns the memories defined by the original module which need to be
+    /// created in JS, by memory index.
+    pub fn memories_defined(&self) -> &[(u32, wasmparser::MemoryType)] {
+        match self {
+            Translation::Normal(_) => &[],
+            Translation::Augmented {
+                memories_defined, ..
+            } => memories_defined,
+        }
+    }
+
+    /// Returns the exports of memories which are not exported by the
+    /// augmented module, and need to be added to its exports in JS.
+    pub fn memory_exports<'b>(
+        &'b self,
+        args: &'b [CoreDef],
+    ) -> Vec<(&'b str, AugmentedMemory<'b>)> {
+        match self {
+            Translation::Normal(_) => Vec::new(),
+            Translation::Augmented { memory_exports, .. } => {
+                let memories = self.memories(args);
+                memory_exports
+                    .iter()
+                    .map(|(name, mem)| (name.as_str(), memories[mem]))
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the contents of the data segments used from JS, by data index.
+    pub fn data(&self) -> &[(u32, &'a [u8])] {
+        match self {
+            Translation::Normal(_) => &[],
+            Translation::Augmented { data, .. } => data,
+        }
+    }
+
+    /// Returns whether the [`AUGMENTS_START`] export needs to be called after
+    /// instantiation.
+    pub fn deferred_start(&self) -> bool {
+        match self {
+            Translation::Normal(_) => false,
+            Translation::Augmented { start, .. } => *start,
+        }
+    }
 }
 
 pub struct Augmenter<'a> {
-    translation: &'a ModuleTranslation<'a>,
+    wasm: &'a [u8],
+    merge_memories: bool,
     imports_removed: HashSet<(String, String)>,
-    imports_added: Vec<(String, String, MemoryIndex, AugmentedOp)>,
-    augments: HashMap<(MemoryIndex, AugmentedOp), u32>,
+    imports_added: Vec<(String, String, u32, AugmentedOp)>,
+    augments: HashMap<(u32, AugmentedOp), u32>,
 
     types: Vec<wasmparser::FuncType>,
     imports: Vec<Import<'a>>,
     imported_funcs: u32,
     imported_memories: u32,
+    imported_globals: u32,
     exports: Vec<Export<'a>>,
+    memory_exports: Vec<(String, u32)>,
     local_funcs: Vec<FunctionBody<'a>>,
     local_func_tys: Vec<u32>,
+    /// Whether each local function needs scratch locals to split SIMD loads
+    /// and stores, or to rewrite accesses of merged memories
+    scratch: Vec<bool>,
+    tables: Vec<Table<'a>>,
+    memories: Vec<wasmparser::MemoryType>,
+    tags: Vec<wasmparser::TagType>,
+    globals: Vec<Global<'a>>,
+    elements: Vec<Element<'a>>,
+    data: Vec<Data<'a>>,
+    data_count: Option<u32>,
+    start: Option<u32>,
+    /// Data segments dropped by `data.drop`
+    data_dropped: HashSet<u32>,
+    /// Data segments of which JS holds a copy, to initialize memories other
+    /// than memory 0
+    js_data: BTreeSet<u32>,
+
+    /// Memories merged into memory 0, including memory 0 itself if any are
+    /// merged
+    merged: BTreeMap<u32, MergedMemory>,
+    /// Functions added to the module to operate on merged memories
+    helpers: IndexSet<Helper>,
+}
+
+/// Memory merged into memory 0 at a reserved range of pages
+struct MergedMemory {
+    /// First page of the range
+    base: u32,
+    /// Maximum size in pages
+    maximum: u32,
+    /// Index of the mutable `i64` global of its current size in bytes
+    size: u32,
+}
+
+/// Function added to the module to operate on merged memories, taking the
+/// same arguments as the instruction it replaces
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Helper {
+    MemoryGrow(u32),
+    MemoryFill(u32),
+    MemoryCopy {
+        dst: u32,
+        src: u32,
+    },
+    MemoryInit {
+        mem: u32,
+        data: u32,
+    },
+    /// Copy by JS between memory 0 and a memory in JS, checked against the
+    /// size of memory 0 first
+    JsMemoryCopy {
+        dst: u32,
+        src: u32,
+    },
 }
 
 impl Augmenter<'_> {
@@ -217,7 +490,7 @@ impl Augmenter<'_> {
         // The first step is to parse the input original wasm and learn about
         // its structure. This validates that all the sections are supported and
         // records various bits of information about the module within `self`.
-        for payload in Parser::new(0).parse_all(self.translation.wasm) {
+        for payload in Parser::new(0).parse_all(self.wasm) {
             match payload? {
                 Payload::Version { .. } => {}
                 Payload::End(_) => {}
@@ -232,15 +505,19 @@ impl Augmenter<'_> {
                         let i = i?;
                         match i.ty {
                             TypeRef::Func(_) => self.imported_funcs += 1,
-                            TypeRef::Memory(_) => {
-                                if self.imported_memories > 0 {
+                            TypeRef::Global(_) => self.imported_globals += 1,
+                            TypeRef::Memory(ty) => {
+                                self.imported_memories += 1;
+                                if self.imported_memories > 1 {
+                                    if ty.memory64 {
+                                        bail!("64-bit memories other than the first are not supported without multi-memory")
+                                    }
                                     let ok = self
                                         .imports_removed
                                         .insert((i.module.to_string(), i.name.to_string()));
                                     assert!(ok);
                                     continue;
                                 }
-                                self.imported_memories += 1;
                             }
                             _ => {}
                         }
@@ -251,6 +528,10 @@ impl Augmenter<'_> {
                 Payload::ExportSection(s) => {
                     for e in s {
                         let e = e?;
+                        if e.kind == ExternalKind::Memory && e.index > 0 {
+                            self.memory_exports.push((e.name.to_string(), e.index));
+                            continue;
+                        }
                         self.exports.push(e);
                     }
                 }
@@ -262,6 +543,43 @@ impl Augmenter<'_> {
                     }
                 }
 
+                Payload::TableSection(s) => {
+                    for table in s {
+                        self.tables.push(table?);
+                    }
+                }
+                Payload::MemorySection(s) => {
+                    for ty in s {
+                        let ty = ty?;
+                        if ty.memory64 && self.imported_memories + self.memories.len() as u32 > 0 {
+                            bail!("64-bit memories other than the first are not supported without multi-memory")
+                        }
+                        self.memories.push(ty);
+                    }
+                }
+                Payload::TagSection(s) => {
+                    for tag in s {
+                        self.tags.push(tag?);
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        self.globals.push(global?);
+                    }
+                }
+                Payload::StartSection { func, .. } => self.start = Some(func),
+                Payload::ElementSection(s) => {
+                    for element in s {
+                        self.elements.push(element?);
+                    }
+                }
+                Payload::DataCountSection { count, .. } => self.data_count = Some(count),
+                Payload::DataSection(s) => {
+                    for data in s {
+                        self.data.push(data?);
+                    }
+                }
+
                 Payload::CodeSectionStart { .. } => {}
                 Payload::CodeSectionEntry(body) => {
                     self.local_funcs.push(body);
@@ -270,17 +588,7 @@ impl Augmenter<'_> {
                 // Ignore all custom sections for now
                 Payload::CustomSection(_) => {}
 
-                // NB: these sections are theoretically possible to handle but
-                // are not required at this time.
-                Payload::DataCountSection { .. }
-                | Payload::GlobalSection(_)
-                | Payload::TableSection(_)
-                | Payload::MemorySection(_)
-                | Payload::ElementSection(_)
-                | Payload::DataSection(_)
-                | Payload::StartSection { .. }
-                | Payload::TagSection(_)
-                | Payload::UnknownSection { .. } => {
+                Payload::UnknownSection { .. } => {
                     bail!("unsupported section found in module using multiple memories")
                 }
 
@@ -301,6 +609,10 @@ impl Augmenter<'_> {
             }
         }
 
+        if self.merge_memories {
+            self.merge()?;
+        }
+
         // After the module has been parsed next the set of adapter functions is
         // determined. This is done by parsing all instructions in the module
         // and looking for anything that operates on memory index 1 or greater.
@@ -308,9 +620,38 @@ impl Augmenter<'_> {
         // This will fill out `self.augments` which is a list of functionality
         // that must be provided by JS to mutate non-index-0 memories.
         for body in self.local_funcs.clone() {
+            let mut collect = CollectMemOps {
+                augmenter: self,
+                scratch: false,
+            };
             let mut reader = body.get_operators_reader()?;
             while !reader.eof() {
-                reader.visit_operator(&mut CollectMemOps(self))?;
+                reader.visit_operator(&mut collect)??;
+            }
+            let scratch = collect.scratch;
+            self.scratch.push(scratch);
+        }
+
+        // Active data segments of memories other than memory 0 are applied
+        // from JS, or by a helper for merged memories (including memory 0),
+        // and dropped afterwards.
+        for (index, data) in self.data.clone().iter().enumerate() {
+            if let DataKind::Active { memory_index, .. } = data.kind {
+                if self.merged.contains_key(&memory_index) {
+                    self.helper(Helper::MemoryInit {
+                        mem: memory_index,
+                        data: index as u32,
+                    });
+                } else if memory_index > 0 {
+                    self.js_data.insert(index as u32);
+                    self.augment_op(memory_index, AugmentedOp::MemoryInit(index as u32));
+                    self.data_dropped.insert(index as u32);
+                }
+            }
+        }
+        for index in self.js_data.clone() {
+            if self.data_dropped.contains(&index) {
+                self.augment_op(0, AugmentedOp::DataDrop(index));
             }
         }
 
@@ -319,25 +660,167 @@ impl Augmenter<'_> {
         self.encode()
     }
 
+    /// Select the memories defined by the module which can be merged into
+    /// memory 0, and reserve their ranges of pages after the maximum of
+    /// memory 0.
+    fn merge(&mut self) -> Result<()> {
+        // Memory 0 needs to be defined by the module, with a maximum, to
+        // reserve ranges after it
+        let mergeable = |ty: &wasmparser::MemoryType| {
+            ty.maximum.is_some() && !ty.memory64 && !ty.shared && ty.page_size_log2.is_none()
+        };
+        if self.imported_memories > 0 || !self.memories.first().is_some_and(mergeable) {
+            return Ok(());
+        }
+
+        // Accesses of memory 0 by atomic instructions and `memory.discard`
+        // can't be checked against its size once memories are merged after it
+        for body in self.local_funcs.iter() {
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                if reader.visit_operator(&mut UncheckedAccess)? {
+                    return Ok(());
+                }
+            }
+        }
+
+        // Memories exported are needed in JS, so can't be merged, and the
+        // total size needs to fit in a 32-bit memory.
+        let mut pages = self.memories[0].maximum.unwrap();
+        let mut candidates = BTreeSet::new();
+        for (index, ty) in self.memories.iter().enumerate().skip(1) {
+            let index = index as u32;
+            if !mergeable(ty) || self.memory_exports.iter().any(|(_, mem)| *mem == index) {
+                continue;
+            }
+            if pages + ty.maximum.unwrap() <= 1 << 16 {
+                pages += ty.maximum.unwrap();
+                candidates.insert(index);
+            }
+        }
+
+        // Copies between merged memories and memories in JS would need JS to
+        // know of merged memories, so these aren't merged either.
+        let mut copies = HashSet::new();
+        for body in self.local_funcs.iter() {
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                if let Operator::MemoryCopy { dst_mem, src_mem } = reader.read()? {
+                    copies.insert((dst_mem, src_mem));
+                }
+            }
+        }
+        loop {
+            let in_js = |mem: u32| mem > 0 && !candidates.contains(&mem);
+            let excluded = copies
+                .iter()
+                .filter_map(|(dst, src)| match (in_js(*dst), in_js(*src)) {
+                    (true, false) if *src > 0 => Some(*src),
+                    (false, true) if *dst > 0 => Some(*dst),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if excluded.is_empty() {
+                break;
+            }
+            for mem in excluded {
+                candidates.remove(&mem);
+            }
+        }
+        if candidates.is_empty() {
+            return Ok(());
+        }
+
+        let mut base = 0;
+        let mut size = self.imported_globals + self.globals.len() as u32;
+        for index in [0].into_iter().chain(candidates) {
+            let maximum = self.memories[index as usize].maximum.unwrap() as u32;
+            self.merged.insert(
+                index,
+                MergedMemory {
+                    base,
+                    maximum,
+                    size,
+                },
+            );
+            base += maximum;
+            size += 1;
+        }
+        Ok(())
+    }
+
+    fn helper(&mut self, helper: Helper) {
+        self.helpers.insert(helper);
+    }
+
+    /// Index of the function added for `helper`, after the local functions
+    /// and the initialization function
+    fn helper_func(&self, helper: Helper) -> u32 {
+        self.imported_funcs
+            + self.imports_added.len() as u32
+            + self.local_funcs.len() as u32
+            + self.has_init() as u32
+            + self.helpers.get_index_of(&helper).unwrap() as u32
+    }
+
+    /// The merged memory at the end of memory 0, which is the only one that
+    /// grows memory 0 when it grows
+    fn last_merged(&self) -> Option<(&u32, &MergedMemory)> {
+        self.merged.iter().max_by_key(|(_, merged)| merged.base)
+    }
+
     fn augment_op(&mut self, mem: u32, op: AugmentedOp) {
         // Memory 0 stays in the module and isn't removed, so no need to
-        // register an augmentation.
-        if mem == 0 {
+        // register an augmentation, except to copy to or from other memories
+        // or to drop segments held by JS. Merged memories are handled in the
+        // module as well.
+        if self.merged.contains_key(&mem)
+            && !matches!(
+                op,
+                AugmentedOp::MemoryCopy { .. } | AugmentedOp::DataDrop(_)
+            )
+        {
             return;
         }
-        let index = MemoryIndex::from_u32(mem - 1);
-        self.augments.entry((index, op)).or_insert_with(|| {
-            let idx = self.imported_funcs + self.imports_added.len() as u32;
-            self.imports_added.push((
-                "augments".to_string(),
-                format!("mem{mem} {op:?}"),
-                index,
+        if mem == 0
+            && !matches!(
                 op,
-            ));
+                AugmentedOp::MemoryCopy { .. } | AugmentedOp::DataDrop(_)
+            )
+        {
+            return;
+        }
+        self.augments.entry((mem, op)).or_insert_with(|| {
+            let idx = self.imported_funcs + self.imports_added.len() as u32;
+            self.imports_added
+                .push(("augments".to_string(), format!("mem{mem} {op:?}"), mem, op));
             idx
         });
     }
 
+    /// Whether memory 0 needs to be exported for JS to copy between it and
+    /// other memories, when it is not imported
+    fn exports_memory(&self) -> bool {
+        self.imported_memories == 0
+            && self.augments.keys().any(|(mem, op)| match op {
+                AugmentedOp::MemoryCopy { src } => *mem == 0 || *src == 0,
+                _ => false,
+            })
+    }
+
+    /// Whether the augmented module has an initialization function, applying
+    /// data segments to memories in JS or merged memories and calling the
+    /// start function
+    fn has_init(&self) -> bool {
+        self.start.is_some()
+            || self.data.iter().any(|data| match data.kind {
+                DataKind::Active { memory_index, .. } => {
+                    memory_index > 0 || self.merged.contains_key(&memory_index)
+                }
+                DataKind::Passive => false,
+            })
+    }
+
     fn encode(&self) -> Result<Vec<u8>> {
         let mut module = Module::new();
 
@@ -345,9 +828,11 @@ impl Augmenter<'_> {
         // before.
         let mut types = TypeSection::new();
         for ty in &self.types {
+            let params = ty.params().iter().map(|v| valtype(*v));
+            let results = ty.results().iter().map(|v| valtype(*v));
             types.function(
-                ty.params().iter().map(|v| valtype(*v)),
-                ty.results().iter().map(|v| valtype(*v)),
+                params.collect::<Result<Vec<_>>>()?,
+                results.collect::<Result<Vec<_>>>()?,
             );
         }
 
@@ -358,20 +843,10 @@ impl Augmenter<'_> {
         for import in self.imports.iter() {
             let ty = match import.ty {
                 TypeRef::Func(f) => EntityType::Function(f),
-                TypeRef::Global(g) => EntityType::Global(wasm_encoder::GlobalType {
-                    mutable: g.mutable,
-                    val_type: valtype(g.content_type),
-                    shared: g.shared,
-                }),
-                TypeRef::Memory(m) => EntityType::Memory(wasm_encoder::MemoryType {
-                    maximum: m.maximum,
-                    minimum: m.initial,
-                    memory64: m.memory64,
-                    shared: m.shared,
-                    page_size_log2: m.page_size_log2,
-                }),
-                TypeRef::Table(_) => unimplemented!(),
-                TypeRef::Tag(_) => unimplemented!(),
+                TypeRef::Global(g) => EntityType::Global(globaltype(g)?),
+                TypeRef::Memory(m) => EntityType::Memory(memorytype(m)),
+                TypeRef::Table(t) => EntityType::Table(tabletype(t)?),
+                TypeRef::Tag(t) => EntityType::Tag(tagtype(t)),
             };
             imports.import(import.module, import.name, ty);
         }
@@ -393,64 +868,419 @@ impl Augmenter<'_> {
         }
 
         // The function section remains the same as we're not tampering with the
-        // count or types of all local functions.
+        // count or types of all local functions, apart from the addition of
+        // the initialization function at the end.
         let mut funcs = FunctionSection::new();
         for ty in self.local_func_tys.iter() {
             funcs.function(*ty);
         }
+        let init = if self.has_init() {
+            let ty = types.len();
+            types.function([], []);
+            funcs.function(ty);
+            Some(self.imported_funcs + self.imports_added.len() as u32 + funcs.len() - 1)
+        } else {
+            None
+        };
+        for helper in self.helpers.iter() {
+            let ty = types.len();
+            helper.encode_type(&mut types);
+            funcs.function(ty);
+        }
+
+        let mut tables = TableSection::new();
+        for table in self.tables.iter() {
+            match &table.init {
+                TableInit::RefNull => tables.table(tabletype(table.ty)?),
+                TableInit::Expr(expr) => {
+                    tables.table_with_init(tabletype(table.ty)?, &self.const_expr(expr)?)
+                }
+            };
+        }
+
+        // Only memory 0 remains, if it is defined by the module, and is large
+        // enough for the merged memories after it.
+        let mut memories = MemorySection::new();
+        if self.imported_memories == 0 {
+            if let Some(ty) = self.memories.first() {
+                let mut ty = memorytype(*ty);
+                if let Some((index, last)) = self.last_merged() {
+                    ty.minimum = u64::from(last.base) + self.memories[*index as usize].initial;
+                    ty.maximum = Some(u64::from(last.base + last.maximum));
+                }
+                memories.memory(ty);
+            }
+        }
+
+        let mut tags = TagSection::new();
+        for tag in self.tags.iter() {
+            tags.tag(tagtype(*tag));
+        }
+
+        let mut globals = GlobalSection::new();
+        for global in self.globals.iter() {
+            globals.global(globaltype(global.ty)?, &self.const_expr(&global.init_expr)?);
+        }
+        for (index, _) in self.merged.iter() {
+            let size = self.memories[*index as usize].initial << 16;
+            globals.global(
+                wasm_encoder::GlobalType {
+                    val_type: wasm_encoder::ValType::I64,
+                    mutable: true,
+                    shared: false,
+                },
+                &wasm_encoder::ConstExpr::i64_const(size as i64),
+            );
+        }
 
         // Exports all remain the same with the one caveat that the function
-        // index space has changed so those indices are remapped.
+        // index space has changed so those indices are remapped. Exports of
+        // memories other than memory 0 were removed, to be provided by JS.
         let mut exports = ExportSection::new();
         for e in self.exports.iter() {
             let (kind, index) = match e.kind {
                 ExternalKind::Func => (ExportKind::Func, self.remap_func(e.index)),
                 ExternalKind::Table => (ExportKind::Table, e.index),
                 ExternalKind::Global => (ExportKind::Global, e.index),
-                ExternalKind::Memory => {
-                    assert!(e.index < 1);
-                    (ExportKind::Memory, e.index)
-                }
+                ExternalKind::Memory => (ExportKind::Memory, self.remap_memory(e.index)),
                 ExternalKind::Tag => (ExportKind::Tag, e.index),
             };
             exports.export(e.name, kind, index);
         }
+        if self.exports_memory() {
+            exports.export(AUGMENTS_MEMORY, ExportKind::Memory, 0);
+        }
+        if let Some(init) = init {
+            exports.export(AUGMENTS_START, ExportKind::Func, init);
+        }
+
+        let mut elements = ElementSection::new();
+        for element in self.elements.iter() {
+            let mut functions = Vec::new();
+            let mut exprs = Vec::new();
+            let items = match &element.items {
+                ElementItems::Functions(s) => {
+                    for func in s.clone() {
+                        functions.push(self.remap_func(func?));
+                    }
+                    Elements::Functions(&functions)
+                }
+                ElementItems::Expressions(ty, s) => {
+                    for expr in s.clone() {
+                        exprs.push(self.const_expr(&expr?)?);
+                    }
+                    Elements::Expressions(reftype(*ty)?, &exprs)
+                }
+            };
+            match &element.kind {
+                ElementKind::Passive => elements.passive(items),
+                ElementKind::Declared => elements.declared(items),
+                ElementKind::Active {
+                    table_index,
+                    offset_expr,
+                } => elements.active(*table_index, &self.const_expr(offset_expr)?, items),
+            };
+        }
 
         // Finally the code section is remapped. This is done by translating
         // operator-by-operator from `wasmparser` to `wasm-encoder`. This
         // is where instructions like `i32.load 1` will become `call
         // $i32_load_memory_1`.
         let mut code = CodeSection::new();
-        for body in self.local_funcs.iter() {
+        for (i, body) in self.local_funcs.iter().enumerate() {
             let mut locals = Vec::new();
 
+            // Scratch locals are added after the parameters and locals of the
+            // function, when needed
+            let ty = &self.types[self.local_func_tys[i] as usize];
+            let mut scratch = ty.params().len() as u32;
             for local in body.get_locals_reader()? {
                 let (cnt, ty) = local?;
-                locals.push((cnt, valtype(ty)));
+                scratch += cnt;
+                locals.push((cnt, valtype(ty)?));
+            }
+            if self.scratch[i] {
+                locals.extend(SCRATCH.iter().map(|ty| (1, *ty)));
             }
 
             let mut f = Function::new(locals);
 
             let mut ops = body.get_operators_reader()?;
             while !ops.eof() {
-                ops.visit_operator(&mut Translator {
+                let mut translator = Translator {
                     func: &mut f,
                     augmenter: self,
-                })?;
+                    scratch,
+                };
+                // Accesses of merged memories, including memory 0, are checked
+                // against the size of the memory and moved to its range in
+                // memory 0 before the instruction itself is translated.
+                if let Some((memarg, width, value)) = access(&ops.clone().read()?) {
+                    if self.merged.contains_key(&memarg.memory) {
+                        translator.merged_access(memarg, width, value)?;
+                    }
+                }
+                ops.visit_operator(&mut translator)??;
             }
 
             code.function(&f);
         }
 
+        // The initialization function applies active data segments to
+        // memories in JS or merged memories, in order, and then calls the
+        // original start function.
+        if init.is_some() {
+            let mut f = Function::new([]);
+            for (index, data) in self.data.iter().enumerate() {
+                let index = index as u32;
+                let DataKind::Active {
+                    memory_index,
+                    offset_expr,
+                } = &data.kind
+                else {
+                    continue;
+                };
+                if *memory_index == 0 && !self.merged.contains_key(&0) {
+                    continue;
+                }
+                f.raw(self.const_expr_bytes(offset_expr)?);
+                f.instruction(&Instruction::I32Const(0));
+                f.instruction(&Instruction::I32Const(data.data.len() as i32));
+                if self.merged.contains_key(memory_index) {
+                    f.instruction(&Instruction::Call(self.helper_func(Helper::MemoryInit {
+                        mem: *memory_index,
+                        data: index,
+                    })));
+                    f.instruction(&Instruction::DataDrop(index));
+                    continue;
+                }
+                f.instruction(&Instruction::Call(
+                    self.augments[&(*memory_index, AugmentedOp::MemoryInit(index))],
+                ));
+                f.instruction(&Instruction::Call(
+                    self.augments[&(0, AugmentedOp::DataDrop(index))],
+                ));
+            }
+            if let Some(start) = self.start {
+                f.instruction(&Instruction::Call(self.remap_func(start)));
+            }
+            f.instruction(&Instruction::End);
+            code.function(&f);
+        }
+
+        for helper in self.helpers.iter() {
+            code.function(&self.helper_body(*helper));
+        }
+
+        // Data segments of other memories are held by JS, and are replaced by
+        // empty passive segments to keep the same data indices. Those of
+        // merged memories, including memory 0, are made passive, to be
+        // applied by the initialization function.
+        let mut data = DataSection::new();
+        for segment in self.data.iter() {
+            match &segment.kind {
+                DataKind::Passive => data.passive(segment.data.iter().copied()),
+                DataKind::Active { memory_index, .. } if self.merged.contains_key(memory_index) => {
+                    data.passive(segment.data.iter().copied())
+                }
+                DataKind::Active {
+                    memory_index: 0,
+                    offset_expr,
+                } => data.active(
+                    0,
+                    &self.const_expr(offset_expr)?,
+                    segment.data.iter().copied(),
+                ),
+                DataKind::Active { .. } => data.passive([]),
+            };
+        }
+
         module.section(&types);
         module.section(&imports);
         module.section(&funcs);
+        if !tables.is_empty() {
+            module.section(&tables);
+        }
+        if !memories.is_empty() {
+            module.section(&memories);
+        }
+        if !tags.is_empty() {
+            module.section(&tags);
+        }
+        if !globals.is_empty() {
+            module.section(&globals);
+        }
         module.section(&exports);
+        if !elements.is_empty() {
+            module.section(&elements);
+        }
+        if self.data_count.is_some() || !self.merged.is_empty() && !self.data.is_empty() {
+            module.section(&DataCountSection {
+                count: self.data.len() as u32,
+            });
+        }
         module.section(&code);
+        if !data.is_empty() {
+            module.section(&data);
+        }
 
         Ok(module.finish())
     }
 
+    /// Body of a function operating on merged memories, checking bounds
+    /// against their size in the globals and then operating on memory 0
+    fn helper_body(&self, helper: Helper) -> Function {
+        use wasm_encoder::Instruction::*;
+
+        // Traps unless `local + len` is within the size of `mem`
+        let check = |f: &mut Function, mem: u32, local: u32, len: u32| {
+            f.instruction(&LocalGet(local));
+            f.instruction(&I64ExtendI32U);
+            f.instruction(&LocalGet(len));
+            f.instruction(&I64ExtendI32U);
+            f.instruction(&I64Add);
+            f.instruction(&GlobalGet(self.merged[&mem].size));
+            f.instruction(&I64GtU);
+            f.instruction(&If(wasm_encoder::BlockType::Empty));
+            f.instruction(&Unreachable);
+            f.instruction(&End);
+        };
+        // Moves the address in `local` to the range of `mem`
+        let address = |f: &mut Function, mem: u32, local: u32| {
+            f.instruction(&LocalGet(local));
+            f.instruction(&I32Const((self.merged[&mem].base << 16) as i32));
+            f.instruction(&I32Add);
+        };
+
+        let mut f;
+        match helper {
+            Helper::MemoryGrow(mem) => {
+                let merged = &self.merged[&mem];
+                f = Function::new([(2, wasm_encoder::ValType::I32)]);
+                let (delta, old, extra) = (0, 1, 2);
+                f.instruction(&GlobalGet(merged.size));
+                f.instruction(&I64Const(16));
+                f.instruction(&I64ShrU);
+                f.instruction(&I32WrapI64);
+                f.instruction(&LocalSet(old));
+
+                // Fails if growing past the maximum
+                f.instruction(&LocalGet(delta));
+                f.instruction(&I32Const(merged.maximum as i32));
+                f.instruction(&LocalGet(old));
+                f.instruction(&I32Sub);
+                f.instruction(&I32GtU);
+                f.instruction(&If(wasm_encoder::BlockType::Empty));
+                f.instruction(&I32Const(-1));
+                f.instruction(&Return);
+                f.instruction(&End);
+
+                // The last merged memory grows memory 0 as needed
+                if self.last_merged().is_some_and(|(index, _)| *index == mem) {
+                    f.instruction(&I32Const(merged.base as i32));
+                    f.instruction(&LocalGet(old));
+                    f.instruction(&I32Add);
+                    f.instruction(&LocalGet(delta));
+                    f.instruction(&I32Add);
+                    f.instruction(&MemorySize(0));
+                    f.instruction(&I32Sub);
+                    f.instruction(&LocalTee(extra));
+                    f.instruction(&I32Const(0));
+                    f.instruction(&I32GtS);
+                    f.instruction(&If(wasm_encoder::BlockType::Empty));
+                    f.instruction(&LocalGet(extra));
+                    f.instruction(&MemoryGrow(0));
+                    f.instruction(&I32Const(-1));
+                    f.instruction(&I32Eq);
+                    f.instruction(&If(wasm_encoder::BlockType::Empty));
+                    f.instruction(&I32Const(-1));
+                    f.instruction(&Return);
+                    f.instruction(&End);
+                    f.instruction(&End);
+                }
+
+                f.instruction(&GlobalGet(merged.size));
+                f.instruction(&LocalGet(delta));
+                f.instruction(&I64ExtendI32U);
+                f.instruction(&I64Const(16));
+                f.instruction(&I64Shl);
+                f.instruction(&I64Add);
+                f.instruction(&GlobalSet(merged.size));
+                f.instruction(&LocalGet(old));
+            }
+            Helper::MemoryFill(mem) => {
+                f = Function::new([]);
+                check(&mut f, mem, 0, 2);
+                address(&mut f, mem, 0);
+                f.instruction(&LocalGet(1));
+                f.instruction(&LocalGet(2));
+                f.instruction(&MemoryFill(0));
+            }
+            Helper::MemoryCopy { dst, src } => {
+                f = Function::new([]);
+                check(&mut f, dst, 0, 2);
+                check(&mut f, src, 1, 2);
+                address(&mut f, dst, 0);
+                address(&mut f, src, 1);
+                f.instruction(&LocalGet(2));
+                f.instruction(&MemoryCopy {
+                    src_mem: 0,
+                    dst_mem: 0,
+                });
+            }
+            Helper::MemoryInit { mem, data } => {
+                f = Function::new([]);
+                check(&mut f, mem, 0, 2);
+                address(&mut f, mem, 0);
+                f.instruction(&LocalGet(1));
+                f.instruction(&LocalGet(2));
+                f.instruction(&MemoryInit {
+                    mem: 0,
+                    data_index: data,
+                });
+            }
+            Helper::JsMemoryCopy { dst, src } => {
+                f = Function::new([]);
+                if dst < 1 {
+                    check(&mut f, 0, 0, 2);
+                }
+                if src < 1 {
+                    check(&mut f, 0, 1, 2);
+                }
+                f.instruction(&LocalGet(0));
+                f.instruction(&LocalGet(1));
+                f.instruction(&LocalGet(2));
+                f.instruction(&Call(
+                    self.augments[&(dst, AugmentedOp::MemoryCopy { src })],
+                ));
+            }
+        }
+        f.instruction(&End);
+        f
+    }
+
+    /// Translate a constant expression, of which only `ref.func` needs to be
+    /// remapped
+    fn const_expr(&self, expr: &wasmparser::ConstExpr) -> Result<wasm_encoder::ConstExpr> {
+        Ok(wasm_encoder::ConstExpr::raw(self.const_expr_bytes(expr)?))
+    }
+
+    fn const_expr_bytes(&self, expr: &wasmparser::ConstExpr) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut reader = expr.get_operators_reader();
+        while !reader.eof() {
+            let start = reader.original_position();
+            match reader.read()? {
+                Operator::RefFunc { function_index } => {
+                    Instruction::RefFunc(self.remap_func(function_index)).encode(&mut bytes)
+                }
+                Operator::End => {}
+                _ => bytes.extend_from_slice(&self.wasm[start..reader.original_position()]),
+            }
+        }
+        Ok(bytes)
+    }
+
     fn remap_func(&self, index: u32) -> u32 {
         if index < self.imported_funcs {
             index
@@ -460,30 +1290,134 @@ impl Augmenter<'_> {
     }
 
     fn remap_memory(&self, index: u32) -> u32 {
+        if self.merged.contains_key(&index) {
+            return 0;
+        }
         assert!(index < 1);
         index
     }
 }
 
-fn valtype(ty: wasmparser::ValType) -> wasm_encoder::ValType {
-    match ty {
+fn valtype(ty: wasmparser::ValType) -> Result<wasm_encoder::ValType> {
+    Ok(match ty {
         wasmparser::ValType::I32 => wasm_encoder::ValType::I32,
         wasmparser::ValType::I64 => wasm_encoder::ValType::I64,
         wasmparser::ValType::F32 => wasm_encoder::ValType::F32,
         wasmparser::ValType::F64 => wasm_encoder::ValType::F64,
         wasmparser::ValType::V128 => wasm_encoder::ValType::V128,
-        wasmparser::ValType::Ref(_) => unimplemented!(),
+        wasmparser::ValType::Ref(ty) => wasm_encoder::ValType::Ref(reftype(ty)?),
+    })
+}
+
+fn reftype(ty: wasmparser::RefType) -> Result<wasm_encoder::RefType> {
+    Ok(wasm_encoder::RefType {
+        nullable: ty.is_nullable(),
+        heap_type: heapty(ty.heap_type())?,
+    })
+}
+
+fn heapty(ty: wasmparser::HeapType) -> Result<wasm_encoder::HeapType> {
+    use wasmparser::AbstractHeapType::*;
+    Ok(match ty {
+        wasmparser::HeapType::Abstract { shared, ty } => wasm_encoder::HeapType::Abstract {
+            shared,
+            ty: match ty {
+                Func => wasm_encoder::AbstractHeapType::Func,
+                Extern => wasm_encoder::AbstractHeapType::Extern,
+                Any => wasm_encoder::AbstractHeapType::Any,
+                None => wasm_encoder::AbstractHeapType::None,
+                NoExtern => wasm_encoder::AbstractHeapType::NoExtern,
+                NoFunc => wasm_encoder::AbstractHeapType::NoFunc,
+                Eq => wasm_encoder::AbstractHeapType::Eq,
+                Struct => wasm_encoder::AbstractHeapType::Struct,
+                Array => wasm_encoder::AbstractHeapType::Array,
+                I31 => wasm_encoder::AbstractHeapType::I31,
+                Exn => wasm_encoder::AbstractHeapType::Exn,
+                NoExn => wasm_encoder::AbstractHeapType::NoExn,
+            },
+        },
+        wasmparser::HeapType::Concrete(index) => match index.as_module_index() {
+            Some(module_index) => wasm_encoder::HeapType::Concrete(module_index),
+            _ => bail!("heap type {index} is not the index of a module type"),
+        },
+    })
+}
+
+fn globaltype(ty: wasmparser::GlobalType) -> Result<wasm_encoder::GlobalType> {
+    Ok(wasm_encoder::GlobalType {
+        val_type: valtype(ty.content_type)?,
+        mutable: ty.mutable,
+        shared: ty.shared,
+    })
+}
+
+fn memorytype(ty: wasmparser::MemoryType) -> wasm_encoder::MemoryType {
+    wasm_encoder::MemoryType {
+        maximum: ty.maximum,
+        minimum: ty.initial,
+        memory64: ty.memory64,
+        shared: ty.shared,
+        page_size_log2: ty.page_size_log2,
     }
 }
 
-struct CollectMemOps<'a, 'b>(&'a mut Augmenter<'b>);
+fn tabletype(ty: wasmparser::TableType) -> Result<wasm_encoder::TableType> {
+    Ok(wasm_encoder::TableType {
+        element_type: reftype(ty.element_type)?,
+        table64: ty.table64,
+        minimum: ty.initial,
+        maximum: ty.maximum,
+        shared: ty.shared,
+    })
+}
+
+fn tagtype(ty: wasmparser::TagType) -> wasm_encoder::TagType {
+    wasm_encoder::TagType {
+        kind: wasm_encoder::TagKind::Exception,
+        func_type_idx: ty.func_type_idx,
+    }
+}
+
+struct CollectMemOps<'a, 'b> {
+    augmenter: &'a mut Augmenter<'b>,
+    /// Whether scratch locals are needed to split SIMD loads and stores
+    scratch: bool,
+}
+
+impl CollectMemOps<'_, '_> {
+    /// Register a load or store, which for merged memories is rewritten in
+    /// the module using scratch locals
+    fn access(&mut self, mem: u32, op: AugmentedOp) {
+        if self.augmenter.merged.contains_key(&mem) {
+            self.scratch = true;
+            return;
+        }
+        self.augmenter.augment_op(mem, op);
+    }
+
+    /// Register the scalar operations that a SIMD load or store on memories
+    /// other than memory 0 is split into
+    fn simd(&mut self, mem: u32, ops: &[AugmentedOp]) {
+        if mem == 0 && !self.augmenter.merged.contains_key(&0) {
+            return;
+        }
+        self.scratch = true;
+        if self.augmenter.merged.contains_key(&mem) {
+            return;
+        }
+        for op in ops {
+            self.augmenter.augment_op(mem, *op);
+        }
+    }
+}
 
 macro_rules! define_visit {
     ($(@$p:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
         $(
             #[allow(unreachable_code)]
-            fn $visit(&mut self $( $( ,$arg: $argty)* )?) {
+            fn $visit(&mut self $( $( ,$arg: $argty)* )?) -> Result<()> {
                 define_visit!(augment self $op $($($arg)*)?);
+                Ok(())
             }
         )*
     };
@@ -491,72 +1425,326 @@ macro_rules! define_visit {
     // List of instructions that are augmented which register the memory index
     // and the relevant augmentation operation.
     (augment $self:ident I32Load $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Load);
+        $self.access($memarg.memory, AugmentedOp::I32Load);
     };
     (augment $self:ident I64Load $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I64Load);
+        $self.access($memarg.memory, AugmentedOp::I64Load);
     };
     (augment $self:ident F32Load $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::F32Load);
+        $self.access($memarg.memory, AugmentedOp::F32Load);
     };
     (augment $self:ident F64Load $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::F64Load);
+        $self.access($memarg.memory, AugmentedOp::F64Load);
     };
     (augment $self:ident I32Load8U $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Load8U);
+        $self.access($memarg.memory, AugmentedOp::I32Load8U);
     };
     (augment $self:ident I32Load8S $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Load8S);
+        $self.access($memarg.memory, AugmentedOp::I32Load8S);
     };
     (augment $self:ident I32Load16U $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Load16U);
+        $self.access($memarg.memory, AugmentedOp::I32Load16U);
     };
     (augment $self:ident I32Load16S $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Load16S);
+        $self.access($memarg.memory, AugmentedOp::I32Load16S);
+    };
+    (augment $self:ident I64Load8U $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Load8U);
+    };
+    (augment $self:ident I64Load8S $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Load8S);
+    };
+    (augment $self:ident I64Load16U $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Load16U);
+    };
+    (augment $self:ident I64Load16S $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Load16S);
+    };
+    (augment $self:ident I64Load32U $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Load32U);
+    };
+    (augment $self:ident I64Load32S $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Load32S);
     };
     (augment $self:ident I32Store $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Store);
+        $self.access($memarg.memory, AugmentedOp::I32Store);
     };
     (augment $self:ident I64Store $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I64Store);
+        $self.access($memarg.memory, AugmentedOp::I64Store);
     };
     (augment $self:ident F32Store $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::F32Store);
+        $self.access($memarg.memory, AugmentedOp::F32Store);
     };
     (augment $self:ident F64Store $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::F64Store);
+        $self.access($memarg.memory, AugmentedOp::F64Store);
     };
     (augment $self:ident I32Store8 $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Store8);
+        $self.access($memarg.memory, AugmentedOp::I32Store8);
     };
     (augment $self:ident I32Store16 $memarg:ident) => {
-        $self.0.augment_op($memarg.memory, AugmentedOp::I32Store16);
+        $self.access($memarg.memory, AugmentedOp::I32Store16);
+    };
+    (augment $self:ident I64Store8 $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Store8);
+    };
+    (augment $self:ident I64Store16 $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Store16);
+    };
+    (augment $self:ident I64Store32 $memarg:ident) => {
+        $self.access($memarg.memory, AugmentedOp::I64Store32);
     };
     (augment $self:ident MemorySize $mem:ident) => {
-        $self.0.augment_op($mem, AugmentedOp::MemorySize);
+        $self.augmenter.augment_op($mem, AugmentedOp::MemorySize);
+    };
+    (augment $self:ident MemoryGrow $mem:ident) => {
+        if $self.augmenter.merged.contains_key(&$mem) {
+            $self.augmenter.helper(Helper::MemoryGrow($mem));
+        }
+        $self.augmenter.augment_op($mem, AugmentedOp::MemoryGrow);
+    };
+    (augment $self:ident MemoryFill $mem:ident) => {
+        if $self.augmenter.merged.contains_key(&$mem) {
+            $self.augmenter.helper(Helper::MemoryFill($mem));
+        }
+        $self.augmenter.augment_op($mem, AugmentedOp::MemoryFill);
+    };
+    (augment $self:ident MemoryCopy $dst_mem:ident $src_mem:ident) => {
+        let merged = |mem| $self.augmenter.merged.contains_key(&mem);
+        if merged($dst_mem) && merged($src_mem) {
+            $self.augmenter.helper(Helper::MemoryCopy { dst: $dst_mem, src: $src_mem });
+        } else if $dst_mem > 0 || $src_mem > 0 {
+            let checked = merged(0) && ($dst_mem < 1 || $src_mem < 1);
+            $self.augmenter.augment_op($dst_mem, AugmentedOp::MemoryCopy { src: $src_mem });
+            if checked {
+                $self.augmenter.helper(Helper::JsMemoryCopy { dst: $dst_mem, src: $src_mem });
+            }
+        }
+    };
+    (augment $self:ident MemoryInit $data_index:ident $mem:ident) => {
+        if $self.augmenter.merged.contains_key(&$mem) {
+            $self.augmenter.helper(Helper::MemoryInit { mem: $mem, data: $data_index });
+        } else if $mem > 0 {
+            $self.augmenter.js_data.insert($data_index);
+            $self.augmenter.augment_op($mem, AugmentedOp::MemoryInit($data_index));
+        }
+    };
+    (augment $self:ident DataDrop $data_index:ident) => {
+        $self.augmenter.data_dropped.insert($data_index);
+    };
+
+    // SIMD loads and stores are split into scalar loads and stores
+    (augment $self:ident V128Load $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Store $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Store]);
+    };
+    (augment $self:ident V128Load8x8S $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load8x8U $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load16x4S $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load16x4U $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load32x2S $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load32x2U $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load8Splat $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load8U]);
+    };
+    (augment $self:ident V128Load16Splat $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load16U]);
+    };
+    (augment $self:ident V128Load32Splat $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load]);
+    };
+    (augment $self:ident V128Load64Splat $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load32Zero $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load]);
+    };
+    (augment $self:ident V128Load64Zero $memarg:ident) => {
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Load8Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load8U]);
+    };
+    (augment $self:ident V128Load16Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load16U]);
+    };
+    (augment $self:ident V128Load32Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I32Load]);
+    };
+    (augment $self:ident V128Load64Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I64Load]);
+    };
+    (augment $self:ident V128Store8Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I32Store8]);
+    };
+    (augment $self:ident V128Store16Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I32Store16]);
+    };
+    (augment $self:ident V128Store32Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I32Store]);
+    };
+    (augment $self:ident V128Store64Lane $memarg:ident $lane:ident) => {
+        let _ = $lane;
+        $self.simd($memarg.memory, &[AugmentedOp::I64Store]);
     };
 
-    // Catch-all which asserts that none of the `$arg` looks like a memory
-    // index ty catch any missing instructions from the list above.
+    // Catch-all which checks that none of the `$arg` refers to a memory other
+    // than memory 0, as the remaining instructions (atomics and
+    // `memory.discard`) can't be augmented.
     (augment $self:ident $op:ident $($arg:ident)*) => {
         $(
-            define_visit!(assert_not_mem $op $arg);
+            define_visit!(assert_not_mem $op $arg $arg);
         )*
     };
 
-    (assert_not_mem $op:ident mem) => {panic!(concat!("missed case ", stringify!($op)));};
-    (assert_not_mem $op:ident src_mem) => {panic!(concat!("missed case ", stringify!($op)));};
-    (assert_not_mem $op:ident dst_mem) => {panic!(concat!("missed case ", stringify!($op)));};
-    (assert_not_mem $op:ident memarg) => {panic!(concat!("missed case ", stringify!($op)));};
-    (assert_not_mem $op:ident $other:ident) => {};
+    (assert_not_mem $op:ident mem $mem:ident) => {
+        if $mem > 0 {
+            bail!(concat!("unsupported instruction ", stringify!($op), " on a memory other than the first without multi-memory"));
+        }
+    };
+    (assert_not_mem $op:ident src_mem $mem:ident) => {panic!(concat!("missed case ", stringify!($op)));};
+    (assert_not_mem $op:ident dst_mem $mem:ident) => {panic!(concat!("missed case ", stringify!($op)));};
+    (assert_not_mem $op:ident memarg $memarg:ident) => {
+        if $memarg.memory > 0 {
+            bail!(concat!("unsupported instruction ", stringify!($op), " on a memory other than the first without multi-memory"));
+        }
+    };
+    (assert_not_mem $op:ident $other:ident $arg:ident) => {let _ = $arg;};
 }
 
 impl<'a> VisitOperator<'a> for CollectMemOps<'_, 'a> {
-    type Output = ();
+    type Output = Result<()>;
 
     wasmparser::for_each_operator!(define_visit);
 }
 
+/// Visitor of the instructions accessing memory other than loads and stores,
+/// which can't be checked against the size of memory 0 when memories are
+/// merged into it: the atomic instructions and `memory.discard`
+struct UncheckedAccess;
+
+macro_rules! define_unchecked_access {
+    ($(@$p:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        $(
+            fn $visit(&mut self $( $( ,$arg: $argty)* )?) -> bool {
+                $( $( let _ = $arg; )* )?
+                define_unchecked_access!(unchecked $p $op)
+            }
+        )*
+    };
+
+    (unchecked threads AtomicFence) => { false };
+    (unchecked threads $op:ident) => { true };
+    (unchecked memory_control $op:ident) => { true };
+    (unchecked $p:ident $op:ident) => { false };
+}
+
+impl<'a> VisitOperator<'a> for UncheckedAccess {
+    type Output = bool;
+
+    wasmparser::for_each_operator!(define_unchecked_access);
+}
+
+impl Helper {
+    fn encode_type(&self, section: &mut TypeSection) {
+        use wasm_encoder::ValType::*;
+        match self {
+            Helper::MemoryGrow(_) => section.function([I32], [I32]),
+            Helper::MemoryFill(_)
+            | Helper::MemoryCopy { .. }
+            | Helper::MemoryInit { .. }
+            | Helper::JsMemoryCopy { .. } => section.function([I32, I32, I32], []),
+        };
+    }
+}
+
+/// Types of the scratch locals of functions: an address, then a value of
+/// each type
+const SCRATCH: [wasm_encoder::ValType; 6] = [
+    wasm_encoder::ValType::I32,
+    wasm_encoder::ValType::I32,
+    wasm_encoder::ValType::I64,
+    wasm_encoder::ValType::F32,
+    wasm_encoder::ValType::F64,
+    wasm_encoder::ValType::V128,
+];
+
+/// Returns the memory argument of a load or store, with the number of bytes
+/// accessed and the type of the value stored, if any.
+fn access(op: &Operator) -> Option<(wasmparser::MemArg, u64, Option<wasmparser::ValType>)> {
+    use wasmparser::ValType::*;
+    Some(match *op {
+        Operator::I32Load { memarg } | Operator::F32Load { memarg } => (memarg, 4, None),
+        Operator::I64Load { memarg } | Operator::F64Load { memarg } => (memarg, 8, None),
+        Operator::I32Load8S { memarg }
+        | Operator::I32Load8U { memarg }
+        | Operator::I64Load8S { memarg }
+        | Operator::I64Load8U { memarg } => (memarg, 1, None),
+        Operator::I32Load16S { memarg }
+        | Operator::I32Load16U { memarg }
+        | Operator::I64Load16S { memarg }
+        | Operator::I64Load16U { memarg } => (memarg, 2, None),
+        Operator::I64Load32S { memarg } | Operator::I64Load32U { memarg } => (memarg, 4, None),
+        Operator::I32Store { memarg } => (memarg, 4, Some(I32)),
+        Operator::I32Store8 { memarg } => (memarg, 1, Some(I32)),
+        Operator::I32Store16 { memarg } => (memarg, 2, Some(I32)),
+        Operator::I64Store { memarg } => (memarg, 8, Some(I64)),
+        Operator::I64Store8 { memarg } => (memarg, 1, Some(I64)),
+        Operator::I64Store16 { memarg } => (memarg, 2, Some(I64)),
+        Operator::I64Store32 { memarg } => (memarg, 4, Some(I64)),
+        Operator::F32Store { memarg } => (memarg, 4, Some(F32)),
+        Operator::F64Store { memarg } => (memarg, 8, Some(F64)),
+        Operator::V128Load { memarg } => (memarg, 16, None),
+        Operator::V128Store { memarg } => (memarg, 16, Some(V128)),
+        Operator::V128Load8x8S { memarg }
+        | Operator::V128Load8x8U { memarg }
+        | Operator::V128Load16x4S { memarg }
+        | Operator::V128Load16x4U { memarg }
+        | Operator::V128Load32x2S { memarg }
+        | Operator::V128Load32x2U { memarg }
+        | Operator::V128Load64Splat { memarg }
+        | Operator::V128Load64Zero { memarg } => (memarg, 8, None),
+        Operator::V128Load8Splat { memarg } => (memarg, 1, None),
+        Operator::V128Load16Splat { memarg } => (memarg, 2, None),
+        Operator::V128Load32Splat { memarg } | Operator::V128Load32Zero { memarg } => {
+            (memarg, 4, None)
+        }
+        Operator::V128Load8Lane { memarg, .. } | Operator::V128Store8Lane { memarg, .. } => {
+            (memarg, 1, Some(V128))
+        }
+        Operator::V128Load16Lane { memarg, .. } | Operator::V128Store16Lane { memarg, .. } => {
+            (memarg, 2, Some(V128))
+        }
+        Operator::V128Load32Lane { memarg, .. } | Operator::V128Store32Lane { memarg, .. } => {
+            (memarg, 4, Some(V128))
+        }
+        Operator::V128Load64Lane { memarg, .. } | Operator::V128Store64Lane { memarg, .. } => {
+            (memarg, 8, Some(V128))
+        }
+        _ => return None,
+    })
+}
+
 impl AugmentedOp {
     fn encode_type(&self, section: &mut TypeSection) {
         use wasm_encoder::ValType::*;
@@ -571,7 +1759,13 @@ impl AugmentedOp {
             | AugmentedOp::I32Load16S => {
                 section.function([I32, I32], [I32]);
             }
-            AugmentedOp::I64Load => {
+            AugmentedOp::I64Load
+            | AugmentedOp::I64Load8U
+            | AugmentedOp::I64Load8S
+            | AugmentedOp::I64Load16U
+            | AugmentedOp::I64Load16S
+            | AugmentedOp::I64Load32U
+            | AugmentedOp::I64Load32S => {
                 section.function([I32, I32], [I64]);
             }
             AugmentedOp::F32Load => {
@@ -586,7 +1780,10 @@ impl AugmentedOp {
             AugmentedOp::I32Store | AugmentedOp::I32Store8 | AugmentedOp::I32Store16 => {
                 section.function([I32, I32, I32], []);
             }
-            AugmentedOp::I64Store => {
+            AugmentedOp::I64Store
+            | AugmentedOp::I64Store8
+            | AugmentedOp::I64Store16
+            | AugmentedOp::I64Store32 => {
                 section.function([I32, I64, I32], []);
             }
             AugmentedOp::F32Store => {
@@ -599,6 +1796,20 @@ impl AugmentedOp {
             AugmentedOp::MemorySize => {
                 section.function([], [I32]);
             }
+            AugmentedOp::MemoryGrow => {
+                section.function([I32], [I32]);
+            }
+
+            // Bulk memory operations take the same arguments as the
+            // instruction.
+            AugmentedOp::MemoryFill
+            | AugmentedOp::MemoryCopy { .. }
+            | AugmentedOp::MemoryInit(_) => {
+                section.function([I32, I32, I32], []);
+            }
+            AugmentedOp::DataDrop(_) => {
+                section.function([], []);
+            }
         }
     }
 }
@@ -606,6 +1817,9 @@ impl AugmentedOp {
 struct Translator<'a, 'b> {
     func: &'a mut wasm_encoder::Function,
     augmenter: &'a Augmenter<'b>,
+    /// Index of the first of the scratch locals, of types `i32`, `i64` and
+    /// `v128`, if the function has them
+    scratch: u32,
 }
 
 // Helper macro to create a wasmparser visitor which will translate each
@@ -617,11 +1831,12 @@ macro_rules! define_translate {
     ($(@$p:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
         $(
             #[allow(dropping_copy_types)]
-            fn $visit(&mut self $(, $($arg: $argty),*)?)  {
+            fn $visit(&mut self $(, $($arg: $argty),*)?) -> Result<()> {
                 #[allow(unused_imports)]
                 use wasm_encoder::Instruction::*;
 
-                define_translate!(translate self $op $($($arg)*)?)
+                define_translate!(translate self $op $($($arg)*)?);
+                Ok(())
             }
         )*
     };
@@ -647,6 +1862,24 @@ macro_rules! define_translate {
     (translate $self:ident I64Load $memarg:ident) => {{
         $self.augment(AugmentedOp::I64Load, I64Load, $memarg)
     }};
+    (translate $self:ident I64Load8U $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Load8U, I64Load8U, $memarg)
+    }};
+    (translate $self:ident I64Load8S $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Load8S, I64Load8S, $memarg)
+    }};
+    (translate $self:ident I64Load16U $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Load16U, I64Load16U, $memarg)
+    }};
+    (translate $self:ident I64Load16S $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Load16S, I64Load16S, $memarg)
+    }};
+    (translate $self:ident I64Load32U $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Load32U, I64Load32U, $memarg)
+    }};
+    (translate $self:ident I64Load32S $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Load32S, I64Load32S, $memarg)
+    }};
     (translate $self:ident F32Load $memarg:ident) => {{
         $self.augment(AugmentedOp::F32Load, F32Load, $memarg)
     }};
@@ -665,6 +1898,15 @@ macro_rules! define_translate {
     (translate $self:ident I64Store $memarg:ident) => {{
         $self.augment(AugmentedOp::I64Store, I64Store, $memarg)
     }};
+    (translate $self:ident I64Store8 $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Store8, I64Store8, $memarg)
+    }};
+    (translate $self:ident I64Store16 $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Store16, I64Store16, $memarg)
+    }};
+    (translate $self:ident I64Store32 $memarg:ident) => {{
+        $self.augment(AugmentedOp::I64Store32, I64Store32, $memarg)
+    }};
     (translate $self:ident F32Store $memarg:ident) => {{
         $self.augment(AugmentedOp::F32Store, F32Store, $memarg)
     }};
@@ -672,12 +1914,179 @@ macro_rules! define_translate {
         $self.augment(AugmentedOp::F64Store, F64Store, $memarg)
     }};
     (translate $self:ident MemorySize $mem:ident) => {{
-        if $mem < 1 {
+        if let Some(merged) = $self.augmenter.merged.get(&$mem) {
+            $self.func.instruction(&GlobalGet(merged.size));
+            $self.func.instruction(&I64Const(16));
+            $self.func.instruction(&I64ShrU);
+            $self.func.instruction(&I32WrapI64);
+        } else if $mem < 1 {
             $self.func.instruction(&MemorySize($mem));
         } else {
-            let mem = MemoryIndex::from_u32($mem - 1);
-            let func = $self.augmenter.augments[&(mem, AugmentedOp::MemorySize)];
-            $self.func.instruction(&Call(func));
+            $self.call_augment($mem, AugmentedOp::MemorySize);
+        }
+    }};
+    (translate $self:ident MemoryGrow $mem:ident) => {{
+        if $self.augmenter.merged.contains_key(&$mem) {
+            $self.call_helper(Helper::MemoryGrow($mem));
+        } else if $mem < 1 {
+            $self.func.instruction(&MemoryGrow($mem));
+        } else {
+            $self.call_augment($mem, AugmentedOp::MemoryGrow);
+        }
+    }};
+    (translate $self:ident MemoryFill $mem:ident) => {{
+        if $self.augmenter.merged.contains_key(&$mem) {
+            $self.call_helper(Helper::MemoryFill($mem));
+        } else if $mem < 1 {
+            $self.func.instruction(&MemoryFill($mem));
+        } else {
+            $self.call_augment($mem, AugmentedOp::MemoryFill);
+        }
+    }};
+    (translate $self:ident MemoryCopy $dst_mem:ident $src_mem:ident) => {{
+        let merged = |mem| $self.augmenter.merged.contains_key(&mem);
+        if merged($dst_mem) && merged($src_mem) {
+            $self.call_helper(Helper::MemoryCopy { dst: $dst_mem, src: $src_mem });
+        } else if $dst_mem < 1 && $src_mem < 1 {
+            $self.func.instruction(&MemoryCopy { src_mem: $src_mem, dst_mem: $dst_mem });
+        } else if merged(0) && ($dst_mem < 1 || $src_mem < 1) {
+            $self.call_helper(Helper::JsMemoryCopy { dst: $dst_mem, src: $src_mem });
+        } else {
+            $self.call_augment($dst_mem, AugmentedOp::MemoryCopy { src: $src_mem });
+        }
+    }};
+    (translate $self:ident MemoryInit $data_index:ident $mem:ident) => {{
+        if $self.augmenter.merged.contains_key(&$mem) {
+            $self.call_helper(Helper::MemoryInit { mem: $mem, data: $data_index });
+        } else if $mem < 1 {
+            $self.func.instruction(&MemoryInit { mem: $mem, data_index: $data_index });
+        } else {
+            $self.call_augment($mem, AugmentedOp::MemoryInit($data_index));
+        }
+    }};
+    (translate $self:ident DataDrop $data_index:ident) => {{
+        $self.func.instruction(&DataDrop($data_index));
+        if $self.augmenter.augments.contains_key(&(0, AugmentedOp::DataDrop($data_index))) {
+            $self.call_augment(0, AugmentedOp::DataDrop($data_index));
+        }
+    }};
+
+    // SIMD loads and stores of memories other than memory 0 are split into
+    // scalar loads and stores, as `v128` values can't be passed to JS.
+    (translate $self:ident V128Load $memarg:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load($self.memarg($memarg)));
+        } else {
+            $self.v128_load($memarg);
+        }
+    }};
+    (translate $self:ident V128Store $memarg:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Store($self.memarg($memarg)));
+        } else {
+            $self.v128_store($memarg);
+        }
+    }};
+    (translate $self:ident V128Load8x8S $memarg:ident) => {{
+        $self.v128_load_extend(V128Load8x8S, I16x8ExtendLowI8x16S, $memarg)
+    }};
+    (translate $self:ident V128Load8x8U $memarg:ident) => {{
+        $self.v128_load_extend(V128Load8x8U, I16x8ExtendLowI8x16U, $memarg)
+    }};
+    (translate $self:ident V128Load16x4S $memarg:ident) => {{
+        $self.v128_load_extend(V128Load16x4S, I32x4ExtendLowI16x8S, $memarg)
+    }};
+    (translate $self:ident V128Load16x4U $memarg:ident) => {{
+        $self.v128_load_extend(V128Load16x4U, I32x4ExtendLowI16x8U, $memarg)
+    }};
+    (translate $self:ident V128Load32x2S $memarg:ident) => {{
+        $self.v128_load_extend(V128Load32x2S, I64x2ExtendLowI32x4S, $memarg)
+    }};
+    (translate $self:ident V128Load32x2U $memarg:ident) => {{
+        $self.v128_load_extend(V128Load32x2U, I64x2ExtendLowI32x4U, $memarg)
+    }};
+    (translate $self:ident V128Load8Splat $memarg:ident) => {{
+        $self.v128_load_splat(V128Load8Splat, AugmentedOp::I32Load8U, I8x16Splat, $memarg)
+    }};
+    (translate $self:ident V128Load16Splat $memarg:ident) => {{
+        $self.v128_load_splat(V128Load16Splat, AugmentedOp::I32Load16U, I16x8Splat, $memarg)
+    }};
+    (translate $self:ident V128Load32Splat $memarg:ident) => {{
+        $self.v128_load_splat(V128Load32Splat, AugmentedOp::I32Load, I32x4Splat, $memarg)
+    }};
+    (translate $self:ident V128Load64Splat $memarg:ident) => {{
+        $self.v128_load_splat(V128Load64Splat, AugmentedOp::I64Load, I64x2Splat, $memarg)
+    }};
+    (translate $self:ident V128Load32Zero $memarg:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load32Zero($self.memarg($memarg)));
+        } else {
+            $self.func.instruction(&V128Const(0));
+            $self.v128_load_lane(AugmentedOp::I32Load, I32x4ReplaceLane(0), $memarg);
+        }
+    }};
+    (translate $self:ident V128Load64Zero $memarg:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load64Zero($self.memarg($memarg)));
+        } else {
+            $self.func.instruction(&V128Const(0));
+            $self.v128_load_lane(AugmentedOp::I64Load, I64x2ReplaceLane(0), $memarg);
+        }
+    }};
+    (translate $self:ident V128Load8Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load8Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_load_lane(AugmentedOp::I32Load8U, I8x16ReplaceLane($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Load16Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load16Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_load_lane(AugmentedOp::I32Load16U, I16x8ReplaceLane($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Load32Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load32Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_load_lane(AugmentedOp::I32Load, I32x4ReplaceLane($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Load64Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Load64Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_load_lane(AugmentedOp::I64Load, I64x2ReplaceLane($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Store8Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Store8Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_store_lane(AugmentedOp::I32Store8, I8x16ExtractLaneU($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Store16Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Store16Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_store_lane(AugmentedOp::I32Store16, I16x8ExtractLaneU($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Store32Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Store32Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_store_lane(AugmentedOp::I32Store, I32x4ExtractLane($lane), $memarg);
+        }
+    }};
+    (translate $self:ident V128Store64Lane $memarg:ident $lane:ident) => {{
+        if $self.native($memarg.memory) {
+            $self.func.instruction(&V128Store64Lane { memarg: $self.memarg($memarg), lane: $lane });
+        } else {
+            $self.v128_store_lane(AugmentedOp::I64Store, I64x2ExtractLane($lane), $memarg);
         }
     }};
 
@@ -713,10 +2122,7 @@ macro_rules! define_translate {
     (mk F32Const $v:ident) => (F32Const(f32::from_bits($v.bits())));
     (mk F64Const $v:ident) => (F64Const(f64::from_bits($v.bits())));
     (mk V128Const $v:ident) => (V128Const($v.i128()));
-    (mk TryTable $v:ident) => (unimplemented_try_table());
-    (mk MemoryGrow $($x:tt)*) => ({
-        if true { unimplemented!() } Nop
-    });
+    (mk TryTable $v:ident) => (TryTable($v.0, $v.1.into()));
 
     // Catch-all for the translation of one payload argument which is typically
     // represented as a tuple-enum in wasm-encoder.
@@ -730,8 +2136,8 @@ macro_rules! define_translate {
     // `define_visit` macro above.
     (map $self:ident $arg:ident memarg) => {$self.memarg($arg)};
     (map $self:ident $arg:ident ordering) => {$self.ordering($arg)};
-    (map $self:ident $arg:ident blockty) => {$self.blockty($arg)};
-    (map $self:ident $arg:ident hty) => {$self.heapty($arg)};
+    (map $self:ident $arg:ident blockty) => {$self.blockty($arg)?};
+    (map $self:ident $arg:ident hty) => {heapty($arg)?};
     (map $self:ident $arg:ident tag_index) => {$arg};
     (map $self:ident $arg:ident relative_depth) => {$arg};
     (map $self:ident $arg:ident function_index) => {$self.augmenter.remap_func($arg)};
@@ -744,7 +2150,7 @@ macro_rules! define_translate {
     (map $self:ident $arg:ident src_table) => {$arg};
     (map $self:ident $arg:ident dst_table) => {$arg};
     (map $self:ident $arg:ident type_index) => {$arg};
-    (map $self:ident $arg:ident ty) => {valtype($arg)};
+    (map $self:ident $arg:ident ty) => {valtype($arg)?};
     (map $self:ident $arg:ident local_index) => {$arg};
     (map $self:ident $arg:ident lane) => {$arg};
     (map $self:ident $arg:ident lanes) => {$arg};
@@ -757,21 +2163,21 @@ macro_rules! define_translate {
         $arg.targets().map(|i| i.unwrap()).collect::<Vec<_>>().into(),
         $arg.default(),
     ));
-    (map $self:ident $arg:ident try_table) => {$arg};
-    (map $self:ident $arg:ident struct_type_index) => {$self.remap(Item::Type, $arg).unwrap()};
+    (map $self:ident $arg:ident try_table) => {$self.try_table($arg)?};
+    (map $self:ident $arg:ident struct_type_index) => {$self.remap(Item::Type, $arg)?};
     (map $self:ident $arg:ident field_index) => {$arg};
-    (map $self:ident $arg:ident array_type_index) => {$self.remap(Item::Type, $arg).unwrap()};
+    (map $self:ident $arg:ident array_type_index) => {$self.remap(Item::Type, $arg)?};
     (map $self:ident $arg:ident array_size) => {$arg};
-    (map $self:ident $arg:ident array_data_index) => ($self.remap(Item::Data, $arg).unwrap());
-    (map $self:ident $arg:ident array_elem_index) => ($self.remap(Item::Element, $arg).unwrap());
-    (map $self:ident $arg:ident array_type_index_dst) => ($self.remap(Item::Type, $arg).unwrap());
-    (map $self:ident $arg:ident array_type_index_src) => ($self.remap(Item::Type, $arg).unwrap());
-    (map $self:ident $arg:ident from_ref_type) => ($self.refty(&$arg).unwrap());
-    (map $self:ident $arg:ident to_ref_type) => ($self.refty(&$arg).unwrap());
+    (map $self:ident $arg:ident array_data_index) => ($self.remap(Item::Data, $arg)?);
+    (map $self:ident $arg:ident array_elem_index) => ($self.remap(Item::Element, $arg)?);
+    (map $self:ident $arg:ident array_type_index_dst) => ($self.remap(Item::Type, $arg)?);
+    (map $self:ident $arg:ident array_type_index_src) => ($self.remap(Item::Type, $arg)?);
+    (map $self:ident $arg:ident from_ref_type) => ($self.refty(&$arg)?);
+    (map $self:ident $arg:ident to_ref_type) => ($self.refty(&$arg)?);
 }
 
 impl<'a> VisitOperator<'a> for Translator<'_, 'a> {
-    type Output = ();
+    type Output = Result<()>;
 
     wasmparser::for_each_operator!(define_translate);
 }
@@ -793,18 +2199,33 @@ impl Translator<'_, '_> {
         let _ = item;
         Ok(idx)
     }
-    fn refty(&mut self, _ty: &wasmparser::RefType) -> Result<wasm_encoder::RefType> {
-        unimplemented!()
+    fn refty(&mut self, ty: &wasmparser::RefType) -> Result<wasm_encoder::RefType> {
+        reftype(*ty)
     }
-    fn blockty(&self, ty: wasmparser::BlockType) -> wasm_encoder::BlockType {
-        match ty {
+    fn blockty(&self, ty: wasmparser::BlockType) -> Result<wasm_encoder::BlockType> {
+        Ok(match ty {
             wasmparser::BlockType::Empty => wasm_encoder::BlockType::Empty,
-            wasmparser::BlockType::Type(t) => wasm_encoder::BlockType::Result(valtype(t)),
+            wasmparser::BlockType::Type(t) => wasm_encoder::BlockType::Result(valtype(t)?),
             wasmparser::BlockType::FuncType(i) => wasm_encoder::BlockType::FunctionType(i),
-        }
+        })
     }
-    fn heapty(&self, _ty: wasmparser::HeapType) -> wasm_encoder::HeapType {
-        unimplemented!()
+    fn try_table(
+        &self,
+        try_table: wasmparser::TryTable,
+    ) -> Result<(wasm_encoder::BlockType, Vec<wasm_encoder::Catch>)> {
+        let catches = try_table
+            .catches
+            .into_iter()
+            .map(|catch| match catch {
+                wasmparser::Catch::One { tag, label } => wasm_encoder::Catch::One { tag, label },
+                wasmparser::Catch::OneRef { tag, label } => {
+                    wasm_encoder::Catch::OneRef { tag, label }
+                }
+                wasmparser::Catch::All { label } => wasm_encoder::Catch::All { label },
+                wasmparser::Catch::AllRef { label } => wasm_encoder::Catch::AllRef { label },
+            })
+            .collect();
+        Ok((self.blockty(try_table.ty)?, catches))
     }
 
     fn memarg(&self, ty: wasmparser::MemArg) -> wasm_encoder::MemArg {
@@ -822,20 +2243,188 @@ impl Translator<'_, '_> {
         }
     }
 
+    /// Whether accesses of `mem` are native, for memory 0 and the memories
+    /// merged into it
+    fn native(&self, mem: u32) -> bool {
+        mem < 1 || self.augmenter.merged.contains_key(&mem)
+    }
+
+    fn call_helper(&mut self, helper: Helper) {
+        let func = self.augmenter.helper_func(helper);
+        self.func
+            .instruction(&wasm_encoder::Instruction::Call(func));
+    }
+
+    /// Check a load or store of a merged memory against its size, and move
+    /// its address to the range of the memory in memory 0, keeping the value
+    /// stored aside in a scratch local meanwhile
+    fn merged_access(
+        &mut self,
+        memarg: wasmparser::MemArg,
+        width: u64,
+        value: Option<wasmparser::ValType>,
+    ) -> Result<()> {
+        use wasm_encoder::Instruction::*;
+        let merged = &self.augmenter.merged[&memarg.memory];
+        let addr = self.scratch;
+        let value = match value {
+            Some(ty) => Some(self.scratch_local(valtype(ty)?)),
+            None => None,
+        };
+        if let Some(value) = value {
+            self.func.instruction(&LocalSet(value));
+        }
+        self.func.instruction(&LocalTee(addr));
+        self.func.instruction(&I64ExtendI32U);
+        self.func
+            .instruction(&I64Const((memarg.offset + width) as i64));
+        self.func.instruction(&I64Add);
+        self.func.instruction(&GlobalGet(merged.size));
+        self.func.instruction(&I64GtU);
+        self.func.instruction(&If(wasm_encoder::BlockType::Empty));
+        self.func.instruction(&Unreachable);
+        self.func.instruction(&End);
+        self.func.instruction(&LocalGet(addr));
+        if merged.base > 0 {
+            self.func.instruction(&I32Const((merged.base << 16) as i32));
+            self.func.instruction(&I32Add);
+        }
+        if let Some(value) = value {
+            self.func.instruction(&LocalGet(value));
+        }
+        Ok(())
+    }
+
+    /// Index of the scratch local for a value of type `ty`
+    fn scratch_local(&self, ty: wasm_encoder::ValType) -> u32 {
+        let index = SCRATCH.iter().skip(1).position(|t| *t == ty).unwrap();
+        self.scratch + 1 + index as u32
+    }
+
+    fn call_augment(&mut self, mem: u32, op: AugmentedOp) {
+        let func = self.augmenter.augments[&(mem, op)];
+        self.func
+            .instruction(&wasm_encoder::Instruction::Call(func));
+    }
+
+    /// Call the augmentation of a load or store, passing the static offset of
+    /// the instruction (plus `delta`) after the usual arguments
+    fn call_augment_offset(&mut self, op: AugmentedOp, memarg: wasmparser::MemArg, delta: u64) {
+        let offset = memarg.offset + delta;
+        self.func
+            .instruction(&wasm_encoder::Instruction::I32Const(offset as i32));
+        self.call_augment(memarg.memory, op);
+    }
+
     fn augment(
         &mut self,
         op: AugmentedOp,
         insn: fn(wasm_encoder::MemArg) -> wasm_encoder::Instruction<'static>,
         memarg: wasmparser::MemArg,
     ) {
+        if self.native(memarg.memory) {
+            self.func.instruction(&insn(self.memarg(memarg)));
+            return;
+        }
+        self.call_augment_offset(op, memarg, 0);
+    }
+
+    /// `v128.load` as two `i64.load`s
+    fn v128_load(&mut self, memarg: wasmparser::MemArg) {
+        use wasm_encoder::Instruction::*;
+        let addr = self.scratch;
+        self.func.instruction(&LocalTee(addr));
+        self.call_augment_offset(AugmentedOp::I64Load, memarg, 0);
+        self.func.instruction(&I64x2Splat);
+        self.func.instruction(&LocalGet(addr));
+        self.call_augment_offset(AugmentedOp::I64Load, memarg, 8);
+        self.func.instruction(&I64x2ReplaceLane(1));
+    }
+
+    /// `v128.store` as two `i64.store`s
+    fn v128_store(&mut self, memarg: wasmparser::MemArg) {
         use wasm_encoder::Instruction::*;
-        if memarg.memory < 1 {
+        let (addr, value) = (
+            self.scratch,
+            self.scratch_local(wasm_encoder::ValType::V128),
+        );
+        self.func.instruction(&LocalSet(value));
+        self.func.instruction(&LocalTee(addr));
+        self.func.instruction(&LocalGet(value));
+        self.func.instruction(&I64x2ExtractLane(0));
+        self.call_augment_offset(AugmentedOp::I64Store, memarg, 0);
+        self.func.instruction(&LocalGet(addr));
+        self.func.instruction(&LocalGet(value));
+        self.func.instruction(&I64x2ExtractLane(1));
+        self.call_augment_offset(AugmentedOp::I64Store, memarg, 8);
+    }
+
+    /// Extending loads as an `i64.load` extended from the low half of a vector
+    fn v128_load_extend(
+        &mut self,
+        insn: fn(wasm_encoder::MemArg) -> wasm_encoder::Instruction<'static>,
+        extend: wasm_encoder::Instruction<'static>,
+        memarg: wasmparser::MemArg,
+    ) {
+        if self.native(memarg.memory) {
+            self.func.instruction(&insn(self.memarg(memarg)));
+            return;
+        }
+        self.call_augment_offset(AugmentedOp::I64Load, memarg, 0);
+        self.func
+            .instruction(&wasm_encoder::Instruction::I64x2Splat);
+        self.func.instruction(&extend);
+    }
+
+    /// Splat loads as a scalar load which is splatted
+    fn v128_load_splat(
+        &mut self,
+        insn: fn(wasm_encoder::MemArg) -> wasm_encoder::Instruction<'static>,
+        op: AugmentedOp,
+        splat: wasm_encoder::Instruction<'static>,
+        memarg: wasmparser::MemArg,
+    ) {
+        if self.native(memarg.memory) {
             self.func.instruction(&insn(self.memarg(memarg)));
             return;
         }
-        let idx = MemoryIndex::from_u32(memarg.memory - 1);
-        let func = self.augmenter.augments[&(idx, op)];
-        self.func.instruction(&I32Const(memarg.offset as i32));
-        self.func.instruction(&Call(func));
+        self.call_augment_offset(op, memarg, 0);
+        self.func.instruction(&splat);
+    }
+
+    /// Lane loads as a scalar load replacing a lane of the vector operand
+    fn v128_load_lane(
+        &mut self,
+        op: AugmentedOp,
+        replace: wasm_encoder::Instruction<'static>,
+        memarg: wasmparser::MemArg,
+    ) {
+        use wasm_encoder::Instruction::*;
+        let value = self.scratch_local(wasm_encoder::ValType::V128);
+        let scalar = match op {
+            AugmentedOp::I64Load => self.scratch_local(wasm_encoder::ValType::I64),
+            _ => self.scratch_local(wasm_encoder::ValType::I32),
+        };
+        self.func.instruction(&LocalSet(value));
+        self.call_augment_offset(op, memarg, 0);
+        self.func.instruction(&LocalSet(scalar));
+        self.func.instruction(&LocalGet(value));
+        self.func.instruction(&LocalGet(scalar));
+        self.func.instruction(&replace);
+    }
+
+    /// Lane stores as a scalar store of a lane extracted from the vector
+    fn v128_store_lane(
+        &mut self,
+        op: AugmentedOp,
+        extract: wasm_encoder::Instruction<'static>,
+        memarg: wasmparser::MemArg,
+    ) {
+        use wasm_encoder::Instruction::*;
+        let value = self.scratch_local(wasm_encoder::ValType::V128);
+        self.func.instruction(&LocalSet(value));
+        self.func.instruction(&LocalGet(value));
+        self.func.instruction(&extract);
+        self.call_augment_offset(op, memarg, 0);
     }
 }
@@ -4,6 +4,8 @@ use crate::intrinsics::Intrinsic;
 use crate::names::{maybe_quote_id, maybe_quote_member, LocalNames};
 use crate::source::Source;
 use crate::{uwrite, uwriteln, TranspileOpts};
+use anyhow::{bail, Result};
+use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 use std::fmt::Write;
 
@@ -24,6 +26,8 @@ enum ExportBinding {
 #[derive(Default)]
 pub struct EsmBindgen {
     imports: BTreeMap<String, ImportBinding>,
+    /// types of the imported functions, by their import path
+    import_signatures: BTreeMap<Vec<String>, String>,
     exports: BTreeMap<String, ExportBinding>,
     export_aliases: BTreeMap<String, String>,
 }
@@ -32,8 +36,14 @@ impl EsmBindgen {
     /// add imported function binding, using a path slice starting with the import specifier as its
     /// first segment
     /// arbitrary nesting of interfaces is supported in order to support virtual WASI interfaces
-    /// only two-level nesting supports serialization into imports currently
-    pub fn add_import_binding(&mut self, path: &[String], binding_name: String) {
+    /// bindings of multiple versions of an interface mapped to the same specifier are merged, and
+    /// must have the same shape, with functions of the same type when their `signature` is given
+    pub fn add_import_binding(
+        &mut self,
+        path: &[String],
+        binding_name: String,
+        signature: Option<String>,
+    ) -> Result<()> {
         let mut iface = &mut self.imports;
         for i in 0..path.len() - 1 {
             if !iface.contains_key(&path[i]) {
@@ -44,17 +54,18 @@ impl EsmBindgen {
             }
             iface = match iface.get_mut(&path[i]).unwrap() {
                 ImportBinding::Interface(iface) => iface,
-                ImportBinding::Local(_) => panic!(
-                    "Imported interface {} cannot be both a function and an interface",
-                    &path[0..i].join(".")
+                ImportBinding::Local(_) => bail!(
+                    "import `{}` cannot be both a function and an interface",
+                    import_path(&path[0..=i])
                 ),
             };
         }
         if let Some(ref mut existing) = iface.get_mut(&path[path.len() - 1]) {
             match existing {
-                ImportBinding::Interface(_) => {
-                    unreachable!("Multi-version interfaces must have the same shape")
-                }
+                ImportBinding::Interface(_) => bail!(
+                    "import `{}` cannot be both a function and an interface, as imported through different versions of an interface mapped to the same specifier",
+                    import_path(path)
+                ),
                 ImportBinding::Local(ref mut local_names) => {
                     if !local_names.contains(&binding_name) {
                         local_names.push(binding_name);
@@ -67,38 +78,65 @@ impl EsmBindgen {
                 ImportBinding::Local(vec![binding_name]),
             );
         }
+        if let Some(signature) = signature {
+            match self.import_signatures.entry(path.to_vec()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(signature);
+                }
+                Entry::Occupied(entry) => {
+                    if *entry.get() != signature {
+                        bail!(
+                            "import `{}` cannot be both `{}` and `{signature}`, as imported through different interfaces mapped to the same specifier",
+                            import_path(path),
+                            entry.get()
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
-    /// add an exported function binding, optionally on an interface id or kebab name
+    /// add an exported function binding, on a path of nested interfaces starting with an
+    /// interface id or kebab name, which is empty for top-level functions
     pub fn add_export_binding(
         &mut self,
-        iface_id_or_kebab: Option<&str>,
+        iface_path: &[String],
         local_name: String,
         func_name: String,
-    ) {
+    ) -> Result<()> {
         let mut iface = &mut self.exports;
-        if let Some(iface_id_or_kebab) = iface_id_or_kebab {
+        for (i, iface_id_or_kebab) in iface_path.iter().enumerate() {
             // convert kebab names to camel case, leave ids as-is
             let iface_id_or_kebab = if iface_id_or_kebab.contains(':') {
                 iface_id_or_kebab.to_string()
             } else {
                 iface_id_or_kebab.to_lower_camel_case()
             };
-            if !iface.contains_key(&iface_id_or_kebab) {
-                iface.insert(
-                    iface_id_or_kebab.to_string(),
-                    ExportBinding::Interface(BTreeMap::new()),
-                );
-            }
-            iface = match iface.get_mut(&iface_id_or_kebab).unwrap() {
+            iface = match iface
+                .entry(iface_id_or_kebab)
+                .or_insert_with(|| ExportBinding::Interface(BTreeMap::new()))
+            {
                 ExportBinding::Interface(iface) => iface,
-                ExportBinding::Local(_) => panic!(
-                    "Exported interface {} cannot be both a function and an interface",
-                    iface_id_or_kebab
+                ExportBinding::Local(_) => bail!(
+                    "export `{}` cannot be both a function and an interface",
+                    iface_path[0..=i].join(".")
                 ),
             };
         }
+        if let Some(ExportBinding::Interface(_)) = iface.get(&func_name) {
+            bail!(
+                "export `{}` cannot be both a function and an interface",
+                iface_path
+                    .iter()
+                    .chain([&func_name])
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            );
+        }
         iface.insert(func_name, ExportBinding::Local(local_name));
+        Ok(())
     }
 
     /// once all exports have been created, aliases can be populated for interface
@@ -151,9 +189,7 @@ impl EsmBindgen {
             }
             return;
         }
-        // first create all the interfaces
-        // we currently only support first-level nesting so there is no ordering to figure out
-        // in the process we also populate the alias info
+        // first create all the interfaces, with nested interfaces as nested object literals
         for (export_name, export) in self.exports.iter() {
             let ExportBinding::Interface(iface) = export else {
                 continue;
@@ -161,12 +197,7 @@ impl EsmBindgen {
             let (local_name, _) =
                 local_names.get_or_create(&format!("export:{export_name}"), export_name);
             uwriteln!(output, "const {local_name} = {{");
-            for (func_name, export) in iface {
-                let ExportBinding::Local(local_name) = export else {
-                    panic!("Unsupported nested export interface");
-                };
-                uwriteln!(output, "{}: {local_name},", maybe_quote_id(func_name));
-            }
+            Self::render_export_interface(output, iface);
             uwriteln!(output, "\n}};");
         }
         uwrite!(
@@ -220,6 +251,21 @@ impl EsmBindgen {
         uwrite!(output, " }}");
     }
 
+    fn render_export_interface(output: &mut Source, iface: &BTreeMap<String, ExportBinding>) {
+        for (name, export) in iface {
+            match export {
+                ExportBinding::Local(local_name) => {
+                    uwriteln!(output, "{}: {local_name},", maybe_quote_id(name));
+                }
+                ExportBinding::Interface(iface) => {
+                    uwriteln!(output, "{}: {{", maybe_quote_id(name));
+                    Self::render_export_interface(output, iface);
+                    uwriteln!(output, "}},");
+                }
+            }
+        }
+    }
+
     fn contains_js_quote(&self, js_string: &String) -> bool {
         js_string.contains("\"") || js_string.contains("'") || js_string.contains("`")
     }
@@ -260,7 +306,11 @@ impl EsmBindgen {
                             match import {
                                 ImportBinding::Interface(iface) => {
                                     let iface_local_name = local_names.create_once(specifier);
-                                    iface_imports.push((iface_local_name.to_string(), iface));
+                                    iface_imports.push((
+                                        iface_local_name.to_string(),
+                                        format!("{specifier}#default"),
+                                        iface,
+                                    ));
                                     uwriteln!(output, "{iface_local_name} from '{specifier}';");
                                 }
                                 ImportBinding::Local(local_names) => {
@@ -288,11 +338,14 @@ impl EsmBindgen {
                                 } else {
                                     output.push_str(", ");
                                 }
-                                let (iface_local_name, _) = local_names.get_or_create(
-                                    &format!("import:{specifier}#{external_name}"),
-                                    external_name,
-                                );
-                                iface_imports.push((iface_local_name.to_string(), iface));
+                                let iface_path = format!("{specifier}#{external_name}");
+                                let (iface_local_name, _) = local_names
+                                    .get_or_create(&format!("import:{iface_path}"), external_name);
+                                iface_imports.push((
+                                    iface_local_name.to_string(),
+                                    iface_path,
+                                    iface,
+                                ));
                                 if external_name == iface_local_name {
                                     uwrite!(output, "{external_name}");
                                 } else if imports_object.is_some() || idl_binding.is_some() {
@@ -358,15 +411,29 @@ impl EsmBindgen {
                 }
             }
         }
-        // render interface import member getters
-        for (iface_local_name, iface_imports) in iface_imports {
+        // render interface import member getters, where nested interfaces
+        // are queued to be read off of the local name of their interface
+        let mut i = 0;
+        while i < iface_imports.len() {
+            let (iface_local_name, iface_path, iface) = iface_imports[i].clone();
+            i += 1;
             uwrite!(output, "const {{");
             let mut first = true;
-            for (member_name, binding) in iface_imports {
-                let ImportBinding::Local(local_names) = binding else {
-                    continue;
+            for (member_name, binding) in iface {
+                let nested_local_name;
+                let member_local_names = match binding {
+                    ImportBinding::Interface(nested_iface) => {
+                        let nested_path = format!("{iface_path}.{member_name}");
+                        nested_local_name = local_names
+                            .get_or_create(&format!("import:{nested_path}"), member_name)
+                            .0
+                            .to_string();
+                        iface_imports.push((nested_local_name.clone(), nested_path, nested_iface));
+                        std::slice::from_ref(&nested_local_name)
+                    }
+                    ImportBinding::Local(local_names) => local_names.as_slice(),
                 };
-                for local_name in local_names {
+                for local_name in member_local_names {
                     if first {
                         output.push_str(" ");
                         first = false;
@@ -387,3 +454,13 @@ impl EsmBindgen {
         }
     }
 }
+
+/// Readable path of an import binding, as its specifier followed by its members
+fn import_path(path: &[String]) -> String {
+    match path.split_first() {
+        Some((specifier, members)) if !members.is_empty() => {
+            format!("{specifier}#{}", members.join("."))
+        }
+        _ => path.join(""),
+    }
+}
@@ -25,6 +25,10 @@ pub enum ResourceData {
         rid: ResourceIndex,
         local_name: String,
         dtor_name: Option<String>,
+        /// Expression assigning a new capture rep to an imported resource
+        /// object, instead of `++captureCnt{rid}`, for capture tables shared
+        /// between modules
+        capture_rep: Option<String>,
     },
     Guest {
         resource_name: String,
@@ -1238,6 +1242,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         rid,
                         local_name,
                         dtor_name,
+                        ..
                     } => {
                         let tid = tid.as_u32();
                         let rid = rid.as_u32();
@@ -1380,6 +1385,7 @@ impl Bindgen for FunctionBindgen<'_> {
                         tid,
                         rid,
                         local_name,
+                        capture_rep,
                         ..
                     } => {
                         let tid = tid.as_u32();
@@ -1427,6 +1433,9 @@ impl Bindgen for FunctionBindgen<'_> {
                             // Fall back to assign a new rep in the capture table, when the imported
                             // resource was constructed externally.
                             let symbol_resource_rep = self.intrinsic(Intrinsic::SymbolResourceRep);
+                            let capture_rep = capture_rep
+                                .clone()
+                                .unwrap_or_else(|| format!("++captureCnt{rid}"));
                             let rsc_table_create = if is_own {
                                 self.intrinsic(Intrinsic::ResourceTableCreateOwn)
                             } else {
@@ -1436,7 +1445,7 @@ impl Bindgen for FunctionBindgen<'_> {
                             uwriteln!(
                                 self.src,
                                 "if (!{handle}) {{
-                                    const rep = {op}[{symbol_resource_rep}] || ++captureCnt{rid};
+                                    const rep = {op}[{symbol_resource_rep}] || {capture_rep};
                                     captureTable{rid}.set(rep, {op});
                                     {handle} = {rsc_table_create}(handleTable{tid}, rep);
                                 }}"
@@ -6,6 +6,7 @@ use std::fmt::Write;
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Intrinsic {
     Base64Compile,
+    Base64Decode,
     ClampGuest,
     ComponentError,
     CurResourceBorrows,
@@ -36,7 +37,9 @@ pub enum Intrinsic {
     ResourceTransferBorrowValidLifting,
     ResourceTransferOwn,
     ScopeId,
+    SupportsMultiMemory,
     SymbolCabiDispose,
+    SymbolCabiLift,
     SymbolCabiLower,
     SymbolResourceHandle,
     SymbolResourceRep,
@@ -124,6 +127,16 @@ pub fn render_intrinsics(
                 ")
             },
 
+            Intrinsic::Base64Decode => if !no_nodejs_compat {
+                output.push_str("
+                    const base64Decode = str => typeof Buffer !== 'undefined' ? new Uint8Array(Buffer.from(str, 'base64')) : Uint8Array.from(atob(str), b => b.charCodeAt(0));
+                ")
+            } else {
+                output.push_str("
+                    const base64Decode = str => Uint8Array.from(atob(str), b => b.charCodeAt(0));
+                ")
+            },
+
             Intrinsic::ClampGuest => output.push_str("
                 function clampGuest(i, min, max) {
                     if (i < min || i > max) \
@@ -464,6 +477,10 @@ pub fn render_intrinsics(
                 const symbolCabiDispose = Symbol.for('cabiDispose');
             "),
 
+            Intrinsic::SymbolCabiLift => output.push_str("
+                const symbolCabiLift = Symbol.for('cabiLift');
+            "),
+
             Intrinsic::SymbolCabiLower => output.push_str("
                 const symbolCabiLower = Symbol.for('cabiLower');
             "),
@@ -472,6 +489,11 @@ pub fn render_intrinsics(
                 let scopeId = 0;
             "),
 
+            // Validates a module with two memories
+            Intrinsic::SupportsMultiMemory => output.push_str("
+                const supportsMultiMemory = WebAssembly.validate(new Uint8Array([0, 97, 115, 109, 1, 0, 0, 0, 5, 5, 2, 0, 0, 0, 0]));
+            "),
+
             Intrinsic::SymbolResourceHandle => output.push_str("
                 const symbolRscHandle = Symbol('handle');
             "),
@@ -655,6 +677,7 @@ impl Intrinsic {
         &[
             // Intrinsic list exactly as below
             "base64Compile",
+            "base64Decode",
             "clampGuest",
             "ComponentError",
             "curResourceBorrows",
@@ -683,7 +706,9 @@ impl Intrinsic {
             "rscTableRemove",
             "rscTableTryGet",
             "scopeId",
+            "supportsMultiMemory",
             "symbolCabiDispose",
+            "symbolCabiLift",
             "symbolCabiLower",
             "symbolDispose",
             "symbolRscHandle",
@@ -734,6 +759,7 @@ impl Intrinsic {
     pub fn name(&self) -> &'static str {
         match self {
             Intrinsic::Base64Compile => "base64Compile",
+            Intrinsic::Base64Decode => "base64Decode",
             Intrinsic::ClampGuest => "clampGuest",
             Intrinsic::ComponentError => "ComponentError",
             Intrinsic::CurResourceBorrows => "curResourceBorrows",
@@ -764,7 +790,9 @@ impl Intrinsic {
             Intrinsic::ResourceTransferBorrowValidLifting => "resourceTransferBorrowValidLifting",
             Intrinsic::ResourceTransferOwn => "resourceTransferOwn",
             Intrinsic::ScopeId => "scopeId",
+            Intrinsic::SupportsMultiMemory => "supportsMultiMemory",
             Intrinsic::SymbolCabiDispose => "symbolCabiDispose",
+            Intrinsic::SymbolCabiLift => "symbolCabiLift",
             Intrinsic::SymbolCabiLower => "symbolCabiLower",
             Intrinsic::SymbolDispose => "symbolDispose",
             Intrinsic::SymbolResourceHandle => "symbolRscHandle",
@@ -0,0 +1,615 @@
+//! Generation of runnable JS test doubles for the imports of a world.
+//!
+//! Every import specifier of the world, as resolved through the same import
+//! mapping used for transpilation, gets its own ES module under `mocks/`. The
+//! functions of these modules record their calls and return configurable
+//! values (defaulting to the zero value of the WIT result type), while the
+//! resource classes record their construction and disposal.
+//!
+//! A `mocks/index.js` module is generated alongside, exporting an `imports`
+//! object keyed by import specifier which can be passed directly to the
+//! `instantiate` function of components transpiled in instantiation mode.
+
+use crate::files::Files;
+use crate::function_bindgen::{array_ty, maybe_null};
+use crate::names::{maybe_quote_id, maybe_quote_member, specifier_goal_name, LocalNames};
+use crate::source::Source;
+use crate::transpile_bindgen::{map_import, TranspileOpts};
+use crate::{dealias, uwrite, uwriteln};
+use heck::*;
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use wit_parser::*;
+
+/// Name of the shared runtime module imported by every generated mock module
+const MOCK_RUNTIME: &str = "mock-runtime.js";
+
+/// Global names used by the generated mock modules, which must not be
+/// shadowed by local definitions
+const MOCK_GLOBAL_NAMES: &[&str] = &[
+    "BigInt64Array",
+    "BigUint64Array",
+    "Error",
+    "Float32Array",
+    "Float64Array",
+    "Int16Array",
+    "Int32Array",
+    "Int8Array",
+    "Object",
+    "String",
+    "Symbol",
+    "Uint16Array",
+    "Uint32Array",
+    "Uint8Array",
+    "mockFn",
+    "mockResource",
+];
+
+/// Location of an imported resource class within the generated mock modules
+struct ResourceLocation {
+    specifier: String,
+    member: Option<String>,
+    class_name: String,
+}
+
+#[derive(Default)]
+struct MockModule<'a> {
+    /// Mocked items, keyed by the nested namespace member they are exported
+    /// under when the import map contains `#` member mappings
+    members: BTreeMap<Option<String>, MockMember<'a>>,
+}
+
+#[derive(Default)]
+struct MockMember<'a> {
+    /// Freestanding functions, by export name (`default` for world-level
+    /// function imports)
+    funcs: Vec<(String, &'a Function)>,
+    /// Resources defined by this member, with their functions
+    resources: IndexMap<TypeId, Vec<&'a Function>>,
+}
+
+struct JsMockgen<'a> {
+    resolve: &'a Resolve,
+    modules: BTreeMap<String, MockModule<'a>>,
+    resources: HashMap<TypeId, ResourceLocation>,
+}
+
+pub fn js_mockgen(
+    resolve: &Resolve,
+    id: WorldId,
+    opts: &TranspileOpts,
+    files: &mut Files,
+) -> anyhow::Result<()> {
+    let world = &resolve.worlds[id];
+    let mut gen = JsMockgen {
+        resolve,
+        modules: BTreeMap::new(),
+        resources: HashMap::new(),
+    };
+
+    // World-level resource functions are attached to their resource class, so
+    // first register all imported resources before any functions.
+    for (key, item) in world.imports.iter() {
+        let import_name = resolve.name_world_key(key);
+        let (specifier, member) = map_import(&opts.map, &import_name);
+        match item {
+            WorldItem::Interface { id, .. } => {
+                for ty in resolve.interfaces[*id].types.values() {
+                    if matches!(resolve.types[*ty].kind, TypeDefKind::Resource) {
+                        gen.add_resource(*ty, &specifier, member.clone());
+                    }
+                }
+            }
+            // World-level resources are always imported by name, ignoring any
+            // member mapping
+            WorldItem::Type(ty) => {
+                if matches!(resolve.types[*ty].kind, TypeDefKind::Resource) {
+                    gen.add_resource(*ty, &specifier, None);
+                }
+            }
+            WorldItem::Function(_) => {}
+        }
+    }
+
+    for (key, item) in world.imports.iter() {
+        let import_name = resolve.name_world_key(key);
+        let (specifier, member) = map_import(&opts.map, &import_name);
+        match item {
+            WorldItem::Function(func) => gen.add_func(func, &specifier, None, "default"),
+            WorldItem::Interface { id, .. } => {
+                for (name, func) in resolve.interfaces[*id].functions.iter() {
+                    gen.add_func(
+                        func,
+                        &specifier,
+                        member.clone(),
+                        &name.to_lower_camel_case(),
+                    );
+                }
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+
+    gen.finish(files);
+
+    Ok(())
+}
+
+impl<'a> JsMockgen<'a> {
+    fn add_resource(&mut self, ty: TypeId, specifier: &str, member: Option<String>) {
+        let class_name = self.resolve.types[ty]
+            .name
+            .as_ref()
+            .expect("resource name")
+            .to_upper_camel_case();
+        self.modules
+            .entry(specifier.to_string())
+            .or_default()
+            .members
+            .entry(member.clone())
+            .or_default()
+            .resources
+            .insert(ty, Vec::new());
+        self.resources.insert(
+            ty,
+            ResourceLocation {
+                specifier: specifier.to_string(),
+                member,
+                class_name,
+            },
+        );
+    }
+
+    fn add_func(
+        &mut self,
+        func: &'a Function,
+        specifier: &str,
+        member: Option<String>,
+        export_name: &str,
+    ) {
+        match func.kind {
+            FunctionKind::Freestanding => {
+                self.modules
+                    .entry(specifier.to_string())
+                    .or_default()
+                    .members
+                    .entry(member)
+                    .or_default()
+                    .funcs
+                    .push((export_name.to_string(), func));
+            }
+            FunctionKind::Method(ty) | FunctionKind::Static(ty) | FunctionKind::Constructor(ty) => {
+                let ty = dealias(self.resolve, ty);
+                let location = &self.resources[&ty];
+                self.modules
+                    .get_mut(&location.specifier)
+                    .unwrap()
+                    .members
+                    .get_mut(&location.member)
+                    .unwrap()
+                    .resources
+                    .get_mut(&ty)
+                    .unwrap()
+                    .push(func);
+            }
+        }
+    }
+
+    fn finish(self, files: &mut Files) {
+        // Allocate a unique file name per import specifier
+        let mut file_names = BTreeMap::new();
+        let mut used_names = HashSet::new();
+        for specifier in self.modules.keys() {
+            let goal_name = specifier_goal_name(specifier);
+            let mut file_name = format!("{goal_name}.js");
+            let mut idx = 1;
+            while !used_names.insert(file_name.clone()) {
+                idx += 1;
+                file_name = format!("{goal_name}-{idx}.js");
+            }
+            file_names.insert(specifier.to_string(), file_name);
+        }
+
+        let mut index = Source::default();
+        let mut index_imports = Source::default();
+        for (i, (specifier, module)) in self.modules.iter().enumerate() {
+            let file_name = &file_names[specifier];
+            let mut gen = MockModuleGen {
+                resolve: self.resolve,
+                specifier,
+                resources: &self.resources,
+                file_names: &file_names,
+                local_names: LocalNames::default(),
+                local_resources: HashMap::new(),
+                module_imports: BTreeMap::new(),
+                needs_mock_resource: false,
+                src: Source::default(),
+            };
+            gen.local_names.exclude_globals(MOCK_GLOBAL_NAMES);
+            let src = gen.generate(module);
+            files.push(&format!("mocks/{file_name}"), src.as_bytes());
+
+            uwriteln!(index_imports, "import * as mock{i} from './{file_name}';");
+            uwriteln!(index, "'{specifier}': mock{i},");
+        }
+
+        let mut output = Source::default();
+        output.push_str(&index_imports);
+        uwriteln!(
+            output,
+            "export {{ resetAllMocks }} from './{MOCK_RUNTIME}';
+
+            export const imports = {{"
+        );
+        output.push_str(&index);
+        uwriteln!(output, "}};");
+        files.push("mocks/index.js", output.as_bytes());
+        files.push(
+            &format!("mocks/{MOCK_RUNTIME}"),
+            MOCK_RUNTIME_SOURCE.trim_start().as_bytes(),
+        );
+    }
+}
+
+/// Generator for a single mock module, corresponding to one import specifier
+struct MockModuleGen<'a, 'b> {
+    resolve: &'a Resolve,
+    specifier: &'b str,
+    resources: &'b HashMap<TypeId, ResourceLocation>,
+    file_names: &'b BTreeMap<String, String>,
+    local_names: LocalNames,
+    /// Local names of the resource classes defined by this module
+    local_resources: HashMap<TypeId, String>,
+    /// Namespace imports of other mock modules, by file name
+    module_imports: BTreeMap<String, String>,
+    needs_mock_resource: bool,
+    src: Source,
+}
+
+impl<'a> MockModuleGen<'a, '_> {
+    fn generate(&mut self, module: &MockModule<'a>) -> String {
+        // Resource classes are named up-front, since function default values
+        // may construct resources declared later on in the module.
+        for member in module.members.values() {
+            for ty in member.resources.keys() {
+                let class_name = &self.resources[ty].class_name;
+                let local_name = self.local_names.create_once(class_name).to_string();
+                self.local_resources.insert(*ty, local_name);
+            }
+        }
+
+        let mut exports: Vec<(String, String)> = Vec::new();
+        for (member, items) in module.members.iter() {
+            let mut member_exports = Vec::new();
+
+            for (ty, funcs) in items.resources.iter() {
+                let class_name = self.resources[ty].class_name.clone();
+                self.resource(*ty, &class_name, funcs);
+                member_exports.push((self.local_resources[ty].clone(), class_name));
+            }
+
+            if !items.funcs.is_empty() {
+                self.separate();
+            }
+            for (export_name, func) in items.funcs.iter() {
+                let local_name = self.local_names.create_once(&func.name).to_string();
+                let default = self.default_return(func);
+                uwriteln!(
+                    self.src,
+                    "const {local_name} = mockFn('{}', {default});",
+                    func.name
+                );
+                member_exports.push((local_name, export_name.to_string()));
+            }
+
+            match member {
+                Some(member) => {
+                    let local_name = self.local_names.create_once(member).to_string();
+                    self.separate();
+                    uwriteln!(self.src, "const {local_name} = {{");
+                    for (local_name, export_name) in member_exports {
+                        let export_name = maybe_quote_id(&export_name);
+                        if local_name == export_name {
+                            uwriteln!(self.src, "{local_name},");
+                        } else {
+                            uwriteln!(self.src, "{export_name}: {local_name},");
+                        }
+                    }
+                    uwriteln!(self.src, "}};");
+                    exports.push((local_name, member.to_lower_camel_case()));
+                }
+                None => exports.extend(member_exports),
+            }
+        }
+
+        let mut output = Source::default();
+        uwriteln!(
+            output,
+            "// Mock implementation of the '{}' import",
+            self.specifier
+        );
+        if self.needs_mock_resource {
+            uwriteln!(
+                output,
+                "import {{ mockFn, mockResource }} from './{MOCK_RUNTIME}';"
+            );
+        } else {
+            uwriteln!(output, "import {{ mockFn }} from './{MOCK_RUNTIME}';");
+        }
+        for (file_name, local_name) in self.module_imports.iter() {
+            uwriteln!(output, "import * as {local_name} from './{file_name}';");
+        }
+        uwriteln!(output, "");
+        output.push_str(&self.src);
+        uwriteln!(output, "");
+        uwrite!(output, "export {{");
+        for (i, (local_name, export_name)) in exports.iter().enumerate() {
+            if i > 0 {
+                uwrite!(output, ",");
+            }
+            if local_name == export_name {
+                uwrite!(output, " {local_name}");
+            } else {
+                uwrite!(output, " {local_name} as {export_name}");
+            }
+        }
+        uwriteln!(output, " }};");
+
+        output.into()
+    }
+
+    fn resource(&mut self, ty: TypeId, class_name: &str, funcs: &[&Function]) {
+        let local_name = self.local_resources[&ty].clone();
+        self.separate();
+        self.needs_mock_resource = true;
+        uwriteln!(
+            self.src,
+            "const {local_name} = mockResource('{class_name}');"
+        );
+        for func in funcs {
+            let method_name = func.item_name().to_lower_camel_case();
+            match func.kind {
+                FunctionKind::Constructor(_) => {
+                    uwriteln!(
+                        self.src,
+                        "{local_name}.construct = mockFn('{class_name}.constructor', () => {{}});"
+                    );
+                }
+                FunctionKind::Method(_) => {
+                    let default = self.default_return(func);
+                    uwriteln!(
+                        self.src,
+                        "{local_name}.prototype{} = mockFn('{class_name}.{method_name}', {default});",
+                        maybe_quote_member(&method_name)
+                    );
+                }
+                FunctionKind::Static(_) => {
+                    let default = self.default_return(func);
+                    uwriteln!(
+                        self.src,
+                        "{local_name}{} = mockFn('{class_name}.{method_name}', {default});",
+                        maybe_quote_member(&method_name)
+                    );
+                }
+                FunctionKind::Freestanding => unreachable!("resource function"),
+            }
+        }
+    }
+
+    /// Separate the next block of declarations by an empty line
+    fn separate(&mut self) {
+        if !self.src.is_empty() {
+            uwriteln!(self.src, "");
+        }
+    }
+
+    /// Default implementation of a mocked function, returning the zero value
+    /// of its result type
+    fn default_return(&mut self, func: &Function) -> String {
+        let value = if let Some((ok, _)) = func.results.throws(self.resolve) {
+            ok.map(|ok| self.default_value(ok))
+        } else {
+            match func.results.len() {
+                0 => None,
+                1 => Some(self.default_value(func.results.iter_types().next().unwrap())),
+                _ => {
+                    let values = func
+                        .results
+                        .iter_types()
+                        .map(|ty| self.default_value(ty))
+                        .collect::<Vec<_>>();
+                    Some(format!("[{}]", values.join(", ")))
+                }
+            }
+        };
+        match value {
+            Some(value) => format!("() => ({value})"),
+            None => "() => {}".to_string(),
+        }
+    }
+
+    /// The zero value of a type, in its JS bindings representation
+    fn default_value(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "false".to_string(),
+            Type::U8
+            | Type::S8
+            | Type::U16
+            | Type::S16
+            | Type::U32
+            | Type::S32
+            | Type::F32
+            | Type::F64 => "0".to_string(),
+            Type::U64 | Type::S64 => "0n".to_string(),
+            Type::Char => "'\\0'".to_string(),
+            Type::String => "''".to_string(),
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(t) => self.default_value(t),
+                TypeDefKind::Record(record) => {
+                    let fields = record
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            format!(
+                                "{}: {}",
+                                maybe_quote_id(&field.name.to_lower_camel_case()),
+                                self.default_value(&field.ty)
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    format!("{{ {} }}", fields.join(", "))
+                }
+                TypeDefKind::Tuple(tuple) => {
+                    let values = tuple
+                        .types
+                        .iter()
+                        .map(|ty| self.default_value(ty))
+                        .collect::<Vec<_>>();
+                    format!("[{}]", values.join(", "))
+                }
+                TypeDefKind::Flags(_) => "{}".to_string(),
+                TypeDefKind::Enum(enum_) => format!("'{}'", enum_.cases[0].name),
+                TypeDefKind::Variant(variant) => {
+                    let case = &variant.cases[0];
+                    match &case.ty {
+                        Some(ty) => {
+                            format!(
+                                "{{ tag: '{}', val: {} }}",
+                                case.name,
+                                self.default_value(ty)
+                            )
+                        }
+                        None => format!("{{ tag: '{}' }}", case.name),
+                    }
+                }
+                TypeDefKind::Option(t) => {
+                    if maybe_null(self.resolve, t) {
+                        "{ tag: 'none' }".to_string()
+                    } else {
+                        "undefined".to_string()
+                    }
+                }
+                TypeDefKind::Result(result) => match &result.ok {
+                    Some(ok) => format!("{{ tag: 'ok', val: {} }}", self.default_value(ok)),
+                    None => "{ tag: 'ok' }".to_string(),
+                },
+                TypeDefKind::List(t) => match array_ty(self.resolve, t) {
+                    Some(array_ty) => format!("new {array_ty}(0)"),
+                    None => "[]".to_string(),
+                },
+                TypeDefKind::Handle(Handle::Own(ty) | Handle::Borrow(ty)) => {
+                    format!("new {}()", self.resource_class(*ty))
+                }
+                TypeDefKind::Future(_)
+                | TypeDefKind::Stream(_)
+                | TypeDefKind::Resource
+                | TypeDefKind::Unknown => "undefined".to_string(),
+            },
+        }
+    }
+
+    /// Expression referencing the mock class of a resource, which may be
+    /// defined by another mock module
+    fn resource_class(&mut self, ty: TypeId) -> String {
+        let ty = dealias(self.resolve, ty);
+        if let Some(local_name) = self.local_resources.get(&ty) {
+            return local_name.to_string();
+        }
+        let location = &self.resources[&ty];
+        let file_name = &self.file_names[&location.specifier];
+        let ns = match self.module_imports.get(file_name) {
+            Some(ns) => ns.to_string(),
+            None => {
+                let ns = self
+                    .local_names
+                    .create_once(&format!("{}$mock", file_name.trim_end_matches(".js")))
+                    .to_string();
+                self.module_imports
+                    .insert(file_name.to_string(), ns.clone());
+                ns
+            }
+        };
+        let member = location
+            .member
+            .as_ref()
+            .map(|member| maybe_quote_member(&member.to_lower_camel_case()))
+            .unwrap_or_default();
+        format!("{ns}{member}{}", maybe_quote_member(&location.class_name))
+    }
+}
+
+const MOCK_RUNTIME_SOURCE: &str = "
+const symbolDispose = Symbol.dispose || Symbol.for('dispose');
+
+const allMocks = new Set();
+
+/**
+ * Create a mock function, recording its calls and returning the value of
+ * `defaultImpl` unless configured otherwise.
+ */
+export function mockFn (name, defaultImpl) {
+  function mock (...args) {
+    mock.calls.push(args);
+    mock.contexts.push(this);
+    const impl = mock.onceImpls.length ? mock.onceImpls.shift() : mock.impl;
+    return impl.apply(this, args);
+  }
+  Object.defineProperty(mock, 'name', { value: name });
+  mock.mockReset = () => {
+    mock.calls = [];
+    mock.contexts = [];
+    mock.onceImpls = [];
+    mock.impl = defaultImpl;
+    return mock;
+  };
+  mock.mockImplementation = impl => {
+    mock.impl = impl;
+    return mock;
+  };
+  mock.mockImplementationOnce = impl => {
+    mock.onceImpls.push(impl);
+    return mock;
+  };
+  mock.mockReturnValue = val => mock.mockImplementation(() => val);
+  mock.mockReturnValueOnce = val => mock.mockImplementationOnce(() => val);
+  // Functions returning a `result` report their error case via a thrown payload
+  mock.mockError = payload => mock.mockImplementation(() => { throw { payload }; });
+  mock.mockErrorOnce = payload => mock.mockImplementationOnce(() => { throw { payload }; });
+  allMocks.add(mock);
+  return mock.mockReset();
+}
+
+/**
+ * Create a mock resource class, recording the instances created and dropped.
+ */
+export function mockResource (name) {
+  const Resource = class {
+    constructor (...args) {
+      Resource.created.push({ instance: this, args });
+      if (Resource.construct) Resource.construct.apply(this, args);
+    }
+    [symbolDispose] () {
+      Resource.dropped.push(this);
+    }
+  };
+  Object.defineProperty(Resource, 'name', { value: name });
+  Resource.mockReset = () => {
+    Resource.created = [];
+    Resource.dropped = [];
+    return Resource;
+  };
+  Resource.liveInstances = () => Resource.created
+    .map(({ instance }) => instance)
+    .filter(instance => !Resource.dropped.includes(instance));
+  allMocks.add(Resource);
+  return Resource.mockReset();
+}
+
+/**
+ * Reset the recorded state and configured behaviours of all mocks.
+ */
+export function resetAllMocks () {
+  for (const mock of allMocks) mock.mockReset();
+}
+";
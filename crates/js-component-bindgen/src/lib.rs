@@ -1,5 +1,9 @@
 mod core;
 mod files;
+mod js_mockgen;
+mod link_bindgen;
+mod lower_bindgen;
+mod semver_check;
 mod transpile_bindgen;
 mod ts_bindgen;
 mod ts_stubgen;
@@ -9,10 +13,15 @@ pub mod function_bindgen;
 pub mod intrinsics;
 pub mod names;
 pub mod source;
-pub use transpile_bindgen::{BindingsMode, InstantiationMode, TranspileOpts};
+pub use link_bindgen::{Link, LinkComponent, Linked};
+pub use semver_check::{semver_check, ApiChange, ChangeKind, ChangedItem};
+pub use transpile_bindgen::{
+    BindingsMode, ExportBindingsMode, InstantiationMode, TranspileOpts, TranspileWarning,
+};
 
 use anyhow::Result;
-use transpile_bindgen::transpile_bindgen;
+use link_bindgen::LinkedComponent;
+use transpile_bindgen::{map_import, map_warnings, multi_memory_file_name, transpile_bindgen};
 
 use anyhow::{bail, ensure, Context};
 use wasmtime_environ::component::{ComponentTypesBuilder, Export, StaticModuleIndex};
@@ -21,7 +30,7 @@ use wasmtime_environ::{PrimaryMap, ScopeVec, Tunables};
 use wit_component::DecodedWasm;
 
 use ts_bindgen::ts_bindgen;
-use wit_parser::{Package, Resolve, Stability, Type, TypeDefKind, TypeId, WorldId};
+use wit_parser::{Package, Resolve, Stability, Type, TypeDefKind, TypeId, WorldId, WorldItem};
 
 /// Calls [`write!`] with the passed arguments and unwraps the result.
 ///
@@ -53,6 +62,8 @@ pub struct Transpiled {
     pub files: Vec<(String, Vec<u8>)>,
     pub imports: Vec<String>,
     pub exports: Vec<(String, Export)>,
+    /// Map entries matching no import, and imports matching several entries
+    pub warnings: Vec<TranspileWarning>,
 }
 
 pub struct ComponentInfo {
@@ -85,6 +96,51 @@ pub fn generate_typescript_stubs(
     Ok(files.into_iter().collect())
 }
 
+/// Generate TypeScript implementation skeletons for the imports of a world,
+/// one module per imported interface, matching the import specifiers as
+/// mapped by `opts.map`
+pub fn generate_typescript_import_stubs(
+    resolve: Resolve,
+    world_id: WorldId,
+    opts: TranspileOpts,
+) -> Result<Vec<(String, Vec<u8>)>, anyhow::Error> {
+    let mut files = files::Files::default();
+
+    ts_stubgen::ts_import_stubgen(&resolve, world_id, &opts, &mut files)?;
+
+    Ok(files.into_iter().collect())
+}
+
+/// Generate runnable JS mock modules for the imports of a world, one module
+/// per import specifier as mapped by `opts.map`
+pub fn generate_js_mocks(
+    resolve: Resolve,
+    world_id: WorldId,
+    opts: TranspileOpts,
+) -> Result<Vec<(String, Vec<u8>)>, anyhow::Error> {
+    let mut files = files::Files::default();
+
+    js_mockgen::js_mockgen(&resolve, world_id, &opts, &mut files)
+        .context("failed to generate JS mocks")?;
+
+    Ok(files.into_iter().collect())
+}
+
+/// Generate JS modules attaching `Symbol.for('cabiLower')` lowering factories
+/// to the host implementations of the imports of a world, one module per
+/// import, for use with the hybrid and optimized import bindings modes
+pub fn generate_lower_bindings(
+    resolve: Resolve,
+    world_id: WorldId,
+) -> Result<Vec<(String, Vec<u8>)>, anyhow::Error> {
+    let mut files = files::Files::default();
+
+    lower_bindgen::lower_bindgen(&resolve, world_id, &mut files)
+        .context("failed to generate lowering bindings")?;
+
+    Ok(files.into_iter().collect())
+}
+
 /// Generate the JS transpilation bindgen for a given Wasm component binary
 /// Outputs the file map and import and export metadata for the Transpilation
 #[cfg(feature = "transpile-bindgen")]
@@ -133,7 +189,7 @@ pub fn transpile(component: &[u8], opts: TranspileOpts) -> Result<Transpiled, an
 
     let modules: PrimaryMap<StaticModuleIndex, core::Translation<'_>> = modules
         .into_iter()
-        .map(|(_i, module)| core::Translation::new(module, opts.multi_memory))
+        .map(|(_i, module)| core::Translation::new(module, opts.multi_memory, opts.merge_memories))
         .collect::<Result<_>>()?;
 
     let mut wasmtime_component = Component::default();
@@ -143,6 +199,12 @@ pub fn transpile(component: &[u8], opts: TranspileOpts) -> Result<Transpiled, an
     // end up getting used in the `generate_instantiate` method.
     for (i, module) in modules.iter() {
         files.push(&core_file_name(&name, i.as_u32()), module.wasm());
+        if let Some(wasm) = module.multi_memory_wasm() {
+            if opts.detect_multi_memory {
+                let file_name = core_file_name(&name, i.as_u32());
+                files.push(&multi_memory_file_name(&file_name), wasm);
+            }
+        }
     }
 
     if !opts.no_typescript {
@@ -150,9 +212,17 @@ pub fn transpile(component: &[u8], opts: TranspileOpts) -> Result<Transpiled, an
             .context("failed to generate Typescript bindings")?;
     }
 
+    let import_names = resolve.worlds[world_id]
+        .imports
+        .iter()
+        .filter(|(_, item)| !matches!(item, WorldItem::Type(_)))
+        .map(|(key, _)| resolve.name_world_key(key))
+        .collect::<Vec<_>>();
+    let warnings = map_warnings(&opts.map, import_names.iter().map(String::as_str));
+
     let (imports, exports) = transpile_bindgen(
         &name, &component, &modules, &types.0, &resolve, world_id, opts, &mut files,
-    );
+    )?;
 
     let mut files_out: Vec<(String, Vec<u8>)> = Vec::new();
     for (name, source) in files.iter() {
@@ -162,6 +232,105 @@ pub fn transpile(component: &[u8], opts: TranspileOpts) -> Result<Transpiled, an
         files: files_out,
         imports,
         exports,
+        warnings,
+    })
+}
+
+/// Transpile a set of components linked together, with the given imports of
+/// components wired to the exports of the same name of other components.
+///
+/// Each component is output as `[name].js` in instantiation mode, together
+/// with a `[opts.name].js` module whose `instantiate` function instantiates
+/// the components in dependency order and returns their exports by component
+/// name. Wired functions call the core functions of their exporting component
+/// directly where their types allow it.
+#[cfg(feature = "transpile-bindgen")]
+pub fn link(
+    components: &[LinkComponent],
+    links: &[Link],
+    opts: TranspileOpts,
+) -> Result<Linked, anyhow::Error> {
+    let mut linked = Vec::new();
+    for component in components {
+        if component.name == opts.name {
+            bail!(
+                "component `{}` has the same name as the linked output",
+                component.name
+            );
+        }
+        if linked
+            .iter()
+            .any(|c: &LinkedComponent| c.name == component.name)
+        {
+            bail!("duplicate component name `{}`", component.name);
+        }
+        let decoded = wit_component::decode(component.component).with_context(|| {
+            format!(
+                "failed to extract interface information from component `{}`",
+                component.name
+            )
+        })?;
+        let (resolve, world) = match decoded {
+            DecodedWasm::WitPackage(_, _) => bail!("unexpected wit package as input"),
+            DecodedWasm::Component(resolve, world) => (resolve, world),
+        };
+        linked.push(LinkedComponent {
+            name: &component.name,
+            resolve,
+            world,
+        });
+    }
+    let order = link_bindgen::instantiation_order(&linked, links)?;
+
+    let mut files = files::Files::default();
+    let mut imports = Vec::new();
+    for component in components {
+        let exporter = links.iter().any(|l| l.exporter == component.name);
+        // Wired imports use hybrid bindings to call the adapters of the glue
+        let mut import_bindings_map = opts.import_bindings_map.clone().unwrap_or_default();
+        for link in links.iter().filter(|l| l.importer == component.name) {
+            import_bindings_map.insert(link.import.clone(), BindingsMode::Hybrid);
+        }
+        let transpiled = transpile(
+            component.component,
+            TranspileOpts {
+                name: component.name.clone(),
+                instantiation: Some(opts.instantiation.clone().unwrap_or_default()),
+                import_bindings_map: Some(import_bindings_map),
+                export_bindings: if exporter {
+                    Some(ExportBindingsMode::Hybrid)
+                } else {
+                    opts.export_bindings.clone()
+                },
+                ..opts.clone()
+            },
+        )
+        .with_context(|| format!("failed to transpile component `{}`", component.name))?;
+        let linked_specifiers = links
+            .iter()
+            .filter(|l| l.importer == component.name)
+            .map(|l| map_import(&opts.map, &l.import).0)
+            .collect::<Vec<_>>();
+        for import in transpiled.imports {
+            if !linked_specifiers.contains(&import) && !imports.contains(&import) {
+                imports.push(import);
+            }
+        }
+        for (name, source) in transpiled.files {
+            files.push(&name, &source);
+        }
+    }
+
+    let glue = link_bindgen::link_bindgen(&linked, links, &order, &opts)
+        .context("failed to generate linking bindings")?;
+    files.push(&format!("{}.js", opts.name), glue.as_bytes());
+
+    Ok(Linked {
+        files: files
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_vec()))
+            .collect(),
+        imports,
     })
 }
 
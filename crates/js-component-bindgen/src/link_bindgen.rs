@@ -0,0 +1,850 @@
+//! Direct linking of a set of transpiled components.
+//!
+//! Each component is transpiled in instantiation mode, and a glue module is
+//! generated which instantiates them in dependency order, providing the
+//! exports of components as the imports wired to them.
+//!
+//! Rather than going through JS values, the functions of a wiring are
+//! connected at the canonical ABI level: the exporting component exposes its
+//! core functions with their canonical options under `Symbol.for('cabiLift')`,
+//! and the importing component, using hybrid import bindings, obtains from a
+//! `Symbol.for('cabiLower')` factory an adapter calling the core function of
+//! the exporting component directly, copying strings and lists from memory to
+//! memory.
+//!
+//! Functions whose parameters or results use resources, or variants carrying
+//! strings or lists in their flat representation, are not adapted, and are
+//! still called through JS values.
+
+use crate::names::{maybe_quote_id, maybe_quote_member, LocalNames};
+use crate::source::Source;
+use crate::transpile_bindgen::{map_export, map_import, InstantiationMode, TranspileOpts};
+use crate::{uwrite, uwriteln};
+use anyhow::{bail, Result};
+use heck::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use wit_parser::abi::AbiVariant;
+use wit_parser::*;
+
+/// A component to transpile as part of a linked set
+pub struct LinkComponent<'a> {
+    /// Name of the component, used for its output files and in the wiring
+    pub name: String,
+    pub component: &'a [u8],
+}
+
+/// Wiring of an import of a component to the export of the same name of
+/// another component
+pub struct Link {
+    /// Name of the importing component
+    pub importer: String,
+    /// Name of the import, such as `test:pkg/iface@0.1.0`
+    pub import: String,
+    /// Name of the exporting component
+    pub exporter: String,
+}
+
+pub struct Linked {
+    pub files: Vec<(String, Vec<u8>)>,
+    /// Import specifiers of the linked set which are not wired
+    pub imports: Vec<String>,
+}
+
+/// A component of a linked set, with the world it targets
+pub(crate) struct LinkedComponent<'a> {
+    pub name: &'a str,
+    pub resolve: Resolve,
+    pub world: WorldId,
+}
+
+/// Wiring of a link, between the indices of its components
+struct Wire<'a> {
+    importer: usize,
+    exporter: usize,
+    import: &'a str,
+    item: &'a WorldItem,
+}
+
+/// Check the wiring of the links, returning the order in which to instantiate
+/// the components
+pub(crate) fn instantiation_order(
+    components: &[LinkedComponent],
+    links: &[Link],
+) -> Result<Vec<usize>> {
+    let wires = wires(components, links)?;
+
+    // Exporters are instantiated before their importers
+    let mut dependencies = vec![BTreeSet::new(); components.len()];
+    for wire in wires.iter() {
+        dependencies[wire.importer].insert(wire.exporter);
+    }
+    let mut order = Vec::new();
+    while order.len() < components.len() {
+        let Some(next) = (0..components.len())
+            .find(|i| !order.contains(i) && dependencies[*i].iter().all(|dep| order.contains(dep)))
+        else {
+            let cycle = (0..components.len())
+                .filter(|i| !order.contains(i))
+                .map(|i| components[i].name)
+                .collect::<Vec<_>>();
+            bail!("cyclic links between components {}", cycle.join(", "));
+        };
+        order.push(next);
+    }
+    Ok(order)
+}
+
+fn wires<'a>(components: &'a [LinkedComponent], links: &'a [Link]) -> Result<Vec<Wire<'a>>> {
+    let index = |name: &str| match components.iter().position(|c| c.name == name) {
+        Some(idx) => Ok(idx),
+        None => bail!("link references unknown component `{name}`"),
+    };
+    let mut wires = Vec::new();
+    for link in links {
+        let importer = index(&link.importer)?;
+        let exporter = index(&link.exporter)?;
+        if importer == exporter {
+            bail!("component `{}` cannot be linked to itself", link.importer);
+        }
+        let Some(item) = world_item(&components[importer], link.import.as_str(), true) else {
+            bail!(
+                "component `{}` has no import `{}`",
+                link.importer,
+                link.import
+            );
+        };
+        let Some(export) = world_item(&components[exporter], link.import.as_str(), false) else {
+            bail!(
+                "component `{}` has no export `{}` for the import of `{}`",
+                link.exporter,
+                link.import,
+                link.importer
+            );
+        };
+        match (item, export) {
+            (WorldItem::Function(_), WorldItem::Function(_)) => {}
+            (WorldItem::Interface { id, .. }, WorldItem::Interface { id: export_id, .. }) => {
+                let funcs = &components[exporter].resolve.interfaces[*export_id].functions;
+                let resolve = &components[importer].resolve;
+                if let Some(name) = resolve.interfaces[*id]
+                    .functions
+                    .keys()
+                    .find(|name| !funcs.contains_key(*name))
+                {
+                    bail!(
+                        "export `{}` of component `{}` has no function `{name}`",
+                        link.import,
+                        link.exporter
+                    );
+                }
+            }
+            _ => bail!(
+                "import `{}` of component `{}` does not match the export of component `{}`",
+                link.import,
+                link.importer,
+                link.exporter
+            ),
+        }
+        if wires
+            .iter()
+            .any(|w: &Wire| w.importer == importer && w.import == link.import)
+        {
+            bail!(
+                "import `{}` of component `{}` is linked more than once",
+                link.import,
+                link.importer
+            );
+        }
+        wires.push(Wire {
+            importer,
+            exporter,
+            import: &link.import,
+            item,
+        });
+    }
+    Ok(wires)
+}
+
+/// The import or export item of a world by name
+fn world_item<'a>(
+    component: &'a LinkedComponent,
+    name: &str,
+    import: bool,
+) -> Option<&'a WorldItem> {
+    let world = &component.resolve.worlds[component.world];
+    let items = if import {
+        &world.imports
+    } else {
+        &world.exports
+    };
+    items
+        .iter()
+        .find(|(key, _)| component.resolve.name_world_key(key) == name)
+        .map(|(_, item)| item)
+}
+
+/// Generate the glue module instantiating the linked components
+pub(crate) fn link_bindgen(
+    components: &[LinkedComponent],
+    links: &[Link],
+    order: &[usize],
+    opts: &TranspileOpts,
+) -> Result<String> {
+    let wires = wires(components, links)?;
+    let sync = matches!(opts.instantiation, Some(InstantiationMode::Sync));
+
+    let mut local_names = LocalNames::default();
+    local_names.exclude_globals(LINK_GLOBAL_NAMES);
+    let mut adapters = Source::default();
+    let mut instantiate = Source::default();
+    let mut output = Source::default();
+
+    let instantiate_fns = components
+        .iter()
+        .map(|component| {
+            let local_name = local_names
+                .create_once(&format!("instantiate-{}", component.name))
+                .to_string();
+            uwriteln!(
+                output,
+                "import {{ instantiate as {local_name} }} from './{}.js';",
+                component.name
+            );
+            local_name
+        })
+        .collect::<Vec<_>>();
+    let component_exports = components
+        .iter()
+        .map(|component| local_names.create_once(component.name).to_string())
+        .collect::<Vec<_>>();
+
+    for &idx in order {
+        let component = &components[idx];
+
+        // Wired imports, grouped by import specifier and member
+        let mut linked_imports: BTreeMap<String, BTreeMap<Option<String>, String>> =
+            BTreeMap::new();
+        for wire in wires.iter().filter(|w| w.importer == idx) {
+            let exporter = &components[wire.exporter];
+            let exports = &component_exports[wire.exporter];
+            let (specifier, member) = map_import(&opts.map, wire.import);
+            let value = match wire.item {
+                WorldItem::Function(func) => {
+                    let export = format!(
+                        "{exports}{}",
+                        maybe_quote_member(&func.name.to_lower_camel_case())
+                    );
+                    let export_func = match world_item(exporter, wire.import, false) {
+                        Some(WorldItem::Function(func)) => func,
+                        _ => unreachable!(),
+                    };
+                    let linked = link_function(
+                        &mut adapters,
+                        &mut local_names,
+                        &component.resolve,
+                        func,
+                        &exporter.resolve,
+                        export_func,
+                        &export,
+                    );
+                    format!("{{ default: {linked} }}")
+                }
+                WorldItem::Interface { id, .. } => {
+                    let export_id = match world_item(exporter, wire.import, false) {
+                        Some(WorldItem::Interface { id, .. }) => *id,
+                        _ => unreachable!(),
+                    };
+                    // interfaces of the export map are read off their namespaces
+                    let iface = match map_export(&opts.export_map, wire.import) {
+                        Some(path) => path.iter().fold(exports.to_string(), |iface, member| {
+                            format!("{iface}{}", maybe_quote_member(member))
+                        }),
+                        None => format!("{exports}{}", maybe_quote_member(wire.import)),
+                    };
+                    let mut value = format!("{{\n...{iface},\n");
+                    for (name, func) in component.resolve.interfaces[*id].functions.iter() {
+                        if !matches!(func.kind, FunctionKind::Freestanding) {
+                            continue;
+                        }
+                        let export_func = &exporter.resolve.interfaces[export_id].functions[name];
+                        let name = name.to_lower_camel_case();
+                        let linked = link_function(
+                            &mut adapters,
+                            &mut local_names,
+                            &component.resolve,
+                            func,
+                            &exporter.resolve,
+                            export_func,
+                            &format!("{iface}{}", maybe_quote_member(&name)),
+                        );
+                        uwriteln!(value, "{}: {linked},", maybe_quote_id(&name));
+                    }
+                    value.push('}');
+                    value
+                }
+                WorldItem::Type(_) => unreachable!(),
+            };
+            linked_imports
+                .entry(specifier)
+                .or_default()
+                .insert(member, value);
+        }
+
+        let call = if sync { "" } else { "await " };
+        let exports = &component_exports[idx];
+        if linked_imports.is_empty() {
+            uwriteln!(
+                instantiate,
+                "const {exports} = {call}{}(getCoreModule, imports, instantiateCore);",
+                instantiate_fns[idx]
+            );
+            continue;
+        }
+        uwriteln!(
+            instantiate,
+            "const {exports} = {call}{}(getCoreModule, {{\n...imports,",
+            instantiate_fns[idx]
+        );
+        for (specifier, members) in linked_imports {
+            let quoted = maybe_quote_id(&specifier);
+            uwrite!(instantiate, "{quoted}: ");
+            if members.contains_key(&None) {
+                instantiate.push_str(&members[&None]);
+            } else {
+                uwriteln!(
+                    instantiate,
+                    "{{\n...imports{},",
+                    maybe_quote_member(&specifier)
+                );
+                for (member, value) in members {
+                    let member = member.unwrap();
+                    uwriteln!(instantiate, "{}: {value},", maybe_quote_id(&member));
+                }
+                instantiate.push_str("}");
+            }
+            uwriteln!(instantiate, ",");
+        }
+        uwriteln!(instantiate, "}}, instantiateCore);");
+    }
+
+    output.push_str(LINK_RUNTIME);
+    output.push_str(&adapters);
+    uwriteln!(
+        output,
+        "\nexport {}function instantiate (getCoreModule, imports, instantiateCore) {{",
+        if sync { "" } else { "async " }
+    );
+    output.push_str(&instantiate);
+    let exports = components
+        .iter()
+        .zip(component_exports.iter())
+        .map(|(component, local_name)| {
+            let name = maybe_quote_id(component.name);
+            if name == *local_name {
+                name
+            } else {
+                format!("{name}: {local_name}")
+            }
+        })
+        .collect::<Vec<_>>();
+    uwriteln!(output, "return {{ {} }};", exports.join(", "));
+    uwriteln!(output, "}}");
+
+    Ok(output.into())
+}
+
+/// Expression for the linked version of an exported function, with a
+/// lowering factory adapting the calls to it when possible
+fn link_function(
+    adapters: &mut Source,
+    local_names: &mut LocalNames,
+    resolve: &Resolve,
+    func: &Function,
+    export_resolve: &Resolve,
+    export_func: &Function,
+    export: &str,
+) -> String {
+    let mut sizes = SizeAlign::default();
+    sizes.fill(resolve);
+    let mut adapter = Adapter {
+        resolve,
+        sizes: &sizes,
+        src: Source::default(),
+        tmp: 0,
+    };
+    // The function types of the import and export must match, which is
+    // checked on their flat signatures
+    let import_sig = resolve.wasm_signature(AbiVariant::GuestImport, func);
+    let export_sig = export_resolve.wasm_signature(AbiVariant::GuestImport, export_func);
+    if import_sig != export_sig || !adapter.supported(func) {
+        return format!("linkFunction({export})");
+    }
+    adapter.function(func);
+    let local_name = local_names
+        .create_once(&format!("lower-{}", func.name))
+        .to_string();
+    uwriteln!(
+        adapters,
+        "\nfunction {local_name} (caller, callee) {{\nreturn {};\n}}",
+        &adapter.src as &str
+    );
+    format!("linkFunction({export}, {local_name})")
+}
+
+/// Generator of the adapter of a function, calling the core function of the
+/// exporting component (`callee`) with the core arguments of the importing
+/// component (`caller`)
+struct Adapter<'a> {
+    resolve: &'a Resolve,
+    sizes: &'a SizeAlign,
+    src: Source,
+    tmp: usize,
+}
+
+impl Adapter<'_> {
+    fn tmp(&mut self) -> usize {
+        let ret = self.tmp;
+        self.tmp += 1;
+        ret
+    }
+
+    /// Whether calls to a function can be adapted
+    fn supported(&self, func: &Function) -> bool {
+        if !matches!(func.kind, FunctionKind::Freestanding) {
+            return false;
+        }
+        let import_sig = self.resolve.wasm_signature(AbiVariant::GuestImport, func);
+        let types = func
+            .params
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(func.results.iter_types());
+        for ty in types {
+            if !self.copyable(ty) {
+                return false;
+            }
+        }
+        // Flat values are copied by type, unless passed through memory
+        (import_sig.indirect_params || func.params.iter().all(|(_, ty)| self.flat_copyable(ty)))
+            && (import_sig.retptr || func.results.iter_types().all(|ty| self.flat_copyable(ty)))
+    }
+
+    /// Whether values of a type can be copied between memories, which is the
+    /// case unless they contain handles
+    fn copyable(&self, ty: &Type) -> bool {
+        let Type::Id(id) = ty else {
+            return true;
+        };
+        match &self.resolve.types[*id].kind {
+            TypeDefKind::Type(ty) | TypeDefKind::List(ty) | TypeDefKind::Option(ty) => {
+                self.copyable(ty)
+            }
+            TypeDefKind::Record(record) => record.fields.iter().all(|f| self.copyable(&f.ty)),
+            TypeDefKind::Tuple(tuple) => tuple.types.iter().all(|ty| self.copyable(ty)),
+            TypeDefKind::Variant(variant) => variant
+                .cases
+                .iter()
+                .all(|c| c.ty.as_ref().map_or(true, |ty| self.copyable(ty))),
+            TypeDefKind::Result(result) => result
+                .ok
+                .iter()
+                .chain(result.err.iter())
+                .all(|ty| self.copyable(ty)),
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => true,
+            TypeDefKind::Handle(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Unknown => false,
+        }
+    }
+
+    /// Whether flat values of a type can be copied, which is not supported
+    /// for variants carrying strings or lists
+    fn flat_copyable(&self, ty: &Type) -> bool {
+        let Type::Id(id) = ty else {
+            return true;
+        };
+        match &self.resolve.types[*id].kind {
+            TypeDefKind::Type(ty) => self.flat_copyable(ty),
+            TypeDefKind::Record(record) => record.fields.iter().all(|f| self.flat_copyable(&f.ty)),
+            TypeDefKind::Tuple(tuple) => tuple.types.iter().all(|ty| self.flat_copyable(ty)),
+            TypeDefKind::Variant(_) | TypeDefKind::Option(_) | TypeDefKind::Result(_) => {
+                self.pointer_free(ty)
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether values of a type are copied as-is, not containing strings or
+    /// lists
+    fn pointer_free(&self, ty: &Type) -> bool {
+        let Type::Id(id) = ty else {
+            return !matches!(ty, Type::String);
+        };
+        match &self.resolve.types[*id].kind {
+            TypeDefKind::Type(ty) | TypeDefKind::Option(ty) => self.pointer_free(ty),
+            TypeDefKind::List(_) => false,
+            TypeDefKind::Record(record) => record.fields.iter().all(|f| self.pointer_free(&f.ty)),
+            TypeDefKind::Tuple(tuple) => tuple.types.iter().all(|ty| self.pointer_free(ty)),
+            TypeDefKind::Variant(variant) => variant
+                .cases
+                .iter()
+                .all(|c| c.ty.as_ref().map_or(true, |ty| self.pointer_free(ty))),
+            TypeDefKind::Result(result) => result
+                .ok
+                .iter()
+                .chain(result.err.iter())
+                .all(|ty| self.pointer_free(ty)),
+            _ => true,
+        }
+    }
+
+    fn flat_len(&self, ty: &Type) -> usize {
+        let mut flat = Vec::new();
+        self.resolve.push_flat(ty, &mut flat);
+        flat.len()
+    }
+
+    /// Adapter of a function, copying its parameters from the caller to the
+    /// callee and its results back
+    fn function(&mut self, func: &Function) {
+        let sig = self.resolve.wasm_signature(AbiVariant::GuestImport, func);
+        let params = (0..sig.params.len())
+            .map(|i| format!("arg{i}"))
+            .collect::<Vec<_>>();
+        uwriteln!(self.src, "function ({}) {{", params.join(", "));
+        uwriteln!(self.src, "let src = caller, dst = callee;");
+        let args = if sig.indirect_params {
+            let (size, align) = self.sizes.record(func.params.iter().map(|(_, ty)| ty));
+            let ptr = format!("ptr{}", self.tmp());
+            uwriteln!(
+                self.src,
+                "var {ptr} = callee.realloc(0, 0, {align}, {size});"
+            );
+            let offsets = self
+                .sizes
+                .field_offsets(func.params.iter().map(|(_, ty)| ty));
+            for (offset, ty) in offsets {
+                self.copy(
+                    ty,
+                    &format!("{} + {offset}", params[0]),
+                    &format!("{ptr} + {offset}"),
+                );
+            }
+            vec![ptr]
+        } else {
+            let mut args = Vec::new();
+            let mut flat = &params[..];
+            for (_, ty) in func.params.iter() {
+                let len = self.flat_len(ty);
+                args.extend(self.copy_flat(ty, &flat[..len]));
+                flat = &flat[len..];
+            }
+            args
+        };
+
+        if sig.retptr {
+            let retptr = params.last().unwrap();
+            uwriteln!(self.src, "const ret = callee.func({});", args.join(", "));
+            uwriteln!(self.src, "src = callee, dst = caller;");
+            let offsets = self.sizes.field_offsets(func.results.iter_types());
+            for (offset, ty) in offsets {
+                self.copy(
+                    ty,
+                    &format!("ret + {offset}"),
+                    &format!("{retptr} + {offset}"),
+                );
+            }
+            uwriteln!(self.src, "if (callee.postReturn) callee.postReturn(ret);");
+        } else if sig.results.is_empty() {
+            uwriteln!(self.src, "callee.func({});", args.join(", "));
+            uwriteln!(self.src, "if (callee.postReturn) callee.postReturn();");
+        } else {
+            // Single flat results are scalars, returned as-is
+            uwriteln!(self.src, "const ret = callee.func({});", args.join(", "));
+            uwriteln!(self.src, "if (callee.postReturn) callee.postReturn(ret);");
+            uwriteln!(self.src, "return ret;");
+        }
+        uwrite!(self.src, "}}");
+    }
+
+    /// Copy the flat values of a type from `src` to `dst`, returning the flat
+    /// values for `dst`
+    fn copy_flat(&mut self, ty: &Type, vals: &[String]) -> Vec<String> {
+        if self.pointer_free(ty) {
+            return vals.to_vec();
+        }
+        match ty {
+            Type::String => {
+                let tmp = self.tmp();
+                uwriteln!(
+                    self.src,
+                    "var [ptr{tmp}, len{tmp}] = copyString(src, dst, {}, {});",
+                    vals[0],
+                    vals[1]
+                );
+                vec![format!("ptr{tmp}"), format!("len{tmp}")]
+            }
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(ty) => self.copy_flat(ty, vals),
+                TypeDefKind::List(elem) => {
+                    let ptr = self.copy_list(elem, &vals[0], &vals[1]);
+                    vec![ptr, vals[1].clone()]
+                }
+                TypeDefKind::Record(record) => {
+                    self.copy_flat_fields(record.fields.iter().map(|f| &f.ty), vals)
+                }
+                TypeDefKind::Tuple(tuple) => self.copy_flat_fields(tuple.types.iter(), vals),
+                _ => unreachable!("unsupported flat copy"),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn copy_flat_fields<'b>(
+        &mut self,
+        types: impl Iterator<Item = &'b Type>,
+        mut vals: &[String],
+    ) -> Vec<String> {
+        let mut ret = Vec::new();
+        for ty in types {
+            let len = self.flat_len(ty);
+            ret.extend(self.copy_flat(ty, &vals[..len]));
+            vals = &vals[len..];
+        }
+        ret
+    }
+
+    /// Copy a list from `src` to `dst`, returning the pointer to its copy
+    fn copy_list(&mut self, elem: &Type, ptr: &str, len: &str) -> String {
+        let size = self.sizes.size(elem);
+        let align = self.sizes.align(elem);
+        let copy_elem = if self.pointer_free(elem) {
+            "null".to_string()
+        } else {
+            let tmp = self.tmp();
+            let src = std::mem::take(&mut self.src);
+            self.copy(elem, &format!("srcPtr{tmp}"), &format!("dstPtr{tmp}"));
+            let body = std::mem::replace(&mut self.src, src);
+            format!("(srcPtr{tmp}, dstPtr{tmp}) => {{\n{}}}", &body as &str)
+        };
+        let tmp = self.tmp();
+        uwriteln!(
+            self.src,
+            "var ptr{tmp} = copyList(src, dst, {ptr}, {len}, {size}, {align}, {copy_elem});"
+        );
+        format!("ptr{tmp}")
+    }
+
+    /// Copy a value of a type at `src_ptr` in `src` to `dst_ptr` in `dst`
+    fn copy(&mut self, ty: &Type, src_ptr: &str, dst_ptr: &str) {
+        if self.pointer_free(ty) {
+            let size = self.sizes.size(ty);
+            uwriteln!(
+                self.src,
+                "copyBytes(src, dst, {src_ptr}, {dst_ptr}, {size});"
+            );
+            return;
+        }
+        match ty {
+            Type::String => {
+                let tmp = self.tmp();
+                uwriteln!(
+                    self.src,
+                    "var [ptr{tmp}, len{tmp}] = copyString(src, dst, load32(src, {src_ptr}), load32(src, {src_ptr} + 4));"
+                );
+                uwriteln!(self.src, "store32(dst, {dst_ptr}, ptr{tmp});");
+                uwriteln!(self.src, "store32(dst, {dst_ptr} + 4, len{tmp});");
+            }
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(ty) => self.copy(ty, src_ptr, dst_ptr),
+                TypeDefKind::List(elem) => {
+                    let tmp = self.tmp();
+                    uwriteln!(self.src, "var len{tmp} = load32(src, {src_ptr} + 4);");
+                    let ptr = self.copy_list(
+                        elem,
+                        &format!("load32(src, {src_ptr})"),
+                        &format!("len{tmp}"),
+                    );
+                    uwriteln!(self.src, "store32(dst, {dst_ptr}, {ptr});");
+                    uwriteln!(self.src, "store32(dst, {dst_ptr} + 4, len{tmp});");
+                }
+                TypeDefKind::Record(record) => {
+                    self.copy_fields(record.fields.iter().map(|f| &f.ty), src_ptr, dst_ptr)
+                }
+                TypeDefKind::Tuple(tuple) => self.copy_fields(tuple.types.iter(), src_ptr, dst_ptr),
+                TypeDefKind::Variant(variant) => self.copy_variant(
+                    variant.tag(),
+                    variant.cases.iter().map(|c| c.ty.as_ref()),
+                    src_ptr,
+                    dst_ptr,
+                ),
+                TypeDefKind::Option(ty) => {
+                    self.copy_variant(Int::U8, [None, Some(ty)], src_ptr, dst_ptr)
+                }
+                TypeDefKind::Result(result) => self.copy_variant(
+                    Int::U8,
+                    [result.ok.as_ref(), result.err.as_ref()],
+                    src_ptr,
+                    dst_ptr,
+                ),
+                _ => unreachable!("unsupported copy"),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn copy_fields<'b>(
+        &mut self,
+        types: impl IntoIterator<Item = &'b Type>,
+        src_ptr: &str,
+        dst_ptr: &str,
+    ) {
+        for (offset, ty) in self.sizes.field_offsets(types) {
+            self.copy(
+                ty,
+                &format!("{src_ptr} + {offset}"),
+                &format!("{dst_ptr} + {offset}"),
+            );
+        }
+    }
+
+    fn copy_variant<'b>(
+        &mut self,
+        tag: Int,
+        cases: impl IntoIterator<Item = Option<&'b Type>> + Clone,
+        src_ptr: &str,
+        dst_ptr: &str,
+    ) {
+        let (tag_size, load) = match tag {
+            Int::U8 => (1, "getUint8"),
+            Int::U16 => (2, "getUint16"),
+            Int::U32 => (4, "getUint32"),
+            Int::U64 => unreachable!(),
+        };
+        let payload_offset = self.sizes.payload_offset(tag, cases.clone());
+        uwriteln!(
+            self.src,
+            "copyBytes(src, dst, {src_ptr}, {dst_ptr}, {tag_size});"
+        );
+        uwriteln!(
+            self.src,
+            "switch (new DataView(src.memory.buffer).{load}({src_ptr} >>> 0, true)) {{"
+        );
+        for (i, ty) in cases.into_iter().enumerate() {
+            let Some(ty) = ty else {
+                continue;
+            };
+            uwriteln!(self.src, "case {i}: {{");
+            self.copy(
+                ty,
+                &format!("{src_ptr} + {payload_offset}"),
+                &format!("{dst_ptr} + {payload_offset}"),
+            );
+            uwriteln!(self.src, "break;\n}}");
+        }
+        uwriteln!(self.src, "}}");
+    }
+}
+
+/// Global names used by the glue module, which must not be shadowed by local
+/// definitions
+const LINK_GLOBAL_NAMES: &[&str] = &[
+    "DataView",
+    "Symbol",
+    "TextDecoder",
+    "TextEncoder",
+    "TypeError",
+    "Uint16Array",
+    "Uint8Array",
+    "copyBytes",
+    "copyList",
+    "copyString",
+    "getCoreModule",
+    "imports",
+    "instantiate",
+    "instantiateCore",
+    "linkFunction",
+    "load32",
+    "store32",
+    "symbolCabiLift",
+    "symbolCabiLower",
+    "utf16Decoder",
+    "utf8Decoder",
+    "utf8Encoder",
+];
+
+const LINK_RUNTIME: &str = "
+const symbolCabiLift = Symbol.for('cabiLift');
+const symbolCabiLower = Symbol.for('cabiLower');
+const utf8Decoder = new TextDecoder();
+const utf16Decoder = new TextDecoder('utf-16');
+const utf8Encoder = new TextEncoder();
+
+// Link an exported function, defining a lowering factory with its adapter
+// when the core function of the export is available
+function linkFunction (fn, adapter) {
+  const linked = function (...args) {
+    return fn.apply(this, args);
+  };
+  Object.defineProperty(linked, 'name', { value: fn.name });
+  if (adapter && fn[symbolCabiLift]) {
+    linked[symbolCabiLower] = ({ memory, realloc, stringEncoding = 'utf8' }) => {
+      const { func, memory: liftMemory, realloc: liftRealloc, postReturn, stringEncoding: liftStringEncoding = 'utf8' } = fn[symbolCabiLift];
+      for (const encoding of [stringEncoding, liftStringEncoding]) {
+        if (encoding !== 'utf8' && encoding !== 'utf16') {
+          throw new TypeError(`Unsupported string encoding \"${encoding}\" for linking \"${fn.name}\"`);
+        }
+      }
+      const caller = { memory, realloc, stringEncoding };
+      const callee = { func, memory: liftMemory, realloc: liftRealloc, postReturn, stringEncoding: liftStringEncoding };
+      return adapter(caller, callee);
+    };
+  }
+  return linked;
+}
+
+function load32 (side, ptr) {
+  return new DataView(side.memory.buffer).getInt32(ptr >>> 0, true);
+}
+
+function store32 (side, ptr, val) {
+  new DataView(side.memory.buffer).setInt32(ptr >>> 0, val, true);
+}
+
+function copyBytes (src, dst, srcPtr, dstPtr, len) {
+  new Uint8Array(dst.memory.buffer, dstPtr >>> 0, len).set(new Uint8Array(src.memory.buffer, srcPtr >>> 0, len));
+}
+
+function copyList (src, dst, ptr, len, size, align, copyElem) {
+  const dstPtr = dst.realloc(0, 0, align, len * size);
+  if (copyElem) {
+    for (let i = 0; i < len; i++) copyElem(ptr + i * size, dstPtr + i * size);
+  } else {
+    copyBytes(src, dst, ptr, dstPtr, len * size);
+  }
+  return dstPtr;
+}
+
+function copyString (src, dst, ptr, len) {
+  if (src.stringEncoding === dst.stringEncoding) {
+    const utf16 = src.stringEncoding === 'utf16';
+    const dstPtr = dst.realloc(0, 0, utf16 ? 2 : 1, utf16 ? len * 2 : len);
+    copyBytes(src, dst, ptr, dstPtr, utf16 ? len * 2 : len);
+    return [dstPtr, len];
+  }
+  if (src.stringEncoding === 'utf16') {
+    const bytes = utf8Encoder.encode(utf16Decoder.decode(new Uint16Array(src.memory.buffer, ptr >>> 0, len)));
+    const dstPtr = dst.realloc(0, 0, 1, bytes.length);
+    new Uint8Array(dst.memory.buffer, dstPtr >>> 0, bytes.length).set(bytes);
+    return [dstPtr, bytes.length];
+  }
+  const str = utf8Decoder.decode(new Uint8Array(src.memory.buffer, ptr >>> 0, len));
+  const dstPtr = dst.realloc(0, 0, 2, str.length * 2);
+  const view = new Uint16Array(dst.memory.buffer, dstPtr >>> 0, str.length);
+  for (let i = 0; i < str.length; i++) view[i] = str.charCodeAt(i);
+  return [dstPtr, str.length];
+}
+";
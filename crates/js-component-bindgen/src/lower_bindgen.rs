@@ -0,0 +1,538 @@
+//! Generation of `Symbol.for('cabiLower')` lowering factories for JS hosts.
+//!
+//! Components transpiled with the hybrid or optimized import bindings modes
+//! look up a `[Symbol.for('cabiLower')]` factory on their imported functions,
+//! and call it with the canonical options of the import,
+//! `{ memory, realloc, postReturn, stringEncoding, resourceTables }`, to obtain
+//! a function taking the core Wasm arguments directly.
+//!
+//! Every import of the world gets its own ES module under `lower/`, exporting
+//! a `cabiLower(impl)` function. Given the high-level JS implementation of the
+//! import, shaped as for the JS bindings (functions by their camel case names,
+//! or `default` for world-level functions, and resource classes by their
+//! upper camel case names), it attaches these factories to its functions,
+//! resource methods, static functions and classes (for constructors), and
+//! returns the implementation.
+//!
+//! The `resourceTables` of a factory are the handle tables of the resources
+//! used by the function, in the order the resources are defined in. Resource
+//! classes of other imports are read from the module generated for them, which
+//! must be passed its implementation before the factories are called. That
+//! module also owns the capture table of the resource, mapping the reps of
+//! resource objects passed to the component back to the objects, so that the
+//! same object is lifted whichever import it crosses.
+
+use crate::files::Files;
+use crate::function_bindgen::{
+    ErrHandling, FunctionBindgen, ResourceData, ResourceMap, ResourceTable,
+};
+use crate::intrinsics::{render_intrinsics, Intrinsic};
+use crate::names::{maybe_quote_member, specifier_goal_name, LocalNames};
+use crate::source::Source;
+use crate::{dealias, uwrite, uwriteln};
+use heck::*;
+use indexmap::{IndexMap, IndexSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use wasmtime_environ::component::{ResourceIndex, TypeResourceTableIndex};
+use wit_bindgen_core::abi::{self, LiftLower};
+use wit_component::StringEncoding;
+use wit_parser::abi::AbiVariant;
+use wit_parser::*;
+
+/// Location of an imported resource class within the generated modules
+struct ResourceLocation {
+    import_name: String,
+    class_name: String,
+}
+
+#[derive(Default)]
+struct LowerModule<'a> {
+    /// Freestanding functions, by export name (`default` for world-level
+    /// function imports)
+    funcs: Vec<(String, &'a Function)>,
+    /// Resources defined by this import, with their functions
+    resources: IndexMap<TypeId, Vec<&'a Function>>,
+}
+
+struct LowerBindgen<'a> {
+    resolve: &'a Resolve,
+    sizes: SizeAlign,
+    modules: BTreeMap<String, LowerModule<'a>>,
+    resources: HashMap<TypeId, ResourceLocation>,
+}
+
+pub fn lower_bindgen(resolve: &Resolve, id: WorldId, files: &mut Files) -> anyhow::Result<()> {
+    let world = &resolve.worlds[id];
+    let mut sizes = SizeAlign::default();
+    sizes.fill(resolve);
+    let mut gen = LowerBindgen {
+        resolve,
+        sizes,
+        modules: BTreeMap::new(),
+        resources: HashMap::new(),
+    };
+
+    // Resource functions are attached to their resource class, so first
+    // register all imported resources before any functions.
+    for (key, item) in world.imports.iter() {
+        let import_name = resolve.name_world_key(key);
+        match item {
+            WorldItem::Interface { id, .. } => {
+                for ty in resolve.interfaces[*id].types.values() {
+                    if matches!(resolve.types[*ty].kind, TypeDefKind::Resource) {
+                        gen.add_resource(*ty, &import_name);
+                    }
+                }
+            }
+            WorldItem::Type(ty) => {
+                if matches!(resolve.types[*ty].kind, TypeDefKind::Resource) {
+                    gen.add_resource(*ty, &import_name);
+                }
+            }
+            WorldItem::Function(_) => {}
+        }
+    }
+
+    for (key, item) in world.imports.iter() {
+        let import_name = resolve.name_world_key(key);
+        match item {
+            WorldItem::Function(func) => gen.add_func(func, &import_name, "default"),
+            WorldItem::Interface { id, .. } => {
+                for (name, func) in resolve.interfaces[*id].functions.iter() {
+                    gen.add_func(func, &import_name, &name.to_lower_camel_case());
+                }
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+
+    gen.finish(files);
+
+    Ok(())
+}
+
+impl<'a> LowerBindgen<'a> {
+    fn add_resource(&mut self, ty: TypeId, import_name: &str) {
+        let class_name = self.resolve.types[ty]
+            .name
+            .as_ref()
+            .expect("resource name")
+            .to_upper_camel_case();
+        self.modules
+            .entry(import_name.to_string())
+            .or_default()
+            .resources
+            .insert(ty, Vec::new());
+        self.resources.insert(
+            ty,
+            ResourceLocation {
+                import_name: import_name.to_string(),
+                class_name,
+            },
+        );
+    }
+
+    fn add_func(&mut self, func: &'a Function, import_name: &str, export_name: &str) {
+        match func.kind {
+            FunctionKind::Freestanding => {
+                self.modules
+                    .entry(import_name.to_string())
+                    .or_default()
+                    .funcs
+                    .push((export_name.to_string(), func));
+            }
+            FunctionKind::Method(ty) | FunctionKind::Static(ty) | FunctionKind::Constructor(ty) => {
+                let ty = dealias(self.resolve, ty);
+                let location = &self.resources[&ty];
+                self.modules
+                    .get_mut(&location.import_name)
+                    .unwrap()
+                    .resources
+                    .get_mut(&ty)
+                    .unwrap()
+                    .push(func);
+            }
+        }
+    }
+
+    fn finish(self, files: &mut Files) {
+        // Allocate a unique file name per import
+        let mut file_names = BTreeMap::new();
+        let mut used_names = HashSet::new();
+        for import_name in self.modules.keys() {
+            let goal_name = specifier_goal_name(import_name);
+            let mut file_name = format!("{goal_name}.js");
+            let mut idx = 1;
+            while !used_names.insert(file_name.clone()) {
+                idx += 1;
+                file_name = format!("{goal_name}-{idx}.js");
+            }
+            file_names.insert(import_name.to_string(), file_name);
+        }
+
+        for (import_name, module) in self.modules.iter() {
+            let mut gen = LowerModuleGen {
+                resolve: self.resolve,
+                sizes: &self.sizes,
+                import_name,
+                resources: &self.resources,
+                file_names: &file_names,
+                local_names: LocalNames::default(),
+                local_resources: HashMap::new(),
+                resource_imports: BTreeMap::new(),
+                capture_tables: IndexSet::new(),
+                intrinsics: BTreeSet::new(),
+                src: Source::default(),
+            };
+            gen.local_names
+                .exclude_globals(Intrinsic::get_global_names());
+            let src = gen.generate(module);
+            files.push(
+                &format!("lower/{}", file_names[import_name]),
+                src.as_bytes(),
+            );
+        }
+    }
+}
+
+/// Generator for a single lowering module, corresponding to one import
+struct LowerModuleGen<'a, 'b> {
+    resolve: &'a Resolve,
+    sizes: &'b SizeAlign,
+    import_name: &'b str,
+    resources: &'b HashMap<TypeId, ResourceLocation>,
+    file_names: &'b BTreeMap<String, String>,
+    local_names: LocalNames,
+    /// Local names of the resource classes defined by this import
+    local_resources: HashMap<TypeId, String>,
+    /// Resource classes and capture tables imported from the modules of other
+    /// imports, by file name, as pairs of export name and local name
+    resource_imports: BTreeMap<String, Vec<(String, String)>>,
+    /// Resources defined or captured by the factories of this module, indexing
+    /// their `captureTable{rid}` and `captureRep{rid}`
+    capture_tables: IndexSet<TypeId>,
+    intrinsics: BTreeSet<Intrinsic>,
+    src: Source,
+}
+
+impl<'a> LowerModuleGen<'a, '_> {
+    fn generate(&mut self, module: &LowerModule<'a>) -> String {
+        let impl_name = self.local_names.create_once("impl").to_string();
+        for ty in module.resources.keys() {
+            let class_name = &self.resources[ty].class_name;
+            let local_name = self.local_names.create_once(class_name).to_string();
+            self.local_resources.insert(*ty, local_name);
+            self.capture_tables.insert(*ty);
+        }
+
+        for (ty, funcs) in module.resources.iter() {
+            let local_name = self.local_resources[ty].clone();
+            let class_name = &self.resources[ty].class_name;
+            uwriteln!(
+                self.src,
+                "{local_name} = {impl_name}{};",
+                maybe_quote_member(class_name)
+            );
+            for func in funcs {
+                let method_name = func.item_name().to_lower_camel_case();
+                let (target, callee) = match func.kind {
+                    FunctionKind::Constructor(_) => {
+                        (local_name.clone(), format!("new {local_name}"))
+                    }
+                    FunctionKind::Method(_) => (
+                        format!("{local_name}.prototype{}", maybe_quote_member(&method_name)),
+                        method_name.clone(),
+                    ),
+                    FunctionKind::Static(_) => {
+                        let target = format!("{local_name}{}", maybe_quote_member(&method_name));
+                        (target.clone(), target)
+                    }
+                    FunctionKind::Freestanding => unreachable!("resource function"),
+                };
+                self.factory(func, &target, &callee);
+            }
+        }
+        for (export_name, func) in module.funcs.iter() {
+            let target = format!("{impl_name}{}", maybe_quote_member(export_name));
+            self.factory(func, &target, &target);
+        }
+
+        let mut output = Source::default();
+        uwriteln!(
+            output,
+            "// Canonical ABI lowering factories for the '{}' import",
+            self.import_name
+        );
+        for (file_name, classes) in self.resource_imports.iter() {
+            let classes = classes
+                .iter()
+                .map(|(class_name, local_name)| format!("{class_name} as {local_name}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            uwriteln!(output, "import {{ {classes} }} from './{file_name}';");
+        }
+        output.push_str(&render_intrinsics(&mut self.intrinsics, false, false));
+        uwriteln!(output, "");
+        let mut exports = Vec::new();
+        for ty in module.resources.keys() {
+            let local_name = &self.local_resources[ty];
+            let class_name = &self.resources[ty].class_name;
+            uwriteln!(output, "let {local_name};");
+            if local_name == class_name {
+                exports.push(local_name.to_string());
+            } else {
+                exports.push(format!("{local_name} as {class_name}"));
+            }
+        }
+        // Capture tables are defined by the module of their resource, and
+        // imported by the others
+        for (rid, ty) in self.capture_tables.iter().enumerate() {
+            if !module.resources.contains_key(ty) {
+                continue;
+            }
+            let class_name = &self.resources[ty].class_name;
+            uwriteln!(
+                output,
+                "const captureTable{rid} = new Map();
+                let captureCnt{rid} = 0;
+                function captureRep{rid} () {{
+                    return ++captureCnt{rid};
+                }}"
+            );
+            exports.push(format!("captureTable{rid} as {class_name}$captureTable"));
+            exports.push(format!("captureRep{rid} as {class_name}$captureRep"));
+        }
+        uwriteln!(output, "");
+        uwriteln!(output, "export function cabiLower ({impl_name}) {{");
+        output.push_str(&self.src);
+        uwriteln!(output, "return {impl_name};");
+        uwriteln!(output, "}}");
+        // Resource classes are exported for the modules of other imports
+        // using them
+        if !exports.is_empty() {
+            uwriteln!(output, "");
+            uwriteln!(output, "export {{ {} }};", exports.join(", "));
+        }
+
+        output.into()
+    }
+
+    /// Attach the lowering factory of a function to `target`, calling the
+    /// implementation through `callee`
+    fn factory(&mut self, func: &Function, target: &str, callee: &str) {
+        let symbol_cabi_lower = self.intrinsic(Intrinsic::SymbolCabiLower);
+
+        // Resources used by the function, in the order of their definitions,
+        // each getting the handle table at its index in `resourceTables`
+        let mut resource_tys = BTreeSet::new();
+        for (_, ty) in func.params.iter() {
+            self.collect_resources(ty, &mut resource_tys);
+        }
+        for ty in func.results.iter_types() {
+            self.collect_resources(ty, &mut resource_tys);
+        }
+        let mut resource_map = ResourceMap::new();
+        for (tid, ty) in resource_tys.iter().enumerate() {
+            let local_name = self.resource_class(*ty);
+            let rid = self.capture_table(*ty);
+            resource_map.insert(
+                *ty,
+                ResourceTable {
+                    imported: true,
+                    data: ResourceData::Host {
+                        tid: TypeResourceTableIndex::from_u32(tid as u32),
+                        rid: ResourceIndex::from_u32(rid as u32),
+                        local_name,
+                        dtor_name: None,
+                        capture_rep: Some(format!("captureRep{rid}()")),
+                    },
+                },
+            );
+        }
+        let resource_tables = if resource_tys.is_empty() {
+            String::new()
+        } else {
+            let handle_tables = (0..resource_tys.len())
+                .map(|tid| format!("handleTable{tid}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(", resourceTables: [{handle_tables}]")
+        };
+
+        uwriteln!(
+            self.src,
+            "{target}[{symbol_cabi_lower}] = function ({{ memory, realloc, stringEncoding = 'utf8'{resource_tables} }}) {{"
+        );
+        // Bindings only differ by string encoding for functions using strings
+        let utf8 = self.bindgen(func, callee, &resource_map, StringEncoding::UTF8);
+        let utf16 = self.bindgen(func, callee, &resource_map, StringEncoding::UTF16);
+        if utf8 != utf16 {
+            uwriteln!(
+                self.src,
+                "if (stringEncoding === 'utf16') {{
+                    return {utf16};
+                }}
+                if (stringEncoding !== 'utf8') {{
+                    throw new TypeError(`Unsupported string encoding \"${{stringEncoding}}\" for \"{}\"`);
+                }}",
+                func.name
+            );
+        }
+        uwriteln!(self.src, "return {utf8};");
+        uwriteln!(self.src, "}};");
+    }
+
+    /// Binding of a function taking the core Wasm arguments, lifting them to
+    /// call the implementation and lowering its results
+    fn bindgen(
+        &mut self,
+        func: &Function,
+        callee: &str,
+        resource_map: &ResourceMap,
+        encoding: StringEncoding,
+    ) -> String {
+        let memory = "memory".to_string();
+        let realloc = "realloc".to_string();
+        let nparams = self
+            .resolve
+            .wasm_signature(AbiVariant::GuestImport, func)
+            .params
+            .len();
+        let params = (0..nparams).map(|i| format!("arg{i}")).collect::<Vec<_>>();
+
+        let mut src = Source::default();
+        uwriteln!(src, "function ({}) {{", params.join(", "));
+        let mut f = FunctionBindgen {
+            resource_map,
+            cur_resource_borrows: false,
+            intrinsics: &mut self.intrinsics,
+            valid_lifting_optimization: false,
+            sizes: self.sizes,
+            err: if func.results.throws(self.resolve).is_some() {
+                ErrHandling::ResultCatchHandler
+            } else {
+                ErrHandling::None
+            },
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            callee,
+            callee_resource_dynamic: matches!(func.kind, FunctionKind::Method(_)),
+            memory: Some(&memory),
+            realloc: Some(&realloc),
+            tmp: 0,
+            params,
+            post_return: None,
+            tracing_prefix: None,
+            encoding,
+            src: Source::default(),
+            resolve: self.resolve,
+        };
+        abi::call(
+            self.resolve,
+            AbiVariant::GuestImport,
+            LiftLower::LiftArgsLowerResults,
+            func,
+            &mut f,
+        );
+        src.push_str(&f.src);
+        uwrite!(src, "}}");
+        src.into()
+    }
+
+    fn intrinsic(&mut self, intrinsic: Intrinsic) -> String {
+        self.intrinsics.insert(intrinsic);
+        intrinsic.name().to_string()
+    }
+
+    /// Collect the resources of the handles within a type
+    fn collect_resources(&self, ty: &Type, resources: &mut BTreeSet<TypeId>) {
+        let Type::Id(id) = ty else {
+            return;
+        };
+        match &self.resolve.types[*id].kind {
+            TypeDefKind::Type(ty) | TypeDefKind::List(ty) | TypeDefKind::Option(ty) => {
+                self.collect_resources(ty, resources)
+            }
+            TypeDefKind::Record(record) => {
+                for field in record.fields.iter() {
+                    self.collect_resources(&field.ty, resources);
+                }
+            }
+            TypeDefKind::Tuple(tuple) => {
+                for ty in tuple.types.iter() {
+                    self.collect_resources(ty, resources);
+                }
+            }
+            TypeDefKind::Variant(variant) => {
+                for case in variant.cases.iter() {
+                    if let Some(ty) = &case.ty {
+                        self.collect_resources(ty, resources);
+                    }
+                }
+            }
+            TypeDefKind::Result(result) => {
+                for ty in result.ok.iter().chain(result.err.iter()) {
+                    self.collect_resources(ty, resources);
+                }
+            }
+            TypeDefKind::Handle(Handle::Own(ty) | Handle::Borrow(ty)) => {
+                resources.insert(dealias(self.resolve, *ty));
+            }
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Unknown => {}
+        }
+    }
+
+    /// Index of the capture table of a resource, importing it from the module
+    /// of the import defining the resource if needed
+    fn capture_table(&mut self, ty: TypeId) -> usize {
+        let (rid, inserted) = self.capture_tables.insert_full(ty);
+        if inserted {
+            let location = &self.resources[&ty];
+            let class_name = &location.class_name;
+            self.resource_imports
+                .entry(self.file_names[&location.import_name].to_string())
+                .or_default()
+                .extend([
+                    (
+                        format!("{class_name}$captureTable"),
+                        format!("captureTable{rid}"),
+                    ),
+                    (
+                        format!("{class_name}$captureRep"),
+                        format!("captureRep{rid}"),
+                    ),
+                ]);
+        }
+        rid
+    }
+
+    /// Local name of the class of a resource, which may be defined by the
+    /// module of another import
+    fn resource_class(&mut self, ty: TypeId) -> String {
+        if let Some(local_name) = self.local_resources.get(&ty) {
+            return local_name.to_string();
+        }
+        let location = &self.resources[&ty];
+        let file_name = &self.file_names[&location.import_name];
+        let local_name = self
+            .local_names
+            .create_once(&format!(
+                "{}${}",
+                file_name.trim_end_matches(".js").to_lower_camel_case(),
+                location.class_name
+            ))
+            .to_string();
+        self.resource_imports
+            .entry(file_name.to_string())
+            .or_default()
+            .push((location.class_name.clone(), local_name.clone()));
+        self.local_resources.insert(ty, local_name.clone());
+        local_name
+    }
+}
@@ -1,4 +1,4 @@
-use heck::ToLowerCamelCase;
+use heck::{ToKebabCase, ToLowerCamelCase};
 use std::collections::hash_map::RandomState;
 use std::collections::{HashMap, HashSet};
 use std::hash::{BuildHasher, Hash, Hasher};
@@ -168,6 +168,16 @@ pub fn maybe_quote_member(name: &str) -> String {
     }
 }
 
+/// File name (without extension) of the generated module for an import specifier
+pub(crate) fn specifier_goal_name(specifier: &str) -> String {
+    specifier
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_kebab_case()
+}
+
 pub(crate) const RESERVED_KEYWORDS: &[&str] = &[
     "await",
     "break",
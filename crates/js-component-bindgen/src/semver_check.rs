@@ -0,0 +1,567 @@
+//! Semver compatibility checking between two versions of a world.
+//!
+//! Changes are classified from the point of view of the users of a component
+//! targeting the world. Exports are provided by the component, so removing an
+//! export (or one of its functions) is breaking while adding one is additive.
+//! Imports are required from the host, so adding an import (or one of its
+//! functions) is breaking while removing one is additive. Adding a type or
+//! resource is additive, while removing one or changing the signature of an
+//! existing function or type is always breaking.
+//!
+//! Items excluded by `@since`/`@unstable` feature gates are treated as absent.
+
+use std::fmt;
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use jco_shared::wit_display::{func_name, TypeNames};
+use wit_parser::{
+    Function, FunctionKind, InterfaceId, Resolve, TypeDefKind, TypeId, WorldId, WorldItem, WorldKey,
+};
+
+use crate::feature_gate_allowed;
+
+/// Whether a change between two versions of a world breaks its users
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Breaking,
+    Additive,
+}
+
+/// Kind of WIT item affected by a change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangedItem {
+    Interface,
+    Function,
+    Type,
+    Resource,
+}
+
+/// A change between two versions of a world
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiChange {
+    pub kind: ChangeKind,
+    pub item: ChangedItem,
+    /// Path of the changed item, such as `export test:pkg/api#lookup` or
+    /// `import test:pkg/types#descriptor.read`, using the names of the newer
+    /// version for items present in both
+    pub path: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Import,
+    Export,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Import => f.write_str("import"),
+            Direction::Export => f.write_str("export"),
+        }
+    }
+}
+
+/// Compare two versions of a world, returning the breaking and additive
+/// changes from `old_world` to `new_world`
+///
+/// Interfaces are matched by name regardless of their package version, so that
+/// `test:pkg/api@1.0.0` is compared with `test:pkg/api@1.1.0`.
+pub fn semver_check(
+    old: &Resolve,
+    old_world: WorldId,
+    new: &Resolve,
+    new_world: WorldId,
+) -> Result<Vec<ApiChange>> {
+    let old_items = world_items(old, old_world)?;
+    let new_items = world_items(new, new_world)?;
+
+    let mut check = SemverCheck {
+        old,
+        new,
+        changes: Vec::new(),
+    };
+    for (key, (name, old_item)) in old_items.iter() {
+        let (direction, _) = key;
+        match new_items.get(key) {
+            Some((name, new_item)) => {
+                let path = format!("{direction} {name}");
+                check.world_item(*direction, &path, old_item, new_item)?;
+            }
+            None => {
+                let kind = match direction {
+                    Direction::Import => ChangeKind::Additive,
+                    Direction::Export => ChangeKind::Breaking,
+                };
+                check.push(
+                    kind,
+                    world_item_kind(old, old_item),
+                    format!("{direction} {name}"),
+                    format!("{direction} removed"),
+                );
+            }
+        }
+    }
+    for (key, (name, new_item)) in new_items.iter() {
+        if old_items.contains_key(key) {
+            continue;
+        }
+        let (direction, _) = key;
+        let kind = match direction {
+            Direction::Import => ChangeKind::Breaking,
+            Direction::Export => ChangeKind::Additive,
+        };
+        check.push(
+            kind,
+            world_item_kind(new, new_item),
+            format!("{direction} {name}"),
+            format!("{direction} added"),
+        );
+    }
+
+    Ok(check.changes)
+}
+
+/// Items of a world keyed by direction and version-less name, along with their
+/// full names
+type WorldItems<'a> = IndexMap<(Direction, String), (String, &'a WorldItem)>;
+
+/// Items of a world allowed through its feature gates
+fn world_items(resolve: &Resolve, world: WorldId) -> Result<WorldItems<'_>> {
+    let world = &resolve.worlds[world];
+    let package = &resolve.packages[world
+        .package
+        .context("unexpectedly missing package in world")?];
+
+    let mut items = IndexMap::new();
+    let all_items = world
+        .imports
+        .iter()
+        .map(|item| (Direction::Import, item))
+        .chain(world.exports.iter().map(|item| (Direction::Export, item)));
+    for (direction, (key, item)) in all_items {
+        let name = resolve.name_world_key(key);
+        let stability = match item {
+            WorldItem::Interface { stability, .. } => stability,
+            WorldItem::Function(func) => &func.stability,
+            WorldItem::Type(id) => &resolve.types[*id].stability,
+        };
+        if !feature_gate_allowed(resolve, package, stability, &name)
+            .with_context(|| format!("failed to check feature gate for {direction} [{name}]"))?
+        {
+            continue;
+        }
+        let unversioned = match key {
+            WorldKey::Name(name) => name.clone(),
+            WorldKey::Interface(_) => match name.split_once('@') {
+                Some((unversioned, _)) => unversioned.to_string(),
+                None => name.clone(),
+            },
+        };
+        items.insert((direction, unversioned), (name, item));
+    }
+    Ok(items)
+}
+
+fn world_item_kind(resolve: &Resolve, item: &WorldItem) -> ChangedItem {
+    match item {
+        WorldItem::Interface { .. } => ChangedItem::Interface,
+        WorldItem::Function(_) => ChangedItem::Function,
+        WorldItem::Type(id) => type_item_kind(resolve, *id),
+    }
+}
+
+fn type_item_kind(resolve: &Resolve, id: TypeId) -> ChangedItem {
+    match resolve.types[id].kind {
+        TypeDefKind::Resource => ChangedItem::Resource,
+        _ => ChangedItem::Type,
+    }
+}
+
+struct SemverCheck<'a> {
+    old: &'a Resolve,
+    new: &'a Resolve,
+    changes: Vec<ApiChange>,
+}
+
+impl SemverCheck<'_> {
+    fn push(&mut self, kind: ChangeKind, item: ChangedItem, path: String, description: String) {
+        self.changes.push(ApiChange {
+            kind,
+            item,
+            path,
+            description,
+        });
+    }
+
+    fn breaking(&mut self, item: ChangedItem, path: &str, description: String) {
+        self.push(ChangeKind::Breaking, item, path.to_string(), description);
+    }
+
+    fn world_item(
+        &mut self,
+        direction: Direction,
+        path: &str,
+        old: &WorldItem,
+        new: &WorldItem,
+    ) -> Result<()> {
+        match (old, new) {
+            (WorldItem::Interface { id: old, .. }, WorldItem::Interface { id: new, .. }) => {
+                self.interface(direction, path, *old, *new)?
+            }
+            (WorldItem::Function(old), WorldItem::Function(new)) => self.function(path, old, new),
+            (WorldItem::Type(old), WorldItem::Type(new)) => self.type_def(path, *old, *new),
+            _ => {
+                let describe = |resolve: &Resolve, item: &WorldItem| match item {
+                    WorldItem::Interface { .. } => "an interface",
+                    WorldItem::Function(_) => "a function",
+                    WorldItem::Type(id) => match resolve.types[*id].kind {
+                        TypeDefKind::Resource => "a resource",
+                        _ => "a type",
+                    },
+                };
+                let description = format!(
+                    "changed from {} to {}",
+                    describe(self.old, old),
+                    describe(self.new, new)
+                );
+                self.breaking(world_item_kind(self.new, new), path, description);
+            }
+        }
+        Ok(())
+    }
+
+    fn interface(
+        &mut self,
+        direction: Direction,
+        path: &str,
+        old: InterfaceId,
+        new: InterfaceId,
+    ) -> Result<()> {
+        let old_types = interface_types(self.old, old)?;
+        let new_types = interface_types(self.new, new)?;
+        for (name, old_id) in old_types.iter() {
+            let type_path = format!("{path}#{name}");
+            match new_types.get(name) {
+                Some(new_id) => self.type_def(&type_path, *old_id, *new_id),
+                None => {
+                    let item = type_item_kind(self.old, *old_id);
+                    let description = match item {
+                        ChangedItem::Resource => "resource removed",
+                        _ => "type removed",
+                    };
+                    self.breaking(item, &type_path, description.to_string());
+                }
+            }
+        }
+        for (name, new_id) in new_types.iter() {
+            if old_types.contains_key(name) {
+                continue;
+            }
+            let item = type_item_kind(self.new, *new_id);
+            let description = match item {
+                ChangedItem::Resource => "resource added",
+                _ => "type added",
+            };
+            self.push(
+                ChangeKind::Additive,
+                item,
+                format!("{path}#{name}"),
+                description.to_string(),
+            );
+        }
+
+        let old_funcs = interface_functions(self.old, old)?;
+        let new_funcs = interface_functions(self.new, new)?;
+        for (name, old_func) in old_funcs.iter() {
+            match new_funcs.get(name) {
+                Some(new_func) => {
+                    let func_path = format!("{path}#{}", func_name(self.new, new_func));
+                    self.function(&func_path, old_func, new_func);
+                }
+                None => {
+                    let kind = match direction {
+                        Direction::Import => ChangeKind::Additive,
+                        Direction::Export => ChangeKind::Breaking,
+                    };
+                    self.push(
+                        kind,
+                        ChangedItem::Function,
+                        format!("{path}#{}", func_name(self.old, old_func)),
+                        "function removed".to_string(),
+                    );
+                }
+            }
+        }
+        for (name, new_func) in new_funcs.iter() {
+            if old_funcs.contains_key(name) {
+                continue;
+            }
+            let kind = match direction {
+                Direction::Import => ChangeKind::Breaking,
+                Direction::Export => ChangeKind::Additive,
+            };
+            self.push(
+                kind,
+                ChangedItem::Function,
+                format!("{path}#{}", func_name(self.new, new_func)),
+                "function added".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, path: &str, old: &Function, new: &Function) {
+        let kind_name = |kind: &FunctionKind| match kind {
+            FunctionKind::Freestanding => "a freestanding function",
+            FunctionKind::Method(_) => "a method",
+            FunctionKind::Static(_) => "a static function",
+            FunctionKind::Constructor(_) => "a constructor",
+        };
+        if kind_name(&old.kind) != kind_name(&new.kind) {
+            let description = format!(
+                "changed from {} to {}",
+                kind_name(&old.kind),
+                kind_name(&new.kind)
+            );
+            self.breaking(ChangedItem::Function, path, description);
+        }
+
+        let old_params = old
+            .params
+            .iter()
+            .map(|(name, ty)| (name.as_str(), Some(TypeNames::new(self.old).ty(ty))))
+            .collect::<Vec<_>>();
+        let new_params = new
+            .params
+            .iter()
+            .map(|(name, ty)| (name.as_str(), Some(TypeNames::new(self.new).ty(ty))))
+            .collect::<Vec<_>>();
+        self.members(
+            ChangedItem::Function,
+            path,
+            "parameter",
+            &old_params,
+            &new_params,
+        );
+
+        let old_results = TypeNames::new(self.old).results(&old.results);
+        let new_results = TypeNames::new(self.new).results(&new.results);
+        if old_results != new_results {
+            let description = format!("results changed from `{old_results}` to `{new_results}`");
+            self.breaking(ChangedItem::Function, path, description);
+        }
+    }
+
+    fn type_def(&mut self, path: &str, old: TypeId, new: TypeId) {
+        let item = type_item_kind(self.new, new);
+        let old_kind = &self.old.types[old].kind;
+        let new_kind = &self.new.types[new].kind;
+        match (old_kind, new_kind) {
+            (TypeDefKind::Record(old), TypeDefKind::Record(new)) => {
+                let old_fields = old
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.name.as_str(),
+                            Some(TypeNames::new(self.old).ty(&field.ty)),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let new_fields = new
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.name.as_str(),
+                            Some(TypeNames::new(self.new).ty(&field.ty)),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                self.members(item, path, "field", &old_fields, &new_fields);
+            }
+            (TypeDefKind::Variant(old), TypeDefKind::Variant(new)) => {
+                let old_cases = old
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let ty = case.ty.as_ref().map(|ty| TypeNames::new(self.old).ty(ty));
+                        (case.name.as_str(), ty)
+                    })
+                    .collect::<Vec<_>>();
+                let new_cases = new
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let ty = case.ty.as_ref().map(|ty| TypeNames::new(self.new).ty(ty));
+                        (case.name.as_str(), ty)
+                    })
+                    .collect::<Vec<_>>();
+                self.members(item, path, "case", &old_cases, &new_cases);
+            }
+            (TypeDefKind::Enum(old), TypeDefKind::Enum(new)) => {
+                let old_cases = old
+                    .cases
+                    .iter()
+                    .map(|case| (case.name.as_str(), None))
+                    .collect::<Vec<_>>();
+                let new_cases = new
+                    .cases
+                    .iter()
+                    .map(|case| (case.name.as_str(), None))
+                    .collect::<Vec<_>>();
+                self.members(item, path, "case", &old_cases, &new_cases);
+            }
+            (TypeDefKind::Flags(old), TypeDefKind::Flags(new)) => {
+                let old_flags = old
+                    .flags
+                    .iter()
+                    .map(|flag| (flag.name.as_str(), None))
+                    .collect::<Vec<_>>();
+                let new_flags = new
+                    .flags
+                    .iter()
+                    .map(|flag| (flag.name.as_str(), None))
+                    .collect::<Vec<_>>();
+                self.members(item, path, "flag", &old_flags, &new_flags);
+            }
+            // The methods of resources are compared as functions of their interface
+            (TypeDefKind::Resource, TypeDefKind::Resource) => {}
+            _ if std::mem::discriminant(old_kind) != std::mem::discriminant(new_kind)
+                && (is_nominal(old_kind) || is_nominal(new_kind)) =>
+            {
+                let description = format!(
+                    "changed from a {} to a {}",
+                    old_kind.as_str(),
+                    new_kind.as_str()
+                );
+                self.breaking(item, path, description);
+            }
+            _ => {
+                let old_name = TypeNames::new(self.old).type_def(old);
+                let new_name = TypeNames::new(self.new).type_def(new);
+                if old_name != new_name {
+                    let description = format!("changed from `{old_name}` to `{new_name}`");
+                    self.breaking(item, path, description);
+                }
+            }
+        }
+    }
+
+    /// Compare the named members (fields, cases, flags or parameters) of an
+    /// item, along with their optional types
+    fn members(
+        &mut self,
+        item: ChangedItem,
+        path: &str,
+        member: &str,
+        old: &[(&str, Option<String>)],
+        new: &[(&str, Option<String>)],
+    ) {
+        let describe = |ty: &Option<String>| match ty {
+            Some(ty) => format!("`{ty}`"),
+            None => "no payload".to_string(),
+        };
+        let in_old = |name: &str| old.iter().any(|(old_name, _)| *old_name == name);
+        let in_new = |name: &str| new.iter().any(|(new_name, _)| *new_name == name);
+
+        for (i, (name, old_ty)) in old.iter().enumerate() {
+            match new.iter().find(|(new_name, _)| new_name == name) {
+                Some((_, new_ty)) if old_ty != new_ty => {
+                    let description = format!(
+                        "{member} `{name}` changed from {} to {}",
+                        describe(old_ty),
+                        describe(new_ty)
+                    );
+                    self.breaking(item, path, description);
+                }
+                Some(_) => {}
+                None => match new.get(i) {
+                    // A member replaced by a new member at the same position
+                    // is reported as a rename
+                    Some((new_name, new_ty)) if !in_old(new_name) => {
+                        let mut description = format!("{member} `{name}` renamed to `{new_name}`");
+                        if old_ty != new_ty {
+                            description.push_str(&format!(
+                                " and changed from {} to {}",
+                                describe(old_ty),
+                                describe(new_ty)
+                            ));
+                        }
+                        self.breaking(item, path, description);
+                    }
+                    _ => self.breaking(item, path, format!("{member} `{name}` removed")),
+                },
+            }
+        }
+        for (i, (name, _)) in new.iter().enumerate() {
+            let renamed = old.get(i).is_some_and(|(old_name, _)| !in_new(old_name));
+            if !in_old(name) && !renamed {
+                self.breaking(item, path, format!("{member} `{name}` added"));
+            }
+        }
+
+        // Members present in both versions must keep their relative order
+        let old_order = old
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| in_new(name));
+        let new_order = new
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| in_old(name));
+        if !old_order.eq(new_order) {
+            self.breaking(item, path, format!("{member}s reordered"));
+        }
+    }
+}
+
+/// Types of an interface allowed through its feature gates
+fn interface_types(resolve: &Resolve, id: InterfaceId) -> Result<IndexMap<&str, TypeId>> {
+    let iface = &resolve.interfaces[id];
+    let package = &resolve.packages[iface
+        .package
+        .context("unexpectedly missing package in interface")?];
+    let mut types = IndexMap::new();
+    for (name, id) in iface.types.iter() {
+        if feature_gate_allowed(resolve, package, &resolve.types[*id].stability, name)
+            .with_context(|| format!("failed to check feature gate for type [{name}]"))?
+        {
+            types.insert(name.as_str(), *id);
+        }
+    }
+    Ok(types)
+}
+
+/// Functions of an interface allowed through their feature gates
+fn interface_functions(resolve: &Resolve, id: InterfaceId) -> Result<IndexMap<&str, &Function>> {
+    let iface = &resolve.interfaces[id];
+    let package = &resolve.packages[iface
+        .package
+        .context("unexpectedly missing package in interface")?];
+    let mut funcs = IndexMap::new();
+    for (name, func) in iface.functions.iter() {
+        if feature_gate_allowed(resolve, package, &func.stability, name)
+            .with_context(|| format!("failed to check feature gate for function [{name}]"))?
+        {
+            funcs.insert(name.as_str(), func);
+        }
+    }
+    Ok(funcs)
+}
+
+/// Whether a type definition has its own identity, rather than being an alias
+/// or an anonymous type
+fn is_nominal(kind: &TypeDefKind) -> bool {
+    matches!(
+        kind,
+        TypeDefKind::Record(_)
+            | TypeDefKind::Variant(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Flags(_)
+            | TypeDefKind::Resource
+    )
+}
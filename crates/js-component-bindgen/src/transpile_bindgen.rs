@@ -10,9 +10,11 @@ use crate::source;
 use crate::{uwrite, uwriteln};
 use base64::{engine::general_purpose, Engine as _};
 use heck::*;
+use jco_shared::wit_display::TypeNames;
+use semver::{Version, VersionReq};
 use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
-use std::fmt::Write;
+use std::fmt::{self, Write};
 use std::mem;
 use wasmtime_environ::component::{ExportIndex, NameMap, NameMapNoIntern, Transcode};
 use wasmtime_environ::{
@@ -46,9 +48,18 @@ pub struct TranspileOpts {
     /// Configure how import bindings are provided, as high-level JS bindings,
     /// or as hybrid optimized bindings.
     pub import_bindings: Option<BindingsMode>,
+    /// Bindings modes of specific imports, by import name with the wildcard
+    /// syntax of `map`, taking precedence over `import_bindings`.
+    pub import_bindings_map: Option<HashMap<String, BindingsMode>>,
+    /// Configure how export bindings are provided, as high-level JS bindings,
+    /// or also exposing their core functions under `Symbol.for('cabiLift')`.
+    pub export_bindings: Option<ExportBindingsMode>,
     /// Comma-separated list of "from-specifier=./to-specifier.js" mappings of
     /// component import specifiers to JS import specifiers.
     pub map: Option<HashMap<String, String>>,
+    /// Mappings of interface export names, with the wildcard syntax of `map`,
+    /// to `.`-separated paths of the nested namespaces to export them under.
+    pub export_map: Option<HashMap<String, String>>,
     /// Disables compatibility in Node.js without a fetch global.
     pub no_nodejs_compat: bool,
     /// Set the cutoff byte size for base64 inlining core Wasm in instantiation mode
@@ -68,6 +79,14 @@ pub struct TranspileOpts {
     /// Whether to output core Wasm utilizing multi-memory or to polyfill
     /// this handling.
     pub multi_memory: bool,
+    /// When polyfilling multi-memory, merge the memories defined by a core
+    /// module into its first memory where possible, instead of accessing
+    /// them from JS.
+    pub merge_memories: bool,
+    /// When polyfilling multi-memory, also output the core modules using
+    /// multi-memory as-is, selecting between the two at runtime depending on
+    /// the support of multi-memory by the JS engine.
+    pub detect_multi_memory: bool,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -96,6 +115,42 @@ pub enum BindingsMode {
     DirectOptimized,
 }
 
+#[derive(Default, Clone, Debug)]
+pub enum ExportBindingsMode {
+    #[default]
+    Js,
+    Hybrid,
+}
+
+/// Warnings about the import mappings of a transpilation
+#[derive(Clone, Debug, PartialEq)]
+pub enum TranspileWarning {
+    /// A `map` entry which matched no import
+    UnusedMapEntry(String),
+    /// An import matched by several wildcard or version pattern `map`
+    /// entries, which is mapped by the first and most specific of them
+    AmbiguousImportMapping {
+        import: String,
+        entries: Vec<String>,
+    },
+}
+
+impl fmt::Display for TranspileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranspileWarning::UnusedMapEntry(key) => {
+                write!(f, "map entry `{key}` does not match any import")
+            }
+            TranspileWarning::AmbiguousImportMapping { import, entries } => write!(
+                f,
+                "import `{import}` matches multiple map entries ({}), using `{}`",
+                entries.join(", "),
+                entries[0]
+            ),
+        }
+    }
+}
+
 struct JsBindgen<'a> {
     local_names: LocalNames,
 
@@ -110,11 +165,19 @@ struct JsBindgen<'a> {
     /// Core module count
     core_module_cnt: usize,
 
+    /// Core modules also output using multi-memory, to select at runtime
+    multi_memory_modules: BTreeSet<usize>,
+
     /// Various options for code generation.
     opts: &'a TranspileOpts,
 
     /// List of all intrinsics emitted to `src` so far.
     all_intrinsics: BTreeSet<Intrinsic>,
+
+    /// First import or export which could not be bound, such as through
+    /// versions of an interface with different shapes mapped to the same
+    /// specifier
+    binding_error: Option<anyhow::Error>,
 }
 
 pub fn transpile_bindgen(
@@ -126,19 +189,28 @@ pub fn transpile_bindgen(
     id: WorldId,
     opts: TranspileOpts,
     files: &mut Files,
-) -> (Vec<String>, Vec<(String, Export)>) {
+) -> anyhow::Result<(Vec<String>, Vec<(String, Export)>)> {
     let mut bindgen = JsBindgen {
         local_names: LocalNames::default(),
         src: Source::default(),
         esm_bindgen: EsmBindgen::default(),
         core_module_cnt: 0,
+        multi_memory_modules: BTreeSet::new(),
         opts: &opts,
         all_intrinsics: BTreeSet::new(),
+        binding_error: None,
     };
     bindgen
         .local_names
         .exclude_globals(Intrinsic::get_global_names());
     bindgen.core_module_cnt = modules.len();
+    if opts.detect_multi_memory {
+        bindgen.multi_memory_modules = modules
+            .iter()
+            .filter(|(_, module)| module.multi_memory_wasm().is_some())
+            .map(|(i, _)| i.as_u32() as usize)
+            .collect();
+    }
 
     // bindings is the actual `instantiate` method itself, created by this
     // structure.
@@ -171,6 +243,9 @@ pub fn transpile_bindgen(
     instantiator.sizes.fill(resolve);
     instantiator.initialize();
     instantiator.instantiate();
+    if let Some(err) = instantiator.gen.binding_error.take() {
+        return Err(err);
+    }
 
     let mut intrinsic_definitions = source::Source::default();
 
@@ -195,14 +270,27 @@ pub fn transpile_bindgen(
                     .component
                     .exports
                     .get(canon_export_name, &NameMapNoIntern)
-                    .unwrap()
             } else {
                 instantiator
                     .component
                     .exports
                     .get(&canon_export_name.to_kebab_case(), &NameMapNoIntern)
-                    .unwrap()
             };
+            // namespaces of the export map are exported as the first
+            // interface mapped under them
+            let export = export
+                .or_else(|| {
+                    instantiator
+                        .component
+                        .exports
+                        .raw_iter()
+                        .find(|(name, _)| {
+                            map_export(&opts.export_map, name)
+                                .is_some_and(|path| path[0] == *canon_export_name)
+                        })
+                        .map(|(_, export)| export)
+                })
+                .unwrap();
             (
                 export_name.to_string(),
                 instantiator.component.export_items[*export].clone(),
@@ -210,7 +298,7 @@ pub fn transpile_bindgen(
         })
         .collect();
 
-    (bindgen.esm_bindgen.import_specifiers(), exports)
+    Ok((bindgen.esm_bindgen.import_specifiers(), exports))
 }
 
 impl<'a> JsBindgen<'a> {
@@ -229,34 +317,74 @@ impl<'a> JsBindgen<'a> {
         for i in 0..self.core_module_cnt {
             let local_name = format!("module{}", i);
             let mut name_idx = core_file_name(name, i as u32);
-            if self.opts.instantiation.is_some() {
-                uwriteln!(
-                    compilation_promises,
-                    "const {local_name} = getCoreModule('{name_idx}');"
-                );
+            // Modules also output using multi-memory are compiled in the same
+            // way, the file being selected at runtime
+            let multi_memory = self.multi_memory_modules.contains(&i);
+            let compile = if self.opts.instantiation.is_some() {
+                let compile = |name_idx: &str| format!("getCoreModule('{name_idx}')");
+                [
+                    compile(&name_idx),
+                    compile(&multi_memory_file_name(&name_idx)),
+                ]
             } else if files.get_size(&name_idx).unwrap() < self.opts.base64_cutoff {
-                assert!(removed.insert(i));
+                // Only the polyfilled module is inlined, the module using
+                // multi-memory being fetched when selected instead, such that
+                // the module isn't inlined twice. The inlined module is still
+                // used when the other can't be fetched, to keep the output
+                // self-contained. Its file keeps the name of the inlined
+                // file, which can't be reused by later files.
+                if !multi_memory {
+                    assert!(removed.insert(i));
+                }
+                let base64_compile = self.intrinsic(Intrinsic::Base64Compile);
                 let data = files.remove(&name_idx).unwrap();
-                uwriteln!(
-                    compilation_promises,
-                    "const {local_name} = {}('{}');",
-                    self.intrinsic(Intrinsic::Base64Compile),
-                    general_purpose::STANDARD_NO_PAD.encode(&data),
+                let compile = format!(
+                    "{base64_compile}('{}')",
+                    general_purpose::STANDARD_NO_PAD.encode(&data)
                 );
+                if multi_memory {
+                    let fetch_compile = self.intrinsic(Intrinsic::FetchCompile);
+                    uwriteln!(
+                        compilation_promises,
+                        "const {local_name} = ({} ? {fetch_compile}(new URL('./{}', import.meta.url)) : Promise.reject()).catch(() => {compile});",
+                        self.intrinsic(Intrinsic::SupportsMultiMemory),
+                        multi_memory_file_name(&name_idx),
+                    );
+                    continue;
+                }
+                [compile, String::new()]
             } else {
                 // Maintain numerical file orderings when a previous file was
                 // inlined
                 if let Some(&replacement) = removed.iter().next() {
                     assert!(removed.remove(&replacement) && removed.insert(i));
+                    let replacement_name = core_file_name(name, replacement as u32);
                     let data = files.remove(&name_idx).unwrap();
-                    name_idx = core_file_name(name, replacement as u32);
-                    files.push(&name_idx, &data);
+                    files.push(&replacement_name, &data);
+                    if multi_memory {
+                        let data = files.remove(&multi_memory_file_name(&name_idx)).unwrap();
+                        files.push(&multi_memory_file_name(&replacement_name), &data);
+                    }
+                    name_idx = replacement_name;
                 }
+                let fetch_compile = self.intrinsic(Intrinsic::FetchCompile);
+                let compile = |name_idx: &str| {
+                    format!("{fetch_compile}(new URL('./{name_idx}', import.meta.url))")
+                };
+                [
+                    compile(&name_idx),
+                    compile(&multi_memory_file_name(&name_idx)),
+                ]
+            };
+            let [compile, compile_multi_memory] = compile;
+            if multi_memory {
                 uwriteln!(
                     compilation_promises,
-                    "const {local_name} = {}(new URL('./{name_idx}', import.meta.url));",
-                    self.intrinsic(Intrinsic::FetchCompile)
+                    "const {local_name} = {} ? {compile_multi_memory} : {compile};",
+                    self.intrinsic(Intrinsic::SupportsMultiMemory),
                 );
+            } else {
+                uwriteln!(compilation_promises, "const {local_name} = {compile};");
             }
         }
 
@@ -974,13 +1102,98 @@ impl<'a> Instantiator<'a, '_> {
     }
 
     fn instantiate_static_module(&mut self, idx: StaticModuleIndex, args: &[CoreDef]) {
+        let i = self.instances.next_key();
+        let iu32 = i.as_u32();
+
+        // Memories and data segments of modules polyfilling multi-memory are
+        // held in JS, and need to be created before instantiation.
+        let mut init = String::new();
+        for (mem, ty) in self.modules[idx].memories_defined() {
+            let maximum = match ty.maximum {
+                Some(maximum) => format!(", maximum: {maximum}"),
+                None => String::new(),
+            };
+            let shared = if ty.shared { ", shared: true" } else { "" };
+            uwriteln!(
+                init,
+                "const instance{iu32}Memory{mem} = new WebAssembly.Memory({{ initial: {}{maximum}{shared} }});",
+                ty.initial
+            );
+        }
+        if !self.modules[idx].data().is_empty() {
+            let base64_decode = self.gen.intrinsic(Intrinsic::Base64Decode);
+            for (index, data) in self.modules[idx].data() {
+                uwriteln!(
+                    init,
+                    "let instance{iu32}Data{index} = {base64_decode}('{}');",
+                    general_purpose::STANDARD_NO_PAD.encode(data),
+                );
+            }
+        }
+
         // Build a JS "import object" which represents `args`. The `args` is a
         // flat representation which needs to be zip'd with the list of names to
         // correspond to the JS wasm embedding API. This is one of the major
         // differences between Wasmtime's and JS's embedding API.
+        let imports = self.import_object(iu32, self.modules[idx].imports(args));
+
+        let i = self.instances.push(idx);
+        assert_eq!(i.as_u32(), iu32);
+        uwriteln!(self.src.js, "let exports{iu32};");
+        init.push_str(&self.instantiate_core(iu32, idx, &imports));
+
+        // Memories other than the first are exported from JS, and the
+        // initialization deferred until they are available is run.
+        let memory_exports = self.modules[idx].memory_exports(args);
+        if !memory_exports.is_empty() {
+            let members = memory_exports
+                .into_iter()
+                .map(|(name, mem)| {
+                    format!(
+                        "{}: {}",
+                        maybe_quote_id(name),
+                        self.augmented_memory(iu32, mem)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            uwriteln!(init, "exports{iu32} = {{ ...exports{iu32}, {members} }};");
+        }
+        if self.modules[idx].deferred_start() {
+            uwriteln!(
+                init,
+                "exports{iu32}{}();",
+                maybe_quote_member(core::AUGMENTS_START)
+            );
+        }
+
+        // When the module was also output using multi-memory, it is
+        // instantiated as-is if the engine supports it.
+        if self
+            .gen
+            .multi_memory_modules
+            .contains(&(idx.as_u32() as usize))
+        {
+            let imports = self.import_object(iu32, self.modules[idx].multi_memory_imports(args));
+            let supports_multi_memory = self.gen.intrinsic(Intrinsic::SupportsMultiMemory);
+            let instantiate = self.instantiate_core(iu32, idx, &imports);
+            uwriteln!(
+                self.src.js_init,
+                "if ({supports_multi_memory}) {{\n{instantiate}}} else {{\n{init}}}"
+            );
+        } else {
+            self.src.js_init.push_str(&init);
+        }
+    }
+
+    fn import_object(
+        &self,
+        instance: u32,
+        imports: Vec<(&str, &str, core::AugmentedImport<'_>)>,
+    ) -> String {
         let mut import_obj = BTreeMap::new();
-        for (module, name, arg) in self.modules[idx].imports(args) {
-            let def = self.augmented_import_def(arg);
+        for (module, name, arg) in imports {
+            let def = self.augmented_import_def(instance, arg);
             let dst = import_obj.entry(module).or_insert(BTreeMap::new());
             let prev = dst.insert(name, def);
             assert!(
@@ -1003,29 +1216,30 @@ impl<'a> Instantiator<'a, '_> {
             }
             imports.push('}');
         }
+        imports
+    }
 
-        let i = self.instances.push(idx);
-        let iu32 = i.as_u32();
+    fn instantiate_core(&mut self, instance: u32, idx: StaticModuleIndex, imports: &str) -> String {
         let instantiate = self.gen.intrinsic(Intrinsic::InstantiateCore);
-        uwriteln!(self.src.js, "let exports{iu32};");
-
+        let mut src = String::new();
         match self.gen.opts.instantiation {
             Some(InstantiationMode::Async) | None => {
                 uwriteln!(
-                    self.src.js_init,
-                    "({{ exports: exports{iu32} }} = yield {instantiate}(yield module{}{imports}));",
+                    src,
+                    "({{ exports: exports{instance} }} = yield {instantiate}(yield module{}{imports}));",
                     idx.as_u32()
                 )
             }
 
             Some(InstantiationMode::Sync) => {
                 uwriteln!(
-                    self.src.js_init,
-                    "({{ exports: exports{iu32} }} = {instantiate}(module{}{imports}));",
+                    src,
+                    "({{ exports: exports{instance} }} = {instantiate}(module{}{imports}));",
                     idx.as_u32()
                 )
             }
         }
+        src
     }
 
     fn create_resource_fn_map(
@@ -1076,6 +1290,7 @@ impl<'a> Instantiator<'a, '_> {
 
         // nested interfaces only currently possible through mapping
         let (import_specifier, maybe_iface_member) = map_import(&self.gen.opts.map, import_name);
+        let bindings_mode = import_bindings_mode(&self.gen.opts, import_name).cloned();
 
         let (func, func_name, iface_name) =
             match &self.resolve.worlds[self.world].imports[world_key] {
@@ -1152,7 +1367,7 @@ impl<'a> Instantiator<'a, '_> {
             .wasm_signature(AbiVariant::GuestImport, func)
             .params
             .len();
-        match self.gen.opts.import_bindings {
+        match bindings_mode {
             None | Some(BindingsMode::Js) | Some(BindingsMode::Hybrid) => {
                 uwrite!(self.src.js, "\nfunction trampoline{}", trampoline.as_u32());
                 self.bindgen(
@@ -1175,7 +1390,7 @@ impl<'a> Instantiator<'a, '_> {
                 uwriteln!(self.src.js, "let trampoline{};", trampoline.as_u32());
             }
         };
-        if !matches!(self.gen.opts.import_bindings, None | Some(BindingsMode::Js)) {
+        if !matches!(bindings_mode, None | Some(BindingsMode::Js)) {
             let memory = options
                 .memory
                 .map(|idx| format!(" memory: memory{},", idx.as_u32()))
@@ -1207,33 +1422,14 @@ impl<'a> Instantiator<'a, '_> {
                 FunctionKind::Constructor(_) => callee_name[4..].to_string(),
             };
             let resource_tables = {
-                let mut resource_tables: Vec<TypeResourceTableIndex> = Vec::new();
-
-                for (_, data) in resource_map {
-                    let ResourceTable {
-                        data: ResourceData::Host { tid, .. },
-                        ..
-                    } = &data
-                    else {
-                        unreachable!();
-                    };
-                    resource_tables.push(*tid);
-                }
-
-                if resource_tables.len() == 0 {
+                let resource_tables = resource_tables(&resource_map);
+                if resource_tables.is_empty() {
                     "".to_string()
                 } else {
-                    format!(
-                        " resourceTables: [{}],",
-                        resource_tables
-                            .iter()
-                            .map(|x| format!("handleTable{}", x.as_u32().to_string()))
-                            .collect::<Vec<String>>()
-                            .join(", ")
-                    )
+                    format!(" resourceTables: [{}],", resource_tables.join(", "))
                 }
             };
-            match self.gen.opts.import_bindings {
+            match bindings_mode {
                 Some(BindingsMode::Hybrid) => {
                     let symbol_cabi_lower = self.gen.intrinsic(Intrinsic::SymbolCabiLower);
                     uwriteln!(self.src.js_init, "if ({callee_name}[{symbol_cabi_lower}]) {{
@@ -1247,7 +1443,7 @@ impl<'a> Instantiator<'a, '_> {
                             throw new TypeError('import for \"{import_name}\" does not define a Symbol.for('cabiLower') optimized binding');
                         }}");
                     }
-                    uwriteln!(self.src.js_init, "trampoline{} = {callee_name}[{symbol_cabi_lower}]({memory}{realloc}{post_return}{string_encoding}{resource_tables});", trampoline.as_u32());
+                    uwriteln!(self.src.js_init, "trampoline{} = {callee_name}[{symbol_cabi_lower}]({{{memory}{realloc}{post_return}{string_encoding}{resource_tables}}});", trampoline.as_u32());
                 }
                 Some(BindingsMode::DirectOptimized) => {
                     uwriteln!(
@@ -1259,6 +1455,12 @@ impl<'a> Instantiator<'a, '_> {
                 None | Some(BindingsMode::Js) => unreachable!(),
             };
         }
+        // The methods of a resource share its binding, so only freestanding
+        // functions have their type compared across mapped interfaces
+        let signature = match func.kind {
+            FunctionKind::Freestanding => Some(TypeNames::unversioned(self.resolve).func(func)),
+            _ => None,
+        };
         let (import_name, binding_name) = match func.kind {
             FunctionKind::Freestanding => (func_name.to_lower_camel_case(), callee_name),
             FunctionKind::Method(tid)
@@ -1288,6 +1490,7 @@ impl<'a> Instantiator<'a, '_> {
                 None
             },
             binding_name,
+            signature,
         );
     }
 
@@ -1298,37 +1501,45 @@ impl<'a> Instantiator<'a, '_> {
         iface_member: Option<&str>,
         import_binding: Option<String>,
         local_name: String,
+        signature: Option<String>,
     ) {
         if import_specifier.starts_with("webidl:") {
             self.gen.intrinsic(Intrinsic::GlobalThisIdlProxy);
         }
         // add the function import to the ESM bindgen
-        if let Some(_iface_name) = iface_name {
-            // mapping can be used to construct virtual nested namespaces
-            // which is used eg to support WASI interface groupings
+        let path = if let Some(_iface_name) = iface_name {
+            // mapping can be used to construct virtual nested namespaces, with
+            // `.` separated members, which is used eg to support WASI
+            // interface groupings
+            let mut path = vec![import_specifier];
             if let Some(iface_member) = iface_member {
-                self.gen.esm_bindgen.add_import_binding(
-                    &[
-                        import_specifier,
-                        iface_member.to_lower_camel_case(),
-                        import_binding.unwrap().to_string(),
-                    ],
-                    local_name,
-                );
-            } else {
-                self.gen.esm_bindgen.add_import_binding(
-                    &[import_specifier, import_binding.unwrap().to_string()],
-                    local_name,
-                );
+                path.extend(iface_member.split('.').map(str::to_lower_camel_case));
             }
+            path.push(import_binding.unwrap());
+            path
         } else if let Some(import_binding) = import_binding {
-            self.gen
-                .esm_bindgen
-                .add_import_binding(&[import_specifier, import_binding], local_name);
+            vec![import_specifier, import_binding]
         } else {
-            self.gen
-                .esm_bindgen
-                .add_import_binding(&[import_specifier], local_name);
+            vec![import_specifier]
+        };
+        // Conflicting bindings are reported once the bindgen is complete
+        if let Err(err) = self
+            .gen
+            .esm_bindgen
+            .add_import_binding(&path, local_name, signature)
+        {
+            self.gen.binding_error.get_or_insert(err);
+        }
+    }
+
+    fn add_export_binding(&mut self, iface_path: &[String], local_name: String, func_name: String) {
+        // Conflicting bindings are reported once the bindgen is complete
+        if let Err(err) = self
+            .gen
+            .esm_bindgen
+            .add_export_binding(iface_path, local_name, func_name)
+        {
+            self.gen.binding_error.get_or_insert(err);
         }
     }
 
@@ -1416,6 +1627,7 @@ impl<'a> Instantiator<'a, '_> {
                 maybe_iface_member.as_deref(),
                 Some(resource_name),
                 local_name_str.to_string(),
+                None,
             );
 
             local_name_str
@@ -1431,6 +1643,7 @@ impl<'a> Instantiator<'a, '_> {
                 rid: self.types[tid].ty,
                 local_name,
                 dtor_name: dtor_str,
+                capture_rep: None,
             },
         };
         if let Some(existing) = resource_map.get(&resource_id) {
@@ -1627,97 +1840,127 @@ impl<'a> Instantiator<'a, '_> {
         self.src.js("}");
     }
 
-    fn augmented_import_def(&self, def: core::AugmentedImport<'_>) -> String {
+    fn augmented_import_def(&self, instance: u32, def: core::AugmentedImport<'_>) -> String {
         match def {
             core::AugmentedImport::CoreDef(def) => self.core_def(def),
             core::AugmentedImport::Memory { mem, op } => {
-                let mem = self.core_def(mem);
+                let mem = self.augmented_memory(instance, mem);
+                let load = |get: &str| {
+                    format!("(ptr, off) => new DataView({mem}.buffer).{get}((ptr >>> 0) + (off >>> 0), true)")
+                };
+                let load_i64 = |get: &str| {
+                    format!("(ptr, off) => BigInt(new DataView({mem}.buffer).{get}((ptr >>> 0) + (off >>> 0), true))")
+                };
+                let store = |set: &str| {
+                    format!(
+                        "(ptr, val, off) => {{
+                            new DataView({mem}.buffer).{set}((ptr >>> 0) + (off >>> 0), val, true);
+                        }}"
+                    )
+                };
+                let store_i64 = |set: &str, bits: u32| {
+                    format!(
+                        "(ptr, val, off) => {{
+                            new DataView({mem}.buffer).{set}((ptr >>> 0) + (off >>> 0), Number(BigInt.asIntN({bits}, val)), true);
+                        }}"
+                    )
+                };
                 match op {
-                    core::AugmentedOp::I32Load => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getInt32(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::I32Load8U => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getUint8(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::I32Load8S => {
-                        format!("(ptr, off) => new DataView({mem}.buffer).getInt8(ptr + off, true)")
-                    }
-                    core::AugmentedOp::I32Load16U => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getUint16(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::I32Load16S => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getInt16(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::I64Load => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getBigInt64(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::F32Load => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getFloat32(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::F64Load => {
-                        format!(
-                            "(ptr, off) => new DataView({mem}.buffer).getFloat64(ptr + off, true)"
-                        )
-                    }
-                    core::AugmentedOp::I32Store8 => {
-                        format!(
-                            "(ptr, val, offset) => {{
-                                new DataView({mem}.buffer).setInt8(ptr + offset, val, true);
-                            }}"
-                        )
-                    }
-                    core::AugmentedOp::I32Store16 => {
-                        format!(
-                            "(ptr, val, offset) => {{
-                                new DataView({mem}.buffer).setInt16(ptr + offset, val, true);
-                            }}"
-                        )
-                    }
-                    core::AugmentedOp::I32Store => {
-                        format!(
-                            "(ptr, val, offset) => {{
-                                new DataView({mem}.buffer).setInt32(ptr + offset, val, true);
-                            }}"
-                        )
+                    core::AugmentedOp::I32Load => load("getInt32"),
+                    core::AugmentedOp::I32Load8U => load("getUint8"),
+                    core::AugmentedOp::I32Load8S => load("getInt8"),
+                    core::AugmentedOp::I32Load16U => load("getUint16"),
+                    core::AugmentedOp::I32Load16S => load("getInt16"),
+                    core::AugmentedOp::I64Load => load("getBigInt64"),
+                    core::AugmentedOp::I64Load8U => load_i64("getUint8"),
+                    core::AugmentedOp::I64Load8S => load_i64("getInt8"),
+                    core::AugmentedOp::I64Load16U => load_i64("getUint16"),
+                    core::AugmentedOp::I64Load16S => load_i64("getInt16"),
+                    core::AugmentedOp::I64Load32U => load_i64("getUint32"),
+                    core::AugmentedOp::I64Load32S => load_i64("getInt32"),
+                    core::AugmentedOp::F32Load => load("getFloat32"),
+                    core::AugmentedOp::F64Load => load("getFloat64"),
+                    core::AugmentedOp::I32Store8 => store("setInt8"),
+                    core::AugmentedOp::I32Store16 => store("setInt16"),
+                    core::AugmentedOp::I32Store => store("setInt32"),
+                    core::AugmentedOp::I64Store => store("setBigInt64"),
+                    core::AugmentedOp::I64Store8 => store_i64("setInt8", 8),
+                    core::AugmentedOp::I64Store16 => store_i64("setInt16", 16),
+                    core::AugmentedOp::I64Store32 => store_i64("setInt32", 32),
+                    core::AugmentedOp::F32Store => store("setFloat32"),
+                    core::AugmentedOp::F64Store => store("setFloat64"),
+                    core::AugmentedOp::MemorySize => {
+                        format!("() => {mem}.buffer.byteLength / 65536")
                     }
-                    core::AugmentedOp::I64Store => {
+                    core::AugmentedOp::MemoryGrow => {
                         format!(
-                            "(ptr, val, offset) => {{
-                                new DataView({mem}.buffer).setBigInt64(ptr + offset, val, true);
+                            "delta => {{
+                                try {{
+                                    return {mem}.grow(delta >>> 0);
+                                }} catch {{
+                                    return -1;
+                                }}
                             }}"
                         )
                     }
-                    core::AugmentedOp::F32Store => {
+                    core::AugmentedOp::MemoryFill => {
                         format!(
-                            "(ptr, val, offset) => {{
-                                new DataView({mem}.buffer).setFloat32(ptr + offset, val, true);
+                            "(dst, val, len) => {{
+                                const buf = new Uint8Array({mem}.buffer);
+                                dst >>>= 0;
+                                len >>>= 0;
+                                if (dst + len > buf.length) throw new WebAssembly.RuntimeError('out of bounds memory access');
+                                buf.fill(val, dst, dst + len);
                             }}"
                         )
                     }
-                    core::AugmentedOp::F64Store => {
+                    core::AugmentedOp::MemoryInit(data) => {
                         format!(
-                            "(ptr, val, offset) => {{
-                                new DataView({mem}.buffer).setFloat64(ptr + offset, val, true);
+                            "(dst, src, len) => {{
+                                const buf = new Uint8Array({mem}.buffer);
+                                const data = instance{instance}Data{data};
+                                dst >>>= 0;
+                                src >>>= 0;
+                                len >>>= 0;
+                                if (dst + len > buf.length || src + len > data.length) throw new WebAssembly.RuntimeError('out of bounds memory access');
+                                buf.set(data.subarray(src, src + len), dst);
                             }}"
                         )
                     }
-                    core::AugmentedOp::MemorySize => {
-                        format!("ptr => {mem}.buffer.byteLength / 65536")
+                    core::AugmentedOp::MemoryCopy { .. } | core::AugmentedOp::DataDrop(_) => {
+                        unreachable!()
                     }
                 }
             }
+            core::AugmentedImport::MemoryCopy { dst, src } => {
+                let dst = self.augmented_memory(instance, dst);
+                let src = self.augmented_memory(instance, src);
+                format!(
+                    "(dst, src, len) => {{
+                        const dstBuf = new Uint8Array({dst}.buffer);
+                        const srcBuf = new Uint8Array({src}.buffer);
+                        dst >>>= 0;
+                        src >>>= 0;
+                        len >>>= 0;
+                        if (dst + len > dstBuf.length || src + len > srcBuf.length) throw new WebAssembly.RuntimeError('out of bounds memory access');
+                        dstBuf.set(srcBuf.subarray(src, src + len), dst);
+                    }}"
+                )
+            }
+            core::AugmentedImport::DataDrop(data) => {
+                format!("() => {{ instance{instance}Data{data} = new Uint8Array(); }}")
+            }
+        }
+    }
+
+    fn augmented_memory(&self, instance: u32, mem: core::AugmentedMemory<'_>) -> String {
+        match mem {
+            core::AugmentedMemory::Import(def) => self.core_def(def),
+            core::AugmentedMemory::Defined(index) => format!("instance{instance}Memory{index}"),
+            core::AugmentedMemory::Module => format!(
+                "exports{instance}{}",
+                maybe_quote_member(core::AUGMENTS_MEMORY)
+            ),
         }
     }
 
@@ -1798,17 +2041,13 @@ impl<'a> Instantiator<'a, '_> {
                     | FunctionKind::Static(ty) = func.kind
                     {
                         let ty = &self.resolve.types[ty];
-                        self.gen.esm_bindgen.add_export_binding(
-                            None,
+                        self.add_export_binding(
+                            &[],
                             local_name,
                             ty.name.as_ref().unwrap().to_upper_camel_case(),
                         );
                     } else {
-                        self.gen.esm_bindgen.add_export_binding(
-                            None,
-                            local_name,
-                            export_name.to_lower_camel_case(),
-                        );
+                        self.add_export_binding(&[], local_name, export_name.to_lower_camel_case());
                     }
                 }
                 Export::Instance { exports, .. } => {
@@ -1816,6 +2055,8 @@ impl<'a> Instantiator<'a, '_> {
                         WorldItem::Interface { id, stability: _ } => *id,
                         WorldItem::Function(_) | WorldItem::Type(_) => unreachable!(),
                     };
+                    let iface_path = map_export(&self.gen.opts.export_map, export_name)
+                        .unwrap_or_else(|| vec![export_name.to_string()]);
                     for (func_name, export_idx) in exports.raw_iter() {
                         let export = &self.component.export_items[*export_idx];
                         let (def, options, func_ty) = match export {
@@ -1858,14 +2099,14 @@ impl<'a> Instantiator<'a, '_> {
                         {
                             let ty = &self.resolve.types[ty];
                             let resource = ty.name.as_ref().unwrap();
-                            self.gen.esm_bindgen.add_export_binding(
-                                Some(export_name),
+                            self.add_export_binding(
+                                &iface_path,
                                 local_name,
                                 resource.to_upper_camel_case(),
                             );
                         } else {
-                            self.gen.esm_bindgen.add_export_binding(
-                                Some(export_name),
+                            self.add_export_binding(
+                                &iface_path,
                                 local_name,
                                 func_name.to_lower_camel_case(),
                             );
@@ -1956,9 +2197,78 @@ impl<'a> Instantiator<'a, '_> {
             FunctionKind::Method(_) | FunctionKind::Static(_) => self.src.js(";\n"),
             FunctionKind::Constructor(_) => self.src.js("\n}\n"),
         }
+
+        // The core function and canonical options are only available after
+        // instantiation, so are read through getters
+        if matches!(
+            self.gen.opts.export_bindings,
+            Some(ExportBindingsMode::Hybrid)
+        ) {
+            let symbol_cabi_lift = self.gen.intrinsic(Intrinsic::SymbolCabiLift);
+            let target = match func.kind {
+                FunctionKind::Freestanding | FunctionKind::Constructor(_) => local_name.to_string(),
+                FunctionKind::Method(_) => format!(
+                    "{local_name}.prototype.{}",
+                    func.item_name().to_lower_camel_case()
+                ),
+                FunctionKind::Static(_) => {
+                    format!("{local_name}.{}", func.item_name().to_lower_camel_case())
+                }
+            };
+            uwriteln!(
+                self.src.js,
+                "{target}[{symbol_cabi_lift}] = {{
+                    get func () {{ return {callee}; }},"
+            );
+            if let Some(idx) = options.memory {
+                let idx = idx.as_u32();
+                uwriteln!(self.src.js, "get memory () {{ return memory{idx}; }},");
+            }
+            if let Some(idx) = options.realloc {
+                let idx = idx.as_u32();
+                uwriteln!(self.src.js, "get realloc () {{ return realloc{idx}; }},");
+            }
+            if let Some(idx) = options.post_return {
+                let idx = idx.as_u32();
+                uwriteln!(
+                    self.src.js,
+                    "get postReturn () {{ return postReturn{idx}; }},"
+                );
+            }
+            match options.string_encoding {
+                component::StringEncoding::Utf8 => {}
+                component::StringEncoding::Utf16 => {
+                    uwriteln!(self.src.js, "stringEncoding: 'utf16',")
+                }
+                component::StringEncoding::CompactUtf16 => {
+                    uwriteln!(self.src.js, "stringEncoding: 'compact-utf16',")
+                }
+            }
+            let resource_tables = resource_tables(resource_map);
+            if !resource_tables.is_empty() {
+                uwriteln!(
+                    self.src.js,
+                    "get resourceTables () {{ return [{}]; }},",
+                    resource_tables.join(", ")
+                );
+            }
+            uwriteln!(self.src.js, "}};");
+        }
     }
 }
 
+/// The handle tables of the resources used by a function, as passed in the
+/// `resourceTables` canonical option
+fn resource_tables(resource_map: &ResourceMap) -> Vec<String> {
+    resource_map
+        .values()
+        .filter_map(|table| match &table.data {
+            ResourceData::Host { tid, .. } => Some(format!("handleTable{}", tid.as_u32())),
+            ResourceData::Guest { .. } => None,
+        })
+        .collect()
+}
+
 #[derive(Default)]
 pub struct Source {
     pub js: source::Source,
@@ -1974,66 +2284,217 @@ impl Source {
     }
 }
 
-fn map_import(map: &Option<HashMap<String, String>>, impt: &str) -> (String, Option<String>) {
+pub(crate) fn map_import(
+    map: &Option<HashMap<String, String>>,
+    impt: &str,
+) -> (String, Option<String>) {
     let impt_sans_version = match impt.find('@') {
         Some(version_idx) => &impt[0..version_idx],
         None => impt,
     };
-    if let Some(map) = map.as_ref() {
-        if let Some(mapping) = map.get(impt) {
-            return if let Some(hash_idx) = mapping[1..].find('#') {
-                (
-                    mapping[0..hash_idx + 1].to_string(),
-                    Some(mapping[hash_idx + 2..].into()),
-                )
-            } else {
-                (mapping.into(), None)
-            };
-        }
-        if let Some(mapping) = map.get(impt_sans_version) {
-            return if let Some(hash_idx) = mapping[1..].find('#') {
-                (
-                    mapping[0..hash_idx + 1].to_string(),
-                    Some(mapping[hash_idx + 2..].into()),
-                )
-            } else {
-                (mapping.into(), None)
+    if let Some((mapping, matched)) = map.as_ref().and_then(|map| match_import(map, impt)) {
+        let mapping = match matched {
+            Some(matched) => mapping.replace('*', matched),
+            None => mapping.to_string(),
+        };
+        return if let Some(hash_idx) = mapping[1..].find('#') {
+            (
+                mapping[0..hash_idx + 1].to_string(),
+                Some(mapping[hash_idx + 2..].into()),
+            )
+        } else {
+            (mapping, None)
+        };
+    }
+    (impt_sans_version.to_string(), None)
+}
+
+/// Path of the nested namespaces to export an interface under, from the
+/// `export_map` entry matching its export name
+pub(crate) fn map_export(map: &Option<HashMap<String, String>>, expt: &str) -> Option<Vec<String>> {
+    let (mapping, matched) = map.as_ref().and_then(|map| match_import(map, expt))?;
+    let mapping = match matched {
+        Some(matched) => mapping.replace('*', matched),
+        None => mapping.to_string(),
+    };
+    Some(
+        mapping
+            .split('.')
+            .map(|segment| segment.to_lower_camel_case())
+            .collect(),
+    )
+}
+
+/// Bindings mode of an import, from `import_bindings_map` or else
+/// `import_bindings`
+pub(crate) fn import_bindings_mode<'a>(
+    opts: &'a TranspileOpts,
+    impt: &str,
+) -> Option<&'a BindingsMode> {
+    opts.import_bindings_map
+        .as_ref()
+        .and_then(|map| match_import(map, impt))
+        .map(|(mode, _)| mode)
+        .or(opts.import_bindings.as_ref())
+}
+
+/// Find the most specific entry of a map keyed by import names matching an
+/// import, returning the part of the import matched by its wildcard
+fn match_import<'a, 'b, T>(
+    map: &'a HashMap<String, T>,
+    impt: &'b str,
+) -> Option<(&'a T, Option<&'b str>)> {
+    import_matches(map, impt)
+        .into_iter()
+        .next()
+        .map(|m| (m.value, m.matched))
+}
+
+/// Entry of a map keyed by import names matching an import
+struct ImportMatch<'a, 'b, T> {
+    key: &'a str,
+    value: &'a T,
+    /// The part of the import matched by the `*` wildcard of the key name
+    matched: Option<&'b str>,
+    /// Whether the key matches through a name wildcard or a version pattern
+    pattern: bool,
+}
+
+/// All the entries of a map keyed by import names matching an import, from
+/// the most specific.
+///
+/// Keys are import names, where the name may contain a `*` wildcard, and the
+/// version may be exact (`@0.2.0`), a semver wildcard (`@0.2.*`) or an
+/// inclusive range (`@0.2.0..0.2.2`). Keys with exact names come first, then
+/// keys with wildcard names by decreasing length of their literal parts, each
+/// ordered by exact versions, version patterns and no version, and by key for
+/// equal specificity.
+fn import_matches<'a, 'b, T>(
+    map: &'a HashMap<String, T>,
+    impt: &'b str,
+) -> Vec<ImportMatch<'a, 'b, T>> {
+    let (impt_sans_version, version) = match impt.find('@') {
+        Some(version_idx) => (&impt[0..version_idx], Some(&impt[version_idx + 1..])),
+        None => (impt, None),
+    };
+    let mut matches = map
+        .iter()
+        .filter_map(|(key, value)| {
+            let (name, key_version) = match key.find('@') {
+                Some(version_idx) => (&key[0..version_idx], Some(&key[version_idx + 1..])),
+                None => (key.as_str(), None),
             };
-        }
-        for (key, mapping) in map {
-            if let Some(wildcard_idx) = key.find('*') {
-                let lhs = &key[0..wildcard_idx];
-                let rhs = &key[wildcard_idx + 1..];
-                if impt_sans_version.starts_with(lhs) && impt_sans_version.ends_with(rhs) {
-                    let matched = &impt_sans_version[wildcard_idx
-                        ..wildcard_idx + impt_sans_version.len() - lhs.len() - rhs.len()];
-                    let mapping = mapping.replace('*', matched);
-                    return if let Some(hash_idx) = mapping[1..].find('#') {
-                        (
-                            mapping[0..hash_idx + 1].to_string(),
-                            Some(mapping[hash_idx + 2..].into()),
-                        )
-                    } else {
-                        (mapping, None)
-                    };
+            let version_specificity = match key_version {
+                None => 0,
+                Some(key_version) if is_version_pattern(key_version) => {
+                    if !version.is_some_and(|version| version_matches(key_version, version)) {
+                        return None;
+                    }
+                    1
                 }
-                if impt.starts_with(lhs) && impt.ends_with(rhs) {
-                    let matched =
-                        &impt[wildcard_idx..wildcard_idx + impt.len() - lhs.len() - rhs.len()];
-                    let mapping = mapping.replace('*', matched);
-                    return if let Some(hash_idx) = mapping[1..].find('#') {
-                        (
-                            mapping[0..hash_idx + 1].to_string(),
-                            Some(mapping[hash_idx + 2..].into()),
-                        )
-                    } else {
-                        (mapping, None)
-                    };
+                Some(key_version) => {
+                    if version != Some(key_version) {
+                        return None;
+                    }
+                    2
                 }
-            }
+            };
+            let matched = match name.find('*') {
+                Some(wildcard_idx) => {
+                    let lhs = &name[0..wildcard_idx];
+                    let rhs = &name[wildcard_idx + 1..];
+                    if impt_sans_version.len() < lhs.len() + rhs.len()
+                        || !impt_sans_version.starts_with(lhs)
+                        || !impt_sans_version.ends_with(rhs)
+                    {
+                        return None;
+                    }
+                    Some(&impt_sans_version[wildcard_idx..impt_sans_version.len() - rhs.len()])
+                }
+                None if name == impt_sans_version => None,
+                None => return None,
+            };
+            let specificity = (
+                matched.is_none(),
+                name.len() - usize::from(matched.is_some()),
+                version_specificity,
+            );
+            let import_match = ImportMatch {
+                key: key.as_str(),
+                value,
+                matched,
+                pattern: matched.is_some() || version_specificity == 1,
+            };
+            Some((specificity, import_match))
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a_specificity, a), (b_specificity, b)| {
+        b_specificity.cmp(a_specificity).then(a.key.cmp(b.key))
+    });
+    matches.into_iter().map(|(_, m)| m).collect()
+}
+
+/// Whether the version of a map key is a semver wildcard or range
+fn is_version_pattern(key_version: &str) -> bool {
+    key_version.contains('*') || key_version.contains("..")
+}
+
+/// Whether a version is within a semver wildcard (`0.2.*`) or inclusive
+/// range (`0.2.0..0.2.2`)
+fn version_matches(pattern: &str, version: &str) -> bool {
+    let Ok(version) = Version::parse(version) else {
+        return false;
+    };
+    match pattern.split_once("..") {
+        Some((start, end)) => match (Version::parse(start), Version::parse(end)) {
+            (Ok(start), Ok(end)) => start <= version && version <= end,
+            _ => false,
+        },
+        None => VersionReq::parse(pattern).is_ok_and(|req| req.matches(&version)),
+    }
+}
+
+/// Warnings about the mappings of the given imports by `map`
+pub(crate) fn map_warnings<'a>(
+    map: &Option<HashMap<String, String>>,
+    imports: impl IntoIterator<Item = &'a str>,
+) -> Vec<TranspileWarning> {
+    let Some(map) = map.as_ref() else {
+        return Vec::new();
+    };
+    let mut warnings = Vec::new();
+    let mut used = BTreeSet::new();
+    for impt in imports {
+        let matches = import_matches(map, impt);
+        used.extend(matches.iter().map(|m| m.key));
+        // Imports with an exact mapping are not ambiguous, and patterns on
+        // the exact import name take precedence over name wildcards
+        let Some(first) = matches.first().filter(|m| m.pattern) else {
+            continue;
+        };
+        let pattern_keys = matches
+            .iter()
+            .filter(|m| m.pattern && m.matched.is_none() == first.matched.is_none())
+            .map(|m| m.key.to_string())
+            .collect::<Vec<_>>();
+        if pattern_keys.len() > 1 {
+            warnings.push(TranspileWarning::AmbiguousImportMapping {
+                import: impt.to_string(),
+                entries: pattern_keys,
+            });
         }
     }
-    (impt_sans_version.to_string(), None)
+    let mut unused = map
+        .keys()
+        .filter(|key| !used.contains(key.as_str()))
+        .collect::<Vec<_>>();
+    unused.sort();
+    warnings.extend(
+        unused
+            .into_iter()
+            .map(|key| TranspileWarning::UnusedMapEntry(key.to_string())),
+    );
+    warnings
 }
 
 pub fn parse_world_key(name: &str) -> Option<(&str, &str, &str)> {
@@ -2068,3 +2529,12 @@ fn core_file_name(name: &str, idx: u32) -> String {
     };
     format!("{}.core{i_str}.wasm", name)
 }
+
+/// Name of the file of a core module using multi-memory, for the file of its
+/// polyfilled version
+pub(crate) fn multi_memory_file_name(core_file_name: &str) -> String {
+    let name = core_file_name
+        .strip_suffix(".wasm")
+        .unwrap_or(core_file_name);
+    format!("{name}.multi-memory.wasm")
+}
@@ -2,12 +2,13 @@ use crate::files::Files;
 use crate::function_bindgen::{array_ty, as_nullable, maybe_null};
 use crate::names::{is_js_identifier, maybe_quote_id, LocalNames, RESERVED_KEYWORDS};
 use crate::source::Source;
-use crate::transpile_bindgen::{parse_world_key, InstantiationMode, TranspileOpts};
+use crate::transpile_bindgen::{
+    map_export, map_import, parse_world_key, InstantiationMode, TranspileOpts,
+};
 use crate::{dealias, feature_gate_allowed, uwrite, uwriteln};
 use anyhow::{Context as _, Result};
 use heck::*;
 use log::debug;
-use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, HashSet};
 use std::fmt::Write;
 use wit_bindgen_core::wit_parser::{
@@ -31,6 +32,14 @@ struct TsBindgen {
     export_object: Source,
 }
 
+/// Type of a namespace of the export map, as the interfaces exported as the
+/// namespace and its nested namespaces
+#[derive(Default)]
+struct ExportNamespace {
+    types: Vec<String>,
+    members: BTreeMap<String, ExportNamespace>,
+}
+
 /// Used to generate a `*.d.ts` file for each imported and exported interface for
 /// a component.
 ///
@@ -104,27 +113,21 @@ pub fn ts_bindgen(
                         continue;
                     }
 
-                    match name {
-                        WorldKey::Name(name) => {
-                            // kebab name -> direct ns namespace import
-                            bindgen.import_interface(resolve, &name, *id, files);
-                        }
-                        // namespaced ns:pkg/iface
-                        // TODO: map support
-                        WorldKey::Interface(id) => {
-                            let import_specifier = resolve.id_of(*id).unwrap();
-                            let (_, _, iface) = parse_world_key(&import_specifier).unwrap();
-                            let iface = iface.to_string();
-                            match interface_imports.entry(import_specifier) {
-                                Entry::Vacant(entry) => {
-                                    entry.insert(vec![("*".into(), id)]);
-                                }
-                                Entry::Occupied(ref mut entry) => {
-                                    entry.get_mut().push((iface, id));
-                                }
-                            }
-                        }
-                    }
+                    // imports are grouped by their mapped specifier, with
+                    // mapped `#` members as nested namespaces
+                    let iface_name = match name {
+                        WorldKey::Name(name) => name.as_str(),
+                        WorldKey::Interface(_) => *iface_name,
+                    };
+                    let (import_specifier, iface_member) =
+                        map_import(&opts.map, &resolve.name_world_key(name));
+                    let member_path: Vec<String> = iface_member
+                        .map(|member| member.split('.').map(str::to_lower_camel_case).collect())
+                        .unwrap_or_default();
+                    interface_imports
+                        .entry(import_specifier)
+                        .or_insert_with(Vec::new)
+                        .push((member_path, iface_name.to_string(), *id));
                 }
                 WorldItem::Type(tid) => {
                     let ty = &resolve.types[*tid];
@@ -168,16 +171,21 @@ pub fn ts_bindgen(
         for (name, func) in funcs {
             bindgen.import_funcs(resolve, &name, func, files);
         }
-        // namespace imports are grouped by namespace / kebab name
-        // kebab name imports are direct
-        for (name, import_interfaces) in interface_imports {
-            bindgen.import_interfaces(resolve, name.as_ref(), import_interfaces, files);
+        for (import_specifier, ifaces) in interface_imports {
+            uwrite!(
+                bindgen.import_object,
+                "{}: ",
+                maybe_quote_id(&import_specifier)
+            );
+            bindgen.import_interfaces(resolve, ifaces, files);
+            uwriteln!(bindgen.import_object, ",");
         }
     }
 
     let mut funcs = Vec::new();
     let mut seen_names = HashSet::new();
     let mut export_aliases: Vec<(String, String)> = Vec::new();
+    let mut export_namespaces: BTreeMap<String, ExportNamespace> = BTreeMap::new();
 
     for (name, export) in world.exports.iter() {
         match export {
@@ -213,6 +221,18 @@ pub fn ts_bindgen(
                     continue;
                 }
 
+                // interfaces of the export map are exported on nested namespaces
+                if let Some(path) = map_export(&opts.export_map, export_name) {
+                    let local_name = bindgen.generate_interface(export_name, resolve, *id, files);
+                    seen_names.insert(path[0].clone());
+                    let mut namespace = export_namespaces.entry(path[0].clone()).or_default();
+                    for member in &path[1..] {
+                        namespace = namespace.members.entry(member.clone()).or_default();
+                    }
+                    namespace.types.push(format!("typeof {local_name}"));
+                    continue;
+                }
+
                 seen_names.insert(export_name.to_string());
                 let local_name = bindgen.export_interface(
                     resolve,
@@ -226,6 +246,17 @@ pub fn ts_bindgen(
             WorldItem::Type(_) => unimplemented!("type exports"),
         }
     }
+    for (name, namespace) in export_namespaces {
+        if opts.instantiation.is_some() {
+            uwriteln!(bindgen.export_object, "{name}: {},", namespace.render());
+        } else {
+            uwriteln!(
+                bindgen.export_object,
+                "export const {name}: {};",
+                namespace.render()
+            );
+        }
+    }
     for (alias, local_name) in export_aliases {
         if !seen_names.contains(&alias) {
             if opts.instantiation.is_some() {
@@ -360,51 +391,62 @@ pub fn ts_bindgen(
     Ok(())
 }
 
-impl TsBindgen {
-    fn import_interface(
-        &mut self,
-        resolve: &Resolve,
-        name: &str,
-        id: InterfaceId,
-        files: &mut Files,
-    ) -> String {
-        // in case an imported type is used as an exported type
-        let local_name = self.generate_interface(name, resolve, id, files);
-        uwriteln!(
-            self.import_object,
-            "{}: typeof {local_name},",
-            maybe_quote_id(name)
-        );
-        local_name
+impl ExportNamespace {
+    /// Renders the type of the namespace, as the intersection of the types of
+    /// its interfaces and of the object type of its nested namespaces
+    fn render(&self) -> String {
+        let mut types = self.types.clone();
+        if !self.members.is_empty() {
+            let mut members = String::from("{\n");
+            for (name, namespace) in &self.members {
+                uwriteln!(members, "{}: {},", maybe_quote_id(name), namespace.render());
+            }
+            members.push('}');
+            types.push(members);
+        }
+        types.join(" & ")
     }
+}
+
+impl TsBindgen {
+    /// Writes the type of the imports of a specifier, given its interfaces by
+    /// their paths of nested members
     fn import_interfaces(
         &mut self,
         resolve: &Resolve,
-        import_name: &str,
-        ifaces: Vec<(String, &InterfaceId)>,
+        ifaces: Vec<(Vec<String>, String, InterfaceId)>,
         files: &mut Files,
     ) {
-        if ifaces.len() == 1 {
-            let (iface_name, &id) = ifaces.first().unwrap();
-            if iface_name == "*" {
-                uwrite!(self.import_object, "{}: ", maybe_quote_id(import_name));
-                let name = resolve.interfaces[id].name.as_ref().unwrap();
-                let local_name = self.generate_interface(name, resolve, id, files);
-                uwriteln!(self.import_object, "typeof {local_name},",);
-                return;
+        let mut types = Vec::new();
+        let mut members = BTreeMap::new();
+        for (path, name, id) in ifaces {
+            match path.split_first() {
+                None => {
+                    let local_name = self.generate_interface(&name, resolve, id, files);
+                    types.push(format!("typeof {local_name}"));
+                }
+                Some((member, path)) => members
+                    .entry(member.to_string())
+                    .or_insert_with(Vec::new)
+                    .push((path.to_vec(), name, id)),
             }
         }
-        uwriteln!(self.import_object, "{}: {{", maybe_quote_id(import_name));
-        for (iface_name, &id) in ifaces {
-            let name = resolve.interfaces[id].name.as_ref().unwrap();
-            let local_name = self.generate_interface(name, resolve, id, files);
-            uwriteln!(
-                self.import_object,
-                "{}: typeof {local_name},",
-                iface_name.to_lower_camel_case()
-            );
+        // interfaces of multiple versions mapped to the same specifier are
+        // merged, as in the ESM bindgen
+        uwrite!(self.import_object, "{}", types.join(" & "));
+        if members.is_empty() {
+            return;
         }
-        uwriteln!(self.import_object, "}},");
+        if !types.is_empty() {
+            uwrite!(self.import_object, " & ");
+        }
+        uwriteln!(self.import_object, "{{");
+        for (member, ifaces) in members {
+            uwrite!(self.import_object, "{}: ", maybe_quote_id(&member));
+            self.import_interfaces(resolve, ifaces, files);
+            uwriteln!(self.import_object, ",");
+        }
+        uwrite!(self.import_object, "}}");
     }
 
     fn import_funcs(
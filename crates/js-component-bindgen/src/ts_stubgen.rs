@@ -1,12 +1,16 @@
 use crate::files::Files;
 use crate::function_bindgen::{array_ty, as_nullable, maybe_null};
-use crate::names::{is_js_identifier, maybe_quote_id, LocalNames, RESERVED_KEYWORDS};
+use crate::names::{
+    is_js_identifier, maybe_quote_id, specifier_goal_name, LocalNames, RESERVED_KEYWORDS,
+};
 use crate::source::Source;
+use crate::transpile_bindgen::{map_import, TranspileOpts};
 use crate::{dealias, uwrite, uwriteln};
-use anyhow::bail;
+use anyhow::{bail, ensure};
 use heck::*;
 use indexmap::map::Entry;
 use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write;
 use wit_parser::*;
 
@@ -143,6 +147,194 @@ struct ExportFunction<'a> {
     func: &'a Function,
 }
 
+/// An item provided by the module of an import specifier
+enum ImportStub<'a> {
+    /// An imported interface, exposed under `member` when the import map
+    /// contains a `#` member mapping
+    Interface {
+        id: InterfaceId,
+        member: Option<String>,
+    },
+    /// A world-level function import, provided as the default export
+    Function(&'a Function),
+}
+
+/// Generate TypeScript implementation skeletons for the imports of a world.
+///
+/// Every imported interface gets its own module under `imports/`, with typed
+/// functions and resource classes throwing "not implemented" errors. Import
+/// specifiers (as mapped by `opts.map`) not provided by exactly one interface
+/// module, such as world-level function imports or `#` member mappings, get an
+/// additional module re-exporting their interfaces.
+pub fn ts_import_stubgen(
+    resolve: &Resolve,
+    id: WorldId,
+    opts: &TranspileOpts,
+    files: &mut Files,
+) -> anyhow::Result<()> {
+    let world = &resolve.worlds[id];
+
+    let mut used_file_names = HashSet::new();
+    let mut unique_file_name = |goal_name: String| {
+        let mut file_name = format!("{goal_name}.ts");
+        let mut idx = 1;
+        while !used_file_names.insert(file_name.clone()) {
+            idx += 1;
+            file_name = format!("{goal_name}-{idx}.ts");
+        }
+        file_name
+    };
+
+    let mut iface_files: HashMap<InterfaceId, String> = HashMap::new();
+    let mut specifiers: BTreeMap<String, Vec<ImportStub>> = BTreeMap::new();
+    let mut world_types: Vec<TypeId> = Vec::new();
+
+    for (key, import) in world.imports.iter() {
+        let import_name = resolve.name_world_key(key);
+        let (specifier, member) = map_import(&opts.map, &import_name);
+        match import {
+            WorldItem::Interface { id, .. } => {
+                iface_files.insert(*id, unique_file_name(interface_goal_name(&import_name)));
+                specifiers
+                    .entry(specifier)
+                    .or_default()
+                    .push(ImportStub::Interface { id: *id, member });
+            }
+            WorldItem::Function(func) => {
+                if !matches!(func.kind, FunctionKind::Freestanding) {
+                    bail!("World-level resource imports not implemented {import_name}");
+                }
+                specifiers
+                    .entry(specifier)
+                    .or_default()
+                    .push(ImportStub::Function(func));
+            }
+            WorldItem::Type(tid) => world_types.push(*tid),
+        }
+    }
+
+    for (specifier, stubs) in specifiers.iter() {
+        for stub in stubs {
+            if let ImportStub::Interface { id, member } = stub {
+                let src = import_interface_stub(resolve, *id, specifier, member, &iface_files)?;
+                files.push(&format!("imports/{}", iface_files[id]), src.as_bytes());
+            }
+        }
+
+        // The interface module can be imported directly
+        if let [ImportStub::Interface { member: None, .. }] = stubs.as_slice() {
+            continue;
+        }
+
+        let mut gen = TsInterface::new(resolve);
+        if stubs.iter().any(|s| matches!(s, ImportStub::Function(_))) {
+            for tid in world_types.iter() {
+                gen.import_type_def(*tid, None, &iface_files)?;
+            }
+        }
+
+        let mut exports = Source::default();
+        let mut has_default = false;
+        for stub in stubs {
+            match stub {
+                ImportStub::Interface { id, member: None } => {
+                    let file_name = &iface_files[id];
+                    uwriteln!(
+                        exports,
+                        "export * from './{}.js';",
+                        &file_name[..file_name.len() - 3]
+                    );
+                }
+                ImportStub::Interface {
+                    id,
+                    member: Some(member),
+                } => {
+                    let file_name = &iface_files[id];
+                    uwriteln!(
+                        exports,
+                        "export * as {} from './{}.js';",
+                        member.to_lower_camel_case(),
+                        &file_name[..file_name.len() - 3]
+                    );
+                }
+                ImportStub::Function(func) => {
+                    ensure!(
+                        !has_default,
+                        "multiple world-level functions imported from the same specifier {specifier}"
+                    );
+                    has_default = true;
+                    gen.func_stub(func, true);
+                }
+            }
+        }
+
+        let mut src = Source::default();
+        uwriteln!(src, "// Host implementation of the '{specifier}' import");
+        src.push_str(&gen.imports);
+        if !gen.imports.is_empty() {
+            uwriteln!(src, "");
+        }
+        src.push_str(&exports);
+        src.push_str(&gen.finish());
+        let file_name = unique_file_name(specifier_goal_name(specifier));
+        files.push(&format!("imports/{file_name}"), src.as_bytes());
+    }
+
+    Ok(())
+}
+
+/// Generate the implementation skeleton module of an imported interface
+fn import_interface_stub(
+    resolve: &Resolve,
+    id: InterfaceId,
+    specifier: &str,
+    member: &Option<String>,
+    iface_files: &HashMap<InterfaceId, String>,
+) -> anyhow::Result<String> {
+    let iface = &resolve.interfaces[id];
+    let mut gen = TsInterface::new(resolve);
+
+    for (name, tid) in iface.types.iter() {
+        gen.import_type_def(*tid, Some(name), iface_files)?;
+    }
+
+    let mut resources: IndexMap<TypeId, ResourceImport> = IndexMap::new();
+    for (_, func) in iface.functions.iter() {
+        match func.kind {
+            FunctionKind::Freestanding => gen.func_stub(func, false),
+            FunctionKind::Method(tid)
+            | FunctionKind::Static(tid)
+            | FunctionKind::Constructor(tid) => {
+                resources.entry(tid).or_default().push_func(func);
+            }
+        }
+    }
+
+    for tid in iface.types.values() {
+        let ty = &resolve.types[*tid];
+        if let TypeDefKind::Resource = ty.kind {
+            let name = ty.name.as_ref().expect("resource name");
+            let resource = resources.shift_remove(tid).unwrap_or_default();
+            gen.as_printer().resource_stub(name, &resource);
+        }
+    }
+
+    let mut src = Source::default();
+    match member {
+        Some(member) => uwriteln!(
+            src,
+            "// Host implementation of the '{member}' member of the '{specifier}' import"
+        ),
+        None => uwriteln!(src, "// Host implementation of the '{specifier}' import"),
+    }
+    src.push_str(&gen.imports);
+    if !gen.imports.is_empty() {
+        uwriteln!(src, "");
+    }
+    src.push_str(&gen.finish());
+    Ok(src.into())
+}
+
 impl<'a> TsStubgen<'a> {
     fn import_interfaces(&mut self, ifaces: impl Iterator<Item = InterfaceId>) {
         for id in ifaces {
@@ -360,6 +552,9 @@ struct TsInterface<'a> {
     needs_ty_option: bool,
     needs_ty_result: bool,
     local_names: LocalNames,
+    /// Type imports of implementation skeletons, which must be placed at the
+    /// top of the module
+    imports: Source,
     // Resources are aggregated, because the only way to get metadata for resource is by looking up their functions.
     resources: IndexMap<&'a str, ResourceImport<'a>>,
 }
@@ -370,6 +565,7 @@ impl<'a> TsInterface<'a> {
             src: Source::default(),
             resources: IndexMap::default(),
             local_names: LocalNames::default(),
+            imports: Source::default(),
             resolve,
             needs_ty_option: false,
             needs_ty_result: false,
@@ -488,6 +684,80 @@ impl<'a> TsInterface<'a> {
         self.src.push_str("\n");
     }
 
+    /// Generate a type definition for an implementation skeleton, importing
+    /// the types defined by other interfaces from their skeleton modules.
+    fn import_type_def(
+        &mut self,
+        id: TypeId,
+        name: Option<&str>,
+        iface_files: &HashMap<InterfaceId, String>,
+    ) -> anyhow::Result<()> {
+        let ty = &self.resolve.types[id];
+        let name = name.unwrap_or_else(|| ty.name.as_ref().expect("type name"));
+        let type_name = name.to_upper_camel_case();
+
+        let orig_id = dealias(self.resolve, id);
+        let orig = &self.resolve.types[orig_id];
+        if orig.owner != ty.owner {
+            let TypeOwner::Interface(owner) = orig.owner else {
+                bail!("Type imported from a world not implemented {name}");
+            };
+            let orig_name = orig.name.as_ref().unwrap().to_upper_camel_case();
+            let module = match iface_files.get(&owner) {
+                Some(file_name) => format!("./{}.js", &file_name[..file_name.len() - 3]),
+                None => interface_module_name(self.resolve, owner),
+            };
+            if orig_name == type_name {
+                uwriteln!(
+                    self.imports,
+                    "import type {{ {type_name} }} from '{module}';"
+                );
+            } else {
+                uwriteln!(
+                    self.imports,
+                    "import type {{ {orig_name} as {type_name} }} from '{module}';"
+                );
+            }
+            return Ok(());
+        }
+
+        match (&ty.kind, ty.owner) {
+            (TypeDefKind::Resource, TypeOwner::World(_)) => {
+                bail!("World-level resource imports not implemented {name}");
+            }
+            (_, TypeOwner::Interface(owner)) => self.type_def(id, Some(name), Some(owner)),
+            _ => self.type_def(id, Some(name), None),
+        }
+        Ok(())
+    }
+
+    /// Generate a freestanding function throwing a "not implemented" error
+    fn func_stub(&mut self, func: &Function, default_export: bool) {
+        self.as_printer().docs(&func.docs);
+
+        let name = func.item_name().to_lower_camel_case();
+        let export_alias = if default_export {
+            let local_name = self.local_names.create_once(&name);
+            uwrite!(self.src, "export default function {local_name}");
+            None
+        } else if is_js_identifier(&name) {
+            uwrite!(self.src, "export function {name}");
+            None
+        } else {
+            let local_name = self.local_names.create_once(&name).to_string();
+            uwrite!(self.src, "function {local_name}");
+            Some(local_name)
+        };
+
+        let mut printer = self.as_printer();
+        printer.ts_func_signature(func);
+        printer.not_implemented_body();
+
+        if let Some(local_name) = export_alias {
+            uwriteln!(self.src, "export {{ {local_name} as {name} }};");
+        }
+    }
+
     fn post_types(&mut self) {
         if self.needs_ty_option {
             self.src
@@ -623,6 +893,50 @@ impl<'a> Printer<'a> {
         }
     }
 
+    /// Generate an implementation skeleton class for a resource
+    fn resource_stub(&mut self, name: &str, resource: &ResourceImport) {
+        uwriteln!(self.src, "export class {} {{", AsUpperCamelCase(name));
+
+        if let Some(func) = resource.constructor {
+            self.docs(&func.docs);
+            self.src.push_str("constructor");
+            self.ts_func_signature(func);
+            self.not_implemented_body();
+        }
+
+        for func in resource.method_funcs.iter() {
+            self.docs(&func.docs);
+            let name = func.item_name().to_lower_camel_case();
+            if is_js_identifier(&name) {
+                uwrite!(self.src, "{name}");
+            } else {
+                uwrite!(self.src, "'{name}'",);
+            }
+            self.ts_func_signature(func);
+            self.not_implemented_body();
+        }
+
+        for func in resource.static_funcs.iter() {
+            self.docs(&func.docs);
+            let name = func.item_name().to_lower_camel_case();
+            if is_js_identifier(&name) {
+                uwrite!(self.src, "static {name}");
+            } else {
+                uwrite!(self.src, "static '{name}'");
+            }
+            self.ts_func_signature(func);
+            self.not_implemented_body();
+        }
+
+        uwriteln!(self.src, "}}");
+    }
+
+    fn not_implemented_body(&mut self) {
+        self.src.push_str(" {\n");
+        self.src.push_str("throw new Error('not implemented');\n");
+        self.src.push_str("}\n");
+    }
+
     fn docs_raw(&mut self, docs: &str) {
         self.src.push_str("/**\n");
         for line in docs.lines() {
@@ -0,0 +1,60 @@
+//! Helpers shared by the integration tests, each of which uses only some of
+//! them
+
+#![allow(dead_code)]
+
+use wit_component::{ComponentEncoder, StringEncoding};
+use wit_parser::Resolve;
+
+/// Component of a dummy module implementing a world of the given WIT, the
+/// only world of the package if none is given
+pub fn component(wit: &str, world: Option<&str>) -> Vec<u8> {
+    let mut resolve = Resolve::default();
+    let pkg = resolve.push_str("test.wit", wit).unwrap();
+    let world = resolve.select_world(pkg, world).unwrap();
+    let mut module = wit_component::dummy_module(&resolve, world);
+    wit_component::embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)
+        .unwrap();
+    ComponentEncoder::default()
+        .module(&module)
+        .unwrap()
+        .validate(true)
+        .encode()
+        .unwrap()
+}
+
+/// Assert that the given lines appear in order, ignoring indentation
+#[track_caller]
+pub fn assert_lines(actual: &str, expected: &[&str]) {
+    let mut lines = actual.lines().map(|l| l.trim());
+    for e in expected {
+        assert!(
+            lines.any(|a| a == *e),
+            "\nExpected line in order:`{e}`\nFull:\n{actual}"
+        );
+    }
+}
+
+/// Assert that the lines of the source are the expected ones, ignoring
+/// indentation and blank lines
+#[track_caller]
+pub fn compare_str(actual: &str, expected: &str) {
+    fn remove_whitespace(s: &str) -> impl Iterator<Item = &str> {
+        s.lines().map(|l| l.trim()).filter(|l| !l.is_empty())
+    }
+
+    let mut expected_iter = remove_whitespace(expected);
+    let mut actual_iter = remove_whitespace(actual);
+
+    loop {
+        match (expected_iter.next(), actual_iter.next()) {
+            (None, None) => break,
+            (Some(e), Some(a)) => {
+                assert_eq!(e, a, "\nExpected:`{e}`\nActual:`{a}`\nFull:\n{actual}");
+            }
+            (e, a) => {
+                assert_eq!(e, a, "\nExpected:`{e:?}`\nActual:`{a:?}`\nFull:\n{actual}");
+            }
+        }
+    }
+}
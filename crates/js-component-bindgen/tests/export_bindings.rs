@@ -0,0 +1,157 @@
+mod common;
+
+use std::collections::HashMap;
+
+use js_component_bindgen::{transpile, ExportBindingsMode, InstantiationMode, TranspileOpts};
+
+use common::{assert_lines, component};
+
+const WIT: &str = "
+    package test:t-export;
+
+    interface iface {
+        resource counter {
+            constructor(start: u32);
+            next: func() -> u32;
+        }
+        greet: func(name: string) -> string;
+        reset: func(c: borrow<counter>);
+    }
+
+    world test {
+        export iface;
+    }
+";
+
+const EXPORT_MAP_WIT: &str = "
+    package test:t-export-map;
+
+    interface incoming {
+        handle: func() -> u32;
+    }
+
+    interface outgoing {
+        send: func(x: u32);
+    }
+
+    world test {
+        export incoming;
+        export outgoing;
+    }
+";
+
+#[test]
+fn hybrid_export_bindings() {
+    let source = transpile_js(Some(ExportBindingsMode::Hybrid));
+    assert_lines(
+        &source,
+        &[
+            "greet[symbolCabiLift] = {",
+            "get func () { return exports0['test:t-export/iface#greet']; },",
+            "get memory () { return memory0; },",
+        ],
+    );
+    assert!(source.contains("get realloc () { return realloc"));
+    assert!(source.contains("get postReturn () { return postReturn"));
+    assert!(source.contains("Counter[symbolCabiLift] = {"));
+    assert!(source.contains("Counter.prototype.next[symbolCabiLift] = {"));
+    assert!(source.contains("get resourceTables () { return [handleTable0]; },"));
+
+    let source = transpile_js(None);
+    assert!(!source.contains("symbolCabiLift"));
+}
+
+#[test]
+fn export_map() {
+    let export_map = |entries: &[(&str, &str)]| {
+        Some(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+        )
+    };
+    let transpiled = transpile(
+        &component(EXPORT_MAP_WIT, None),
+        TranspileOpts {
+            name: "test".into(),
+            export_map: export_map(&[("test:t-export-map/*", "platform.http.*")]),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let file = |name: &str| {
+        let (_, source) = transpiled.files.iter().find(|(n, _)| n == name).unwrap();
+        String::from_utf8(source.clone()).unwrap()
+    };
+
+    // Mapped interfaces are only exported on their nested namespaces
+    let source = file("test.js");
+    assert_lines(
+        &source,
+        &[
+            "const platform = {",
+            "http: {",
+            "incoming: {",
+            "handle: handle,",
+            "},",
+            "outgoing: {",
+            "send: send,",
+            "},",
+            "},",
+        ],
+    );
+    assert!(source.contains("export { platform,  }"));
+    assert!(!source.contains("'test:t-export-map/incoming'"));
+    assert_eq!(transpiled.exports.len(), 1);
+    assert_eq!(transpiled.exports[0].0, "platform");
+
+    assert_lines(
+        &file("test.d.ts"),
+        &[
+            "export const platform: {",
+            "http: {",
+            "incoming: typeof TestTExportMapIncoming,",
+            "outgoing: typeof TestTExportMapOutgoing,",
+            "},",
+            "};",
+        ],
+    );
+
+    // Functions and interfaces can't be exported under the same name
+    let err = transpile(
+        &component(EXPORT_MAP_WIT, None),
+        TranspileOpts {
+            name: "test".into(),
+            export_map: export_map(&[
+                ("test:t-export-map/incoming", "platform"),
+                ("test:t-export-map/outgoing", "platform.handle"),
+            ]),
+            ..Default::default()
+        },
+    )
+    .err()
+    .unwrap();
+    assert!(format!("{err:#}")
+        .contains("export `platform.handle` cannot be both a function and an interface"));
+}
+
+fn transpile_js(export_bindings: Option<ExportBindingsMode>) -> String {
+    let transpiled = transpile(
+        &component(WIT, None),
+        TranspileOpts {
+            name: "test".into(),
+            no_typescript: true,
+            instantiation: Some(InstantiationMode::Async),
+            export_bindings,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let (_, source) = transpiled
+        .files
+        .into_iter()
+        .find(|(name, _)| name == "test.js")
+        .unwrap();
+    String::from_utf8(source).unwrap()
+}
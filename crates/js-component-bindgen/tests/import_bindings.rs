@@ -0,0 +1,296 @@
+mod common;
+
+use std::collections::HashMap;
+
+use js_component_bindgen::{transpile, BindingsMode, InstantiationMode, TranspileOpts};
+
+use common::component;
+
+#[test]
+fn import_bindings_map() {
+    let wit = "
+        package test:t-bindings;
+
+        interface fast {
+            add: func(a: u32, b: u32) -> u32;
+        }
+
+        interface slow {
+            sub: func(a: u32, b: u32) -> u32;
+        }
+
+        interface other {
+            mul: func(a: u32, b: u32) -> u32;
+        }
+
+        world test {
+            import fast;
+            import slow;
+            import other;
+            export run: func();
+        }
+    ";
+    let transpiled = transpile(
+        &component(wit, None),
+        TranspileOpts {
+            name: "test".into(),
+            no_typescript: true,
+            instantiation: Some(InstantiationMode::Async),
+            import_bindings_map: Some(HashMap::from([
+                ("test:t-bindings/*".to_string(), BindingsMode::Optimized),
+                ("test:t-bindings/slow".to_string(), BindingsMode::Hybrid),
+                ("test:t-bindings/other".to_string(), BindingsMode::Js),
+            ])),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let (_, source) = transpiled
+        .files
+        .iter()
+        .find(|(name, _)| name == "test.js")
+        .unwrap();
+    let source = String::from_utf8(source.clone()).unwrap();
+
+    // Wildcard matches apply where no exact match is defined
+    assert!(source.contains("if (!add[symbolCabiLower]) {"));
+    assert!(source.contains("= add[symbolCabiLower]({"));
+    // Exact matches take precedence
+    assert!(source.contains("if (sub[symbolCabiLower]) {"));
+    assert!(!source.contains("mul[symbolCabiLower]"));
+}
+
+#[test]
+fn map_warnings() {
+    let wit = "
+        package test:t-map;
+
+        interface fast {
+            add: func(a: u32, b: u32) -> u32;
+        }
+
+        interface slow {
+            sub: func(a: u32, b: u32) -> u32;
+        }
+
+        world test {
+            import fast;
+            import slow;
+        }
+    ";
+    let transpiled = transpile(
+        &component(wit, None),
+        TranspileOpts {
+            name: "test".into(),
+            no_typescript: true,
+            map: Some(HashMap::from([
+                ("test:*".to_string(), "./test.js#*".to_string()),
+                ("test:t-map/*".to_string(), "./t-map.js#*".to_string()),
+                ("test:t-map/slow".to_string(), "./slow.js".to_string()),
+                ("test:t-mpa/*".to_string(), "./typo.js#*".to_string()),
+            ])),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // The most specific wildcard is used for ambiguous imports
+    let (_, source) = transpiled
+        .files
+        .iter()
+        .find(|(name, _)| name == "test.js")
+        .unwrap();
+    let source = String::from_utf8(source.clone()).unwrap();
+    assert!(source.contains("from './t-map.js';"));
+    assert!(source.contains("from './slow.js';"));
+
+    assert_eq!(
+        transpiled
+            .warnings
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            "import `test:t-map/fast` matches multiple map entries (test:t-map/*, test:*), using `test:t-map/*`",
+            "map entry `test:t-mpa/*` does not match any import",
+        ]
+    );
+}
+
+#[test]
+fn versioned_map() {
+    let wit = "
+        package test:t-versions;
+
+        package test:io@0.2.1 {
+            interface streams {
+                read: func(len: u64) -> list<u8>;
+            }
+        }
+
+        package test:io@0.3.0 {
+            interface streams {
+                read: func(len: u64) -> list<u8>;
+            }
+        }
+
+        world test {
+            import test:io/streams@0.2.1;
+            import test:io/streams@0.3.0;
+        }
+    ";
+    let transpile_map = |map: &[(&str, &str)]| {
+        transpile(
+            &component(wit, None),
+            TranspileOpts {
+                name: "test".into(),
+                no_typescript: true,
+                map: Some(
+                    map.iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                ),
+                ..Default::default()
+            },
+        )
+    };
+
+    let transpiled = transpile_map(&[
+        ("test:io/*@0.2.0..0.2.2", "./io-0.2.js#*"),
+        ("test:io/*@0.3.*", "./io-0.3.js#*"),
+    ])
+    .unwrap();
+    let (_, source) = transpiled
+        .files
+        .iter()
+        .find(|(name, _)| name == "test.js")
+        .unwrap();
+    let source = String::from_utf8(source.clone()).unwrap();
+    assert!(source.contains("from './io-0.2.js';"));
+    assert!(source.contains("from './io-0.3.js';"));
+    assert!(transpiled.warnings.is_empty());
+
+    // Versions mapped to the same specifier must have the same shape
+    let error = transpile_map(&[
+        ("test:io/streams@0.2.1", "./io.js#read"),
+        ("test:io/streams@0.3.0", "./io.js"),
+    ])
+    .err()
+    .expect("expected a conflicting import error")
+    .to_string();
+    assert!(
+        error.starts_with("import `./io.js#read` cannot be both a function and an interface"),
+        "{error}"
+    );
+}
+
+#[test]
+fn nested_map() {
+    let wit = "
+        package test:t-nested;
+
+        interface streams {
+            read: func(len: u64) -> list<u8>;
+        }
+
+        interface poll {
+            ready: func() -> bool;
+        }
+
+        world test {
+            import streams;
+            import poll;
+        }
+    ";
+    let transpiled = transpile(
+        &component(wit, None),
+        TranspileOpts {
+            name: "test".into(),
+            instantiation: Some(InstantiationMode::Async),
+            map: Some(HashMap::from([(
+                "test:t-nested/*".to_string(),
+                "./platform.js#io.*".to_string(),
+            )])),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let file = |name: &str| {
+        let (_, source) = transpiled.files.iter().find(|(n, _)| n == name).unwrap();
+        String::from_utf8(source.clone()).unwrap()
+    };
+
+    // Members are read off of their nested interfaces
+    let source = file("test.js");
+    assert!(source.contains("const { io } = imports['./platform.js'];"));
+    assert!(source.contains("const { read } = streams;"));
+    assert!(source.contains("const { ready } = poll;"));
+
+    let types = file("test.d.ts");
+    assert!(types.contains(
+        "'./platform.js': {
+    io: {
+      poll: typeof TestTNestedPoll,
+      streams: typeof TestTNestedStreams,
+    },
+  },"
+    ));
+}
+
+#[test]
+fn versioned_map_signatures() {
+    let transpile_versions = |read_v3: &str| {
+        let wit = format!(
+            "
+            package test:t-signatures;
+
+            package test:io@0.2.1 {{
+                interface streams {{
+                    resource pollable;
+                    wait: func(p: borrow<pollable>);
+                    read: func(len: u64) -> list<u8>;
+                }}
+            }}
+
+            package test:io@0.3.0 {{
+                interface streams {{
+                    resource pollable;
+                    wait: func(p: borrow<pollable>);
+                    read: {read_v3};
+                }}
+            }}
+
+            world test {{
+                import test:io/streams@0.2.1;
+                import test:io/streams@0.3.0;
+            }}
+            "
+        );
+        transpile(
+            &component(&wit, None),
+            TranspileOpts {
+                name: "test".into(),
+                no_typescript: true,
+                map: Some(HashMap::from([(
+                    "test:io/*".to_string(),
+                    "./io.js#*".to_string(),
+                )])),
+                ..Default::default()
+            },
+        )
+    };
+
+    // Resources of different versions are compared by name
+    transpile_versions("func(len: u64) -> list<u8>").unwrap();
+
+    let error = transpile_versions("func(len: u32) -> list<u8>")
+        .err()
+        .expect("expected a conflicting import error")
+        .to_string();
+    assert!(
+        error.starts_with(
+            "import `./io.js#streams.read` cannot be both `func(len: u64) -> list<u8>` and `func(len: u32) -> list<u8>`"
+        ),
+        "{error}"
+    );
+}
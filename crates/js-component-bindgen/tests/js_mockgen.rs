@@ -0,0 +1,249 @@
+mod common;
+
+use std::collections::HashMap;
+
+use js_component_bindgen::{generate_js_mocks, TranspileOpts};
+
+use common::compare_str;
+
+#[test]
+fn interface_functions() {
+    let wit = "
+        package test:t-funcs;
+
+        world test {
+            import funcs;
+            import log: func(msg: string);
+        }
+
+        interface funcs {
+            record point {
+                x: u32,
+                y: s64,
+                label: option<string>,
+            }
+
+            enum color {
+                red,
+                green,
+            }
+
+            variant shape {
+                circle(u32),
+                square,
+            }
+
+            get-point: func() -> point;
+            get-color: func() -> color;
+            get-shape: func() -> shape;
+            get-bytes: func() -> list<u8>;
+            get-names: func() -> list<string>;
+            try-get: func() -> result<tuple<bool, char>, string>;
+            delete: func(key: string);
+        }
+    ";
+
+    let expected = &[
+        ExpectedJs {
+            file_name: "mocks/test-t-funcs-funcs.js",
+            expected: r#"
+                // Mock implementation of the 'test:t-funcs/funcs' import
+                import { mockFn } from './mock-runtime.js';
+
+                const getPoint = mockFn('get-point', () => ({ x: 0, y: 0n, label: undefined }));
+                const getColor = mockFn('get-color', () => ('red'));
+                const getShape = mockFn('get-shape', () => ({ tag: 'circle', val: 0 }));
+                const getBytes = mockFn('get-bytes', () => (new Uint8Array(0)));
+                const getNames = mockFn('get-names', () => ([]));
+                const tryGet = mockFn('try-get', () => ([false, '\0']));
+                const _delete = mockFn('delete', () => {});
+
+                export { getPoint, getColor, getShape, getBytes, getNames, tryGet, _delete as delete };
+            "#,
+        },
+        ExpectedJs {
+            file_name: "mocks/log.js",
+            expected: r#"
+                // Mock implementation of the 'log' import
+                import { mockFn } from './mock-runtime.js';
+
+                const log = mockFn('log', () => {});
+
+                export { log as default };
+            "#,
+        },
+        ExpectedJs {
+            file_name: "mocks/index.js",
+            expected: r#"
+                import * as mock0 from './log.js';
+                import * as mock1 from './test-t-funcs-funcs.js';
+                export { resetAllMocks } from './mock-runtime.js';
+
+                export const imports = {
+                    'log': mock0,
+                    'test:t-funcs/funcs': mock1,
+                };
+            "#,
+        },
+    ];
+
+    test_mocks(wit, None, expected);
+}
+
+#[test]
+fn resources_across_interfaces() {
+    let wit = "
+        package test:t-resource;
+
+        world test {
+            import files;
+        }
+
+        interface streams {
+            resource input-stream {
+                read: func(len: u64) -> list<u8>;
+                subscribe: static func() -> input-stream;
+            }
+        }
+
+        interface files {
+            use streams.{input-stream};
+
+            resource descriptor {
+                constructor(path: string);
+                read-via-stream: func() -> input-stream;
+            }
+
+            open: func(path: string) -> descriptor;
+        }
+    ";
+
+    let expected = &[
+        ExpectedJs {
+            file_name: "mocks/test-t-resource-streams.js",
+            expected: r#"
+                // Mock implementation of the 'test:t-resource/streams' import
+                import { mockFn, mockResource } from './mock-runtime.js';
+
+                const InputStream = mockResource('InputStream');
+                InputStream.prototype.read = mockFn('InputStream.read', () => (new Uint8Array(0)));
+                InputStream.subscribe = mockFn('InputStream.subscribe', () => (new InputStream()));
+
+                export { InputStream };
+            "#,
+        },
+        ExpectedJs {
+            file_name: "mocks/test-t-resource-files.js",
+            expected: r#"
+                // Mock implementation of the 'test:t-resource/files' import
+                import { mockFn, mockResource } from './mock-runtime.js';
+                import * as testTResourceStreamsMock from './test-t-resource-streams.js';
+
+                const Descriptor = mockResource('Descriptor');
+                Descriptor.construct = mockFn('Descriptor.constructor', () => {});
+                Descriptor.prototype.readViaStream = mockFn('Descriptor.readViaStream', () => (new testTResourceStreamsMock.InputStream()));
+
+                const open = mockFn('open', () => (new Descriptor()));
+
+                export { Descriptor, open };
+            "#,
+        },
+    ];
+
+    test_mocks(wit, None, expected);
+}
+
+#[test]
+fn mapped_member_imports() {
+    let wit = "
+        package test:t-map;
+
+        world test {
+            import first;
+            import second;
+        }
+
+        interface first {
+            resource handle {
+                get: func() -> u32;
+            }
+        }
+
+        interface second {
+            use first.{handle};
+
+            make: func() -> handle;
+        }
+    ";
+
+    let map = HashMap::from([("test:t-map/*".to_string(), "./shim.js#*".to_string())]);
+
+    let expected = &[ExpectedJs {
+        file_name: "mocks/shim-js.js",
+        expected: r#"
+            // Mock implementation of the './shim.js' import
+            import { mockFn, mockResource } from './mock-runtime.js';
+
+            const Handle = mockResource('Handle');
+            Handle.prototype.get = mockFn('Handle.get', () => (0));
+
+            const first = {
+                Handle,
+            };
+
+            const make = mockFn('make', () => (new Handle()));
+
+            const second = {
+                make,
+            };
+
+            export { first, second };
+        "#,
+    }];
+
+    test_mocks(wit, Some(map), expected);
+}
+
+struct ExpectedJs {
+    file_name: &'static str,
+    expected: &'static str,
+}
+
+#[track_caller]
+fn test_mocks(wit: &str, map: Option<HashMap<String, String>>, expected: &[ExpectedJs]) {
+    let mut resolve = js_component_bindgen::source::wit_parser::Resolve::default();
+    let package = wit_parser::UnresolvedPackageGroup::parse("test.wit", wit).expect("valid wit");
+    resolve.push_group(package).expect("push package");
+
+    let world = resolve
+        .worlds
+        .iter()
+        .find(|(_, w)| w.name == "test")
+        .expect("world exists")
+        .0;
+
+    let opts = TranspileOpts {
+        map,
+        ..Default::default()
+    };
+
+    let files = generate_js_mocks(resolve, world, opts)
+        .expect("Successful generation")
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    assert!(files.contains_key("mocks/mock-runtime.js"));
+
+    for ExpectedJs {
+        file_name,
+        expected,
+    } in expected
+    {
+        let Some(file) = files.get(*file_name) else {
+            let all_files = files.keys().collect::<Vec<_>>();
+            panic!("Expected file `{file_name}` not found in files: {all_files:?}",)
+        };
+        let actual = std::str::from_utf8(file).expect("valid utf8");
+        compare_str(actual, expected);
+    }
+}
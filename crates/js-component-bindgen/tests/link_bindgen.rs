@@ -0,0 +1,151 @@
+mod common;
+
+use js_component_bindgen::{link, Link, LinkComponent, TranspileOpts};
+
+use common::{assert_lines, component};
+
+const WIT: &str = "
+    package test:t-link@0.1.0;
+
+    interface iface {
+        resource thing {
+            constructor();
+        }
+        add: func(a: u32, b: u32) -> u32;
+        greet: func(name: string) -> string;
+        take: func(t: borrow<thing>);
+    }
+
+    world lib {
+        export iface;
+        export run: func(args: list<string>) -> u32;
+    }
+
+    world app {
+        import iface;
+        import run: func(args: list<string>) -> u32;
+        import log: func(msg: string);
+        export start: func();
+    }
+";
+
+#[test]
+fn linked_components() {
+    let (app, lib) = (component(WIT, Some("app")), component(WIT, Some("lib")));
+    let linked = link(
+        &[
+            LinkComponent {
+                name: "app".into(),
+                component: &app,
+            },
+            LinkComponent {
+                name: "lib".into(),
+                component: &lib,
+            },
+        ],
+        &[
+            link_to("app", "test:t-link/iface@0.1.0", "lib"),
+            link_to("app", "run", "lib"),
+        ],
+        TranspileOpts {
+            name: "linked".into(),
+            no_typescript: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let files = linked
+        .files
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    for file in [
+        "app.js",
+        "app.core.wasm",
+        "lib.js",
+        "lib.core.wasm",
+        "linked.js",
+    ] {
+        assert!(files.contains(&file), "missing {file} in {files:?}");
+    }
+    // Only the import which is not wired remains
+    assert_eq!(linked.imports, ["log"]);
+
+    let source = |name: &str| {
+        let (_, source) = linked.files.iter().find(|(n, _)| n == name).unwrap();
+        String::from_utf8(source.clone()).unwrap()
+    };
+    assert!(source("lib.js").contains("[symbolCabiLift] = {"));
+    assert!(source("app.js").contains("[symbolCabiLower]({"));
+
+    let glue = source("linked.js");
+    assert_lines(
+        &glue,
+        &[
+            "import { instantiate as instantiateApp } from './app.js';",
+            "import { instantiate as instantiateLib } from './lib.js';",
+            "export async function instantiate (getCoreModule, imports, instantiateCore) {",
+            "const lib = await instantiateLib(getCoreModule, imports, instantiateCore);",
+            "const app = await instantiateApp(getCoreModule, {",
+            "run: { default: linkFunction(lib.run, lowerRun) },",
+            "'test:t-link/iface': {",
+            "...lib['test:t-link/iface@0.1.0'],",
+            "add: linkFunction(lib['test:t-link/iface@0.1.0'].add, lowerAdd),",
+            "greet: linkFunction(lib['test:t-link/iface@0.1.0'].greet, lowerGreet),",
+            "take: linkFunction(lib['test:t-link/iface@0.1.0'].take),",
+            "return { app, lib };",
+        ],
+    );
+    // Strings and lists are copied between the memories of the components
+    assert!(glue.contains("var [ptr0, len0] = copyString(src, dst, arg0, arg1);"));
+    assert!(
+        glue.contains("var ptr2 = copyList(src, dst, arg0, arg1, 8, 4, (srcPtr0, dstPtr0) => {")
+    );
+}
+
+#[test]
+fn invalid_links() {
+    let (app, lib) = (component(WIT, Some("app")), component(WIT, Some("lib")));
+    let components = [
+        LinkComponent {
+            name: "app".into(),
+            component: &app,
+        },
+        LinkComponent {
+            name: "lib".into(),
+            component: &lib,
+        },
+    ];
+    let error = |links: &[Link]| {
+        let opts = TranspileOpts {
+            name: "linked".into(),
+            ..Default::default()
+        };
+        link(&components, links, opts)
+            .err()
+            .expect("expected link error")
+            .to_string()
+    };
+
+    assert_eq!(
+        error(&[link_to("app", "run", "other")]),
+        "link references unknown component `other`"
+    );
+    assert_eq!(
+        error(&[link_to("app", "log", "lib")]),
+        "component `lib` has no export `log` for the import of `app`"
+    );
+    assert_eq!(
+        error(&[link_to("lib", "run", "app")]),
+        "component `lib` has no import `run`"
+    );
+}
+
+fn link_to(importer: &str, import: &str, exporter: &str) -> Link {
+    Link {
+        importer: importer.into(),
+        import: import.into(),
+        exporter: exporter.into(),
+    }
+}
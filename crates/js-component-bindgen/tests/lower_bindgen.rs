@@ -0,0 +1,145 @@
+mod common;
+
+use std::collections::HashMap;
+
+use js_component_bindgen::generate_lower_bindings;
+
+use common::assert_lines;
+
+#[test]
+fn interface_functions() {
+    let wit = "
+        package test:t-lower;
+
+        world test {
+            import funcs;
+            import log: func(msg: string);
+        }
+
+        interface funcs {
+            add: func(a: u32, b: u32) -> u32;
+            greet: func(name: string) -> string;
+            try-get: func(key: u32) -> result<u64, string>;
+        }
+    ";
+
+    let files = lower_bindings(wit);
+    assert_eq!(
+        files.keys().map(|s| s.as_str()).collect::<Vec<_>>(),
+        ["lower/log.js", "lower/test-t-lower-funcs.js"]
+    );
+
+    let funcs = &files["lower/test-t-lower-funcs.js"];
+    assert_lines(
+        funcs,
+        &[
+            "// Canonical ABI lowering factories for the 'test:t-lower/funcs' import",
+            "const symbolCabiLower = Symbol.for('cabiLower');",
+            "export function cabiLower (impl) {",
+            "impl.add[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8' }) {",
+            "impl.greet[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8' }) {",
+            "impl.tryGet[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8' }) {",
+            "return impl;",
+        ],
+    );
+    // Only functions using strings depend on the string encoding
+    assert_eq!(
+        funcs.matches("if (stringEncoding === 'utf16') {").count(),
+        2
+    );
+    assert!(funcs.contains("impl.add(arg0 >>> 0, arg1 >>> 0)"));
+
+    let log = &files["lower/log.js"];
+    assert_lines(
+        log,
+        &[
+            "// Canonical ABI lowering factories for the 'log' import",
+            "impl.default[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8' }) {",
+        ],
+    );
+}
+
+#[test]
+fn resources() {
+    let wit = "
+        package test:t-lower-rsc;
+
+        world test {
+            import types;
+            import store;
+        }
+
+        interface types {
+            resource blob {
+                constructor(data: list<u8>);
+                size: func() -> u32;
+                merge: static func(a: borrow<blob>, b: borrow<blob>) -> blob;
+            }
+        }
+
+        interface store {
+            use types.{blob};
+            resource entry {
+                blob: func() -> blob;
+            }
+            put: func(key: string, value: borrow<blob>) -> entry;
+        }
+    ";
+
+    let files = lower_bindings(wit);
+
+    let types = &files["lower/test-t-lower-rsc-types.js"];
+    assert_lines(
+        types,
+        &[
+            "let Blob;",
+            "const captureTable0 = new Map();",
+            "let captureCnt0 = 0;",
+            "function captureRep0 () {",
+            "export function cabiLower (impl) {",
+            "Blob = impl.Blob;",
+            "Blob[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8', resourceTables: [handleTable0] }) {",
+            "Blob.prototype.size[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8', resourceTables: [handleTable0] }) {",
+            "Blob.merge[symbolCabiLower] = function ({ memory, realloc, stringEncoding = 'utf8', resourceTables: [handleTable0] }) {",
+            "export { Blob, captureTable0 as Blob$captureTable, captureRep0 as Blob$captureRep };",
+        ],
+    );
+    assert!(types.contains("new Blob("));
+
+    // Resources of other imports and their capture tables are read from their
+    // module
+    let store = &files["lower/test-t-lower-rsc-store.js"];
+    assert_lines(
+        store,
+        &[
+            "import { Blob as testTLowerRscTypes$Blob, Blob$captureTable as captureTable1, Blob$captureRep as captureRep1 } from './test-t-lower-rsc-types.js';",
+            "let Entry;",
+            "const captureTable0 = new Map();",
+            "Entry = impl.Entry;",
+            "export { Entry, captureTable0 as Entry$captureTable, captureRep0 as Entry$captureRep };",
+        ],
+    );
+    assert_eq!(store.matches("new Map()").count(), 1);
+    assert!(store.contains("captureRep1()"));
+    assert!(store.contains("instanceof testTLowerRscTypes$Blob"));
+    assert!(store.contains("resourceTables: [handleTable0, handleTable1]"));
+}
+
+fn lower_bindings(wit: &str) -> HashMap<String, String> {
+    let mut resolve = js_component_bindgen::source::wit_parser::Resolve::default();
+    let package = wit_parser::UnresolvedPackageGroup::parse("test.wit", wit).expect("valid wit");
+    resolve.push_group(package).expect("push package");
+
+    let world = resolve
+        .worlds
+        .iter()
+        .find(|(_, w)| w.name == "test")
+        .expect("world exists")
+        .0;
+
+    generate_lower_bindings(resolve, world)
+        .expect("Successful generation")
+        .into_iter()
+        .map(|(name, file)| (name, String::from_utf8(file).expect("valid utf8")))
+        .collect()
+}
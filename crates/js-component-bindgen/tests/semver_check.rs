@@ -0,0 +1,281 @@
+use js_component_bindgen::{semver_check, ApiChange, ChangeKind, ChangedItem};
+use wit_parser::{Resolve, WorldId};
+
+#[test]
+fn interface_changes() {
+    let old = "
+        package test:api@1.0.0;
+
+        interface types {
+            record point {
+                x: u32,
+                y: u32,
+            }
+
+            enum color {
+                red,
+                green,
+            }
+
+            resource handle {
+                get: func() -> u32;
+            }
+        }
+
+        interface api {
+            use types.{point, handle};
+
+            lookup: func(id: u32) -> option<point>;
+            remove: func(id: u32);
+            open: func() -> handle;
+        }
+
+        world app {
+            export api;
+        }
+    ";
+    let new = "
+        package test:api@1.1.0;
+
+        interface types {
+            record point {
+                x: u32,
+                y: u64,
+                z: u32,
+            }
+
+            enum color {
+                green,
+                red,
+            }
+
+            resource handle {
+                get: func() -> u32;
+                reset: func();
+            }
+
+            flags perms {
+                read,
+                write,
+            }
+        }
+
+        interface api {
+            use types.{point, handle};
+
+            lookup: func(key: u32) -> option<point>;
+            open: func() -> handle;
+            count: func() -> u32;
+        }
+
+        world app {
+            export api;
+        }
+    ";
+
+    let expected = [
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Type,
+            "import test:api/types@1.1.0#point",
+            "field `y` changed from `u32` to `u64`",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Type,
+            "import test:api/types@1.1.0#point",
+            "field `z` added",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Type,
+            "import test:api/types@1.1.0#color",
+            "cases reordered",
+        ),
+        (
+            ChangeKind::Additive,
+            ChangedItem::Type,
+            "import test:api/types@1.1.0#perms",
+            "type added",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Function,
+            "import test:api/types@1.1.0#handle.reset",
+            "function added",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Function,
+            "export test:api/api@1.1.0#lookup",
+            "parameter `id` renamed to `key`",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Function,
+            "export test:api/api@1.1.0#remove",
+            "function removed",
+        ),
+        (
+            ChangeKind::Additive,
+            ChangedItem::Function,
+            "export test:api/api@1.1.0#count",
+            "function added",
+        ),
+    ];
+
+    test_semver_check(old, new, &[], &expected);
+}
+
+#[test]
+fn world_changes() {
+    let old = "
+        package test:app@1.0.0;
+
+        interface host {
+            log: func(msg: string);
+        }
+
+        world app {
+            import host;
+            import clock: func() -> u64;
+            export run: func();
+        }
+    ";
+    let new = "
+        package test:app@1.0.0;
+
+        interface host {
+            log: func(msg: string);
+        }
+
+        interface extra {
+            f: func();
+        }
+
+        world app {
+            import host;
+            import extra;
+            export run: func() -> result;
+            export stop: func();
+        }
+    ";
+
+    let expected = [
+        (
+            ChangeKind::Additive,
+            ChangedItem::Function,
+            "import clock",
+            "import removed",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Function,
+            "export run",
+            "results changed from `()` to `result`",
+        ),
+        (
+            ChangeKind::Breaking,
+            ChangedItem::Interface,
+            "import test:app/extra@1.0.0",
+            "import added",
+        ),
+        (
+            ChangeKind::Additive,
+            ChangedItem::Function,
+            "export stop",
+            "export added",
+        ),
+    ];
+
+    test_semver_check(old, new, &[], &expected);
+}
+
+#[test]
+fn feature_gated_changes() {
+    let old = "
+        package test:gates@1.0.0;
+
+        interface api {
+            stable: func();
+        }
+
+        world app {
+            export api;
+        }
+    ";
+    let new = "
+        package test:gates@1.1.0;
+
+        interface api {
+            stable: func();
+
+            @since(version = 1.1.0)
+            released: func();
+
+            @unstable(feature = experimental)
+            experiment: func();
+        }
+
+        world app {
+            export api;
+        }
+    ";
+
+    let expected = [(
+        ChangeKind::Additive,
+        ChangedItem::Function,
+        "export test:gates/api@1.1.0#released",
+        "function added",
+    )];
+    test_semver_check(old, new, &[], &expected);
+
+    let expected = [
+        (
+            ChangeKind::Additive,
+            ChangedItem::Function,
+            "export test:gates/api@1.1.0#released",
+            "function added",
+        ),
+        (
+            ChangeKind::Additive,
+            ChangedItem::Function,
+            "export test:gates/api@1.1.0#experiment",
+            "function added",
+        ),
+    ];
+    test_semver_check(old, new, &["experimental"], &expected);
+}
+
+fn load_world(wit: &str, features: &[&str]) -> (Resolve, WorldId) {
+    let mut resolve = Resolve::default();
+    resolve
+        .features
+        .extend(features.iter().map(|feature| feature.to_string()));
+    let package = resolve.push_str("test.wit", wit).expect("valid wit");
+    let world = resolve.select_world(package, None).expect("world exists");
+    (resolve, world)
+}
+
+#[track_caller]
+fn test_semver_check(
+    old: &str,
+    new: &str,
+    features: &[&str],
+    expected: &[(ChangeKind, ChangedItem, &str, &str)],
+) {
+    let (old, old_world) = load_world(old, features);
+    let (new, new_world) = load_world(new, features);
+
+    let changes = semver_check(&old, old_world, &new, new_world).expect("successful check");
+    let expected = expected
+        .iter()
+        .map(|(kind, item, path, description)| ApiChange {
+            kind: *kind,
+            item: *item,
+            path: path.to_string(),
+            description: description.to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(changes, expected);
+}
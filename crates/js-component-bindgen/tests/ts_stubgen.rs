@@ -1,6 +1,12 @@
+mod common;
+
 use std::collections::HashMap;
 
-use js_component_bindgen::generate_typescript_stubs;
+use js_component_bindgen::{
+    generate_typescript_import_stubs, generate_typescript_stubs, TranspileOpts,
+};
+
+use common::compare_str;
 
 // Enable this to write the generated files to the `tests/temp` directory
 static IS_DEBUG: bool = false;
@@ -708,6 +714,193 @@ fn reserved_keywords() {
     test_files(wit, expected);
 }
 
+#[test]
+fn import_stubs_resources() {
+    let wit = "
+        package test:t-resource;
+
+        world test {
+            import files;
+        }
+
+        interface streams {
+            resource input-stream {
+                read: func(len: u64) -> list<u8>;
+                subscribe: static func() -> input-stream;
+            }
+        }
+
+        interface files {
+            use streams.{input-stream};
+
+            resource descriptor {
+                constructor(path: string);
+                read-via-stream: func() -> input-stream;
+            }
+
+            open: func(path: string) -> descriptor;
+            delete: func(path: string) -> result<_, string>;
+        }
+    ";
+
+    let expected = &[
+        ExpectedTs {
+            file_name: "imports/test-t-resource-streams.ts",
+            expected: r#"
+            // Host implementation of the 'test:t-resource/streams' import
+            export class InputStream {
+                read(len: bigint): Uint8Array {
+                    throw new Error('not implemented');
+                }
+                static subscribe(): InputStream {
+                    throw new Error('not implemented');
+                }
+            }
+            "#,
+        },
+        ExpectedTs {
+            file_name: "imports/test-t-resource-files.ts",
+            expected: r#"
+            // Host implementation of the 'test:t-resource/files' import
+            import type { InputStream } from './test-t-resource-streams.js';
+
+            export function open(path: string): Descriptor {
+                throw new Error('not implemented');
+            }
+            function _delete(path: string): void {
+                throw new Error('not implemented');
+            }
+            export { _delete as delete };
+            export class Descriptor {
+                constructor(path: string) {
+                    throw new Error('not implemented');
+                }
+                readViaStream(): InputStream {
+                    throw new Error('not implemented');
+                }
+            }
+            "#,
+        },
+    ];
+
+    test_import_stubs(wit, None, expected);
+}
+
+#[test]
+fn import_stubs_world_functions() {
+    let wit = "
+        package test:t-world@1.0.0;
+
+        interface types {
+            record entry {
+                key: string,
+                value: option<option<u32>>,
+            }
+        }
+
+        world test {
+            use types.{entry};
+
+            record local {
+                e: entry,
+            }
+
+            import get-entry: func(l: local) -> result<entry, string>;
+        }
+    ";
+
+    let expected = &[
+        ExpectedTs {
+            file_name: "imports/test-t-world-types.ts",
+            expected: r#"
+            // Host implementation of the 'test:t-world/types' import
+            export interface Entry {
+                key: string,
+                value: Option<number | undefined>,
+            }
+            export type Option<T> = { tag: 'none' } | { tag: 'some', val: T };
+            "#,
+        },
+        ExpectedTs {
+            file_name: "imports/get-entry.ts",
+            expected: r#"
+            // Host implementation of the 'get-entry' import
+            import type { Entry } from './test-t-world-types.js';
+
+            export interface Local {
+                e: Entry,
+            }
+            export default function getEntry(l: Local): Entry {
+                throw new Error('not implemented');
+            }
+            "#,
+        },
+    ];
+
+    test_import_stubs(wit, None, expected);
+}
+
+#[test]
+fn import_stubs_mapped() {
+    let wit = "
+        package test:t-map;
+
+        world test {
+            import first;
+            import second;
+        }
+
+        interface first {
+            resource handle {
+                get: func() -> u32;
+            }
+        }
+
+        interface second {
+            use first.{handle as first-handle};
+
+            make: func() -> first-handle;
+        }
+    ";
+
+    let map = HashMap::from([("test:t-map/*".to_string(), "./shim.js#*".to_string())]);
+
+    let expected = &[
+        ExpectedTs {
+            file_name: "imports/shim-js.ts",
+            expected: r#"
+            // Host implementation of the './shim.js' import
+            export * as first from './test-t-map-first.js';
+            export * as second from './test-t-map-second.js';
+            "#,
+        },
+        ExpectedTs {
+            file_name: "imports/test-t-map-first.ts",
+            expected: r#"
+            // Host implementation of the 'first' member of the './shim.js' import
+            export class Handle {
+                get(): number {
+                    throw new Error('not implemented');
+                }
+            }
+            "#,
+        },
+        ExpectedTs {
+            file_name: "imports/test-t-map-second.ts",
+            expected: r#"
+            // Host implementation of the 'second' member of the './shim.js' import
+            import type { Handle as FirstHandle } from './test-t-map-first.js';
+
+            export function make(): FirstHandle {
+                throw new Error('not implemented');
+            }
+            "#,
+        },
+    ];
+
+    test_import_stubs(wit, Some(map), expected);
+}
+
 struct WitFile {
     wit: &'static str,
 }
@@ -771,6 +964,56 @@ fn test_resolved_world(
     }
 }
 
+#[track_caller]
+fn test_import_stubs(
+    wit: &'static str,
+    map: Option<HashMap<String, String>>,
+    expected: &[ExpectedTs],
+) {
+    let mut resolver = js_component_bindgen::source::wit_parser::Resolve::default();
+    let package = wit_parser::UnresolvedPackageGroup::parse("test.wit", wit).expect("valid wit");
+    resolver.push_group(package).expect("push package");
+
+    let world = resolver
+        .worlds
+        .iter()
+        .find(|(_, w)| w.name == "test")
+        .expect("world exists")
+        .0;
+
+    let opts = TranspileOpts {
+        map,
+        ..Default::default()
+    };
+
+    let mut files = generate_typescript_import_stubs(resolver, world, opts)
+        .expect("Successful generation")
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    if IS_DEBUG {
+        write_files(files.iter());
+    }
+
+    for ExpectedTs {
+        file_name,
+        expected,
+    } in expected
+    {
+        let Some(file) = files.remove(*file_name) else {
+            let all_files = files.iter().map(|(name, _)| name).collect::<Vec<_>>();
+            panic!("Expected file `{file_name}` not found in files: {all_files:?}",)
+        };
+        let actual = std::str::from_utf8(&file).expect("valid utf8");
+        compare_str(actual, expected);
+    }
+
+    if !files.is_empty() {
+        let all_files = files.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        panic!("Missing expected files: {all_files:?}")
+    }
+}
+
 #[track_caller]
 fn test_single_file(wit: &'static str, expected: &'static str) {
     test_files(
@@ -782,28 +1025,6 @@ fn test_single_file(wit: &'static str, expected: &'static str) {
     )
 }
 
-#[track_caller]
-fn compare_str(actual: &str, expected: &str) {
-    fn remove_whitespace(s: &str) -> impl Iterator<Item = &str> {
-        s.lines().map(|l| l.trim()).filter(|l| !l.is_empty())
-    }
-
-    let mut expected_iter = remove_whitespace(expected);
-    let mut actual_iter = remove_whitespace(actual);
-
-    loop {
-        match (expected_iter.next(), actual_iter.next()) {
-            (None, None) => break,
-            (Some(e), Some(a)) => {
-                assert_eq!(e, a, "\nExpected:`{e}`\nActual:`{a}`\nFull:\n{actual}");
-            }
-            (e, a) => {
-                assert_eq!(e, a, "\nExpected:`{e:?}`\nActual:`{a:?}`\nFull:\n{actual}");
-            }
-        }
-    }
-}
-
 fn write_files<'a>(files: impl Iterator<Item = (&'a String, &'a Vec<u8>)>) {
     let prefix = std::path::Path::new("tests/temp");
     let _ = std::fs::remove_dir_all(&prefix);
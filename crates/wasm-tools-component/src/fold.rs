@@ -0,0 +1,668 @@
+//! Printing of instructions in folded (s-expression) form
+//!
+//! The printer only prints instructions in their flat form, so function
+//! bodies are folded from its output instead. Each line of a printed body is
+//! one operator, found by its binary offset, and operators take the
+//! expressions printed before them as their operands when these produce
+//! exactly the values they consume. Since a folded expression is defined as
+//! its operands followed by its operator, folding never changes the
+//! instructions printed, and operators whose arity isn't known here are
+//! simply not folded.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::mem;
+use wasmparser::{BlockType, CompositeInnerType, Encoding, Operator, Parser, Payload, TypeRef};
+
+/// Operator of a function body, as its binary offset and how it folds
+struct Op {
+    offset: usize,
+    kind: OpKind,
+}
+
+enum OpKind {
+    /// Operator taking and producing values, with its arity if it depends on
+    /// the module, or else the arity given by its name
+    Plain(Option<(u32, u32)>),
+    /// Start of a `block`, `loop`, `if` or `try_table`, with the arity of its
+    /// block type
+    BlockStart {
+        is_if: bool,
+        params: u32,
+        results: u32,
+    },
+    Else,
+    End,
+    /// Operator of the legacy exceptions proposal, which isn't folded
+    Unsupported,
+}
+
+/// Types of a module, for the arity of the operators of its function bodies
+#[derive(Default)]
+struct ModuleTypes {
+    /// Arity of the function types, by type index
+    types: Vec<Option<(u32, u32)>>,
+    /// Type indices of the imported and defined functions
+    funcs: Vec<u32>,
+    /// Number of imported functions
+    imported_funcs: u32,
+    /// Number of function bodies read so far
+    bodies: u32,
+}
+
+impl ModuleTypes {
+    fn func_arity(&self, func_idx: u32) -> Option<(u32, u32)> {
+        self.type_arity(*self.funcs.get(func_idx as usize)?)
+    }
+
+    fn type_arity(&self, type_idx: u32) -> Option<(u32, u32)> {
+        *self.types.get(type_idx as usize)?
+    }
+
+    fn block_arity(&self, ty: BlockType) -> Option<(u32, u32)> {
+        match ty {
+            BlockType::Empty => Some((0, 0)),
+            BlockType::Type(_) => Some((0, 1)),
+            BlockType::FuncType(idx) => self.type_arity(idx),
+        }
+    }
+}
+
+/// Print a binary with the instructions of its function bodies folded
+pub(crate) fn print_folded(printer: &mut wasmprinter::Printer, binary: &[u8]) -> Result<String> {
+    let bodies = function_bodies(binary)?;
+    let lines = printer
+        .offsets_and_lines(binary)?
+        .map(|(offset, line)| (offset, line.to_string()))
+        .collect::<Vec<_>>();
+    let mut line_of_offset = HashMap::new();
+    for (i, (offset, _)) in lines.iter().enumerate() {
+        if let Some(offset) = offset {
+            line_of_offset.entry(*offset).or_insert(i);
+        }
+    }
+
+    // Folded bodies replacing the lines of their operators, by first line
+    let mut folded = HashMap::new();
+    for ops in bodies {
+        let Some(&start) = ops.first().and_then(|op| line_of_offset.get(&op.offset)) else {
+            continue;
+        };
+        let body_lines = match lines.get(start..start + ops.len()) {
+            Some(body_lines) => body_lines,
+            None => continue,
+        };
+        // Bodies are only folded when each operator is printed on its own
+        // line, which isn't the case with branch hints for example
+        if body_lines
+            .iter()
+            .zip(&ops)
+            .any(|((offset, _), op)| *offset != Some(op.offset))
+        {
+            continue;
+        }
+        if let Some(body) = fold_body(&ops, body_lines) {
+            folded.insert(start, (ops.len(), body));
+        }
+    }
+
+    let mut output = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        match folded.remove(&i) {
+            Some((len, body)) => {
+                output.push_str(&body);
+                i += len;
+            }
+            None => {
+                output.push_str(&lines[i].1);
+                i += 1;
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Operators of the function bodies of a binary and its nested modules, in
+/// order of appearance and without their final `end`
+fn function_bodies(binary: &[u8]) -> Result<Vec<Vec<Op>>> {
+    let mut bodies = Vec::new();
+    // Types of the modules being parsed, none for components
+    let mut stack: Vec<Option<ModuleTypes>> = Vec::new();
+    for payload in Parser::new(0).parse_all(binary) {
+        match payload? {
+            Payload::Version { encoding, .. } => stack.push(match encoding {
+                Encoding::Module => Some(ModuleTypes::default()),
+                Encoding::Component => None,
+            }),
+            Payload::End(_) => {
+                stack.pop();
+            }
+            Payload::TypeSection(reader) => {
+                let Some(Some(module)) = stack.last_mut() else {
+                    continue;
+                };
+                for rec_group in reader {
+                    for ty in rec_group?.into_types() {
+                        module.types.push(match &ty.composite_type.inner {
+                            CompositeInnerType::Func(f) => {
+                                Some((f.params().len() as u32, f.results().len() as u32))
+                            }
+                            _ => None,
+                        });
+                    }
+                }
+            }
+            Payload::ImportSection(reader) => {
+                let Some(Some(module)) = stack.last_mut() else {
+                    continue;
+                };
+                for import in reader {
+                    if let TypeRef::Func(idx) = import?.ty {
+                        module.funcs.push(idx);
+                        module.imported_funcs += 1;
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                let Some(Some(module)) = stack.last_mut() else {
+                    continue;
+                };
+                for idx in reader {
+                    module.funcs.push(idx?);
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let Some(Some(module)) = stack.last_mut() else {
+                    continue;
+                };
+                let func_idx = module.imported_funcs + module.bodies;
+                module.bodies += 1;
+                bodies.push(body_ops(module, func_idx, &body)?);
+            }
+            _ => {}
+        }
+    }
+    Ok(bodies)
+}
+
+/// Operators of a function body, without its final `end`
+fn body_ops(
+    module: &ModuleTypes,
+    func_idx: u32,
+    body: &wasmparser::FunctionBody,
+) -> Result<Vec<Op>> {
+    let mut ops = Vec::new();
+    // Arity of the labels of the enclosing blocks, from the function itself
+    let mut labels = vec![module.func_arity(func_idx).map(|(_, results)| results)];
+    let label = |labels: &[Option<u32>], depth: u32| -> Option<u32> {
+        *labels.iter().rev().nth(depth as usize)?
+    };
+    let mut reader = body.get_operators_reader()?;
+    while !reader.eof() {
+        let (op, offset) = reader.read_with_offset()?;
+        let kind = match op {
+            // The final `end` isn't printed
+            Operator::End if reader.eof() => break,
+            Operator::Block { blockty } | Operator::Loop { blockty } | Operator::If { blockty } => {
+                let arity = module.block_arity(blockty);
+                labels.push(match (&op, arity) {
+                    (Operator::Loop { .. }, Some((params, _))) => Some(params),
+                    (_, arity) => arity.map(|(_, results)| results),
+                });
+                block_start(matches!(op, Operator::If { .. }), arity)
+            }
+            Operator::TryTable { try_table } => {
+                let arity = module.block_arity(try_table.ty);
+                labels.push(arity.map(|(_, results)| results));
+                block_start(false, arity)
+            }
+            Operator::Else => OpKind::Else,
+            Operator::End => {
+                labels.pop();
+                OpKind::End
+            }
+            Operator::Try { .. }
+            | Operator::Catch { .. }
+            | Operator::CatchAll
+            | Operator::Delegate { .. } => OpKind::Unsupported,
+            Operator::Br { relative_depth } => {
+                OpKind::Plain(label(&labels, relative_depth).map(|n| (n, 0)))
+            }
+            Operator::BrIf { relative_depth } => {
+                OpKind::Plain(label(&labels, relative_depth).map(|n| (n + 1, n)))
+            }
+            Operator::BrTable { targets } => {
+                OpKind::Plain(label(&labels, targets.default()).map(|n| (n + 1, 0)))
+            }
+            Operator::Return => OpKind::Plain(labels[0].map(|n| (n, 0))),
+            Operator::Call { function_index } => OpKind::Plain(module.func_arity(function_index)),
+            Operator::ReturnCall { function_index } => OpKind::Plain(
+                module
+                    .func_arity(function_index)
+                    .map(|(params, _)| (params, 0)),
+            ),
+            Operator::CallIndirect { type_index, .. } | Operator::CallRef { type_index } => {
+                OpKind::Plain(
+                    module
+                        .type_arity(type_index)
+                        .map(|(params, results)| (params + 1, results)),
+                )
+            }
+            Operator::ReturnCallIndirect { type_index, .. }
+            | Operator::ReturnCallRef { type_index } => OpKind::Plain(
+                module
+                    .type_arity(type_index)
+                    .map(|(params, _)| (params + 1, 0)),
+            ),
+            _ => OpKind::Plain(None),
+        };
+        ops.push(Op { offset, kind });
+    }
+    Ok(ops)
+}
+
+fn block_start(is_if: bool, arity: Option<(u32, u32)>) -> OpKind {
+    match arity {
+        Some((params, results)) => OpKind::BlockStart {
+            is_if,
+            params,
+            results,
+        },
+        // Blocks of unknown types are folded without their operands and as
+        // producing no value
+        None => OpKind::BlockStart {
+            is_if,
+            params: 0,
+            results: 0,
+        },
+    }
+}
+
+/// Arity of an operator, given by its name as printed
+fn named_arity(text: &str) -> Option<(u32, u32)> {
+    let name = text.split_whitespace().next()?;
+    let (ty, op) = name.split_once('.').unwrap_or(("", name));
+    Some(match (ty, op) {
+        ("", "nop" | "unreachable") => (0, 0),
+        ("", "drop") => (1, 0),
+        ("", "select") => (3, 1),
+        ("local" | "global", "get") => (0, 1),
+        ("local" | "global", "set") => (1, 0),
+        ("local", "tee") => (1, 1),
+        ("ref", "null" | "func") => (0, 1),
+        ("ref", "is_null" | "as_non_null") => (1, 1),
+        ("ref", "eq") => (2, 1),
+        ("memory" | "table", "size") => (0, 1),
+        ("memory", "grow") => (1, 1),
+        ("table", "grow") => (2, 1),
+        ("table", "get") => (1, 1),
+        ("table", "set") => (2, 0),
+        ("memory" | "table", "fill" | "copy" | "init") => (3, 0),
+        ("data" | "elem", "drop") => (0, 0),
+        ("atomic", "fence") => (0, 0),
+        ("memory", "atomic.notify") => (2, 1),
+        ("memory", "atomic.wait32" | "atomic.wait64") => (3, 1),
+        (_, op) if op.starts_with("atomic.rmw") && op.ends_with("cmpxchg") => (3, 1),
+        (_, op) if op.starts_with("atomic.rmw") => (2, 1),
+        (_, op) if op.starts_with("atomic.load") => (1, 1),
+        (_, op) if op.starts_with("atomic.store") => (2, 0),
+        (_, "const") => (0, 1),
+        (_, op) if op.starts_with("load") && op.ends_with("_lane") => (2, 1),
+        (_, op) if op.starts_with("load") => (1, 1),
+        (_, op) if op.starts_with("store") => (2, 0),
+        (_, "shuffle" | "replace_lane") => (2, 1),
+        (_, op) if op.starts_with("extract_lane") => (1, 1),
+        (_, "splat" | "any_true" | "all_true" | "bitmask") => (1, 1),
+        (
+            _,
+            "bitselect"
+            | "relaxed_madd"
+            | "relaxed_nmadd"
+            | "relaxed_laneselect"
+            | "relaxed_dot_i8x16_i7x16_add_s",
+        ) => (3, 1),
+        (
+            _,
+            "clz" | "ctz" | "popcnt" | "eqz" | "abs" | "neg" | "sqrt" | "ceil" | "floor" | "trunc"
+            | "nearest" | "not" | "extend8_s" | "extend16_s" | "extend32_s",
+        ) => (1, 1),
+        (_, op)
+            if [
+                "narrow_",
+                "extmul_",
+                "dot_",
+                "q15mulr_",
+                "relaxed_dot_",
+                "relaxed_q15mulr_",
+            ]
+            .iter()
+            .any(|prefix| op.starts_with(prefix)) =>
+        {
+            (2, 1)
+        }
+        (_, op)
+            if [
+                "wrap_",
+                "extend_",
+                "trunc_",
+                "convert_",
+                "demote_",
+                "promote_",
+                "reinterpret_",
+                "extadd_pairwise_",
+                "relaxed_trunc_",
+            ]
+            .iter()
+            .any(|prefix| op.starts_with(prefix)) =>
+        {
+            (1, 1)
+        }
+        (
+            _,
+            "add" | "sub" | "mul" | "div" | "div_s" | "div_u" | "rem_s" | "rem_u" | "and" | "or"
+            | "xor" | "andnot" | "shl" | "shr_s" | "shr_u" | "rotl" | "rotr" | "min" | "min_s"
+            | "min_u" | "max" | "max_s" | "max_u" | "copysign" | "eq" | "ne" | "lt" | "lt_s"
+            | "lt_u" | "gt" | "gt_s" | "gt_u" | "le" | "le_s" | "le_u" | "ge" | "ge_s" | "ge_u"
+            | "avgr_u" | "add_sat_s" | "add_sat_u" | "sub_sat_s" | "sub_sat_u" | "swizzle"
+            | "relaxed_swizzle" | "pmin" | "pmax" | "relaxed_min" | "relaxed_max",
+        ) => (2, 1),
+        _ => return None,
+    })
+}
+
+/// Folded expression, by the indices of its operators in the body
+enum Expr {
+    Op {
+        op: usize,
+        operands: Vec<Expr>,
+    },
+    Block {
+        op: usize,
+        operands: Vec<Expr>,
+        body: Vec<Expr>,
+        /// `else` of an `if` and its body
+        else_: Option<(usize, Vec<Expr>)>,
+        is_if: bool,
+    },
+}
+
+/// Folded expression and the number of values it produces
+struct Folded {
+    expr: Expr,
+    results: u32,
+}
+
+/// Block being folded
+struct Frame {
+    op: usize,
+    is_if: bool,
+    results: u32,
+    operands: Vec<Expr>,
+    /// The `then` body of an `if` once its `else` is reached
+    then: Option<(usize, Vec<Folded>)>,
+    body: Vec<Folded>,
+}
+
+/// Take the last expressions of a body as the operands of an operator, when
+/// they produce exactly the values it consumes
+fn take_operands(body: &mut Vec<Folded>, mut params: u32) -> Vec<Expr> {
+    let mut start = body.len();
+    while params > 0 && start > 0 {
+        let results = body[start - 1].results;
+        if results == 0 || results > params {
+            break;
+        }
+        params -= results;
+        start -= 1;
+    }
+    if params > 0 {
+        return Vec::new();
+    }
+    body.drain(start..).map(|folded| folded.expr).collect()
+}
+
+/// Printed line of an operator, as the offset annotation and indentation
+/// printed before it, its text, and the parentheses closing the items it
+/// ends
+struct Line<'a> {
+    prefix: &'a str,
+    indent: &'a str,
+    text: &'a str,
+    closing: &'a str,
+}
+
+fn split_line(line: &str) -> Option<Line<'_>> {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let prefix_len = match line.strip_prefix("(;@") {
+        Some(rest) => rest.find(";)")? + 5,
+        None => 0,
+    };
+    let (prefix, rest) = line.split_at(prefix_len);
+    let content = rest.trim_start_matches(' ');
+    let indent = &rest[..rest.len() - content.len()];
+
+    // The text ends at the first unmatched closing parenthesis
+    let bytes = content.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    let mut end = content.len();
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' if bytes.get(i + 1) == Some(&b';') => {
+                i += content[i..].find(";)")? + 2;
+                continue;
+            }
+            b';' if bytes.get(i + 1) == Some(&b';') => break,
+            b'"' => {
+                i += 1;
+                while bytes.get(i)? != &b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'(' => depth += 1,
+            b')' if depth == 0 => {
+                end = i;
+                break;
+            }
+            b')' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    let closing = &content[end..];
+    if closing.bytes().any(|b| b != b')') {
+        return None;
+    }
+    Some(Line {
+        prefix,
+        indent,
+        text: content[..end].trim_end(),
+        closing,
+    })
+}
+
+/// Fold the operators of a body from their printed lines
+fn fold_body(ops: &[Op], lines: &[(Option<usize>, String)]) -> Option<String> {
+    let lines = lines
+        .iter()
+        .map(|(_, line)| split_line(line))
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut body: Vec<Folded> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        match op.kind {
+            OpKind::Plain(arity) => {
+                let expr = match arity.or_else(|| named_arity(lines[i].text)) {
+                    Some((params, results)) => Folded {
+                        expr: Expr::Op {
+                            op: i,
+                            operands: take_operands(&mut body, params),
+                        },
+                        results,
+                    },
+                    // Operators of unknown arity are neither folded nor
+                    // folded into others
+                    None => Folded {
+                        expr: Expr::Op {
+                            op: i,
+                            operands: Vec::new(),
+                        },
+                        results: 0,
+                    },
+                };
+                body.push(expr);
+            }
+            OpKind::BlockStart {
+                is_if,
+                params,
+                results,
+            } => {
+                let operands = if is_if {
+                    take_operands(&mut body, params + 1)
+                } else {
+                    Vec::new()
+                };
+                frames.push(Frame {
+                    op: i,
+                    is_if,
+                    results,
+                    operands,
+                    then: None,
+                    body: mem::take(&mut body),
+                });
+            }
+            OpKind::Else => {
+                let frame = frames.last_mut()?;
+                if !frame.is_if || frame.then.is_some() {
+                    return None;
+                }
+                frame.then = Some((i, mem::take(&mut body)));
+            }
+            OpKind::End => {
+                let frame = frames.pop()?;
+                let outer = mem::replace(&mut body, frame.body);
+                let (block_body, else_) = match frame.then {
+                    Some((else_op, then)) => (then, Some((else_op, outer))),
+                    None => (outer, None),
+                };
+                body.push(Folded {
+                    expr: Expr::Block {
+                        op: frame.op,
+                        operands: frame.operands,
+                        body: block_body.into_iter().map(|f| f.expr).collect(),
+                        else_: else_
+                            .map(|(op, body)| (op, body.into_iter().map(|f| f.expr).collect())),
+                        is_if: frame.is_if,
+                    },
+                    results: frame.results,
+                });
+            }
+            OpKind::Unsupported => return None,
+        }
+    }
+    if !frames.is_empty() {
+        return None;
+    }
+
+    let mut printer = FoldedPrinter {
+        lines: &lines,
+        offsets: ops.iter().map(|op| op.offset).collect(),
+        base_indent: lines[0].indent,
+        annotated: !lines[0].prefix.is_empty(),
+        output: Vec::new(),
+    };
+    for folded in &body {
+        printer.expr(&folded.expr, 0);
+    }
+    let last = lines.last()?;
+    let mut output = printer.output.join("\n");
+    output.push_str(last.closing);
+    output.push('\n');
+    Some(output)
+}
+
+struct FoldedPrinter<'a> {
+    lines: &'a [Line<'a>],
+    offsets: Vec<usize>,
+    base_indent: &'a str,
+    /// Whether lines are annotated with the offsets of their operators
+    annotated: bool,
+    output: Vec<String>,
+}
+
+impl FoldedPrinter<'_> {
+    fn line(&mut self, op: Option<usize>, depth: usize, text: &str) {
+        let prefix = match op {
+            Some(op) if self.annotated => format!("(;@{:<6x};)", self.offsets[op]),
+            _ if self.annotated => " ".repeat(11),
+            _ => String::new(),
+        };
+        self.output.push(format!(
+            "{prefix}{}{}{text}",
+            self.base_indent,
+            "  ".repeat(depth)
+        ));
+    }
+
+    /// Close the last expression printed, on its own line when the last line
+    /// ends with a comment
+    fn close(&mut self, depth: usize) {
+        let last = self.output.last_mut().unwrap();
+        if last.contains(";;") {
+            self.line(None, depth, ")");
+        } else {
+            last.push(')');
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, depth: usize) {
+        match expr {
+            Expr::Op { op, operands } => {
+                let text = self.lines[*op].text;
+                if operands.is_empty() {
+                    self.line(Some(*op), depth, &format!("({text})"));
+                    return;
+                }
+                self.line(Some(*op), depth, &format!("({text}"));
+                for operand in operands {
+                    self.expr(operand, depth + 1);
+                }
+                self.close(depth);
+            }
+            Expr::Block {
+                op,
+                operands,
+                body,
+                else_,
+                is_if,
+            } => {
+                let text = self.lines[*op].text;
+                self.line(Some(*op), depth, &format!("({text}"));
+                for operand in operands {
+                    self.expr(operand, depth + 1);
+                }
+                if *is_if {
+                    self.line(None, depth + 1, "(then");
+                    for expr in body {
+                        self.expr(expr, depth + 2);
+                    }
+                    self.close(depth + 1);
+                    if let Some((else_op, body)) = else_ {
+                        self.line(Some(*else_op), depth + 1, "(else");
+                        for expr in body {
+                            self.expr(expr, depth + 2);
+                        }
+                        self.close(depth + 1);
+                    }
+                } else {
+                    for expr in body {
+                        self.expr(expr, depth + 1);
+                    }
+                }
+                self.close(depth);
+            }
+        }
+    }
+}
@@ -0,0 +1,805 @@
+//! Structured description of modules and components
+//!
+//! Items are numbered in the index space of their sort as they are defined,
+//! by type definitions, imports, aliases, instances, canonical functions and
+//! exports, so that the indices reported match those used by the binary.
+
+use anyhow::{Context, Result};
+use wasmparser::{
+    CanonicalFunction, CanonicalOption, ComponentAlias, ComponentDefinedType,
+    ComponentExternalKind, ComponentFuncResult, ComponentInstance, ComponentOuterAliasKind,
+    ComponentType, ComponentTypeDeclaration, ComponentTypeRef, ComponentValType,
+    CompositeInnerType, CoreType, Encoding, ExternalKind, FuncType, GlobalType, Instance,
+    InstanceTypeDeclaration, MemoryType, ModuleTypeDeclaration, Parser, Payload, PrimitiveValType,
+    SubType, TableType, TagType, TypeBounds, TypeRef,
+};
+
+use crate::exports::local::wasm_tools::tools::{
+    AliasTarget, BinaryKind, CanonicalFunction as InspectedCanonicalFunction, CanonicalKind,
+    InspectedAlias, InspectedBinary, InspectedExport, InspectedImport, InspectedInstance,
+    InspectedType, InstanceArg, ItemKind, SectionSize, StringEncoding,
+};
+
+/// Number of item sorts, each with its own index space
+const ITEM_KINDS: usize = ItemKind::Component as usize + 1;
+
+/// Describe a module or component, followed by each of its nested modules and
+/// components in order of appearance
+pub(crate) fn inspect(binary: &[u8]) -> Result<Vec<InspectedBinary>> {
+    let mut inspected = Vec::new();
+    let mut stack: Vec<Inspector> = Vec::new();
+    let mut path = Vec::new();
+    let mut counts = vec![0];
+    let mut nested_range = None;
+
+    for payload in Parser::new(0).parse_all(binary) {
+        let payload = payload.context("failed to parse Wasm")?;
+        if let (Some(inspector), Some((id, range))) = (stack.last_mut(), payload.as_section()) {
+            let name = match &payload {
+                Payload::CustomSection(section) => format!("custom:{}", section.name()),
+                _ => section_name(inspector.binary.kind, id),
+            };
+            inspector.binary.sections.push(SectionSize {
+                name,
+                range: (range.start as u32, range.end as u32),
+            });
+        }
+
+        match payload {
+            Payload::Version {
+                encoding, range, ..
+            } => {
+                let range = nested_range.take().unwrap_or(range.start..binary.len());
+                let kind = match encoding {
+                    Encoding::Module => BinaryKind::Module,
+                    Encoding::Component => BinaryKind::Component,
+                };
+                // Reserve the place of the binary before its nested binaries
+                stack.push(Inspector::new(
+                    inspected.len(),
+                    InspectedBinary {
+                        path: path.clone(),
+                        kind,
+                        range: (range.start as u32, range.end as u32),
+                        types: Vec::new(),
+                        imports: Vec::new(),
+                        exports: Vec::new(),
+                        instances: Vec::new(),
+                        aliases: Vec::new(),
+                        canonical_functions: Vec::new(),
+                        sections: Vec::new(),
+                    },
+                ));
+                inspected.push(None);
+            }
+            Payload::End(_) => {
+                let inspector = stack.pop().unwrap();
+                let slot = inspector.slot;
+                inspected[slot] = Some(inspector.finish());
+                path.pop();
+                counts.pop();
+            }
+            Payload::ModuleSection {
+                ref unchecked_range,
+                ..
+            }
+            | Payload::ComponentSection {
+                ref unchecked_range,
+                ..
+            } => {
+                let kind = match payload {
+                    Payload::ModuleSection { .. } => ItemKind::CoreModule,
+                    _ => ItemKind::Component,
+                };
+                stack.last_mut().unwrap().push(kind, None);
+
+                let count = counts.last_mut().unwrap();
+                path.push(*count);
+                *count += 1;
+                counts.push(0);
+                nested_range = Some(unchecked_range.clone());
+            }
+            payload => stack.last_mut().unwrap().payload(payload)?,
+        }
+    }
+
+    Ok(inspected.into_iter().flatten().collect())
+}
+
+struct Inspector {
+    /// Place of the binary in the inspection output
+    slot: usize,
+    binary: InspectedBinary,
+    /// Type of each item of each index space, when known
+    spaces: [Vec<Option<String>>; ITEM_KINDS],
+}
+
+impl Inspector {
+    fn new(slot: usize, binary: InspectedBinary) -> Self {
+        Inspector {
+            slot,
+            binary,
+            spaces: Default::default(),
+        }
+    }
+
+    fn finish(mut self) -> InspectedBinary {
+        for (kind, core) in [(ItemKind::CoreType, true), (ItemKind::Type, false)] {
+            let types = self.spaces[kind as usize].iter().enumerate();
+            self.binary
+                .types
+                .extend(types.map(|(index, definition)| InspectedType {
+                    index: index as u32,
+                    core,
+                    definition: definition.clone().unwrap_or_default(),
+                }));
+        }
+        self.binary
+    }
+
+    /// Add an item to the index space of its sort, returning its index
+    fn push(&mut self, kind: ItemKind, ty: Option<String>) -> u32 {
+        let space = &mut self.spaces[kind as usize];
+        space.push(ty);
+        (space.len() - 1) as u32
+    }
+
+    fn type_of(&self, kind: ItemKind, index: u32) -> Option<String> {
+        self.spaces[kind as usize]
+            .get(index as usize)
+            .cloned()
+            .flatten()
+    }
+
+    /// Type by index, or a reference to it when its definition is unknown
+    fn type_ref(&self, kind: ItemKind, index: u32) -> String {
+        self.type_of(kind, index)
+            .unwrap_or_else(|| format!("type {index}"))
+    }
+
+    fn payload(&mut self, payload: Payload) -> Result<()> {
+        match payload {
+            // Core modules
+            Payload::TypeSection(reader) => {
+                for rec_group in reader {
+                    for ty in rec_group?.types() {
+                        self.push(ItemKind::CoreType, Some(sub_type(ty)));
+                    }
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    let (kind, ty) = match import.ty {
+                        TypeRef::Func(ty) => {
+                            (ItemKind::CoreFunc, self.type_ref(ItemKind::CoreType, ty))
+                        }
+                        TypeRef::Table(ty) => (ItemKind::CoreTable, table_type(&ty)),
+                        TypeRef::Memory(ty) => (ItemKind::CoreMemory, memory_type(&ty)),
+                        TypeRef::Global(ty) => (ItemKind::CoreGlobal, global_type(&ty)),
+                        TypeRef::Tag(ty) => (ItemKind::CoreTag, tag_type(&ty)),
+                    };
+                    let index = self.push(kind, Some(ty.clone()));
+                    self.binary.imports.push(InspectedImport {
+                        module: Some(import.module.to_string()),
+                        name: import.name.to_string(),
+                        kind,
+                        index,
+                        ty,
+                    });
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for ty in reader {
+                    let ty = self.type_ref(ItemKind::CoreType, ty?);
+                    self.push(ItemKind::CoreFunc, Some(ty));
+                }
+            }
+            Payload::TableSection(reader) => {
+                for table in reader {
+                    self.push(ItemKind::CoreTable, Some(table_type(&table?.ty)));
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    self.push(ItemKind::CoreMemory, Some(memory_type(&memory?)));
+                }
+            }
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    self.push(ItemKind::CoreGlobal, Some(global_type(&global?.ty)));
+                }
+            }
+            Payload::TagSection(reader) => {
+                for tag in reader {
+                    self.push(ItemKind::CoreTag, Some(tag_type(&tag?)));
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    let kind = core_kind(export.kind);
+                    self.binary.exports.push(InspectedExport {
+                        name: export.name.to_string(),
+                        kind,
+                        index: export.index,
+                        ty: self.type_of(kind, export.index),
+                    });
+                }
+            }
+
+            // Components
+            Payload::CoreTypeSection(reader) => {
+                for ty in reader {
+                    let ty = match ty? {
+                        CoreType::Sub(ty) => sub_type(&ty),
+                        CoreType::Module(decls) => module_type(&decls),
+                    };
+                    self.push(ItemKind::CoreType, Some(ty));
+                }
+            }
+            Payload::ComponentTypeSection(reader) => {
+                for ty in reader {
+                    self.push(ItemKind::Type, Some(component_type(&ty?)));
+                }
+            }
+            Payload::ComponentImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    let kind = type_ref_kind(&import.ty);
+                    let ty = self.component_type_ref(&import.ty);
+                    let index = self.push(kind, Some(ty.clone()));
+                    self.binary.imports.push(InspectedImport {
+                        module: None,
+                        name: import.name.0.to_string(),
+                        kind,
+                        index,
+                        ty,
+                    });
+                }
+            }
+            Payload::ComponentExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    let kind = component_kind(export.kind);
+                    let ty = match &export.ty {
+                        Some(ty) => Some(self.component_type_ref(ty)),
+                        None => self.type_of(kind, export.index),
+                    };
+                    // Exports define a new item in their index space
+                    self.push(kind, ty.clone());
+                    self.binary.exports.push(InspectedExport {
+                        name: export.name.0.to_string(),
+                        kind,
+                        index: export.index,
+                        ty,
+                    });
+                }
+            }
+            Payload::InstanceSection(reader) => {
+                for instance in reader {
+                    let (instantiate, args) = match instance? {
+                        Instance::Instantiate { module_index, args } => (
+                            Some(module_index),
+                            args.iter()
+                                .map(|arg| InstanceArg {
+                                    name: arg.name.to_string(),
+                                    kind: ItemKind::CoreInstance,
+                                    index: arg.index,
+                                })
+                                .collect(),
+                        ),
+                        Instance::FromExports(exports) => (
+                            None,
+                            exports
+                                .iter()
+                                .map(|export| InstanceArg {
+                                    name: export.name.to_string(),
+                                    kind: core_kind(export.kind),
+                                    index: export.index,
+                                })
+                                .collect(),
+                        ),
+                    };
+                    let index = self.push(ItemKind::CoreInstance, None);
+                    self.binary.instances.push(InspectedInstance {
+                        index,
+                        core: true,
+                        instantiate,
+                        args,
+                    });
+                }
+            }
+            Payload::ComponentInstanceSection(reader) => {
+                for instance in reader {
+                    let (instantiate, args) = match instance? {
+                        ComponentInstance::Instantiate {
+                            component_index,
+                            args,
+                        } => (
+                            Some(component_index),
+                            args.iter()
+                                .map(|arg| InstanceArg {
+                                    name: arg.name.to_string(),
+                                    kind: component_kind(arg.kind),
+                                    index: arg.index,
+                                })
+                                .collect(),
+                        ),
+                        ComponentInstance::FromExports(exports) => (
+                            None,
+                            exports
+                                .iter()
+                                .map(|export| InstanceArg {
+                                    name: export.name.0.to_string(),
+                                    kind: component_kind(export.kind),
+                                    index: export.index,
+                                })
+                                .collect(),
+                        ),
+                    };
+                    let index = self.push(ItemKind::Instance, None);
+                    self.binary.instances.push(InspectedInstance {
+                        index,
+                        core: false,
+                        instantiate,
+                        args,
+                    });
+                }
+            }
+            Payload::ComponentAliasSection(reader) => {
+                for alias in reader {
+                    let (kind, target) = match alias? {
+                        ComponentAlias::InstanceExport {
+                            kind,
+                            instance_index,
+                            name,
+                        } => (
+                            component_kind(kind),
+                            AliasTarget::InstanceExport((instance_index, name.to_string())),
+                        ),
+                        ComponentAlias::CoreInstanceExport {
+                            kind,
+                            instance_index,
+                            name,
+                        } => (
+                            core_kind(kind),
+                            AliasTarget::CoreInstanceExport((instance_index, name.to_string())),
+                        ),
+                        ComponentAlias::Outer { kind, count, index } => {
+                            (outer_kind(kind), AliasTarget::Outer((count, index)))
+                        }
+                    };
+                    // Aliased types are described by their target
+                    let ty = match kind {
+                        ItemKind::Type | ItemKind::CoreType => Some(alias_target(&target)),
+                        _ => None,
+                    };
+                    let index = self.push(kind, ty);
+                    self.binary.aliases.push(InspectedAlias {
+                        kind,
+                        index,
+                        target,
+                    });
+                }
+            }
+            Payload::ComponentCanonicalSection(reader) => {
+                for func in reader {
+                    let func = match func? {
+                        CanonicalFunction::Lift {
+                            core_func_index,
+                            type_index,
+                            options,
+                        } => {
+                            let ty = self.type_ref(ItemKind::Type, type_index);
+                            let index = self.push(ItemKind::Func, Some(ty.clone()));
+                            canonical_function(
+                                CanonicalKind::Lift,
+                                index,
+                                core_func_index,
+                                Some(ty),
+                                &options,
+                            )
+                        }
+                        CanonicalFunction::Lower {
+                            func_index,
+                            options,
+                        } => {
+                            let index = self.push(ItemKind::CoreFunc, None);
+                            canonical_function(
+                                CanonicalKind::Lower,
+                                index,
+                                func_index,
+                                None,
+                                &options,
+                            )
+                        }
+                        CanonicalFunction::ResourceNew { resource } => {
+                            let index = self.push(ItemKind::CoreFunc, None);
+                            canonical_function(
+                                CanonicalKind::ResourceNew,
+                                index,
+                                resource,
+                                None,
+                                &[],
+                            )
+                        }
+                        CanonicalFunction::ResourceDrop { resource } => {
+                            let index = self.push(ItemKind::CoreFunc, None);
+                            canonical_function(
+                                CanonicalKind::ResourceDrop,
+                                index,
+                                resource,
+                                None,
+                                &[],
+                            )
+                        }
+                        CanonicalFunction::ResourceRep { resource } => {
+                            let index = self.push(ItemKind::CoreFunc, None);
+                            canonical_function(
+                                CanonicalKind::ResourceRep,
+                                index,
+                                resource,
+                                None,
+                                &[],
+                            )
+                        }
+                    };
+                    self.binary.canonical_functions.push(func);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn component_type_ref(&self, ty: &ComponentTypeRef) -> String {
+        match ty {
+            ComponentTypeRef::Module(index) => self.type_ref(ItemKind::CoreType, *index),
+            ComponentTypeRef::Func(index)
+            | ComponentTypeRef::Instance(index)
+            | ComponentTypeRef::Component(index) => self.type_ref(ItemKind::Type, *index),
+            ComponentTypeRef::Value(ty) => format!("value {}", val_type(ty)),
+            ComponentTypeRef::Type(TypeBounds::Eq(index)) => format!("eq type {index}"),
+            ComponentTypeRef::Type(TypeBounds::SubResource) => "sub resource".to_string(),
+        }
+    }
+}
+
+fn canonical_function(
+    kind: CanonicalKind,
+    index: u32,
+    target: u32,
+    ty: Option<String>,
+    options: &[CanonicalOption],
+) -> InspectedCanonicalFunction {
+    let mut func = InspectedCanonicalFunction {
+        kind,
+        index,
+        target,
+        ty,
+        string_encoding: None,
+        memory: None,
+        realloc: None,
+        post_return: None,
+    };
+    for option in options {
+        match option {
+            CanonicalOption::UTF8 => func.string_encoding = Some(StringEncoding::Utf8),
+            CanonicalOption::UTF16 => func.string_encoding = Some(StringEncoding::Utf16),
+            CanonicalOption::CompactUTF16 => {
+                func.string_encoding = Some(StringEncoding::CompactUtf16)
+            }
+            CanonicalOption::Memory(index) => func.memory = Some(*index),
+            CanonicalOption::Realloc(index) => func.realloc = Some(*index),
+            CanonicalOption::PostReturn(index) => func.post_return = Some(*index),
+        }
+    }
+    func
+}
+
+fn section_name(kind: BinaryKind, id: u8) -> String {
+    let name = match (kind, id) {
+        (BinaryKind::Module, 1) => "type",
+        (BinaryKind::Module, 2) => "import",
+        (BinaryKind::Module, 3) => "function",
+        (BinaryKind::Module, 4) => "table",
+        (BinaryKind::Module, 5) => "memory",
+        (BinaryKind::Module, 6) => "global",
+        (BinaryKind::Module, 7) => "export",
+        (BinaryKind::Module, 8) => "start",
+        (BinaryKind::Module, 9) => "element",
+        (BinaryKind::Module, 10) => "code",
+        (BinaryKind::Module, 11) => "data",
+        (BinaryKind::Module, 12) => "data-count",
+        (BinaryKind::Module, 13) => "tag",
+        (BinaryKind::Component, 1) => "core-module",
+        (BinaryKind::Component, 2) => "core-instance",
+        (BinaryKind::Component, 3) => "core-type",
+        (BinaryKind::Component, 4) => "component",
+        (BinaryKind::Component, 5) => "instance",
+        (BinaryKind::Component, 6) => "alias",
+        (BinaryKind::Component, 7) => "type",
+        (BinaryKind::Component, 8) => "canonical",
+        (BinaryKind::Component, 9) => "start",
+        (BinaryKind::Component, 10) => "import",
+        (BinaryKind::Component, 11) => "export",
+        _ => return format!("unknown:{id}"),
+    };
+    name.to_string()
+}
+
+fn core_kind(kind: ExternalKind) -> ItemKind {
+    match kind {
+        ExternalKind::Func => ItemKind::CoreFunc,
+        ExternalKind::Table => ItemKind::CoreTable,
+        ExternalKind::Memory => ItemKind::CoreMemory,
+        ExternalKind::Global => ItemKind::CoreGlobal,
+        ExternalKind::Tag => ItemKind::CoreTag,
+    }
+}
+
+fn component_kind(kind: ComponentExternalKind) -> ItemKind {
+    match kind {
+        ComponentExternalKind::Module => ItemKind::CoreModule,
+        ComponentExternalKind::Func => ItemKind::Func,
+        ComponentExternalKind::Value => ItemKind::Value,
+        ComponentExternalKind::Type => ItemKind::Type,
+        ComponentExternalKind::Instance => ItemKind::Instance,
+        ComponentExternalKind::Component => ItemKind::Component,
+    }
+}
+
+fn outer_kind(kind: ComponentOuterAliasKind) -> ItemKind {
+    match kind {
+        ComponentOuterAliasKind::CoreModule => ItemKind::CoreModule,
+        ComponentOuterAliasKind::CoreType => ItemKind::CoreType,
+        ComponentOuterAliasKind::Type => ItemKind::Type,
+        ComponentOuterAliasKind::Component => ItemKind::Component,
+    }
+}
+
+fn type_ref_kind(ty: &ComponentTypeRef) -> ItemKind {
+    match ty {
+        ComponentTypeRef::Module(_) => ItemKind::CoreModule,
+        ComponentTypeRef::Func(_) => ItemKind::Func,
+        ComponentTypeRef::Value(_) => ItemKind::Value,
+        ComponentTypeRef::Type(_) => ItemKind::Type,
+        ComponentTypeRef::Instance(_) => ItemKind::Instance,
+        ComponentTypeRef::Component(_) => ItemKind::Component,
+    }
+}
+
+fn alias_target(target: &AliasTarget) -> String {
+    match target {
+        AliasTarget::InstanceExport((instance, name)) => {
+            format!("export `{name}` of instance {instance}")
+        }
+        AliasTarget::CoreInstanceExport((instance, name)) => {
+            format!("export `{name}` of core instance {instance}")
+        }
+        AliasTarget::Outer((count, index)) => format!("outer {count} {index}"),
+    }
+}
+
+fn sub_type(ty: &SubType) -> String {
+    match &ty.composite_type.inner {
+        CompositeInnerType::Func(ty) => func_type(ty),
+        _ => ty.to_string(),
+    }
+}
+
+fn func_type(ty: &FuncType) -> String {
+    let mut output = "(func".to_string();
+    if !ty.params().is_empty() {
+        let params = ty.params().iter().map(|ty| ty.to_string());
+        output.push_str(&format!(
+            " (param {})",
+            params.collect::<Vec<_>>().join(" ")
+        ));
+    }
+    if !ty.results().is_empty() {
+        let results = ty.results().iter().map(|ty| ty.to_string());
+        output.push_str(&format!(
+            " (result {})",
+            results.collect::<Vec<_>>().join(" ")
+        ));
+    }
+    output.push(')');
+    output
+}
+
+fn limits(initial: u64, maximum: Option<u64>) -> String {
+    match maximum {
+        Some(maximum) => format!("{initial} {maximum}"),
+        None => initial.to_string(),
+    }
+}
+
+fn table_type(ty: &TableType) -> String {
+    let index = if ty.table64 { "i64 " } else { "" };
+    let limits = limits(ty.initial, ty.maximum);
+    format!("(table {index}{limits} {})", ty.element_type)
+}
+
+fn memory_type(ty: &MemoryType) -> String {
+    let index = if ty.memory64 { "i64 " } else { "" };
+    let shared = if ty.shared { " shared" } else { "" };
+    format!("(memory {index}{}{shared})", limits(ty.initial, ty.maximum))
+}
+
+fn global_type(ty: &GlobalType) -> String {
+    match ty.mutable {
+        true => format!("(global (mut {}))", ty.content_type),
+        false => format!("(global {})", ty.content_type),
+    }
+}
+
+fn tag_type(ty: &TagType) -> String {
+    format!("(tag (type {}))", ty.func_type_idx)
+}
+
+fn core_type_ref_kind(ty: &TypeRef) -> &'static str {
+    match ty {
+        TypeRef::Func(_) => "func",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+fn module_type(decls: &[ModuleTypeDeclaration]) -> String {
+    let decls = decls
+        .iter()
+        .filter_map(|decl| match decl {
+            ModuleTypeDeclaration::Import(import) => Some(format!(
+                "import {}.{}: {}",
+                import.module,
+                import.name,
+                core_type_ref_kind(&import.ty)
+            )),
+            ModuleTypeDeclaration::Export { name, ty } => {
+                Some(format!("export {name}: {}", core_type_ref_kind(ty)))
+            }
+            ModuleTypeDeclaration::Type(_) | ModuleTypeDeclaration::OuterAlias { .. } => None,
+        })
+        .collect::<Vec<_>>();
+    format!("module {{ {} }}", decls.join(", "))
+}
+
+fn component_type_ref_kind(ty: &ComponentTypeRef) -> &'static str {
+    match ty {
+        ComponentTypeRef::Module(_) => "module",
+        ComponentTypeRef::Func(_) => "func",
+        ComponentTypeRef::Value(_) => "value",
+        ComponentTypeRef::Type(_) => "type",
+        ComponentTypeRef::Instance(_) => "instance",
+        ComponentTypeRef::Component(_) => "component",
+    }
+}
+
+fn component_type(ty: &ComponentType) -> String {
+    match ty {
+        ComponentType::Defined(ty) => defined_type(ty),
+        ComponentType::Func(ty) => {
+            let params = ty
+                .params
+                .iter()
+                .map(|(name, ty)| format!("{name}: {}", val_type(ty)))
+                .collect::<Vec<_>>();
+            let results = match &ty.results {
+                ComponentFuncResult::Unnamed(ty) => format!(" -> {}", val_type(ty)),
+                ComponentFuncResult::Named(results) if results.is_empty() => String::new(),
+                ComponentFuncResult::Named(results) => {
+                    let results = results
+                        .iter()
+                        .map(|(name, ty)| format!("{name}: {}", val_type(ty)))
+                        .collect::<Vec<_>>();
+                    format!(" -> ({})", results.join(", "))
+                }
+            };
+            format!("func({}){results}", params.join(", "))
+        }
+        ComponentType::Component(decls) => {
+            let decls = decls
+                .iter()
+                .filter_map(|decl| match decl {
+                    ComponentTypeDeclaration::Import(import) => Some(format!(
+                        "import {}: {}",
+                        import.name.0,
+                        component_type_ref_kind(&import.ty)
+                    )),
+                    ComponentTypeDeclaration::Export { name, ty } => Some(format!(
+                        "export {}: {}",
+                        name.0,
+                        component_type_ref_kind(ty)
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            format!("component {{ {} }}", decls.join(", "))
+        }
+        ComponentType::Instance(decls) => {
+            let decls = decls
+                .iter()
+                .filter_map(|decl| match decl {
+                    InstanceTypeDeclaration::Export { name, ty } => {
+                        Some(format!("{}: {}", name.0, component_type_ref_kind(ty)))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            format!("instance {{ {} }}", decls.join(", "))
+        }
+        ComponentType::Resource { rep, dtor } => match dtor {
+            Some(dtor) => format!("resource (rep {rep}) (dtor {dtor})"),
+            None => format!("resource (rep {rep})"),
+        },
+    }
+}
+
+fn defined_type(ty: &ComponentDefinedType) -> String {
+    let opt_ty = |ty: &Option<ComponentValType>| match ty {
+        Some(ty) => val_type(ty),
+        None => "_".to_string(),
+    };
+    match ty {
+        ComponentDefinedType::Primitive(ty) => primitive(*ty).to_string(),
+        ComponentDefinedType::Record(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(name, ty)| format!("{name}: {}", val_type(ty)))
+                .collect::<Vec<_>>();
+            format!("record {{ {} }}", fields.join(", "))
+        }
+        ComponentDefinedType::Variant(cases) => {
+            let cases = cases
+                .iter()
+                .map(|case| match &case.ty {
+                    Some(ty) => format!("{}({})", case.name, val_type(ty)),
+                    None => case.name.to_string(),
+                })
+                .collect::<Vec<_>>();
+            format!("variant {{ {} }}", cases.join(", "))
+        }
+        ComponentDefinedType::List(ty) => format!("list<{}>", val_type(ty)),
+        ComponentDefinedType::Tuple(types) => {
+            let types = types.iter().map(val_type).collect::<Vec<_>>();
+            format!("tuple<{}>", types.join(", "))
+        }
+        ComponentDefinedType::Flags(names) => format!("flags {{ {} }}", names.join(", ")),
+        ComponentDefinedType::Enum(names) => format!("enum {{ {} }}", names.join(", ")),
+        ComponentDefinedType::Option(ty) => format!("option<{}>", val_type(ty)),
+        ComponentDefinedType::Result { ok, err } => {
+            format!("result<{}, {}>", opt_ty(ok), opt_ty(err))
+        }
+        ComponentDefinedType::Own(index) => format!("own<type {index}>"),
+        ComponentDefinedType::Borrow(index) => format!("borrow<type {index}>"),
+    }
+}
+
+fn val_type(ty: &ComponentValType) -> String {
+    match ty {
+        ComponentValType::Primitive(ty) => primitive(*ty).to_string(),
+        ComponentValType::Type(index) => format!("type {index}"),
+    }
+}
+
+fn primitive(ty: PrimitiveValType) -> &'static str {
+    match ty {
+        PrimitiveValType::Bool => "bool",
+        PrimitiveValType::S8 => "s8",
+        PrimitiveValType::U8 => "u8",
+        PrimitiveValType::S16 => "s16",
+        PrimitiveValType::U16 => "u16",
+        PrimitiveValType::S32 => "s32",
+        PrimitiveValType::U32 => "u32",
+        PrimitiveValType::S64 => "s64",
+        PrimitiveValType::U64 => "u64",
+        PrimitiveValType::F32 => "f32",
+        PrimitiveValType::F64 => "f64",
+        PrimitiveValType::Char => "char",
+        PrimitiveValType::String => "string",
+    }
+}
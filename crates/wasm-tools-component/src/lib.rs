@@ -1,16 +1,32 @@
-use std::collections::VecDeque;
+use anyhow::{bail, Context, Result};
+use jco_shared::error_report::{self, ErrorReport};
+use jco_shared::wit_files::push_files;
+use std::collections::{HashSet, VecDeque};
 use std::fs::metadata;
+use std::mem;
 use std::path::PathBuf;
+use wasm_compose::graph::{CompositionGraph, EncodeOptions, ImportIndex, InstanceId};
 use wasm_encoder::{Encode, Section};
-use wasm_metadata::Producers;
+use wasm_metadata::{AddMetadata, Producers};
+use wasmparser::{
+    CustomSectionReader, FuncValidatorAllocations, Parser, Payload, ValidPayload, Validator,
+    WasmFeatures,
+};
 use wit_component::{ComponentEncoder, DecodedWasm, WitPrinter};
-use wit_parser::Resolve;
+use wit_parser::{PackageId, Resolve};
 
 use exports::local::wasm_tools::tools::{
-    EmbedOpts, EnabledFeatureSet, Guest, ModuleMetaType, ModuleMetadata, ProducersFields,
-    StringEncoding,
+    ComponentNewOpts, ComponentNewOutput, ComposeOpts, ComposeOutput, CustomLicense, CustomSection,
+    CustomSectionFilter, Diagnostic, EmbedOpts, EnabledFeatureSet, Error, ErrorKind, Guest,
+    InspectedBinary, Link, LinkType, MetadataEdit, ModuleMetaType, ModuleMetadata, PrintOpts,
+    ProducersFields, RegistryMetadata, SourceSpan, StringEncoding, StripOpts, TargetsMismatch,
+    UnsatisfiedImport, WasiAdapter,
 };
 
+mod fold;
+mod inspect;
+mod targets;
+
 wit_bindgen::generate!({
     world: "wasm-tools"
 });
@@ -20,94 +36,385 @@ struct WasmToolsJs;
 export!(WasmToolsJs);
 
 impl Guest for WasmToolsJs {
-    fn parse(wat: String) -> Result<Vec<u8>, String> {
-        wat::parse_str(wat).map_err(|e| format!("{:?}", e))
+    fn parse(wat: String) -> Result<Vec<u8>, Error> {
+        wat::parse_str(wat)
+            .context("failed to parse WebAssembly text")
+            .map_err(wasm_error)
+    }
+
+    fn print(component: Vec<u8>) -> Result<String, Error> {
+        Ok(wasmprinter::print_bytes(component)?)
     }
 
-    fn print(component: Vec<u8>) -> Result<String, String> {
-        wasmprinter::print_bytes(component).map_err(|e| format!("{:?}", e))
+    fn print_with_options(binary: Vec<u8>, opts: PrintOpts) -> Result<String, Error> {
+        let mut binary = match &opts.path {
+            Some(path) => nested_binary(&binary, path)?.to_vec(),
+            None => binary,
+        };
+        if !opts.name_section.unwrap_or(true) {
+            binary = rewrite_custom_sections(
+                &binary,
+                |_, section| section.name() != "name" && section.name() != "component-name",
+                None,
+            )?;
+        }
+
+        let mut printer = wasmprinter::Printer::new();
+        printer.print_skeleton(opts.skeleton.unwrap_or(false));
+        printer.name_unnamed(opts.name_unnamed.unwrap_or(false));
+        printer.print_offsets(opts.offsets.unwrap_or(false));
+        if opts.fold_instructions.unwrap_or(false) {
+            return Ok(fold::print_folded(&mut printer, &binary)?);
+        }
+        Ok(printer.print(&binary)?)
+    }
+
+    fn validate(binary: Vec<u8>, features: Option<Vec<String>>) -> Result<Vec<Diagnostic>, Error> {
+        let features = match features {
+            Some(features) => parse_features(&features)?,
+            None => WasmFeatures::default(),
+        };
+
+        let mut validator = Validator::new_with_features(features);
+        let mut diagnostics = Vec::new();
+        let mut allocs = FuncValidatorAllocations::default();
+        for payload in Parser::new(0).parse_all(&binary) {
+            match payload.and_then(|payload| validator.payload(&payload)) {
+                // Function bodies are validated separately so that all invalid
+                // functions are reported, not only the first one
+                Ok(ValidPayload::Func(func, body)) => {
+                    let mut func = func.into_validator(mem::take(&mut allocs));
+                    if let Err(e) = func.validate(&body) {
+                        diagnostics.push(Diagnostic::from(e));
+                    }
+                    allocs = func.into_allocations();
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    diagnostics.push(Diagnostic::from(e));
+                    break;
+                }
+            }
+        }
+        Ok(diagnostics)
     }
 
     fn component_new(
         binary: Vec<u8>,
         adapters: Option<Vec<(String, Vec<u8>)>>,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Vec<u8>, Error> {
+        let opts = ComponentNewOpts {
+            wasi_adapter: None,
+            adapters,
+            realloc_via_memory_grow: None,
+            skip_validation: None,
+        };
+        Ok(Self::component_new_with_options(binary, opts)?.component)
+    }
+
+    fn component_new_with_options(
+        binary: Vec<u8>,
+        opts: ComponentNewOpts,
+    ) -> Result<ComponentNewOutput, Error> {
         let mut encoder = ComponentEncoder::default()
-            .validate(true)
+            .validate(!opts.skip_validation.unwrap_or(false))
+            .realloc_via_memory_grow(opts.realloc_via_memory_grow.unwrap_or(false))
             .module(&binary)
-            .map_err(|e| format!("Failed to decode Wasm\n{:?}", e))?;
+            .context("failed to decode Wasm")?;
 
-        if let Some(adapters) = adapters {
-            for (name, binary) in adapters {
-                encoder = encoder
-                    .adapter(&name, &binary)
-                    .map_err(|e| format!("{:?}", e))?;
-            }
+        let mut adapters = Vec::new();
+        if let Some(adapter) = opts.wasi_adapter {
+            adapters.push((
+                WASI_ADAPTER_NAME.to_string(),
+                wasi_adapter(adapter)?.to_vec(),
+            ));
         }
+        adapters.extend(opts.adapters.unwrap_or_default());
 
-        let bytes = encoder
+        for (name, binary) in &adapters {
+            encoder = encoder
+                .adapter(name, binary)
+                .with_context(|| format!("failed to add adapter [{name}]"))?;
+        }
+
+        let component = encoder
             .encode()
-            .map_err(|e| format!("failed to encode a component from module\n${:?}", e))?;
+            .context("failed to encode a component from module")?;
 
-        Ok(bytes)
+        let adapter_imports = adapters
+            .into_iter()
+            .map(|(name, _)| {
+                let imports = module_imports(&binary, &name)?;
+                Ok((name, imports))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ComponentNewOutput {
+            component,
+            adapter_imports,
+        })
     }
 
-    fn component_wit(binary: Vec<u8>) -> Result<String, String> {
-        let decoded = wit_component::decode(&binary)
-            .map_err(|e| format!("Failed to decode wit component\n{:?}", e))?;
+    fn compose(root: Vec<u8>, opts: ComposeOpts) -> Result<ComposeOutput, Error> {
+        let mut graph = CompositionGraph::new();
+        let mut instances: Vec<(String, InstanceId)> = Vec::new();
+        let root = wasm_compose::graph::Component::from_bytes("root", root)
+            .context("failed to parse root component")?;
+        let root = graph.add_component(root)?;
+        instances.push(("root".to_string(), graph.instantiate(root)?));
+        for (name, binary) in opts.dependencies {
+            if instances.iter().any(|(instance, _)| *instance == name) {
+                return Err(Error::new(
+                    ErrorKind::InvalidArgument,
+                    format!("duplicate dependency name [{name}]"),
+                ));
+            }
+            let component = wasm_compose::graph::Component::from_bytes(&name, binary)
+                .with_context(|| format!("failed to parse dependency component [{name}]"))?;
+            let component = graph.add_component(component)?;
+            instances.push((name, graph.instantiate(component)?));
+        }
 
-        // let world = decode_world("component", &binary);
+        let instance = |name: &str| {
+            instances
+                .iter()
+                .find(|(instance, _)| instance == name)
+                .map(|(_, id)| *id)
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidArgument,
+                        format!("unknown dependency [{name}]"),
+                    )
+                })
+        };
+        let imports = |graph: &CompositionGraph, id: InstanceId| {
+            let (_, component) = graph.get_component_of_instance(id).unwrap();
+            component
+                .imports()
+                .map(|(index, name, _)| (index, name.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let export = |graph: &CompositionGraph, id: InstanceId, name: &str| {
+            let (_, component) = graph.get_component_of_instance(id).unwrap();
+            component.export_by_name(name).map(|(index, ..)| index)
+        };
+
+        let mut connected: HashSet<(InstanceId, ImportIndex)> = HashSet::new();
+        for wire in opts.wiring.unwrap_or_default() {
+            let target = instance(wire.target.as_deref().unwrap_or("root"))?;
+            let source = instance(&wire.source)?;
+            let Some((import, _)) = imports(&graph, target)
+                .into_iter()
+                .find(|(_, name)| *name == wire.import)
+            else {
+                return Err(Error::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "[{}] has no import named [{}]",
+                        wire.target.as_deref().unwrap_or("root"),
+                        wire.import
+                    ),
+                ));
+            };
+            let source_export = match &wire.export {
+                Some(name) => Some(export(&graph, source, name).ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidArgument,
+                        format!("[{}] has no export named [{name}]", wire.source),
+                    )
+                })?),
+                None => None,
+            };
+            graph
+                .connect(source, source_export, target, import)
+                .with_context(|| {
+                    format!(
+                        "failed to connect import [{}] to dependency [{}]",
+                        wire.import, wire.source
+                    )
+                })?;
+            connected.insert((target, import));
+        }
+
+        let mut unsatisfied_imports = Vec::new();
+        for (target_name, target) in &instances {
+            for (import, name) in imports(&graph, *target) {
+                if connected.contains(&(*target, import)) {
+                    continue;
+                }
+                // The root component is never a dependency of another instance
+                let source = instances[1..].iter().find_map(|(source_name, source)| {
+                    if source == target {
+                        return None;
+                    }
+                    let index = export(&graph, *source, &name)?;
+                    Some((source_name, *source, index))
+                });
+                match source {
+                    Some((source_name, source, export)) => {
+                        graph
+                            .connect(source, Some(export), *target, import)
+                            .with_context(|| {
+                                format!(
+                                    "failed to connect import [{name}] of [{target_name}] to dependency [{source_name}]"
+                                )
+                            })?;
+                    }
+                    None => {
+                        let (_, component) = graph.get_component_of_instance(*target).unwrap();
+                        let type_ = import_type(component.bytes(), &name).with_context(|| {
+                            format!("failed to get the type of import [{name}] of [{target_name}]")
+                        })?;
+                        unsatisfied_imports.push(UnsatisfiedImport {
+                            instance: target_name.clone(),
+                            name,
+                            type_,
+                        })
+                    }
+                }
+            }
+        }
+
+        let component = graph
+            .encode(EncodeOptions {
+                define_components: opts.define_components.unwrap_or(true),
+                export: Some(instances[0].1),
+                validate: true,
+            })
+            .context("failed to encode composed component")?;
+
+        let wit = match wit_component::decode(&component)
+            .context("failed to decode composed component")?
+        {
+            DecodedWasm::Component(resolve, world) => {
+                let package = resolve.worlds[world].package.unwrap();
+                let nested = resolve
+                    .packages
+                    .iter()
+                    .map(|(id, _)| id)
+                    .filter(|id| *id != package)
+                    .collect::<Vec<_>>();
+                WitPrinter::default()
+                    .print(&resolve, package, &nested)
+                    .context("unable to print wit")?
+            }
+            DecodedWasm::WitPackage(..) => unreachable!(),
+        };
+
+        Ok(ComposeOutput {
+            component,
+            unsatisfied_imports,
+            wit,
+        })
+    }
+
+    fn component_wit(binary: Vec<u8>, item: Option<WitItem>) -> Result<String, Error> {
+        let decoded = wit_component::decode(&binary).context("failed to decode wit component")?;
 
-        let doc = match &decoded {
-            DecodedWasm::WitPackage(_, _) => panic!("Unexpected wit package"),
+        let package = match &decoded {
+            DecodedWasm::WitPackage(_, package) => *package,
             DecodedWasm::Component(resolve, world) => resolve.worlds[*world].package.unwrap(),
         };
 
-        let output = WitPrinter::default()
-            .print(decoded.resolve(), doc, &[])
-            .map_err(|e| format!("Unable to print wit\n${:?}", e))?;
+        print_wit(decoded.resolve(), package, item)
+    }
 
-        Ok(output)
+    fn wit_encode(wit: WitInput, features: Option<EnabledFeatureSet>) -> Result<Vec<u8>, Error> {
+        let mut resolve = Resolve::default();
+        enable_features(&mut resolve, features);
+        let package = push_wit(&mut resolve, wit)?;
+        Ok(wit_component::encode(&resolve, package).context("failed to encode WIT package")?)
     }
 
-    fn component_embed(embed_opts: EmbedOpts) -> Result<Vec<u8>, String> {
+    fn wit_decode(binary: Vec<u8>) -> Result<Vec<(String, String)>, Error> {
+        let (resolve, package) =
+            match wit_component::decode(&binary).context("failed to decode WIT package")? {
+                DecodedWasm::WitPackage(resolve, package) => (resolve, package),
+                DecodedWasm::Component(..) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidArgument,
+                        "found an actual component instead of an encoded WIT package",
+                    ))
+                }
+            };
+
+        // The decoded package comes first, followed by its dependencies
+        let packages = std::iter::once(package)
+            .chain(
+                resolve
+                    .packages
+                    .iter()
+                    .map(|(id, _)| id)
+                    .filter(|id| *id != package),
+            )
+            .map(|id| {
+                let wit = print_wit(&resolve, id, None)?;
+                Ok((resolve.packages[id].name.to_string(), wit))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(packages)
+    }
+
+    fn targets(
+        binary: Vec<u8>,
+        wit: WitInput,
+        world: Option<String>,
+        features: Option<EnabledFeatureSet>,
+    ) -> Result<Vec<TargetsMismatch>, Error> {
+        let (component, component_world) =
+            match wit_component::decode(&binary).context("failed to decode component")? {
+                DecodedWasm::Component(resolve, world) => (resolve, world),
+                DecodedWasm::WitPackage(..) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidArgument,
+                        "found an encoded WIT package instead of an actual component",
+                    ))
+                }
+            };
+
+        let mut resolve = Resolve::default();
+        enable_features(&mut resolve, features);
+        let package = push_wit(&mut resolve, wit)?;
+        let world = resolve
+            .select_world(package, world.as_deref())
+            .map_err(wit_error)?;
+
+        Ok(targets::targets(
+            &component,
+            component_world,
+            &resolve,
+            world,
+        ))
+    }
+
+    fn component_embed(mut embed_opts: EmbedOpts) -> Result<Vec<u8>, Error> {
         let binary = &embed_opts.binary;
 
         let mut resolve = Resolve::default();
 
         // Add all features specified in embed options to the resolve
         // (this helps identify/use feature gating properly)
-        match embed_opts.features {
-            Some(EnabledFeatureSet::List(ref features)) => {
-                for f in features.into_iter() {
-                    resolve.features.insert(f.to_string());
-                }
-            }
-            Some(EnabledFeatureSet::All) => {
-                resolve.all_features = true;
-            }
-            _ => {}
-        };
+        enable_features(&mut resolve, embed_opts.features.take());
 
-        let ids = if let Some(wit_source) = &embed_opts.wit_source {
-            let path = PathBuf::from("component.wit");
-            resolve
-                .push_str(&path, wit_source)
-                .map_err(|e| e.to_string())?
+        let wit = if let Some(wit_source) = embed_opts.wit_source.take() {
+            WitInput::Source(wit_source)
+        } else if let Some(wit_files) = embed_opts.wit_files.take() {
+            WitInput::Files(wit_files)
+        } else if let Some(wit_path) = embed_opts.wit_path.take() {
+            WitInput::Path(wit_path)
         } else {
-            let wit_path = &PathBuf::from(embed_opts.wit_path.as_ref().unwrap());
-            if metadata(wit_path).unwrap().is_file() {
-                resolve.push_file(wit_path).map_err(|e| e.to_string())?
-            } else {
-                resolve.push_dir(wit_path).map_err(|e| e.to_string())?.0
-            }
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                "no WIT provided. Use one of the `wit-source`, `wit-files` or `wit-path` options.",
+            ));
         };
+        let ids = push_wit(&mut resolve, wit)?;
 
         let world_string = embed_opts.world.as_ref().map(|world| world.to_string());
 
         let world = resolve
             .select_world(ids, world_string.as_deref())
-            .map_err(|e| e.to_string())?;
+            .map_err(wit_error)?;
 
         let string_encoding = match &embed_opts.string_encoding {
             None | Some(StringEncoding::Utf8) => wit_component::StringEncoding::UTF8,
@@ -125,10 +432,10 @@ impl Guest for WasmToolsJs {
             wit_component::dummy_module(&resolve, world)
         } else {
             if binary.is_none() {
-                return Err(
-                    "no core binary provided. Use the `dummy` option to generate an empty binary."
-                        .to_string(),
-                );
+                return Err(Error::new(
+                    ErrorKind::InvalidArgument,
+                    "no core binary provided. Use the `dummy` option to generate an empty binary.",
+                ));
             }
             binary.as_ref().unwrap().clone()
         };
@@ -136,17 +443,7 @@ impl Guest for WasmToolsJs {
         let producers = match &embed_opts.metadata {
             Some(metadata_fields) => {
                 let mut producers = Producers::default();
-                for (field_name, items) in metadata_fields {
-                    if field_name != "sdk"
-                        && field_name != "language"
-                        && field_name != "processed-by"
-                    {
-                        return Err(format!("'{field_name}' is not a valid field to embed in the metadata. Must be one of 'language', 'processed-by' or 'sdk'."));
-                    }
-                    for (name, version) in items {
-                        producers.add(field_name, name, version);
-                    }
-                }
+                add_producers(&mut producers, metadata_fields)?;
                 Some(producers)
             }
             None => None,
@@ -154,7 +451,7 @@ impl Guest for WasmToolsJs {
 
         let encoded =
             wit_component::metadata::encode(&resolve, world, string_encoding, producers.as_ref())
-                .map_err(|e| e.to_string())?;
+                .context("failed to encode component type metadata")?;
 
         let section = wasm_encoder::CustomSection {
             name: "component-type".into(),
@@ -164,39 +461,175 @@ impl Guest for WasmToolsJs {
         core_binary.push(section.id());
         section.encode(&mut core_binary);
 
+        if let Some(registry_metadata) = embed_opts.registry_metadata.take() {
+            let registry_metadata = wasm_metadata::RegistryMetadata::from(registry_metadata);
+            registry_metadata.validate()?;
+            core_binary = registry_metadata.add_to_wasm(&core_binary)?;
+        }
+
         Ok(core_binary)
     }
 
-    fn metadata_add(binary: Vec<u8>, metadata: ProducersFields) -> Result<Vec<u8>, String> {
+    fn custom_sections_list(binary: Vec<u8>) -> Result<Vec<CustomSection>, Error> {
+        let mut sections = Vec::new();
+        visit_custom_sections(&binary, |path, section| {
+            let start = section.data_offset();
+            sections.push(CustomSection {
+                path: path.to_vec(),
+                name: section.name().to_string(),
+                range: (start as u32, (start + section.data().len()) as u32),
+            });
+        })?;
+        Ok(sections)
+    }
+
+    fn custom_section_extract(
+        binary: Vec<u8>,
+        path: Vec<u32>,
+        name: String,
+    ) -> Result<Vec<u8>, Error> {
+        let mut data = None;
+        visit_custom_sections(&binary, |section_path, section| {
+            if data.is_none() && section_path == path && section.name() == name {
+                data = Some(section.data().to_vec());
+            }
+        })?;
+        data.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "no custom section named [{name}] at path [{}]",
+                    display_path(&path)
+                ),
+            )
+        })
+    }
+
+    fn custom_sections_remove(
+        binary: Vec<u8>,
+        filter: CustomSectionFilter,
+    ) -> Result<Vec<u8>, Error> {
+        rewrite_custom_sections(
+            &binary,
+            |path, section| {
+                let selected = filter.path.iter().all(|p| p == path)
+                    && filter
+                        .names
+                        .iter()
+                        .any(|pattern| matches_name(pattern, section.name()));
+                !selected
+            },
+            None,
+        )
+    }
+
+    fn custom_section_insert(
+        binary: Vec<u8>,
+        path: Vec<u32>,
+        name: String,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let section = wasm_encoder::CustomSection {
+            name: name.into(),
+            data: data.into(),
+        };
+        rewrite_custom_sections(&binary, |_, _| true, Some((&path, section)))
+    }
+
+    fn strip(binary: Vec<u8>, opts: Option<StripOpts>) -> Result<Vec<u8>, Error> {
+        let StripOpts { all, keep } = opts.unwrap_or(StripOpts {
+            all: None,
+            keep: None,
+        });
+        let all = all.unwrap_or(false);
+        let keep = keep.unwrap_or_default();
+        rewrite_custom_sections(
+            &binary,
+            |_, section| {
+                let name = section.name();
+                let essential = !all
+                    && ESSENTIAL_CUSTOM_SECTIONS
+                        .iter()
+                        .any(|pattern| matches_name(pattern, name));
+                essential || keep.iter().any(|pattern| matches_name(pattern, name))
+            },
+            None,
+        )
+    }
+
+    fn inspect(binary: Vec<u8>) -> Result<Vec<InspectedBinary>, Error> {
+        Ok(inspect::inspect(&binary)?)
+    }
+
+    fn metadata_add(binary: Vec<u8>, metadata: ProducersFields) -> Result<Vec<u8>, Error> {
         let mut producers = Producers::default();
+        add_producers(&mut producers, &metadata)?;
+        Ok(producers.add_to_wasm(&binary[0..])?)
+    }
 
-        for (field_name, items) in metadata {
-            if field_name != "sdk" && field_name != "language" && field_name != "processed-by" {
-                return Err(format!("'{field_name}' is not a valid field to embed in the metadata. Must be one of 'language', 'processed-by' or 'sdk'."));
+    fn metadata_edit(mut binary: Vec<u8>, edit: MetadataEdit) -> Result<Vec<u8>, Error> {
+        let clear_producers = edit.clear_producers.unwrap_or(false);
+        let rewrite_producers = clear_producers || edit.remove_producers.is_some();
+
+        // Producers are merged into the existing section when added, so
+        // removing entries means rebuilding the section from what is kept
+        let mut producers = Producers::default();
+        if rewrite_producers {
+            if !clear_producers {
+                let remove = edit.remove_producers.unwrap_or_default();
+                if let Some(existing) = Producers::from_wasm(&binary)? {
+                    for (field_name, items) in existing.iter() {
+                        for (name, version) in items.iter() {
+                            if !remove.iter().any(|(f, n)| f == field_name && n == name) {
+                                producers.add(field_name, name, version);
+                            }
+                        }
+                    }
+                }
             }
-            for (name, version) in items {
-                producers.add(&field_name, &name, &version);
+            binary = rewrite_custom_sections(
+                &binary,
+                |path, section| !path.is_empty() || section.name() != "producers",
+                None,
+            )?;
+        }
+        if let Some(metadata) = &edit.add_producers {
+            add_producers(&mut producers, metadata)?;
+        }
+        if producers.iter().next().is_some() {
+            binary = producers.add_to_wasm(&binary)?;
+        }
+
+        if edit.name.is_some() {
+            binary = AddMetadata {
+                name: edit.name,
+                ..Default::default()
             }
+            .to_wasm(&binary)?;
         }
-        producers
-            .add_to_wasm(&binary[0..])
-            .map_err(|e| e.to_string())
+
+        if let Some(registry_metadata) = edit.registry_metadata {
+            let registry_metadata = wasm_metadata::RegistryMetadata::from(registry_metadata);
+            registry_metadata.validate()?;
+            binary = registry_metadata.add_to_wasm(&binary)?;
+        }
+
+        Ok(binary)
     }
 
-    fn metadata_show(binary: Vec<u8>) -> Result<Vec<ModuleMetadata>, String> {
-        let metadata =
-            wasm_metadata::Metadata::from_binary(&binary).map_err(|e| format!("{:?}", e))?;
+    fn metadata_show(binary: Vec<u8>) -> Result<Vec<ModuleMetadata>, Error> {
+        let metadata = wasm_metadata::Metadata::from_binary(&binary)?;
         let mut module_metadata: Vec<ModuleMetadata> = Vec::new();
         let mut to_flatten: VecDeque<wasm_metadata::Metadata> = VecDeque::new();
         to_flatten.push_back(metadata);
         while let Some(metadata) = to_flatten.pop_front() {
-            let (name, producers, meta_type, range) = match metadata {
+            let (name, producers, registry_metadata, meta_type, range) = match metadata {
                 wasm_metadata::Metadata::Component {
                     name,
                     producers,
                     children,
                     range,
-                    registry_metadata: _,
+                    registry_metadata,
                 } => {
                     let children_len = children.len();
                     for child in children {
@@ -205,6 +638,7 @@ impl Guest for WasmToolsJs {
                     (
                         name,
                         producers,
+                        registry_metadata,
                         ModuleMetaType::Component(children_len as u32),
                         range,
                     )
@@ -213,8 +647,14 @@ impl Guest for WasmToolsJs {
                     name,
                     producers,
                     range,
-                    registry_metadata: _,
-                } => (name, producers, ModuleMetaType::Module, range),
+                    registry_metadata,
+                } => (
+                    name,
+                    producers,
+                    registry_metadata,
+                    ModuleMetaType::Module,
+                    range,
+                ),
             };
 
             let mut metadata: Vec<(String, Vec<(String, String)>)> = Vec::new();
@@ -233,9 +673,510 @@ impl Guest for WasmToolsJs {
                 name,
                 meta_type,
                 producers: metadata,
+                registry_metadata: registry_metadata.map(Into::into),
                 range: (range.start as u32, range.end as u32),
             });
         }
         Ok(module_metadata)
     }
 }
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        ErrorReport::new(&e).into()
+    }
+}
+
+impl From<ErrorReport> for Error {
+    fn from(report: ErrorReport) -> Self {
+        Error {
+            kind: match report.kind {
+                error_report::ErrorKind::Wit => ErrorKind::Wit,
+                error_report::ErrorKind::Wasm => ErrorKind::Wasm,
+                error_report::ErrorKind::Io => ErrorKind::Io,
+                error_report::ErrorKind::Other => ErrorKind::Other,
+            },
+            message: report.message,
+            context: report.context,
+            span: report.span.map(|span| SourceSpan {
+                file: span.file,
+                line: span.line,
+                column: span.column,
+            }),
+        }
+    }
+}
+
+impl Error {
+    fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error {
+            kind,
+            message: message.into(),
+            context: Vec::new(),
+            span: None,
+        }
+    }
+}
+
+impl From<RegistryMetadata> for wasm_metadata::RegistryMetadata {
+    fn from(metadata: RegistryMetadata) -> Self {
+        wasm_metadata::RegistryMetadata {
+            authors: metadata.authors,
+            description: metadata.description,
+            license: metadata.license,
+            custom_licenses: metadata.custom_licenses.map(|licenses| {
+                licenses
+                    .into_iter()
+                    .map(|license| wasm_metadata::CustomLicense {
+                        id: license.id,
+                        name: license.name,
+                        text: license.text,
+                        reference: license.reference,
+                    })
+                    .collect()
+            }),
+            links: metadata.links.map(|links| {
+                links
+                    .into_iter()
+                    .map(|link| wasm_metadata::Link {
+                        ty: match link.ty {
+                            LinkType::Documentation => wasm_metadata::LinkType::Documentation,
+                            LinkType::Homepage => wasm_metadata::LinkType::Homepage,
+                            LinkType::Repository => wasm_metadata::LinkType::Repository,
+                            LinkType::Funding => wasm_metadata::LinkType::Funding,
+                            LinkType::Custom(ty) => wasm_metadata::LinkType::Custom(ty),
+                        },
+                        value: link.value,
+                    })
+                    .collect()
+            }),
+            categories: metadata.categories,
+        }
+    }
+}
+
+impl From<wasm_metadata::RegistryMetadata> for RegistryMetadata {
+    fn from(metadata: wasm_metadata::RegistryMetadata) -> Self {
+        RegistryMetadata {
+            authors: metadata.authors,
+            description: metadata.description,
+            license: metadata.license,
+            custom_licenses: metadata.custom_licenses.map(|licenses| {
+                licenses
+                    .into_iter()
+                    .map(|license| CustomLicense {
+                        id: license.id,
+                        name: license.name,
+                        text: license.text,
+                        reference: license.reference,
+                    })
+                    .collect()
+            }),
+            links: metadata.links.map(|links| {
+                links
+                    .into_iter()
+                    .map(|link| Link {
+                        ty: match link.ty {
+                            wasm_metadata::LinkType::Documentation => LinkType::Documentation,
+                            wasm_metadata::LinkType::Homepage => LinkType::Homepage,
+                            wasm_metadata::LinkType::Repository => LinkType::Repository,
+                            wasm_metadata::LinkType::Funding => LinkType::Funding,
+                            wasm_metadata::LinkType::Custom(ty) => LinkType::Custom(ty),
+                        },
+                        value: link.value,
+                    })
+                    .collect()
+            }),
+            categories: metadata.categories,
+        }
+    }
+}
+
+/// Convert an error from parsing or resolving WIT
+fn wit_error(e: anyhow::Error) -> Error {
+    ErrorReport::wit(&e).into()
+}
+
+/// Convert an error from parsing WebAssembly text
+fn wasm_error(e: anyhow::Error) -> Error {
+    ErrorReport::wasm(&e).into()
+}
+
+impl From<wasmparser::BinaryReaderError> for Diagnostic {
+    fn from(e: wasmparser::BinaryReaderError) -> Self {
+        Diagnostic {
+            message: e.message().to_string(),
+            offset: e.offset() as u32,
+        }
+    }
+}
+
+/// Parse a list of feature names, as accepted by `validate`, into the set of
+/// enabled features
+fn parse_features(features: &[String]) -> Result<WasmFeatures, Error> {
+    let mut enabled = WasmFeatures::default();
+    for feature in features {
+        let (enable, name) = match feature.strip_prefix('-') {
+            Some(name) => (false, name),
+            None => (true, feature.as_str()),
+        };
+        let flags = if name == "all" {
+            WasmFeatures::all()
+        } else {
+            WasmFeatures::from_name(&name.to_uppercase().replace('-', "_")).ok_or_else(|| {
+                let names = WasmFeatures::all()
+                    .iter_names()
+                    .map(|(name, _)| name.to_lowercase().replace('_', "-"))
+                    .collect::<Vec<_>>();
+                Error::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "unknown WebAssembly feature [{name}], expected one of: all, {}",
+                        names.join(", ")
+                    ),
+                )
+            })?
+        };
+        enabled.set(flags, enable);
+    }
+    Ok(enabled)
+}
+
+/// Module name of the imports adapted by the WASI preview1 adapters
+const WASI_ADAPTER_NAME: &str = "wasi_snapshot_preview1";
+
+/// Binary of a WASI preview1 adapter shipped in `lib/`
+#[cfg(feature = "wasi-adapters")]
+fn wasi_adapter(adapter: WasiAdapter) -> Result<&'static [u8], Error> {
+    let binary: &[u8] = match adapter {
+        WasiAdapter::Command => include_bytes!("../../../lib/wasi_snapshot_preview1.command.wasm"),
+        WasiAdapter::Reactor => include_bytes!("../../../lib/wasi_snapshot_preview1.reactor.wasm"),
+        WasiAdapter::Proxy => include_bytes!("../../../lib/wasi_snapshot_preview1.proxy.wasm"),
+    };
+    Ok(binary)
+}
+
+/// Binary of a WASI preview1 adapter shipped in `lib/`, which builds without
+/// the `wasi-adapters` feature don't embed
+#[cfg(not(feature = "wasi-adapters"))]
+fn wasi_adapter(adapter: WasiAdapter) -> Result<&'static [u8], Error> {
+    let name = match adapter {
+        WasiAdapter::Command => "command",
+        WasiAdapter::Reactor => "reactor",
+        WasiAdapter::Proxy => "proxy",
+    };
+    Err(Error::new(
+        ErrorKind::InvalidArgument,
+        format!(
+            "the {name} WASI adapter is not embedded in this build, pass it through the adapters instead"
+        ),
+    ))
+}
+
+/// Names of the imports of a core module from the given module name
+fn module_imports(binary: &[u8], module: &str) -> Result<Vec<String>> {
+    let mut imports = Vec::new();
+    for payload in Parser::new(0).parse_all(binary) {
+        if let Payload::ImportSection(reader) = payload.context("failed to parse Wasm")? {
+            for import in reader {
+                let import = import.context("failed to parse Wasm import")?;
+                if import.module == module {
+                    imports.push(import.name.to_string());
+                }
+            }
+        }
+    }
+    Ok(imports)
+}
+
+/// WIT type of the import of a component with the given name
+fn import_type(binary: &[u8], name: &str) -> Result<String> {
+    let DecodedWasm::Component(resolve, world) =
+        wit_component::decode(binary).context("failed to decode component")?
+    else {
+        bail!("expected a component, found a WIT package");
+    };
+    let item = targets::find_item(&resolve, &resolve.worlds[world].imports, name)
+        .with_context(|| format!("import [{name}] not found in the component world"))?;
+    Ok(targets::item_type(&resolve, item))
+}
+
+/// Custom sections kept by `strip`, as they are needed to link or compose a
+/// module or component
+const ESSENTIAL_CUSTOM_SECTIONS: &[&str] = &[
+    "component-type*",
+    "dylink.0",
+    "linking",
+    "reloc.*",
+    "target_features",
+];
+
+/// Match a custom section name against a pattern, where a trailing `*` matches
+/// any suffix
+fn matches_name(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+fn display_path(path: &[u32]) -> String {
+    path.iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Get the binary of the nested module or component at the given path, or
+/// the whole binary for an empty path
+fn nested_binary<'a>(binary: &'a [u8], target: &[u32]) -> Result<&'a [u8], Error> {
+    if target.is_empty() {
+        return Ok(binary);
+    }
+    let mut path = Vec::new();
+    let mut counts = vec![0];
+    for payload in Parser::new(0).parse_all(binary) {
+        match payload.context("failed to parse Wasm")? {
+            Payload::ModuleSection {
+                unchecked_range, ..
+            }
+            | Payload::ComponentSection {
+                unchecked_range, ..
+            } => {
+                let count = counts.last_mut().unwrap();
+                path.push(*count);
+                *count += 1;
+                counts.push(0);
+                if path == target {
+                    return Ok(&binary[unchecked_range]);
+                }
+            }
+            Payload::End(_) => {
+                path.pop();
+                counts.pop();
+            }
+            _ => {}
+        }
+    }
+    Err(Error::new(
+        ErrorKind::InvalidArgument,
+        format!("no module or component at path [{}]", display_path(target)),
+    ))
+}
+
+/// Call `visit` with each custom section of a module or component, including
+/// those of all nested modules and components, along with the path of the
+/// module or component containing it
+fn visit_custom_sections<'a>(
+    binary: &'a [u8],
+    mut visit: impl FnMut(&[u32], &CustomSectionReader<'a>),
+) -> Result<()> {
+    let mut path = Vec::new();
+    let mut counts = vec![0];
+    for payload in Parser::new(0).parse_all(binary) {
+        match payload.context("failed to parse Wasm")? {
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => {
+                let count = counts.last_mut().unwrap();
+                path.push(*count);
+                *count += 1;
+                counts.push(0);
+            }
+            Payload::End(_) => {
+                path.pop();
+                counts.pop();
+            }
+            Payload::CustomSection(section) => visit(&path, &section),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite a module or component, keeping only the custom sections for which
+/// `keep` returns true, and optionally appending a new custom section to the
+/// module or component at the given path. All other sections are copied as-is.
+fn rewrite_custom_sections(
+    binary: &[u8],
+    mut keep: impl FnMut(&[u32], &CustomSectionReader) -> bool,
+    mut insert: Option<(&[u32], wasm_encoder::CustomSection)>,
+) -> Result<Vec<u8>, Error> {
+    let mut path = Vec::new();
+    let mut counts = vec![0];
+    let mut stack = Vec::new();
+    let mut output = Vec::new();
+    for payload in Parser::new(0).parse_all(binary) {
+        let payload = payload.context("failed to parse Wasm")?;
+        match &payload {
+            Payload::Version { encoding, .. } => {
+                output.extend_from_slice(match encoding {
+                    wasmparser::Encoding::Component => &wasm_encoder::Component::HEADER,
+                    wasmparser::Encoding::Module => &wasm_encoder::Module::HEADER,
+                });
+                continue;
+            }
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => {
+                let count = counts.last_mut().unwrap();
+                path.push(*count);
+                *count += 1;
+                counts.push(0);
+                stack.push(mem::take(&mut output));
+                continue;
+            }
+            Payload::End(_) => {
+                if insert.as_ref().is_some_and(|(at, _)| *at == path) {
+                    let (_, section) = insert.take().unwrap();
+                    section.append_to(&mut output);
+                }
+                path.pop();
+                counts.pop();
+                let Some(mut parent) = stack.pop() else {
+                    break;
+                };
+                if output.starts_with(&wasm_encoder::Component::HEADER) {
+                    parent.push(wasm_encoder::ComponentSectionId::Component as u8);
+                } else {
+                    parent.push(wasm_encoder::ComponentSectionId::CoreModule as u8);
+                }
+                output.encode(&mut parent);
+                output = parent;
+                continue;
+            }
+            Payload::CustomSection(section) if !keep(&path, section) => continue,
+            _ => {}
+        }
+        if let Some((id, range)) = payload.as_section() {
+            wasm_encoder::RawSection {
+                id,
+                data: &binary[range],
+            }
+            .append_to(&mut output);
+        }
+    }
+    if let Some((path, _)) = insert {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            format!("no module or component at path [{}]", display_path(path)),
+        ));
+    }
+    Ok(output)
+}
+
+/// Add producers fields to a producers section, only accepting the fields
+/// defined by the tool conventions
+fn add_producers(producers: &mut Producers, metadata: &ProducersFields) -> Result<(), Error> {
+    for (field_name, items) in metadata {
+        if field_name != "sdk" && field_name != "language" && field_name != "processed-by" {
+            return Err(invalid_metadata_field(field_name));
+        }
+        for (name, version) in items {
+            producers.add(field_name, name, version);
+        }
+    }
+    Ok(())
+}
+
+fn invalid_metadata_field(field_name: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidArgument,
+        format!("'{field_name}' is not a valid field to embed in the metadata. Must be one of 'language', 'processed-by' or 'sdk'."),
+    )
+}
+
+/// Add the features enabled for feature gating to a resolve, before pushing WIT
+fn enable_features(resolve: &mut Resolve, features: Option<EnabledFeatureSet>) {
+    match features {
+        Some(EnabledFeatureSet::List(features)) => resolve.features.extend(features),
+        Some(EnabledFeatureSet::All) => resolve.all_features = true,
+        None => {}
+    }
+}
+
+/// Push WIT from any supported input into a resolve, returning the main package
+fn push_wit(resolve: &mut Resolve, wit: WitInput) -> Result<PackageId, Error> {
+    match wit {
+        WitInput::Source(source) => {
+            let path = PathBuf::from("component.wit");
+            resolve.push_str(&path, &source).map_err(wit_error)
+        }
+        WitInput::Files(files) => push_files(resolve, files).map_err(wit_error),
+        WitInput::Path(path) => {
+            let path = &PathBuf::from(path);
+            let is_file = metadata(path)
+                .with_context(|| format!("failed to read WIT path [{}]", path.display()))?
+                .is_file();
+            if is_file {
+                resolve.push_file(path).map_err(wit_error)
+            } else {
+                Ok(resolve.push_dir(path).map_err(wit_error)?.0)
+            }
+        }
+    }
+}
+
+/// Print a WIT package, or only the selected interface or world
+///
+/// A selected item is looked up by name in the given package, unless given as
+/// a fully qualified name such as `wasi:cli/stdout@0.2.0`.
+fn print_wit(
+    resolve: &Resolve,
+    package: PackageId,
+    item: Option<WitItem>,
+) -> Result<String, Error> {
+    let Some(item) = item else {
+        return Ok(WitPrinter::default()
+            .print(resolve, package, &[])
+            .context("unable to print wit")?);
+    };
+
+    let (kind, name) = match &item {
+        WitItem::Interface(name) => ("interface", name),
+        WitItem::World(name) => ("world", name),
+    };
+    let (package, item_name) = match name.split_once('/') {
+        Some((package_name, item_name)) => {
+            // Versions are given after the item name, as in `ns:pkg/item@1.0.0`
+            let (item_name, version) = match item_name.split_once('@') {
+                Some((item_name, version)) => (item_name, Some(version)),
+                None => (item_name, None),
+            };
+            let package_name = match version {
+                Some(version) => format!("{package_name}@{version}"),
+                None => package_name.to_string(),
+            };
+            let package = resolve
+                .packages
+                .iter()
+                .find(|(_, package)| package.name.to_string() == package_name)
+                .map(|(id, _)| id);
+            (package, item_name)
+        }
+        None => (Some(package), name.as_str()),
+    };
+
+    // Print a copy of the package with only the selected item
+    let mut resolve = resolve.clone();
+    let selected = package.filter(|package| {
+        let package = &mut resolve.packages[*package];
+        match &item {
+            WitItem::Interface(_) => {
+                package.interfaces.retain(|name, _| name == item_name);
+                package.worlds.clear();
+                !package.interfaces.is_empty()
+            }
+            WitItem::World(_) => {
+                package.worlds.retain(|name, _| name == item_name);
+                package.interfaces.clear();
+                !package.worlds.is_empty()
+            }
+        }
+    });
+    let Some(package) = selected else {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            format!("no {kind} named [{name}] found"),
+        ));
+    };
+    Ok(WitPrinter::default()
+        .print(&resolve, package, &[])
+        .context("unable to print wit")?)
+}
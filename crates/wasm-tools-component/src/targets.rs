@@ -0,0 +1,226 @@
+//! Checking that a component targets a world
+//!
+//! The world of a component, decoded from its binary, is compared with the
+//! target world item by item, matching imports and exports by name:
+//!
+//! * every export of the target world must be exported by the component,
+//!   with at least the functions of the target interface,
+//! * every import of the component must be imported by the target world,
+//!   with at most the functions of the target interface,
+//! * functions and named types present in both must have the same types.
+//!
+//! Types are compared structurally, with aliases resolved and resources
+//! compared by name.
+
+use jco_shared::wit_display::{func_name, TypeNames};
+use wit_parser::{
+    Function, Interface, Resolve, Type, TypeDefKind, TypeId, WorldId, WorldItem, WorldKey,
+};
+
+use crate::exports::local::wasm_tools::tools::{TargetsMismatch, TargetsMismatchKind};
+
+/// Compare the world of a component with the world it should target,
+/// returning the mismatches found
+pub(crate) fn targets(
+    component: &Resolve,
+    component_world: WorldId,
+    target: &Resolve,
+    target_world: WorldId,
+) -> Vec<TargetsMismatch> {
+    let mut check = TargetsCheck {
+        component,
+        target,
+        mismatches: Vec::new(),
+    };
+
+    let component_world = &component.worlds[component_world];
+    let target_world = &target.worlds[target_world];
+
+    for (key, target_item) in &target_world.exports {
+        let name = target.name_world_key(key);
+        match find_item(component, &component_world.exports, &name) {
+            Some(component_item) => check.item(&name, component_item, target_item, true),
+            None => check.push(
+                TargetsMismatchKind::MissingExport,
+                &name,
+                format!("export `{name}` is missing"),
+            ),
+        }
+    }
+
+    for (key, component_item) in &component_world.imports {
+        let name = component.name_world_key(key);
+        match find_item(target, &target_world.imports, &name) {
+            Some(target_item) => check.item(&name, component_item, target_item, false),
+            None => check.push(
+                TargetsMismatchKind::ExtraImport,
+                &name,
+                format!("import `{name}` is not provided by the world"),
+            ),
+        }
+    }
+
+    check.mismatches
+}
+
+/// Import or export of a world with the given name
+pub(crate) fn find_item<'a>(
+    resolve: &Resolve,
+    items: impl IntoIterator<Item = (&'a WorldKey, &'a WorldItem)>,
+    name: &str,
+) -> Option<&'a WorldItem> {
+    items
+        .into_iter()
+        .find(|(key, _)| resolve.name_world_key(key) == name)
+        .map(|(_, item)| item)
+}
+
+struct TargetsCheck<'a> {
+    component: &'a Resolve,
+    target: &'a Resolve,
+    mismatches: Vec<TargetsMismatch>,
+}
+
+impl TargetsCheck<'_> {
+    fn push(&mut self, kind: TargetsMismatchKind, name: &str, message: String) {
+        self.mismatches.push(TargetsMismatch {
+            kind,
+            name: name.to_string(),
+            message,
+        });
+    }
+
+    fn type_mismatch(&mut self, name: &str, message: String) {
+        self.push(TargetsMismatchKind::TypeMismatch, name, message);
+    }
+
+    /// Compare an import or export present in both worlds
+    fn item(&mut self, name: &str, component: &WorldItem, target: &WorldItem, export: bool) {
+        let (component_resolve, target_resolve) = (self.component, self.target);
+        match (component, target) {
+            (
+                WorldItem::Interface { id: component, .. },
+                WorldItem::Interface { id: target, .. },
+            ) => self.interface(
+                name,
+                &component_resolve.interfaces[*component],
+                &target_resolve.interfaces[*target],
+                export,
+            ),
+            (WorldItem::Function(component), WorldItem::Function(target)) => {
+                self.function(name, component, target)
+            }
+            (WorldItem::Type(component), WorldItem::Type(target)) => {
+                self.named_type(name, *component, *target)
+            }
+            _ => self.type_mismatch(
+                name,
+                format!(
+                    "expected {}, found {}",
+                    item_kind(target),
+                    item_kind(component)
+                ),
+            ),
+        }
+    }
+
+    fn interface(&mut self, name: &str, component: &Interface, target: &Interface, export: bool) {
+        // Types used from other interfaces are compared with those interfaces
+        for (type_name, target_ty) in &target.types {
+            if let TypeDefKind::Type(Type::Id(_)) = self.target.types[*target_ty].kind {
+                continue;
+            }
+            if let Some(component_ty) = component.types.get(type_name) {
+                self.named_type(name, *component_ty, *target_ty);
+            }
+        }
+
+        // Exports must provide at least the functions of the world, and
+        // imports use at most the functions of the world
+        for (key, target_func) in &target.functions {
+            match component.functions.get(key) {
+                Some(component_func) => self.function(name, component_func, target_func),
+                None if export => self.push(
+                    TargetsMismatchKind::MissingExport,
+                    name,
+                    format!(
+                        "function `{}` is missing",
+                        func_name(self.target, target_func)
+                    ),
+                ),
+                None => {}
+            }
+        }
+        if !export {
+            for (key, component_func) in &component.functions {
+                if !target.functions.contains_key(key) {
+                    self.push(
+                        TargetsMismatchKind::ExtraImport,
+                        name,
+                        format!(
+                            "function `{}` is not provided by the world",
+                            func_name(self.component, component_func)
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, name: &str, component: &Function, target: &Function) {
+        let expected = TypeNames::expanded(self.target).func(target);
+        let found = TypeNames::expanded(self.component).func(component);
+        if expected != found {
+            self.type_mismatch(
+                name,
+                format!(
+                    "function `{}` has type `{found}`, expected `{expected}`",
+                    func_name(self.target, target)
+                ),
+            );
+        }
+    }
+
+    fn named_type(&mut self, name: &str, component: TypeId, target: TypeId) {
+        let expected = TypeNames::expanded(self.target).type_def(target);
+        let found = TypeNames::expanded(self.component).type_def(component);
+        if expected != found {
+            let type_name = self.target.types[target].name.as_deref().unwrap_or(name);
+            self.type_mismatch(
+                name,
+                format!("type `{type_name}` is `{found}`, expected `{expected}`"),
+            );
+        }
+    }
+}
+
+fn item_kind(item: &WorldItem) -> &'static str {
+    match item {
+        WorldItem::Interface { .. } => "an interface",
+        WorldItem::Function(_) => "a function",
+        WorldItem::Type(_) => "a type",
+    }
+}
+
+/// Structural description of the type of an import or export, rendering an
+/// interface as its functions
+pub(crate) fn item_type(resolve: &Resolve, item: &WorldItem) -> String {
+    match item {
+        WorldItem::Interface { id, .. } => {
+            let funcs = resolve.interfaces[*id]
+                .functions
+                .values()
+                .map(|func| {
+                    format!(
+                        " {}: {};",
+                        func_name(resolve, func),
+                        TypeNames::expanded(resolve).func(func)
+                    )
+                })
+                .collect::<String>();
+            format!("interface {{{funcs} }}")
+        }
+        WorldItem::Function(func) => TypeNames::expanded(resolve).func(func),
+        WorldItem::Type(id) => TypeNames::expanded(resolve).type_def(*id),
+    }
+}
@@ -1,15 +1,18 @@
 export { optimizeComponent as opt } from './cmd/opt.js';
 export { transpileComponent as transpile, typesComponent as types } from './cmd/transpile.js';
+export { semverCheckWit as semverCheck } from './cmd/semver-check.js';
 import { $init, tools } from "../obj/wasm-tools.js";
-const { print: printFn, parse: parseFn, componentWit: componentWitFn, componentNew: componentNewFn, componentEmbed: componentEmbedFn, metadataAdd: metadataAddFn, metadataShow: metadataShowFn } = tools;
+import { withWasiAdapter } from './cmd/wasm-tools.js';
+const { print: printFn, printWithOptions: printWithOptionsFn, parse: parseFn, validate: validateFn, componentWit: componentWitFn, witEncode: witEncodeFn, witDecode: witDecodeFn, targets: targetsFn, componentNew: componentNewFn, componentNewWithOptions: componentNewWithOptionsFn, compose: composeFn, componentEmbed: componentEmbedFn, metadataAdd: metadataAddFn, metadataEdit: metadataEditFn, metadataShow: metadataShowFn, inspect: inspectFn, customSectionsList: customSectionsListFn, customSectionExtract: customSectionExtractFn, customSectionsRemove: customSectionsRemoveFn, customSectionInsert: customSectionInsertFn, strip: stripFn } = tools;
 
 /**
- * @param {Parameters<import('../obj/wasm-tools.js').print>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').printWithOptions>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').printWithOptions>[1]} [opts]
  * @return {Promise<ReturnType<import('../obj/wasm-tools.js').print>>}
  */
-export async function print (binary) {
+export async function print (binary, opts) {
   await $init;
-  return printFn(binary);
+  return opts ? printWithOptionsFn(binary, opts) : printFn(binary);
 }
 /**
  * @param {Parameters<import('../obj/wasm-tools.js').parse>[0]} wat
@@ -19,13 +22,51 @@ export async function parse (wat) {
   await $init;
   return parseFn(wat);
 }
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').validate>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').validate>[1]} features
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').validate>>}
+ */
+export async function validate (binary, features) {
+  await $init;
+  return validateFn(binary, features);
+}
 /**
  * @param {Parameters<import('../obj/wasm-tools.js').componentWit>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').componentWit>[1]} item
  * @return {Promise<ReturnType<import('../obj/wasm-tools.js').componentWit>>}
  */
-export async function componentWit (binary) {
+export async function componentWit (binary, item) {
+  await $init;
+  return componentWitFn(binary, item);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').witEncode>[0]} wit
+ * @param {Parameters<import('../obj/wasm-tools.js').witEncode>[1]} features
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').witEncode>>}
+ */
+export async function witEncode (wit, features) {
   await $init;
-  return componentWitFn(binary);
+  return witEncodeFn(wit, features);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').targets>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').targets>[1]} wit
+ * @param {Parameters<import('../obj/wasm-tools.js').targets>[2]} world
+ * @param {Parameters<import('../obj/wasm-tools.js').targets>[3]} features
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').targets>>}
+ */
+export async function targets (binary, wit, world, features) {
+  await $init;
+  return targetsFn(binary, wit, world, features);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').witDecode>[0]} binary
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').witDecode>>}
+ */
+export async function witDecode (binary) {
+  await $init;
+  return witDecodeFn(binary);
 }
 /**
  * @param {Parameters<import('../obj/wasm-tools.js').componentNew>[0]} binary
@@ -36,6 +77,24 @@ export async function componentNew (binary, adapters) {
   await $init;
   return componentNewFn(binary, adapters);
 }
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').componentNewWithOptions>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').componentNewWithOptions>[1] & { wasiAdapter?: 'command' | 'reactor' | 'proxy' }} opts
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').componentNewWithOptions>>}
+ */
+export async function componentNewWithOptions (binary, opts) {
+  await $init;
+  return componentNewWithOptionsFn(binary, await withWasiAdapter(opts));
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').compose>[0]} root
+ * @param {Parameters<import('../obj/wasm-tools.js').compose>[1]} opts
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').compose>>}
+ */
+export async function compose (root, opts) {
+  await $init;
+  return composeFn(root, opts);
+}
 /**
  * @param {Parameters<import('../obj/wasm-tools.js').componentEmbed>[0]} embedOpts
  * @return {Promise<ReturnType<import('../obj/wasm-tools.js').componentEmbed>>}
@@ -53,6 +112,23 @@ export async function metadataAdd (binary, metadata) {
   await $init;
   return metadataAddFn(binary, metadata);
 }
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').metadataEdit>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').metadataEdit>[1]} edit
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').metadataEdit>>}
+ */
+export async function metadataEdit (binary, edit) {
+  await $init;
+  return metadataEditFn(binary, edit);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').inspect>[0]} binary
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').inspect>>}
+ */
+export async function inspect (binary) {
+  await $init;
+  return inspectFn(binary);
+}
 /**
  * @param {Parameters<import('../obj/wasm-tools.js').metadataShow>[0]} binary
  * @return {Promise<ReturnType<import('../obj/wasm-tools.js').metadataShow>>}
@@ -61,9 +137,59 @@ export async function metadataShow (binary) {
   await $init;
   return metadataShowFn(binary);
 }
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionsList>[0]} binary
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').customSectionsList>>}
+ */
+export async function customSectionsList (binary) {
+  await $init;
+  return customSectionsListFn(binary);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionExtract>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionExtract>[1]} path
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionExtract>[2]} name
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').customSectionExtract>>}
+ */
+export async function customSectionExtract (binary, path, name) {
+  await $init;
+  return customSectionExtractFn(binary, path, name);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionsRemove>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionsRemove>[1]} filter
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').customSectionsRemove>>}
+ */
+export async function customSectionsRemove (binary, filter) {
+  await $init;
+  return customSectionsRemoveFn(binary, filter);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionInsert>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionInsert>[1]} path
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionInsert>[2]} name
+ * @param {Parameters<import('../obj/wasm-tools.js').customSectionInsert>[3]} data
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').customSectionInsert>>}
+ */
+export async function customSectionInsert (binary, path, name, data) {
+  await $init;
+  return customSectionInsertFn(binary, path, name, data);
+}
+/**
+ * @param {Parameters<import('../obj/wasm-tools.js').strip>[0]} binary
+ * @param {Parameters<import('../obj/wasm-tools.js').strip>[1]} opts
+ * @return {Promise<ReturnType<import('../obj/wasm-tools.js').strip>>}
+ */
+export async function strip (binary, opts) {
+  await $init;
+  return stripFn(binary, opts);
+}
 export function preview1AdapterCommandPath () {
   return new URL('../lib/wasi_snapshot_preview1.command.wasm', import.meta.url);
 }
 export function preview1AdapterReactorPath () {
   return new URL('../lib/wasi_snapshot_preview1.reactor.wasm', import.meta.url);
 }
+export function preview1AdapterProxyPath () {
+  return new URL('../lib/wasi_snapshot_preview1.proxy.wasm', import.meta.url);
+}
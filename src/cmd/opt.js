@@ -135,11 +135,25 @@ export async function optimizeComponent (componentBytes, opts) {
   }
 }
 
+const defaultArgs = ['-O1', '--low-memory-unused', '--enable-bulk-memory'];
+
+/**
+ * Optimizes a core module output using multi-memory, which isn't embedded in
+ * the component and so isn't optimized by `optimizeComponent`.
+ *
+ * @param {Uint8Array} source
+ * @param {{ optArgs?: string[] }} opts?
+ * @returns {Promise<Uint8Array>}
+ */
+export async function optimizeMultiMemoryModule (source, opts) {
+  return wasmOpt(source, [...(opts?.optArgs ?? defaultArgs), '--enable-multimemory']);
+}
+
 /**
  * @param {Uint8Array} source 
  * @returns {Promise<Uint8Array>}
  */
-async function wasmOpt (source, args = ['-O1', '--low-memory-unused', '--enable-bulk-memory']) {
+async function wasmOpt (source, args = defaultArgs) {
   try {
     return await spawnIOTmp(WASM_OPT, source, [
       ...args, '-o'
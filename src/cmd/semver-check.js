@@ -0,0 +1,63 @@
+import { $init, semverCheck as semverCheckFn } from '../../obj/js-component-bindgen-component.js';
+import { extname, resolve } from 'node:path';
+import { platform } from 'node:process';
+import c from 'chalk-template';
+import { readFile } from '../common.js';
+
+const isWindows = platform === 'win32';
+
+export async function semverCheck (oldPath, newPath, opts) {
+  const changes = await semverCheckWit(oldPath, newPath, opts);
+  const breaking = changes.filter(({ kind }) => kind === 'breaking');
+  if (opts.json) {
+    console.log(JSON.stringify(changes, null, 2));
+  } else {
+    let output = '';
+    for (const { kind, path, description } of changes)
+      output += kind === 'breaking'
+        ? c`{red.bold breaking}  ${path}: ${description}\n`
+        : c`{green.bold additive}  ${path}: ${description}\n`;
+    if (changes.length === 0)
+      output += 'No changes\n';
+    process.stdout.write(output);
+  }
+  if (breaking.length > 0 && !opts.allowBreaking)
+    throw `${breaking.length} breaking change${breaking.length === 1 ? '' : 's'} found`;
+}
+
+/**
+ * @param {string} witPath
+ */
+async function witInput (witPath) {
+  // Binaries are either components or encoded WIT packages
+  return extname(witPath) === '.wasm'
+    ? { tag: 'binary', val: await readFile(witPath) }
+    : { tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) };
+}
+
+/**
+ * @param {string} oldPath
+ * @param {string} newPath
+ * @param {{
+ *   oldWorld?: string,
+ *   newWorld?: string,
+ *   feature?: string[],
+ *   allFeatures?: boolean,
+ * }} opts
+ * @returns {Promise<import('../../obj/js-component-bindgen-component.js').ApiChange[]>}
+ */
+export async function semverCheckWit (oldPath, newPath, opts = {}) {
+  await $init;
+  let features;
+  if (opts.allFeatures)
+    features = { tag: 'all' };
+  else if (opts.feature?.length)
+    features = { tag: 'list', val: opts.feature };
+  return semverCheckFn({
+    old: await witInput(oldPath),
+    oldWorld: opts.oldWorld,
+    new: await witInput(newPath),
+    newWorld: opts.newWorld,
+    features,
+  });
+}
@@ -1,7 +1,7 @@
 import { $init, generateTypescriptStubs} from '../../obj/js-component-bindgen-component.js';
-import { resolve } from 'node:path';
+import { extname, resolve } from 'node:path';
 import { platform } from 'node:process';
-import { writeFiles } from '../common.js'
+import { readFile, writeFiles } from '../common.js'
 
 const isWindows = platform === 'win32';
 
@@ -15,6 +15,8 @@ export async function stubgen (witPath, opts) {
  * @param {{
  *   worldName?: string,
  *   outDir?: string,
+ *   imports?: boolean,
+ *   map?: string[],
  * }} opts
  * @returns {Promise<{ [filename: string]: Uint8Array }>}
  */
@@ -23,8 +25,14 @@ async function stubgenWit (witPath, opts) {
   let outDir = (opts.outDir ?? '').replace(/\\/g, '/');
   if (!outDir.endsWith('/') && outDir !== '')
     outDir += '/';
+  // Binaries are either components or encoded WIT packages
+  const wit = extname(witPath) === '.wasm'
+    ? { tag: 'binary', val: await readFile(witPath) }
+    : { tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) };
   return Object.fromEntries(generateTypescriptStubs({
-    wit: { tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) },
-    world: opts.worldName
+    wit,
+    world: opts.worldName,
+    imports: opts.imports,
+    map: opts.map ? opts.map.map(mapping => mapping.split('=')) : undefined,
   }).map(([name, file]) => [`${outDir}${name}`, file]));
 }
\ No newline at end of file
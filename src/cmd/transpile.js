@@ -4,7 +4,7 @@ import { mkdir } from 'node:fs/promises';
 import { dirname, extname, basename, resolve } from 'node:path';
 import c from 'chalk-template';
 import { readFile, sizeStr, table, spawnIOTmp, setShowSpinner, getShowSpinner } from '../common.js';
-import { optimizeComponent } from './opt.js';
+import { optimizeComponent, optimizeMultiMemoryModule } from './opt.js';
 import { minify } from 'terser';
 import { fileURLToPath } from 'url';
 import { $init as wasmToolsInit, tools } from "../../obj/wasm-tools.js";
@@ -15,6 +15,8 @@ import { platform } from 'node:process';
 const isWindows = platform === 'win32';
 
 export async function types (witPath, opts) {
+  if (opts.exportMap)
+    opts.exportMap = Object.fromEntries(opts.exportMap.map(mapping => mapping.split('=')));
   const files = await typesComponent(witPath, opts);
   await writeFiles(files, opts.quiet ? false : 'Generated Type Files');
 }
@@ -26,6 +28,7 @@ export async function types (witPath, opts) {
  *   worldName?: string,
  *   instantiation?: 'async' | 'sync',
  *   tlaCompat?: bool,
+ *   exportMap?: Record<string, string>,
  *   outDir?: string,
  *   features?: string[] | 'all',
  * }} opts
@@ -51,10 +54,16 @@ export async function typesComponent (witPath, opts) {
     features = { tag: 'list', val: opts.feature };
   }
 
+  // Binaries are either components or encoded WIT packages
+  const wit = extname(witPath) === '.wasm'
+    ? { tag: 'binary', val: await readFile(witPath) }
+    : { tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) };
+
   return Object.fromEntries(generateTypes(name, {
-    wit: { tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) },
+    wit,
     instantiation,
     tlaCompat: opts.tlaCompat ?? false,
+    exportMap: opts.exportMap ? Object.entries(opts.exportMap) : null,
     world: opts.worldName,
     features,
   }).map(([name, file]) => [`${outDir}${name}`, file]));
@@ -98,7 +107,13 @@ export async function transpile (componentPath, opts, program) {
     opts.name = basename(componentPath.slice(0, -extname(componentPath).length || Infinity));
   if (opts.map)
     opts.map = Object.fromEntries(opts.map.map(mapping => mapping.split('=')));
-  const { files } = await transpileComponent(component, opts);
+  if (opts.importBindingsMap)
+    opts.importBindingsMap = Object.fromEntries(opts.importBindingsMap.map(mapping => mapping.split('=')));
+  if (opts.exportMap)
+    opts.exportMap = Object.fromEntries(opts.exportMap.map(mapping => mapping.split('=')));
+  const { files, warnings } = await transpileComponent(component, opts);
+  for (const warning of warnings)
+    console.error(c`{yellow.bold warning}: ${warning}`);
   await writeFiles(files, opts.quiet ? false : 'Transpiled JS Component Files');
 }
 
@@ -130,7 +145,10 @@ async function wasm2Js (source) {
  *   name: string,
  *   instantiation?: 'async' | 'sync',
  *   importBindings?: 'js' | 'optimized' | 'hybrid' | 'direct-optimized',
+ *   importBindingsMap?: Record<string, 'js' | 'optimized' | 'hybrid' | 'direct-optimized'>,
+ *   exportBindings?: 'js' | 'hybrid',
  *   map?: Record<string, string>,
+ *   exportMap?: Record<string, string>,
  *   validLiftingOptimization?: bool,
  *   tracing?: bool,
  *   nodejsCompat?: bool,
@@ -142,14 +160,19 @@ async function wasm2Js (source) {
  *   namespacedExports?: bool,
  *   outDir?: string,
  *   multiMemory?: bool,
+ *   mergeMemories?: bool,
+ *   detectMultiMemory?: bool,
  *   experimentalIdlImports?: bool,
  *   optArgs?: string[],
  * }} opts
- * @returns {Promise<{ files: { [filename: string]: Uint8Array }, imports: string[], exports: [string, 'function' | 'instance'][] }>}
+ * @returns {Promise<{ files: { [filename: string]: Uint8Array }, imports: string[], exports: [string, 'function' | 'instance'][], warnings: string[] }>}
  */
 export async function transpileComponent (component, opts = {}) {
   await $init;
   if (opts.instantiation) opts.wasiShim = false;
+  // wasm2js doesn't support multi-memory, so there would be nothing to select
+  if (opts.js && opts.detectMultiMemory)
+    throw new Error('--detect-multi-memory cannot be used with --js');
 
   let spinner;
   const showSpinner = getShowSpinner();
@@ -158,6 +181,9 @@ export async function transpileComponent (component, opts = {}) {
     ({ component } = await optimizeComponent(component, opts));
   }
 
+  // Only report unused entries of the map given by the user, not of the WASI shim defaults
+  const userMapEntries = new Set(Object.keys(opts.map ?? {}));
+
   if (opts.wasiShim !== false) {
     opts.map = Object.assign({
       'wasi:cli/*': '@bytecodealliance/preview2-shim/cli#*',
@@ -181,11 +207,14 @@ export async function transpileComponent (component, opts = {}) {
     instantiation = { tag: 'async' };
   }
 
-  let { files, imports, exports } = generate(component, {
+  let { files, imports, exports, warnings } = generate(component, {
     name: opts.name ?? 'component',
     map: Object.entries(opts.map ?? {}),
+    exportMap: opts.exportMap ? Object.entries(opts.exportMap) : null,
     instantiation,
     importBindings: opts.importBindings ? { tag: opts.importBindings } : null,
+    importBindingsMap: opts.importBindingsMap ? Object.entries(opts.importBindingsMap).map(([name, mode]) => [name, { tag: mode }]) : null,
+    exportBindings: opts.exportBindings ? { tag: opts.exportBindings } : null,
     validLiftingOptimization: opts.validLiftingOptimization ?? false,
     tracing: opts.tracing ?? false,
     noNodejsCompat: opts.nodejsCompat === false,
@@ -194,9 +223,20 @@ export async function transpileComponent (component, opts = {}) {
     base64Cutoff: opts.js ? 0 : opts.base64Cutoff ?? 5000,
     noNamespacedExports: opts.namespacedExports === false,
     multiMemory: opts.multiMemory === true,
+    mergeMemories: opts.mergeMemories === true,
+    detectMultiMemory: opts.detectMultiMemory === true,
     idlImports: opts.experimentalIdlImports === true,
   });
 
+  // Core modules using multi-memory are output next to the component, so
+  // weren't optimized with it
+  if (opts.optimize) {
+    files = await Promise.all(files.map(async ([name, source]) => [
+      name,
+      name.endsWith('.multi-memory.wasm') ? await optimizeMultiMemoryModule(source, opts) : source
+    ]));
+  }
+
   let outDir = (opts.outDir ?? '').replace(/\\/g, '/');
   if (!outDir.endsWith('/') && outDir !== '')
     outDir += '/';
@@ -436,7 +476,11 @@ ${autoInstantiate}`;
     }));
   }
 
-  return { files: Object.fromEntries(files), imports, exports };
+  warnings = warnings
+    .filter(({ kind: { tag, val } }) => tag !== 'unused-map-entry' || userMapEntries.has(val))
+    .map(({ message }) => message);
+
+  return { files: Object.fromEntries(files), imports, exports, warnings };
 }
 
 // emscripten asm mangles specifiers to be valid identifiers
@@ -1,7 +1,7 @@
 import { writeFile } from "node:fs/promises";
 import { readFile, isWindows } from '../common.js';
 import { $init, tools } from "../../obj/wasm-tools.js";
-const { print: printFn, parse: parseFn, componentWit: componentWitFn, componentNew: componentNewFn, componentEmbed: componentEmbedFn, metadataAdd: metadataAddFn, metadataShow: metadataShowFn } = tools;
+const { printWithOptions: printWithOptionsFn, parse: parseFn, validate: validateFn, componentWit: componentWitFn, witEncode: witEncodeFn, targets: targetsFn, componentNewWithOptions: componentNewWithOptionsFn, compose: composeFn, componentEmbed: componentEmbedFn, metadataAdd: metadataAddFn, metadataEdit: metadataEditFn, metadataShow: metadataShowFn, inspect: inspectFn, customSectionsList: customSectionsListFn, customSectionExtract: customSectionExtractFn, customSectionsRemove: customSectionsRemoveFn, customSectionInsert: customSectionInsertFn, strip: stripFn } = tools;
 import { resolve, basename, extname } from 'node:path';
 import c from 'chalk-template';
 
@@ -15,7 +15,14 @@ export async function parse(file, opts) {
 export async function print(file, opts) {
   await $init;
   const source = await readFile(file);
-  const output = printFn(source);
+  const output = printWithOptionsFn(source, {
+    foldInstructions: opts.fold,
+    skeleton: opts.skeleton,
+    nameSection: opts.nameSection,
+    nameUnnamed: opts.nameUnnamed,
+    offsets: opts.offsets,
+    path: opts.path ? parsePath(opts.path) : undefined,
+  });
   if (opts.output) {
     await writeFile(opts.output, output);
   } else {
@@ -23,10 +30,31 @@ export async function print(file, opts) {
   }
 }
 
+export async function validate(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const features = opts.features?.flatMap(features => features.split(',')).filter(Boolean);
+  const diagnostics = validateFn(source, features);
+  if (diagnostics.length === 0)
+    return;
+  for (const { message, offset } of diagnostics)
+    console.error(c`{red.bold error}: ${message} {dim (at offset 0x${offset.toString(16)})}`);
+  throw c`{bold ${file}} failed validation with ${diagnostics.length} error${diagnostics.length === 1 ? '' : 's'}`;
+}
+
 export async function componentWit(file, opts) {
   await $init;
   const source = await readFile(file);
-  const output = componentWitFn(source, opts.document);
+  if (opts.document)
+    console.error(c`{yellow.bold warning}: {bold --document} is deprecated and ignored, use {bold --interface} or {bold --world} to print a single item`);
+  if (opts.interface && opts.world)
+    throw new Error('Must select one of --interface or --world');
+  let item;
+  if (opts.interface)
+    item = { tag: 'interface', val: opts.interface };
+  else if (opts.world)
+    item = { tag: 'world', val: opts.world };
+  const output = componentWitFn(source, item);
   if (opts.output) {
     await writeFile(opts.output, output);
   } else {
@@ -34,16 +62,46 @@ export async function componentWit(file, opts) {
   }
 }
 
+export async function witEncode(witPath, opts) {
+  await $init;
+  let features;
+  if (opts.allFeatures)
+    features = { tag: 'all' };
+  else if (opts.feature)
+    features = { tag: 'list', val: opts.feature };
+  const output = witEncodeFn({ tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) }, features);
+  await writeFile(opts.output, output);
+}
+
+export async function targets(file, witPath, opts) {
+  await $init;
+  const source = await readFile(file);
+  let features;
+  if (opts.allFeatures)
+    features = { tag: 'all' };
+  else if (opts.feature)
+    features = { tag: 'list', val: opts.feature };
+  const mismatches = targetsFn(source, { tag: 'path', val: (isWindows ? '//?/' : '') + resolve(witPath) }, opts.world, features);
+  if (mismatches.length === 0)
+    return;
+  for (const { kind, name, message } of mismatches)
+    console.error(c`{red.bold ${kind}} {bold ${name}}: ${message}`);
+  throw c`{bold ${file}} does not target the world, with ${mismatches.length} mismatch${mismatches.length === 1 ? '' : 'es'}`;
+}
+
 export async function componentNew(file, opts) {
   await $init;
   const source = file ? await readFile(file) : null;
   let adapters = [];
-  if (opts.wasiReactor && opts.wasiCommand)
-    throw new Error('Must select one of --wasi-command or --wasi-reactor');
+  let wasiAdapter;
+  if ([opts.wasiReactor, opts.wasiCommand, opts.wasiProxy].filter(Boolean).length > 1)
+    throw new Error('Must select one of --wasi-command, --wasi-reactor or --wasi-proxy');
   if (opts.wasiReactor)
-    adapters = [['wasi_snapshot_preview1', (await readFile(new URL('../../lib/wasi_snapshot_preview1.reactor.wasm', import.meta.url))).buffer]];
+    wasiAdapter = 'reactor';
   else if (opts.wasiCommand)
-    adapters = [['wasi_snapshot_preview1', (await readFile(new URL('../../lib/wasi_snapshot_preview1.command.wasm', import.meta.url))).buffer]];
+    wasiAdapter = 'command';
+  else if (opts.wasiProxy)
+    wasiAdapter = 'proxy';
   if (opts.adapt)
     adapters = adapters.concat(await Promise.all(opts.adapt.map(async adapt => {
       let adapter;
@@ -54,8 +112,59 @@ export async function componentNew(file, opts) {
       adapter[1] = await readFile(adapter[1]);
       return adapter;
     })));
-  const output = componentNewFn(source, adapters);
-  await writeFile(opts.output, output);
+  const { component } = componentNewWithOptionsFn(source, await withWasiAdapter({
+    wasiAdapter,
+    adapters,
+    reallocViaMemoryGrow: opts.reallocViaMemoryGrow,
+    skipValidation: opts.skipValidation,
+  }));
+  await writeFile(opts.output, component);
+}
+
+/**
+ * Add the WASI preview1 adapter of the given kind, shipped in `lib/`, to the
+ * adapters of `componentNewWithOptions`, reading only the selected adapter.
+ * The adapters are read here rather than selected through the `wasiAdapter`
+ * option of the component, since the wasm-tools component shipped with jco
+ * is built without the `wasi-adapters` feature that embeds them.
+ *
+ * @param {{ wasiAdapter?: 'command' | 'reactor' | 'proxy', adapters?: [string, Uint8Array][] }} opts
+ */
+export async function withWasiAdapter ({ wasiAdapter, ...opts }) {
+  if (!wasiAdapter)
+    return opts;
+  if (!['command', 'reactor', 'proxy'].includes(wasiAdapter))
+    throw new Error(`Unknown WASI adapter ${wasiAdapter}, expected command, reactor or proxy`);
+  const adapter = await readFile(new URL(`../../lib/wasi_snapshot_preview1.${wasiAdapter}.wasm`, import.meta.url));
+  return { ...opts, adapters: [['wasi_snapshot_preview1', adapter], ...opts.adapters || []] };
+}
+
+export async function compose(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const dependencies = await Promise.all((opts.dependency || []).map(async dep => {
+    if (!dep.includes('='))
+      throw c`Invalid dependency {bold ${dep}}, expected name=path`;
+    const [name, path] = dep.split('=');
+    return [name, await readFile(path)];
+  }));
+  const wiring = opts.wire?.map(wire => {
+    const match = wire.match(/^(?:([^:=]+):)?([^=]+)=([^#]+)(?:#(.+))?$/);
+    if (!match)
+      throw c`Invalid wiring {bold ${wire}}, expected [target:]import=source[#export]`;
+    const [, target, imprt, source, exprt] = match;
+    return { target, import: imprt, source, export: exprt };
+  });
+  const { component, unsatisfiedImports, wit } = composeFn(source, {
+    dependencies,
+    wiring,
+    defineComponents: !opts.importComponents,
+  });
+  for (const { instance, name, type } of unsatisfiedImports)
+    console.error(c`{yellow.bold warning}: import {bold ${name}} of {bold ${instance}} with type {bold ${type}} is not satisfied by any dependency`);
+  if (opts.wit)
+    await writeFile(opts.wit, wit);
+  await writeFile(opts.output, component);
 }
 
 export async function componentEmbed(file, opts) {
@@ -85,6 +194,47 @@ export async function metadataAdd(file, opts) {
   await writeFile(opts.output, output);
 }
 
+const linkTypes = ['documentation', 'homepage', 'repository', 'funding'];
+
+export async function metadataEdit(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const edit = {
+    name: opts.name,
+    clearProducers: opts.clearProducers,
+    removeProducers: opts.removeProducers?.map(entry => {
+      const [field, name = ''] = entry.split('=');
+      return [field, name];
+    }),
+    addProducers: opts.metadata?.map(meta => {
+      const [field, data = ''] = meta.split('=');
+      const [name, version = ''] = data.split('@');
+      return [field, [[name, version]]];
+    }),
+  };
+  if (opts.author || opts.description || opts.license || opts.link || opts.category) {
+    // registry metadata is replaced as a whole, so update the existing one
+    const [{ registryMetadata = {} }] = metadataShowFn(source);
+    edit.registryMetadata = {
+      ...registryMetadata,
+      authors: opts.author ?? registryMetadata.authors,
+      description: opts.description ?? registryMetadata.description,
+      license: opts.license ?? registryMetadata.license,
+      links: opts.link ? [...registryMetadata.links ?? [], ...opts.link.map(link => {
+        const [type, ...value] = link.split('=');
+        const tag = type.toLowerCase();
+        return {
+          type: linkTypes.includes(tag) ? { tag } : { tag: 'custom', val: type },
+          value: value.join('='),
+        };
+      })] : registryMetadata.links,
+      categories: opts.category ?? registryMetadata.categories,
+    };
+  }
+  const output = metadataEditFn(source, edit);
+  await writeFile(opts.output, output);
+}
+
 export async function metadataShow(file, opts) {
   await $init;
   const source = await readFile(file);
@@ -94,7 +244,7 @@ export async function metadataShow(file, opts) {
     console.log(JSON.stringify(meta, null, 2));
   }
   else {
-    for (const { name, metaType, producers } of meta) {
+    for (const { name, metaType, producers, registryMetadata } of meta) {
       output += '  '.repeat(stack.length - 1);
       const indent = '  '.repeat(stack.length);
       if (metaType.tag === 'component') {
@@ -104,13 +254,28 @@ export async function metadataShow(file, opts) {
       } else {
         output += c`{bold [module${name ? ' ' + name : ''}]}\n`;
       }
-      if (producers.length === 0)
+      if (producers.length === 0 && !registryMetadata)
         output += `${indent}(no metadata)\n`;
       for (const [field, items] of producers) {
         for (const [name, version] of items) {
           output += `${indent}${(field + ':').padEnd(13, ' ')} ${name}${version ? c`{cyan  ${version}}` : ''}\n`;
         }
       }
+      if (registryMetadata) {
+        const { authors, description, license, customLicenses, links, categories } = registryMetadata;
+        for (const author of authors ?? [])
+          output += `${indent}${'author:'.padEnd(13, ' ')} ${author}\n`;
+        if (description)
+          output += `${indent}${'description:'.padEnd(13, ' ')} ${description}\n`;
+        if (license)
+          output += `${indent}${'license:'.padEnd(13, ' ')} ${license}\n`;
+        for (const { id, name } of customLicenses ?? [])
+          output += `${indent}${'license-ref:'.padEnd(13, ' ')} LicenseRef-${id} ${c`{cyan ${name}}`}\n`;
+        for (const { type, value } of links ?? [])
+          output += `${indent}${((type.tag === 'custom' ? type.val : type.tag) + ':').padEnd(13, ' ')} ${value}\n`;
+        if (categories?.length)
+          output += `${indent}${'categories:'.padEnd(13, ' ')} ${categories.join(', ')}\n`;
+      }
       output += '\n';
       if (stack[stack.length - 1] === 0)
         stack.pop();
@@ -119,3 +284,111 @@ export async function metadataShow(file, opts) {
     process.stdout.write(output);
   }
 }
+
+export async function inspect(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const binaries = inspectFn(source);
+  if (opts.json) {
+    console.log(JSON.stringify(binaries, null, 2));
+    return;
+  }
+  let output = '';
+  for (const { path, kind, range: [start, end], imports, exports, instances, canonicalFunctions, sections } of binaries) {
+    const indent = '  '.repeat(path.length);
+    output += c`${indent}{bold [${kind}${path.length ? ' ' + path.join('.') : ''}]} ${end - start} bytes\n`;
+    if (imports.length) {
+      output += `${indent}  imports:\n`;
+      for (const { module, name, kind, ty } of imports)
+        output += c`${indent}    {cyan ${kind}} ${module === undefined ? '' : module + '.'}{bold ${name}}: ${ty}\n`;
+    }
+    if (exports.length) {
+      output += `${indent}  exports:\n`;
+      for (const { name, kind, index, ty } of exports)
+        output += c`${indent}    {cyan ${kind}} {bold ${name}}: ${ty ?? `${kind} ${index}`}\n`;
+    }
+    if (instances.length) {
+      output += `${indent}  instances:\n`;
+      for (const { index, core, instantiate, args } of instances) {
+        const argList = args.map(({ name, kind, index }) => `${name}: ${kind} ${index}`).join(', ');
+        const from = instantiate === undefined ? 'from exports' : `instantiate ${core ? 'core-module' : 'component'} ${instantiate}`;
+        output += c`${indent}    {cyan ${core ? 'core-instance' : 'instance'}} ${index}: ${from} {dim (${argList})}\n`;
+      }
+    }
+    if (canonicalFunctions.length) {
+      output += `${indent}  canonical functions:\n`;
+      for (const { kind, index, target, ty, stringEncoding, memory, realloc, postReturn } of canonicalFunctions) {
+        const options = [
+          stringEncoding,
+          memory === undefined ? null : `memory ${memory}`,
+          realloc === undefined ? null : `realloc ${realloc}`,
+          postReturn === undefined ? null : `post-return ${postReturn}`,
+        ].filter(Boolean).join(', ');
+        output += c`${indent}    {cyan ${kind}} ${target} as ${kind === 'lift' ? 'func' : 'core-func'} ${index}${ty ? ': ' + ty : ''}${options ? ` (${options})` : ''}\n`;
+      }
+    }
+    output += `${indent}  sections:\n`;
+    for (const { name, range: [start, end] } of sections)
+      output += c`${indent}    {bold ${name}} ${end - start} bytes {dim (at offset 0x${start.toString(16)})}\n`;
+  }
+  process.stdout.write(output);
+}
+
+/**
+ * Parse a dotted `--path` option (e.g. `0.2`) of nested module and component indices
+ */
+function parsePath(path) {
+  if (!path)
+    return [];
+  return path.split('.').map(index => {
+    if (!/^\d+$/.test(index))
+      throw c`Invalid path {bold ${path}}, expected dot-separated indices of nested modules and components (e.g. "0.2")`;
+    return Number(index);
+  });
+}
+
+export async function customSectionsList(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const sections = customSectionsListFn(source);
+  if (opts.json) {
+    console.log(JSON.stringify(sections, null, 2));
+    return;
+  }
+  let output = '';
+  for (const { path, name, range: [start, end] } of sections)
+    output += c`${path.length ? path.join('.') : '-'}\t{bold ${name}}\t${end - start} bytes {dim (at offset 0x${start.toString(16)})}\n`;
+  process.stdout.write(output);
+}
+
+export async function customSectionExtract(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const output = customSectionExtractFn(source, parsePath(opts.path), opts.name);
+  await writeFile(opts.output, output);
+}
+
+export async function customSectionsRemove(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const output = customSectionsRemoveFn(source, {
+    names: opts.name,
+    path: opts.path === undefined ? undefined : parsePath(opts.path),
+  });
+  await writeFile(opts.output, output);
+}
+
+export async function customSectionInsert(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const data = await readFile(opts.data);
+  const output = customSectionInsertFn(source, parsePath(opts.path), opts.name, data);
+  await writeFile(opts.output, output);
+}
+
+export async function strip(file, opts) {
+  await $init;
+  const source = await readFile(file);
+  const output = stripFn(source, { all: opts.all, keep: opts.keep });
+  await writeFile(opts.output, output);
+}
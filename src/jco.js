@@ -3,8 +3,9 @@ import { program, Option } from 'commander';
 import { opt } from './cmd/opt.js';
 import { transpile, types } from './cmd/transpile.js';
 import { stubgen } from './cmd/stubgen.js'
+import { semverCheck } from './cmd/semver-check.js';
 import { run as runCmd, serve as serveCmd } from './cmd/run.js';
-import { parse, print, componentNew, componentEmbed, metadataAdd, metadataShow, componentWit } from './cmd/wasm-tools.js';
+import { parse, print, validate, componentNew, compose, componentEmbed, metadataAdd, metadataEdit, metadataShow, inspect, componentWit, witEncode, targets, customSectionsList, customSectionExtract, customSectionsRemove, customSectionInsert, strip } from './cmd/wasm-tools.js';
 import { componentize } from './cmd/componentize.js';
 import c from 'chalk-template';
 
@@ -53,11 +54,14 @@ program.command('transpile')
   .option('--no-typescript', 'do not output TypeScript .d.ts types')
   .option('--valid-lifting-optimization', 'optimize component binary validations assuming all lifted values are valid')
   .addOption(new Option('--import-bindings [mode]', 'bindings mode for imports').choices(['js', 'optimized', 'hybrid', 'direct-optimized']).preset('js'))
+  .option('--import-bindings-map <mappings...>', 'import=mode bindings modes for specific imports, overriding --import-bindings (e.g. wasi:io/*=optimized)')
+  .addOption(new Option('--export-bindings [mode]', 'bindings mode for exports, with hybrid also exposing their core functions under Symbol.for(\'cabiLift\')').choices(['js', 'hybrid']).preset('js'))
   .option('--tracing', 'emit `tracing` calls on function entry/exit')
   .option('-b, --base64-cutoff <bytes>', 'set the byte size under which core Wasm binaries will be inlined as base64', myParseInt)
   .option('--tla-compat', 'enables compatibility for JS environments without top-level await support via an async $init promise export')
   .option('--no-nodejs-compat', 'disables compatibility in Node.js without a fetch global')
   .option('-M, --map <mappings...>', 'specifier=./output custom mappings for the component imports')
+  .option('--export-map <mappings...>', 'export=path mappings of interface exports to nested export namespaces (e.g. wasi:http/*=http.*)')
   .option('--no-wasi-shim', 'disable automatic rewriting of WASI imports to use @bytecodealliance/preview2-shim')
   .option('--stub', 'generate a stub implementation from a WIT file directly')
   .option('--js', 'output JS instead of core WebAssembly')
@@ -65,32 +69,50 @@ program.command('transpile')
   .option('-q, --quiet', 'disable output summary')
   .option('--no-namespaced-exports', 'disable namespaced exports for typescript compatibility')
   .option('--multi-memory', 'optimized output for Wasm multi-memory')
+  .option('--merge-memories', 'without --multi-memory, merge memories defined by core Wasm modules into their first memory where possible')
+  .option('--detect-multi-memory', 'without --multi-memory, also output core Wasm using multi-memory, used when supported at runtime')
   .option('--', 'for --optimize, custom wasm-opt arguments (defaults to best size optimization)')
   .action(asyncAction(transpile));
 
 program.command('stubgen')
   .description("Generate typescript stubs based on a WIT component defintion")
   .usage('<wit-path> -o <out-dir>')
-  .argument('<wit-path>', "Path to WIT definitions")
+  .argument('<wit-path>', "Path to WIT definitions, or a component or WIT package binary")
   .requiredOption('-o, --out-dir <out-dir>', 'output directory')
   .option('-n, --world-name <world>', 'WIT world to generate types for')
+  .option('--imports', 'generate host implementation skeletons for the world imports instead')
+  .option('-M, --map <mappings...>', 'specifier=./output custom mappings for the component imports')
   .option('-q, --quiet', 'disable output summary')
   .action(asyncAction(stubgen))
 
 program.command('types')
   .description('Generate types for the given WIT')
   .usage('<wit-path> -o <out-dir>')
-  .argument('<wit-path>', 'path to a WIT file or directory')
+  .argument('<wit-path>', 'path to a WIT file or directory, or a component or WIT package binary')
   .option('--name <name>', 'custom output name')
   .option('-n, --world-name <world>', 'WIT world to generate types for')
   .requiredOption('-o, --out-dir <out-dir>', 'output directory')
   .option('--tla-compat', 'generates types for the TLA compat output with an async $init promise export')
+  .option('--export-map <mappings...>', 'export=path mappings of interface exports to nested export namespaces (e.g. wasi:http/*=http.*)')
   .addOption(new Option('-I, --instantiation [mode]', 'type output for custom module instantiation').choices(['async', 'sync']).preset('async'))
   .option('-q, --quiet', 'disable output summary')
   .option('--feature <feature>', 'enable one specific WIT feature (repeatable)', collectOptions, [])
   .option('--all-features', 'enable all features')
   .action(asyncAction(types));
 
+program.command('semver-check')
+  .description('Report breaking and additive changes between two versions of a WIT world or component')
+  .usage('<old-path> <new-path>')
+  .argument('<old-path>', 'path to the previous WIT, or a component or WIT package binary')
+  .argument('<new-path>', 'path to the new WIT, or a component or WIT package binary')
+  .option('--old-world <world>', 'WIT world of the previous version to compare')
+  .option('--new-world <world>', 'WIT world of the new version to compare')
+  .option('--feature <feature>', 'enable one specific WIT feature (repeatable)', collectOptions, [])
+  .option('--all-features', 'enable all features')
+  .option('--allow-breaking', 'do not fail when breaking changes are found')
+  .option('--json', 'output changes as JSON')
+  .action(asyncAction(semverCheck));
+
 program.command('run')
   .description('Run a WASI Command component')
   .usage('<command.wasm> <args...>')
@@ -148,23 +170,60 @@ program.command('opt')
 
 program.command('wit')
   .description('extract the WIT from a WebAssembly Component [wasm-tools component wit]')
-  .argument('<component-path>', 'Wasm component binary filepath')
-  .option('-d, --document <name>', 'WIT document of a package to print')
+  .argument('<component-path>', 'Wasm component or binary WIT package filepath')
+  .option('-i, --interface <name>', 'only print the given interface, by name or fully qualified name')
+  .option('-w, --world <name>', 'only print the given world, by name or fully qualified name')
+  .option('-d, --document <name>', 'deprecated and ignored, use --interface or --world instead')
   .option('-o, --output <output-file>', 'WIT output file path')
   .action(asyncAction(componentWit));
 
+program.command('wit-encode')
+  .description('encode a WIT package into a binary WIT package [wasm-tools component wit --wasm]')
+  .argument('<wit-path>', 'path to a WIT file or package directory')
+  .requiredOption('-o, --output <output-file>', 'binary WIT package output filepath')
+  .option('--feature <feature>', 'enable one specific WIT feature (repeatable)', collectOptions, [])
+  .option('--all-features', 'enable all features')
+  .action(asyncAction(witEncode));
+
+program.command('targets')
+  .description('check that a component targets a WIT world, reporting missing exports, extra imports and type mismatches [wasm-tools component targets]')
+  .argument('<component-path>', 'Wasm component binary filepath')
+  .argument('<wit-path>', 'path to a WIT file or package directory')
+  .option('-w, --world <world>', 'WIT world the component should target')
+  .option('--feature <feature>', 'enable one specific WIT feature (repeatable)', collectOptions, [])
+  .option('--all-features', 'enable all features')
+  .action(asyncAction(targets));
+
 program.command('print')
   .description('print the WebAssembly WAT text for a binary file [wasm-tools print]')
   .argument('<input>', 'input file to process')
   .option('-o, --output <output-file>', 'output file path')
+  .option('--fold', 'print instructions in folded form')
+  .option('--skeleton', 'omit function bodies and the contents of data and element segments')
+  .option('--no-name-section', 'ignore the names of the name custom sections')
+  .option('--name-unnamed', 'synthesize names for unnamed items')
+  .option('--offsets', 'annotate each line with its binary offset')
+  .option('-p, --path <path>', 'dot-separated indices of the nested module or component to print (e.g. "0.2")')
   .action(asyncAction(print));
 
+program.command('validate')
+  .description('validate a WebAssembly binary [wasm-tools validate]')
+  .argument('<input>', 'input file to process')
+  .option('-f, --features <features...>', 'comma-separated WebAssembly features to enable, or disable with a "-" prefix, on top of the defaults (e.g. "multi-memory,-simd" or "all")')
+  .action(asyncAction(validate));
+
 program.command('metadata-show')
   .description('extract the producer metadata for a Wasm binary [wasm-tools metadata show]')
   .argument('[module]', 'Wasm component or core module filepath')
   .option('--json', 'output component metadata as JSON')
   .action(asyncAction(metadataShow));
 
+program.command('inspect')
+  .description('describe the imports, exports, types, instances, canonical functions and sections of a Wasm binary and its nested modules and components')
+  .argument('<input>', 'Wasm component or core module filepath')
+  .option('--json', 'output the description as JSON')
+  .action(asyncAction(inspect));
+
 program.command('metadata-add')
   .description('add producer metadata for a Wasm binary [wasm-tools metadata add]')
   .argument('[module]', 'Wasm component or core module filepath')
@@ -172,6 +231,60 @@ program.command('metadata-add')
   .requiredOption('-o, --output <output-file>', 'output binary path')
   .action(asyncAction(metadataAdd));
 
+program.command('metadata-edit')
+  .description('edit the name, producer and registry metadata of a Wasm binary')
+  .argument('[module]', 'Wasm component or core module filepath')
+  .option('--name <name>', 'set the name of the module or component')
+  .option('--clear-producers', 'remove all existing producer metadata')
+  .option('--remove-producers <entries...>', 'field=name producer metadata entries to remove')
+  .option('-m, --metadata <metadata...>', 'field=name[@version] producer metadata to add, replacing the version of existing entries')
+  .option('--author <authors...>', 'set the authors of the registry metadata')
+  .option('--description <description>', 'set the description of the registry metadata')
+  .option('--license <license>', 'set the SPDX license expression of the registry metadata')
+  .option('--link <links...>', 'type=url links to add to the registry metadata, where type is documentation, homepage, repository, funding or a custom type')
+  .option('--category <categories...>', 'set the categories of the registry metadata')
+  .requiredOption('-o, --output <output-file>', 'output binary path')
+  .action(asyncAction(metadataEdit));
+
+program.command('custom-section-list')
+  .description('list the custom sections of a Wasm binary and its nested modules and components')
+  .argument('<input>', 'Wasm component or core module filepath')
+  .option('--json', 'output custom sections as JSON')
+  .action(asyncAction(customSectionsList));
+
+program.command('custom-section-extract')
+  .description('extract the contents of a custom section of a Wasm binary')
+  .argument('<input>', 'Wasm component or core module filepath')
+  .requiredOption('-n, --name <name>', 'custom section name')
+  .option('-p, --path <path>', 'dot-separated indices of the nested module or component containing the section (e.g. "0.2")')
+  .requiredOption('-o, --output <output-file>', 'output file path')
+  .action(asyncAction(customSectionExtract));
+
+program.command('custom-section-remove')
+  .description('remove custom sections from a Wasm binary')
+  .argument('<input>', 'Wasm component or core module filepath')
+  .requiredOption('-n, --name <names...>', 'custom section names to remove, where a trailing "*" matches any suffix (e.g. ".debug_*")')
+  .option('-p, --path <path>', 'only remove sections of the nested module or component at this path, rather than at any level')
+  .requiredOption('-o, --output <output-file>', 'output binary path')
+  .action(asyncAction(customSectionsRemove));
+
+program.command('custom-section-add')
+  .description('append a custom section to a Wasm binary or one of its nested modules and components')
+  .argument('<input>', 'Wasm component or core module filepath')
+  .requiredOption('-n, --name <name>', 'custom section name')
+  .requiredOption('-d, --data <data-file>', 'file containing the custom section contents')
+  .option('-p, --path <path>', 'dot-separated indices of the nested module or component to add the section to (e.g. "0.2")')
+  .requiredOption('-o, --output <output-file>', 'output binary path')
+  .action(asyncAction(customSectionInsert));
+
+program.command('strip')
+  .description('remove debug information and non-essential custom sections from a Wasm binary [wasm-tools strip]')
+  .argument('<input>', 'Wasm component or core module filepath')
+  .option('-a, --all', 'remove all custom sections')
+  .option('-k, --keep <names...>', 'custom section names to keep, where a trailing "*" matches any suffix')
+  .requiredOption('-o, --output <output-file>', 'output binary path')
+  .action(asyncAction(strip));
+
 program.command('parse')
   .description('parses the Wasm text format into a binary file [wasm-tools parse]')
   .argument('<input>', 'input file to process')
@@ -186,8 +299,21 @@ program.command('new')
   .option('--adapt <[NAME=]adapter...>', 'component adapters to apply')
   .option('--wasi-reactor', 'build with the WASI Reactor adapter')
   .option('--wasi-command', 'build with the WASI Command adapter')
+  .option('--wasi-proxy', 'build with the WASI Proxy adapter')
+  .option('--realloc-via-memory-grow', 'implement realloc for lowered imports by growing memory, for modules without a cabi_realloc export')
+  .option('--skip-validation', 'skip validation of the output component')
   .action(asyncAction(componentNew));
 
+program.command('compose')
+  .description('compose a WebAssembly component with dependency components satisfying its imports [wasm-tools compose]')
+  .argument('<component>', 'root Wasm component filepath')
+  .requiredOption('-o, --output <output-file>', 'composed Wasm component output filepath')
+  .option('-d, --dependency <name=path...>', 'named dependency components, whose exports satisfy imports of the same name')
+  .option('-w, --wire <[target:]import=source[#export]...>', 'explicitly connect an import of the root component (or of the target dependency) to a dependency or one of its exports')
+  .option('--import-components', 'import the dependency components rather than defining them in the composed component')
+  .option('--wit <wit-file>', 'write the WIT of the composed component, including its unsatisfied imports, to a file')
+  .action(asyncAction(compose));
+
 program.command('embed')
   .description('embed the component typing section into a core Wasm module [wasm-tools component embed]')
   .argument('[core-module]', 'Wasm core module filepath')
@@ -214,6 +340,8 @@ function asyncAction (cmd) {
         process.stdout.write(`(jco ${cmd.name}) `);
         if (typeof e === 'string') {
           console.error(c`{red.bold Error}: ${e}\n`);
+        } else if (e?.payload?.kind) {
+          console.error(c`{red.bold Error}: ${formatComponentError(e.payload)}\n`);
         } else {
           console.error(e);
         }
@@ -222,3 +350,15 @@ function asyncAction (cmd) {
     })();
   };
 }
+
+/**
+ * Formats a structured error returned by the bindgen or wasm-tools components
+ */
+function formatComponentError ({ message, context, span }) {
+  let output = message;
+  if (span && !message.includes(`--> ${span.file}:`))
+    output += `\n     --> ${span.file}:${span.line}:${span.column}`;
+  if (context.length)
+    output += `\n\nCaused by:\n${context.map(cause => `    ${cause.replace(/\n/g, '\n    ')}`).join('\n')}`;
+  return output;
+}
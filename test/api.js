@@ -1,4 +1,4 @@
-import { deepStrictEqual, ok, strictEqual } from "node:assert";
+import { deepStrictEqual, ok, rejects, strictEqual } from "node:assert";
 import { readFile } from "node:fs/promises";
 import {
   transpile,
@@ -7,9 +7,27 @@ import {
   print,
   parse,
   componentNew,
+  componentNewWithOptions,
+  componentWit,
+  witEncode,
+  witDecode,
+  targets,
+  compose,
   componentEmbed,
+  metadataAdd,
+  metadataEdit,
   metadataShow,
+  inspect,
+  validate,
+  customSectionsList,
+  customSectionExtract,
+  customSectionsRemove,
+  customSectionInsert,
+  strip,
+  semverCheck,
+  preview1AdapterCommandPath,
   preview1AdapterReactorPath,
+  preview1AdapterProxyPath,
 } from "../src/api.js";
 import { fileURLToPath } from "node:url";
 import { platform } from "node:process";
@@ -75,6 +93,33 @@ export async function apiTest(fixtures) {
       for (let i = 0; i < 2; i++) ok(source.includes(exports[i][0]));
     });
 
+    test("Transpile detecting multi-memory", async () => {
+      const name = "multi-memory";
+      const component = await parse(
+        await readFile(`test/fixtures/components/${name}.component.wat`, "utf8")
+      );
+      const { files } = await transpile(component, {
+        name,
+        detectMultiMemory: true,
+        optimize: true,
+      });
+      // Only the polyfilling core module is inlined, the one using
+      // multi-memory being fetched when used, falling back to the inlined
+      // one when it can't be fetched
+      ok(!files[name + ".core.wasm"]);
+      const multiMemory = files[name + ".core.multi-memory.wasm"];
+      deepStrictEqual(await validate(multiMemory), []);
+      strictEqual((await validate(multiMemory, ["-multi-memory"])).length, 1);
+      const source = Buffer.from(files[name + ".js"]).toString();
+      ok(source.includes("base64Compile("));
+      ok(source.includes(`(supportsMultiMemory ? fetchCompile(new URL('./${name}.core.multi-memory.wasm', import.meta.url)) : Promise.reject()).catch(() => base64Compile(`));
+
+      await rejects(
+        transpile(component, { name, detectMultiMemory: true, js: true }),
+        /--detect-multi-memory cannot be used with --js/
+      );
+    });
+
     test("Transpile map into package imports", async () => {
       const name = "flavorful";
       const component = await readFile(
@@ -101,6 +146,14 @@ export async function apiTest(fixtures) {
      ok(Buffer.from(files[Object.keys(files)[0]]).includes('export const test'));
     });
 
+    test("Semver check", async () => {
+      const changes = await semverCheck("test/fixtures/wit", "test/fixtures/wit", {
+        oldWorld: "test:flavorful/flavorful",
+        newWorld: "test:flavorful/flavorful",
+      });
+      deepStrictEqual(changes, []);
+    });
+
     test("Optimize", async () => {
       const component = await readFile(
         `test/fixtures/components/flavorful.component.wasm`
@@ -120,6 +173,175 @@ export async function apiTest(fixtures) {
       ok(componentParsed);
     });
 
+    test("Print options", async () => {
+      const component = await readFile(
+        `test/fixtures/components/flavorful.component.wasm`
+      );
+      const full = await print(component);
+      const skeleton = await print(component, { skeleton: true });
+      ok(skeleton.length < full.length);
+
+      const module = await print(component, { path: [0] });
+      const folded = await print(component, { path: [0], foldInstructions: true });
+      strictEqual(folded.slice(0, 7), "(module");
+      ok(folded !== module);
+      deepStrictEqual(await parse(folded), await parse(module));
+
+      const offsets = await print(component, { offsets: true });
+      ok(offsets.includes("(;@"));
+    });
+
+    test("Validate", async () => {
+      const component = await readFile(
+        `test/fixtures/components/flavorful.component.wasm`
+      );
+      deepStrictEqual(await validate(component), []);
+
+      const invalid = await parse(
+        "(module (func (result i32) i64.const 1) (func (result i32) i32.const 1) (func (result i64) i32.const 1))"
+      );
+      const diagnostics = await validate(invalid);
+      strictEqual(diagnostics.length, 2);
+      ok(diagnostics[0].message.includes("type mismatch"));
+      ok(diagnostics[0].offset < diagnostics[1].offset);
+
+      const memories = await parse("(module (memory 1) (memory 1))");
+      deepStrictEqual(await validate(memories), []);
+      strictEqual((await validate(memories, ["-multi-memory"])).length, 1);
+    });
+
+    test("Custom sections", async () => {
+      const encoder = new TextEncoder();
+      let component = await parse(
+        "(component (core module) (component (core module)))"
+      );
+      component = await customSectionInsert(component, [0], "name", new Uint8Array([0]));
+      component = await customSectionInsert(component, [0], ".debug_info", encoder.encode("debug"));
+      component = await customSectionInsert(component, [1, 0], "component-type:x", encoder.encode("types"));
+      component = await customSectionInsert(component, [], "extra", encoder.encode("data"));
+      deepStrictEqual(await validate(component), []);
+      deepStrictEqual(
+        (await customSectionsList(component)).map(({ path, name }) => [path, name]),
+        [
+          [[0], "name"],
+          [[0], ".debug_info"],
+          [[1, 0], "component-type:x"],
+          [[], "extra"],
+        ]
+      );
+      strictEqual(
+        Buffer.from(await customSectionExtract(component, [0], ".debug_info")).toString(),
+        "debug"
+      );
+
+      const removed = await customSectionsRemove(component, { names: [".debug_*", "extra"] });
+      deepStrictEqual(
+        (await customSectionsList(removed)).map(({ name }) => name),
+        ["name", "component-type:x"]
+      );
+
+      const inserted = await customSectionInsert(removed, [1, 0], "added", encoder.encode("new"));
+      deepStrictEqual(await validate(inserted), []);
+      strictEqual(
+        Buffer.from(await customSectionExtract(inserted, [1, 0], "added")).toString(),
+        "new"
+      );
+
+      const stripped = await strip(inserted);
+      deepStrictEqual(
+        (await customSectionsList(stripped)).map(({ name }) => name),
+        ["component-type:x"]
+      );
+      deepStrictEqual(await customSectionsList(await strip(inserted, { all: true })), []);
+
+      const flavorful = await readFile(
+        `test/fixtures/components/flavorful.component.wasm`
+      );
+      const strippedFlavorful = await strip(flavorful);
+      ok(strippedFlavorful.byteLength < flavorful.byteLength);
+      deepStrictEqual(await validate(strippedFlavorful), []);
+    });
+
+    test("WIT packages", async () => {
+      const wit = `
+        package test:pkg@1.0.0;
+
+        interface types {
+          type id = u32;
+        }
+
+        interface api {
+          use types.{id};
+          lookup: func(id: id) -> string;
+        }
+
+        world app {
+          export api;
+        }
+      `;
+      const binary = await witEncode({ tag: "source", val: wit });
+      const [[name, decoded]] = await witDecode(binary);
+      strictEqual(name, "test:pkg@1.0.0");
+      ok(decoded.includes("lookup: func(id: id) -> string;"));
+      ok(decoded.includes("world app {"));
+
+      const api = await componentWit(binary, { tag: "interface", val: "api" });
+      ok(api.includes("interface api {"));
+      ok(!api.includes("interface types {"));
+      ok(!api.includes("world app {"));
+
+      const app = await componentWit(binary, {
+        tag: "world",
+        val: "test:pkg/app@1.0.0",
+      });
+      ok(app.includes("world app {"));
+      ok(!app.includes("interface api {"));
+    });
+
+    test("Compose", async () => {
+      const root = await parse(`(component
+        (import "test:compose/answer" (instance $answer
+          (export "get" (func (result u32)))
+        ))
+        (alias export $answer "get" (func $get))
+        (export "get-answer" (func $get))
+      )`);
+      const dep = await parse(`(component
+        (core module $m (func (export "get") (result i32) i32.const 42))
+        (core instance $i (instantiate $m))
+        (func $get (result u32) (canon lift (core func $i "get")))
+        (instance $answer (export "get" (func $get)))
+        (export "test:compose/answer" (instance $answer))
+      )`);
+
+      const { component, unsatisfiedImports } = await compose(root, {
+        dependencies: [["answer", dep]],
+      });
+      deepStrictEqual(unsatisfiedImports, []);
+      deepStrictEqual(await validate(component), []);
+
+      const unsatisfied = await compose(root, { dependencies: [] });
+      deepStrictEqual(unsatisfied.unsatisfiedImports, [
+        {
+          instance: "root",
+          name: "test:compose/answer",
+          type: "interface { get: func() -> u32; }",
+        },
+      ]);
+      ok(unsatisfied.wit.includes("get: func() -> u32"));
+
+      const greet = await parse(`(component
+        (import "greet" (func (param "name" string) (result string)))
+      )`);
+      deepStrictEqual((await compose(greet, { dependencies: [] })).unsatisfiedImports, [
+        {
+          instance: "root",
+          name: "greet",
+          type: "func(name: string) -> string",
+        },
+      ]);
+    });
+
     test("Wit & New", async () => {
       const wit = await readFile(
         `test/fixtures/wit/deps/flavorful/flavorful.wit`,
@@ -162,6 +384,118 @@ export async function apiTest(fixtures) {
       ]);
     });
 
+    test("Targets", async () => {
+      const wit = `
+        package test:plugin;
+
+        interface host {
+          log: func(msg: string);
+        }
+
+        interface plugin {
+          run: func(input: u32) -> u32;
+        }
+
+        world plugin-world {
+          import host;
+          export plugin;
+        }
+      `;
+      const component = await componentNew(
+        await componentEmbed({ witSource: wit, dummy: true })
+      );
+
+      deepStrictEqual(
+        await targets(component, { tag: "source", val: wit }, "plugin-world"),
+        []
+      );
+
+      const mismatches = await targets(
+        component,
+        {
+          tag: "source",
+          val: `
+            package test:plugin;
+
+            interface plugin {
+              run: func(input: u64) -> u32;
+            }
+
+            interface admin {
+              stop: func();
+            }
+
+            world plugin-world {
+              export plugin;
+              export admin;
+            }
+          `,
+        },
+        "plugin-world"
+      );
+      deepStrictEqual(
+        mismatches.map(({ kind, name }) => [kind, name]),
+        [
+          ["type-mismatch", "test:plugin/plugin"],
+          ["missing-export", "test:plugin/admin"],
+          ["extra-import", "test:plugin/host"],
+        ]
+      );
+    });
+
+    test("Inspect", async () => {
+      const component = await componentNew(
+        await componentEmbed({
+          witSource: `
+            package test:inspect;
+
+            world inspected {
+              import log: func(msg: string);
+              export greet: func(name: string) -> string;
+            }
+          `,
+          dummy: true,
+        })
+      );
+
+      const [root, ...nested] = await inspect(component);
+      strictEqual(root.kind, "component");
+      deepStrictEqual(root.path, []);
+      deepStrictEqual(root.range, [0, component.byteLength]);
+      ok(nested.some(({ kind }) => kind === "module"));
+
+      deepStrictEqual(
+        root.imports.map(({ name, kind, ty }) => [name, kind, ty]),
+        [["log", "func", "func(msg: string)"]]
+      );
+      const greet = root.exports.find(({ name }) => name === "greet");
+      strictEqual(greet.kind, "func");
+      strictEqual(greet.ty, "func(name: string) -> string");
+
+      const lift = root.canonicalFunctions.find(({ kind }) => kind === "lift");
+      strictEqual(lift.stringEncoding, "utf8");
+      ok(lift.memory !== undefined);
+      ok(lift.realloc !== undefined);
+      ok(root.canonicalFunctions.some(({ kind }) => kind === "lower"));
+      ok(root.sections.some(({ name }) => name === "core-module"));
+    });
+
+    test("Structured WIT errors", async () => {
+      let err;
+      try {
+        await componentEmbed({
+          witSource: "package test:err;\nworld test {\n  import missing;\n}\n",
+          dummy: true,
+        });
+      } catch (e) {
+        err = e;
+      }
+      ok(err, "expected embedding to fail");
+      strictEqual(err.payload.kind, "wit");
+      ok(err.payload.message.includes("missing"));
+      strictEqual(err.payload.span.line, 3);
+    });
+
     test("Multi-file WIT", async () => {
       const generatedComponent = await componentEmbed({
         dummy: true,
@@ -203,6 +537,37 @@ export async function apiTest(fixtures) {
       ]);
     });
 
+    test("In-memory WIT files", async () => {
+      const flavorful = await readFile(
+        `test/fixtures/wit/deps/flavorful/flavorful.wit`
+      );
+      const root = `
+        package test:in-memory;
+
+        world app {
+          export test:flavorful/test;
+        }
+      `;
+
+      const generatedComponent = await componentEmbed({
+        dummy: true,
+        witFiles: [
+          ["app.wit", new TextEncoder().encode(root)],
+          ["deps/flavorful/flavorful.wit", flavorful],
+        ],
+      });
+      {
+        const output = await print(generatedComponent);
+        strictEqual(output.slice(0, 7), "(module");
+      }
+
+      const newComponent = await componentNew(generatedComponent);
+      {
+        const output = await print(newComponent);
+        strictEqual(output.slice(0, 10), "(component");
+      }
+    });
+
     test("Component new adapt", async () => {
       const component = await readFile(`test/fixtures/modules/exitcode.wasm`);
 
@@ -216,6 +581,36 @@ export async function apiTest(fixtures) {
       await print(generatedComponent);
     });
 
+    test("Component new with bundled adapter", async () => {
+      const component = await readFile(`test/fixtures/modules/exitcode.wasm`);
+
+      const { component: generatedComponent, adapterImports } =
+        await componentNewWithOptions(component, { wasiAdapter: "reactor" });
+      strictEqual((await print(generatedComponent)).slice(0, 10), "(component");
+
+      strictEqual(adapterImports.length, 1);
+      const [name, imports] = adapterImports[0];
+      strictEqual(name, "wasi_snapshot_preview1");
+      ok(imports.includes("proc_exit"));
+
+      // The proxy adapter does not provide `proc_exit`
+      await rejects(componentNewWithOptions(component, { wasiAdapter: "proxy" }));
+    });
+
+    test("Bundled adapters target the same WASI release", async () => {
+      const latest = [];
+      for (const path of [
+        preview1AdapterCommandPath(),
+        preview1AdapterReactorPath(),
+        preview1AdapterProxyPath(),
+      ]) {
+        const wat = await print(await readFile(path));
+        const versions = [...wat.matchAll(/\(import "wasi:[^"@]+@([^"]+)"/g)].map(([, version]) => version);
+        latest.push(versions.sort().at(-1));
+      }
+      deepStrictEqual(latest, ["0.2.3", "0.2.3", "0.2.3"]);
+    });
+
     test("Extract metadata", async () => {
       const component = await readFile(`test/fixtures/modules/exitcode.wasm`);
 
@@ -225,10 +620,45 @@ export async function apiTest(fixtures) {
         {
           metaType: { tag: "module" },
           producers: [],
+          registryMetadata: undefined,
           name: undefined,
           range: [0, 262],
         },
       ]);
     });
+
+    test("Edit metadata", async () => {
+      const component = await readFile(`test/fixtures/modules/exitcode.wasm`);
+
+      const added = await metadataAdd(component, [
+        ["language", [["javascript", ""]]],
+        ["processed-by", [["dummy-gen", "test"]]],
+      ]);
+      const edited = await metadataEdit(added, {
+        name: "exitcode",
+        removeProducers: [["processed-by", "dummy-gen"]],
+        addProducers: [["sdk", [["jco", "1.0.0"]]]],
+        registryMetadata: {
+          license: "Apache-2.0",
+          links: [
+            { type: { tag: "repository" }, value: "https://github.com/bytecodealliance/jco" },
+          ],
+        },
+      });
+
+      const [meta] = await metadataShow(edited);
+      strictEqual(meta.name, "exitcode");
+      deepStrictEqual(meta.producers, [
+        ["language", [["javascript", ""]]],
+        ["sdk", [["jco", "1.0.0"]]],
+      ]);
+      strictEqual(meta.registryMetadata.license, "Apache-2.0");
+      deepStrictEqual(meta.registryMetadata.links, [
+        { type: { tag: "repository" }, value: "https://github.com/bytecodealliance/jco" },
+      ]);
+
+      const cleared = await metadataEdit(edited, { clearProducers: true });
+      deepStrictEqual((await metadataShow(cleared))[0].producers, []);
+    });
   });
 }
@@ -172,6 +172,22 @@ export async function cliTest(fixtures) {
       );
     });
 
+    test("Transpile map warnings", async () => {
+      const name = "flavorful";
+      const { stderr } = await exec(
+        jcoPath,
+        "transpile",
+        `test/fixtures/components/${name}.component.wasm`,
+        "--name",
+        name,
+        "--map",
+        "test:unknown/*=./unknown.js#*",
+        "-o",
+        outDir
+      );
+      ok(stderr.includes("map entry `test:unknown/*` does not match any import"));
+    });
+
     test("Type generation", async () => {
       const { stderr } = await exec(
         jcoPath,
@@ -187,6 +203,21 @@ export async function cliTest(fixtures) {
       ok(source.includes("export const test"));
     });
 
+    test("Type generation from component binary", async () => {
+      const { stderr } = await exec(
+        jcoPath,
+        "types",
+        "test/fixtures/components/flavorful.component.wasm",
+        "--name",
+        "flavorful",
+        "-o",
+        outDir
+      );
+      strictEqual(stderr, "");
+      const source = await readFile(`${outDir}/flavorful.d.ts`, "utf8");
+      ok(source.includes("export const test"));
+    });
+
     test("Type generation (specific features)", async () => {
       const { stderr, stdout } = await exec(
         jcoPath,
@@ -400,6 +431,18 @@ export async function cliTest(fixtures) {
       ok(source.includes("class PString$1{"));
     });
 
+    test("Wit deprecated document option", async () => {
+      const { stderr, stdout } = await exec(
+        jcoPath,
+        "wit",
+        `test/fixtures/components/flavorful.component.wasm`,
+        "-d",
+        "flavorful"
+      );
+      ok(stderr.includes("--document is deprecated"));
+      ok(stdout.includes("world root {"));
+    });
+
     test("Wit & New", async () => {
       const { stderr, stdout } = await exec(
         jcoPath,
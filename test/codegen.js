@@ -66,4 +66,65 @@ export async function codegenTest (fixtures) {
       ok(bindingsSource.includes('Thing: Thing$1'));
     });
   });
+
+  suite(`Lowering factories`, () => {
+    test(`Resources across imports`, async () => {
+      await $init;
+      const files = generateLowerBindings({
+        wit: {
+          tag: 'source',
+          val: `
+            package test:lower-rsc;
+
+            world test {
+              import types;
+              import store;
+            }
+
+            interface types {
+              resource blob {
+                constructor();
+                is-stored: func() -> bool;
+              }
+            }
+
+            interface store {
+              use types.{blob};
+              get: func() -> blob;
+            }
+          `,
+        },
+      });
+
+      const tmpDir = await getTmpDir();
+      try {
+        for (const [name, source] of files) {
+          await mkdir(dirname(resolve(tmpDir, name)), { recursive: true });
+          await writeFile(resolve(tmpDir, name), source);
+        }
+        const types = await import(pathToFileURL(resolve(tmpDir, 'lower/test-lower-rsc-types.js')));
+        const store = await import(pathToFileURL(resolve(tmpDir, 'lower/test-lower-rsc-store.js')));
+
+        let stored;
+        class Blob {
+          isStored () {
+            return this === stored;
+          }
+        }
+        stored = new Blob();
+        types.cabiLower({ Blob });
+        const { get } = store.cabiLower({ get: () => stored });
+
+        // The blob lowered by the store import is lifted from the capture
+        // table of the types import
+        const cabiLower = Symbol.for('cabiLower');
+        const handleTable = [1 << 30, 0];
+        const handle = get[cabiLower]({ resourceTables: [handleTable] })();
+        const isStored = Blob.prototype.isStored[cabiLower]({ resourceTables: [handleTable] });
+        strictEqual(isStored(handle), 1);
+      } finally {
+        await rm(tmpDir, { recursive: true });
+      }
+    });
+  });
 }
@@ -0,0 +1,231 @@
+;; Core module using multiple memories, transpiled without multi-memory:
+;;
+;; * memory 0 is the module memory, with a maximum so that memory 1 can be
+;;   merged into it with --merge-memories,
+;; * memory 1 has a maximum, and is merged into memory 0 with
+;;   --merge-memories,
+;; * memory 2 has no maximum, so it always lives in JS, including when
+;;   copying to and from memory 0.
+(component
+  (core module $m
+    (memory $m0 1 2)
+    (memory $m1 1 2)
+    (memory $m2 1)
+
+    (data (memory $m1) (i32.const 16) "\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10")
+    (data (memory $m2) (i32.const 0) "js")
+    (data $hello "hello")
+    (data (memory $m0) (i32.const 32) "m0")
+
+    ;; The start function runs once data segments of all memories are applied
+    (global $started (mut i32) (i32.const 0))
+    (func $start
+      (global.set $started (i32.load $m1 (i32.const 16))))
+    (start $start)
+    (func (export "started") (result i32)
+      (global.get $started))
+
+    ;; Loads and stores of every width
+    (func (export "i32-load") (param i32) (result i32)
+      (i32.load $m1 (local.get 0)))
+    (func (export "i32-load8-s") (param i32) (result i32)
+      (i32.load8_s $m1 (local.get 0)))
+    (func (export "i32-load8-u") (param i32) (result i32)
+      (i32.load8_u $m1 (local.get 0)))
+    (func (export "i32-load16-s") (param i32) (result i32)
+      (i32.load16_s $m1 (local.get 0)))
+    (func (export "i32-load16-u") (param i32) (result i32)
+      (i32.load16_u $m1 (local.get 0)))
+    (func (export "i64-load") (param i32) (result i64)
+      (i64.load $m1 offset=8 (local.get 0)))
+    (func (export "i64-load8-s") (param i32) (result i64)
+      (i64.load8_s $m1 (local.get 0)))
+    (func (export "i64-load8-u") (param i32) (result i64)
+      (i64.load8_u $m1 (local.get 0)))
+    (func (export "i64-load16-s") (param i32) (result i64)
+      (i64.load16_s $m1 (local.get 0)))
+    (func (export "i64-load16-u") (param i32) (result i64)
+      (i64.load16_u $m1 (local.get 0)))
+    (func (export "i64-load32-s") (param i32) (result i64)
+      (i64.load32_s $m1 (local.get 0)))
+    (func (export "i64-load32-u") (param i32) (result i64)
+      (i64.load32_u $m1 (local.get 0)))
+    (func (export "f32-load") (param i32) (result f32)
+      (f32.load $m1 (local.get 0)))
+    (func (export "f64-load") (param i32) (result f64)
+      (f64.load $m1 (local.get 0)))
+    (func (export "i32-store") (param i32 i32)
+      (i32.store $m1 (local.get 0) (local.get 1)))
+    (func (export "i32-store8") (param i32 i32)
+      (i32.store8 $m1 (local.get 0) (local.get 1)))
+    (func (export "i32-store16") (param i32 i32)
+      (i32.store16 $m1 (local.get 0) (local.get 1)))
+    (func (export "i64-store") (param i32 i64)
+      (i64.store $m1 offset=8 (local.get 0) (local.get 1)))
+    (func (export "i64-store8") (param i32 i64)
+      (i64.store8 $m1 (local.get 0) (local.get 1)))
+    (func (export "i64-store16") (param i32 i64)
+      (i64.store16 $m1 (local.get 0) (local.get 1)))
+    (func (export "i64-store32") (param i32 i64)
+      (i64.store32 $m1 (local.get 0) (local.get 1)))
+    (func (export "f32-store") (param i32 f32)
+      (f32.store $m1 (local.get 0) (local.get 1)))
+    (func (export "f64-store") (param i32 f64)
+      (f64.store $m1 (local.get 0) (local.get 1)))
+
+    ;; Sizes of memories
+    (func (export "size-mem0") (result i32)
+      (memory.size $m0))
+    (func (export "grow-mem0") (param i32) (result i32)
+      (memory.grow $m0 (local.get 0)))
+    (func (export "size-mem1") (result i32)
+      (memory.size $m1))
+    (func (export "grow-mem1") (param i32) (result i32)
+      (memory.grow $m1 (local.get 0)))
+
+    ;; Bulk memory instructions within and across memories
+    (func (export "load-mem0") (param i32) (result i32)
+      (i32.load8_u $m0 (local.get 0)))
+    (func (export "load-mem2") (param i32) (result i32)
+      (i32.load8_u $m2 (local.get 0)))
+    (func (export "fill-mem0") (param i32 i32 i32)
+      (memory.fill $m0 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "fill-mem1") (param i32 i32 i32)
+      (memory.fill $m1 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy-mem0-to-mem0") (param i32 i32 i32)
+      (memory.copy $m0 $m0 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy-mem0-to-mem1") (param i32 i32 i32)
+      (memory.copy $m1 $m0 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy-mem1-to-mem0") (param i32 i32 i32)
+      (memory.copy $m0 $m1 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy-mem1-to-mem1") (param i32 i32 i32)
+      (memory.copy $m1 $m1 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy-mem0-to-mem2") (param i32 i32 i32)
+      (memory.copy $m2 $m0 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "copy-mem2-to-mem0") (param i32 i32 i32)
+      (memory.copy $m0 $m2 (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "init-mem0") (param i32 i32 i32)
+      (memory.init $m0 $hello (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "init-mem1") (param i32 i32 i32)
+      (memory.init $m1 $hello (local.get 0) (local.get 1) (local.get 2)))
+    (func (export "drop-hello")
+      (data.drop $hello))
+
+    ;; SIMD loads and stores, split into scalar loads and stores
+    (func (export "v128-copy") (param i32 i32)
+      (v128.store $m1 (local.get 0) (v128.load $m1 (local.get 1))))
+    (func (export "v128-load8x8-u") (param i32) (result i32)
+      (i16x8.extract_lane_u 3 (v128.load8x8_u $m1 (local.get 0))))
+    (func (export "v128-load32-splat") (param i32) (result i32)
+      (i32x4.extract_lane 3 (v128.load32_splat $m1 (local.get 0))))
+    (func (export "v128-load64-zero") (param i32) (result i64)
+      (i64.add
+        (i64x2.extract_lane 0 (v128.load64_zero $m1 (local.get 0)))
+        (i64x2.extract_lane 1 (v128.load64_zero $m1 (local.get 0)))))
+    (func (export "v128-load32-lane") (param i32) (result i32)
+      (i32x4.extract_lane 1
+        (v128.load32_lane $m1 1 (local.get 0) (v128.const i32x4 0 0 0 0))))
+    (func (export "v128-store16-lane") (param i32)
+      (v128.store16_lane $m1 5 (local.get 0) (v128.const i16x8 0 1 2 3 4 0x1234 6 7)))
+  )
+  (core instance $i (instantiate $m))
+
+  (func (export "started") (result u32)
+    (canon lift (core func $i "started")))
+
+  (func (export "i32-load") (param "addr" u32) (result u32)
+    (canon lift (core func $i "i32-load")))
+  (func (export "i32-load8-s") (param "addr" u32) (result s32)
+    (canon lift (core func $i "i32-load8-s")))
+  (func (export "i32-load8-u") (param "addr" u32) (result u32)
+    (canon lift (core func $i "i32-load8-u")))
+  (func (export "i32-load16-s") (param "addr" u32) (result s32)
+    (canon lift (core func $i "i32-load16-s")))
+  (func (export "i32-load16-u") (param "addr" u32) (result u32)
+    (canon lift (core func $i "i32-load16-u")))
+  (func (export "i64-load") (param "addr" u32) (result u64)
+    (canon lift (core func $i "i64-load")))
+  (func (export "i64-load8-s") (param "addr" u32) (result s64)
+    (canon lift (core func $i "i64-load8-s")))
+  (func (export "i64-load8-u") (param "addr" u32) (result u64)
+    (canon lift (core func $i "i64-load8-u")))
+  (func (export "i64-load16-s") (param "addr" u32) (result s64)
+    (canon lift (core func $i "i64-load16-s")))
+  (func (export "i64-load16-u") (param "addr" u32) (result u64)
+    (canon lift (core func $i "i64-load16-u")))
+  (func (export "i64-load32-s") (param "addr" u32) (result s64)
+    (canon lift (core func $i "i64-load32-s")))
+  (func (export "i64-load32-u") (param "addr" u32) (result u64)
+    (canon lift (core func $i "i64-load32-u")))
+  (func (export "f32-load") (param "addr" u32) (result float32)
+    (canon lift (core func $i "f32-load")))
+  (func (export "f64-load") (param "addr" u32) (result float64)
+    (canon lift (core func $i "f64-load")))
+  (func (export "i32-store") (param "addr" u32) (param "value" u32)
+    (canon lift (core func $i "i32-store")))
+  (func (export "i32-store8") (param "addr" u32) (param "value" u32)
+    (canon lift (core func $i "i32-store8")))
+  (func (export "i32-store16") (param "addr" u32) (param "value" u32)
+    (canon lift (core func $i "i32-store16")))
+  (func (export "i64-store") (param "addr" u32) (param "value" u64)
+    (canon lift (core func $i "i64-store")))
+  (func (export "i64-store8") (param "addr" u32) (param "value" u64)
+    (canon lift (core func $i "i64-store8")))
+  (func (export "i64-store16") (param "addr" u32) (param "value" u64)
+    (canon lift (core func $i "i64-store16")))
+  (func (export "i64-store32") (param "addr" u32) (param "value" u64)
+    (canon lift (core func $i "i64-store32")))
+  (func (export "f32-store") (param "addr" u32) (param "value" float32)
+    (canon lift (core func $i "f32-store")))
+  (func (export "f64-store") (param "addr" u32) (param "value" float64)
+    (canon lift (core func $i "f64-store")))
+
+  (func (export "size-mem0") (result u32)
+    (canon lift (core func $i "size-mem0")))
+  (func (export "grow-mem0") (param "delta" u32) (result s32)
+    (canon lift (core func $i "grow-mem0")))
+  (func (export "size-mem1") (result u32)
+    (canon lift (core func $i "size-mem1")))
+  (func (export "grow-mem1") (param "delta" u32) (result s32)
+    (canon lift (core func $i "grow-mem1")))
+
+  (func (export "load-mem0") (param "addr" u32) (result u32)
+    (canon lift (core func $i "load-mem0")))
+  (func (export "load-mem2") (param "addr" u32) (result u32)
+    (canon lift (core func $i "load-mem2")))
+  (func (export "fill-mem0") (param "dst" u32) (param "value" u32) (param "len" u32)
+    (canon lift (core func $i "fill-mem0")))
+  (func (export "fill-mem1") (param "dst" u32) (param "value" u32) (param "len" u32)
+    (canon lift (core func $i "fill-mem1")))
+  (func (export "copy-mem0-to-mem0") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "copy-mem0-to-mem0")))
+  (func (export "copy-mem0-to-mem1") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "copy-mem0-to-mem1")))
+  (func (export "copy-mem1-to-mem0") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "copy-mem1-to-mem0")))
+  (func (export "copy-mem1-to-mem1") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "copy-mem1-to-mem1")))
+  (func (export "copy-mem0-to-mem2") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "copy-mem0-to-mem2")))
+  (func (export "copy-mem2-to-mem0") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "copy-mem2-to-mem0")))
+  (func (export "init-mem0") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "init-mem0")))
+  (func (export "init-mem1") (param "dst" u32) (param "src" u32) (param "len" u32)
+    (canon lift (core func $i "init-mem1")))
+  (func (export "drop-hello")
+    (canon lift (core func $i "drop-hello")))
+
+  (func (export "v128-copy") (param "dst" u32) (param "src" u32)
+    (canon lift (core func $i "v128-copy")))
+  (func (export "v128-load8x8-u") (param "addr" u32) (result u32)
+    (canon lift (core func $i "v128-load8x8-u")))
+  (func (export "v128-load32-splat") (param "addr" u32) (result u32)
+    (canon lift (core func $i "v128-load32-splat")))
+  (func (export "v128-load64-zero") (param "addr" u32) (result u64)
+    (canon lift (core func $i "v128-load64-zero")))
+  (func (export "v128-load32-lane") (param "addr" u32) (result u32)
+    (canon lift (core func $i "v128-load32-lane")))
+  (func (export "v128-store16-lane") (param "addr" u32)
+    (canon lift (core func $i "v128-store16-lane")))
+)
@@ -17,6 +17,18 @@ export async function runtimeTest (fixtures) {
         const { stderr } = await exec(`test/output/${runtimeName}.js`);
         strictEqual(stderr, '');
       });
+      // Variants of the same component, transpiled with other flags
+      for (const variant of ['merged']) {
+        if (!existsSync(`test/runtime/${runtimeName}.${variant}.ts`))
+          continue;
+        test(`${runtimeName}.${variant}`, async () => {
+          try {
+            await tsGenerationPromise();
+          } catch {}
+          const { stderr } = await exec(`test/output/${runtimeName}.${variant}.js`);
+          strictEqual(stderr, '');
+        });
+      }
     }
   });
 }
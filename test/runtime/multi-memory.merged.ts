@@ -0,0 +1,7 @@
+// Flags: --merge-memories
+
+import { check } from './multi-memory.js';
+
+const wasm = await import('../output/multi-memory.merged/multi-memory.merged.js');
+
+check(wasm);
@@ -0,0 +1,136 @@
+// Flags:
+
+// @ts-ignore
+import * as assert from 'assert';
+
+export function check(wasm: any) {
+  // Data segments of all memories are applied before the start function runs
+  assert.strictEqual(wasm.started(), 0x04030201);
+  assert.strictEqual(wasm.loadMem0(33), 48);
+
+  // Loads and stores of every width
+  assert.strictEqual(wasm.i32Load(16), 0x04030201);
+  assert.strictEqual(wasm.i32Load8U(20), 5);
+  assert.strictEqual(wasm.i32Load16U(16), 0x0201);
+  assert.strictEqual(wasm.i64Load(8), 0x0807060504030201n);
+  assert.strictEqual(wasm.i64Load32U(20), 0x08070605n);
+
+  wasm.i32Store(64, 0xfedcba98);
+  assert.strictEqual(wasm.i32Load(64), 0xfedcba98);
+  assert.strictEqual(wasm.i32Load8S(64), -104);
+  assert.strictEqual(wasm.i32Load8U(64), 152);
+  assert.strictEqual(wasm.i32Load16S(64), -17768);
+  assert.strictEqual(wasm.i32Load16U(64), 47768);
+  assert.strictEqual(wasm.i64Load8S(64), -104n);
+  assert.strictEqual(wasm.i64Load8U(64), 152n);
+  assert.strictEqual(wasm.i64Load16S(64), -17768n);
+  assert.strictEqual(wasm.i64Load16U(64), 47768n);
+  assert.strictEqual(wasm.i64Load32S(64), -19088744n);
+  assert.strictEqual(wasm.i64Load32U(64), 4275878552n);
+
+  wasm.i64Store(72, 0x0123456789abcdefn);
+  assert.strictEqual(wasm.i64Load(72), 0x0123456789abcdefn);
+  assert.strictEqual(wasm.i32Load(80), 0x89abcdef);
+  wasm.i64Store8(96, 0x1ffn);
+  assert.strictEqual(wasm.i32Load8U(96), 0xff);
+  wasm.i64Store16(96, 0x12345n);
+  assert.strictEqual(wasm.i32Load16U(96), 0x2345);
+  wasm.i64Store32(96, 0x123456789n);
+  assert.strictEqual(wasm.i32Load(96), 0x23456789);
+  wasm.i32Store8(100, 0x1ff);
+  assert.strictEqual(wasm.i32Load8U(100), 0xff);
+  wasm.i32Store16(100, 0x12345);
+  assert.strictEqual(wasm.i32Load16U(100), 0x2345);
+  wasm.f32Store(104, 1.5);
+  assert.strictEqual(wasm.f32Load(104), 1.5);
+  wasm.f64Store(112, -2.25);
+  assert.strictEqual(wasm.f64Load(112), -2.25);
+
+  // SIMD loads and stores
+  wasm.v128Copy(128, 16);
+  assert.strictEqual(wasm.i64Load(120), 0x0807060504030201n);
+  assert.strictEqual(wasm.i64Load(128), 0x100f0e0d0c0b0a09n);
+  assert.strictEqual(wasm.v128Load8x8U(16), 4);
+  assert.strictEqual(wasm.v128Load32Splat(20), 0x08070605);
+  assert.strictEqual(wasm.v128Load64Zero(16), 0x0807060504030201n);
+  assert.strictEqual(wasm.v128Load32Lane(24), 0x0c0b0a09);
+  wasm.v128Store16Lane(144);
+  assert.strictEqual(wasm.i32Load16U(144), 0x1234);
+
+  // Bulk memory instructions within and across memories
+  wasm.initMem1(200, 0, 5);
+  assert.strictEqual(wasm.i32Load8U(200), 104);
+  assert.strictEqual(wasm.i32Load8U(204), 111);
+  wasm.copyMem1ToMem0(300, 200, 5);
+  assert.strictEqual(wasm.loadMem0(304), 111);
+  wasm.copyMem0ToMem1(400, 300, 5);
+  assert.strictEqual(wasm.i32Load8U(400), 104);
+  wasm.copyMem1ToMem1(402, 400, 5);
+  assert.strictEqual(wasm.i32Load8U(402), 104);
+  assert.strictEqual(wasm.i32Load8U(406), 111);
+  wasm.fillMem1(500, 7, 4);
+  assert.strictEqual(wasm.i32Load8U(503), 7);
+  assert.strictEqual(wasm.i32Load8U(504), 0);
+  wasm.fillMem0(600, 9, 2);
+  assert.strictEqual(wasm.loadMem0(601), 9);
+  wasm.copyMem0ToMem0(700, 600, 2);
+  assert.strictEqual(wasm.loadMem0(701), 9);
+  wasm.initMem0(800, 1, 3);
+  assert.strictEqual(wasm.loadMem0(800), 101);
+  assert.strictEqual(wasm.loadMem2(0), 106);
+  wasm.copyMem2ToMem0(900, 0, 2);
+  assert.strictEqual(wasm.loadMem0(901), 115);
+  wasm.copyMem0ToMem2(10, 300, 5);
+  assert.strictEqual(wasm.loadMem2(10), 104);
+
+  // Accesses trap at the current size of each memory, including memory 0
+  // when other memories are merged into it
+  const trap = (f: () => void) => assert.throws(f, WebAssembly.RuntimeError);
+  assert.throws(() => wasm.i32Load(65533));
+  assert.throws(() => wasm.i32Store(65533, 0));
+  trap(() => wasm.fillMem1(65535, 0, 2));
+  trap(() => wasm.copyMem1ToMem1(65535, 0, 2));
+  trap(() => wasm.copyMem0ToMem1(65535, 0, 2));
+  trap(() => wasm.copyMem1ToMem0(0, 65535, 2));
+  trap(() => wasm.initMem1(65535, 0, 2));
+  trap(() => wasm.fillMem0(65535, 0, 2));
+  trap(() => wasm.fillMem0(65537, 0, 0));
+  trap(() => wasm.copyMem0ToMem0(65535, 0, 2));
+  trap(() => wasm.copyMem0ToMem0(0, 65535, 2));
+  trap(() => wasm.copyMem0ToMem1(0, 65535, 2));
+  trap(() => wasm.copyMem1ToMem0(65535, 0, 2));
+  trap(() => wasm.initMem0(65535, 0, 2));
+  trap(() => wasm.copyMem0ToMem2(0, 65535, 2));
+  trap(() => wasm.copyMem2ToMem0(65535, 0, 2));
+  trap(() => wasm.loadMem0(65536));
+  wasm.fillMem0(65534, 1, 2);
+  wasm.fillMem0(65536, 1, 0);
+  assert.strictEqual(wasm.loadMem0(65535), 1);
+
+  // Growing memories moves these bounds
+  assert.strictEqual(wasm.sizeMem0(), 1);
+  assert.strictEqual(wasm.sizeMem1(), 1);
+  assert.strictEqual(wasm.growMem1(1), 1);
+  assert.strictEqual(wasm.sizeMem1(), 2);
+  assert.strictEqual(wasm.growMem1(1), -1);
+  wasm.fillMem1(65535, 3, 2);
+  assert.strictEqual(wasm.i32Load16U(65535), 0x0303);
+  trap(() => wasm.fillMem1(2 * 65536 - 1, 0, 2));
+  assert.strictEqual(wasm.growMem0(1), 1);
+  assert.strictEqual(wasm.sizeMem0(), 2);
+  assert.strictEqual(wasm.growMem0(1), -1);
+  wasm.fillMem0(65535, 3, 2);
+  assert.strictEqual(wasm.loadMem0(65536), 3);
+  trap(() => wasm.fillMem0(2 * 65536 - 1, 0, 2));
+  assert.strictEqual(wasm.i32Load16U(65535), 0x0303);
+
+  // Dropped segments can no longer be used
+  wasm.dropHello();
+  wasm.initMem0(0, 0, 0);
+  trap(() => wasm.initMem0(0, 0, 1));
+  trap(() => wasm.initMem1(0, 0, 1));
+}
+
+const wasm = await import('../output/multi-memory/multi-memory.js');
+
+check(wasm);
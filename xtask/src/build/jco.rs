@@ -4,28 +4,60 @@ use std::{collections::HashMap, fs, io::Write, path::PathBuf};
 use wit_component::ComponentEncoder;
 use xshell::{cmd, Shell};
 
+/// WASI preview1 adapter shipped in `lib/`
+#[derive(Clone, Copy)]
+pub(crate) enum WasiAdapter {
+    Command,
+    Reactor,
+    Proxy,
+}
+
+impl WasiAdapter {
+    pub(crate) const ALL: [WasiAdapter; 3] = [
+        WasiAdapter::Command,
+        WasiAdapter::Reactor,
+        WasiAdapter::Proxy,
+    ];
+
+    pub(crate) fn path(self) -> &'static str {
+        match self {
+            WasiAdapter::Command => "lib/wasi_snapshot_preview1.command.wasm",
+            WasiAdapter::Reactor => "lib/wasi_snapshot_preview1.reactor.wasm",
+            WasiAdapter::Proxy => "lib/wasi_snapshot_preview1.proxy.wasm",
+        }
+    }
+}
+
 pub(crate) fn run(release: bool) -> Result<()> {
     let build = if release { "release" } else { "debug" };
     transpile(
         &format!("target/wasm32-wasip1/{build}/js_component_bindgen_component.wasm"),
         "js-component-bindgen-component".to_string(),
+        WasiAdapter::Reactor,
         release,
     )?;
     transpile(
         &format!("target/wasm32-wasip1/{build}/wasm_tools_js.wasm"),
         "wasm-tools".to_string(),
+        WasiAdapter::Reactor,
         release,
     )?;
 
     Ok(())
 }
 
-fn transpile(component_path: &str, name: String, optimize: bool) -> Result<()> {
+fn transpile(
+    component_path: &str,
+    name: String,
+    adapter: WasiAdapter,
+    optimize: bool,
+) -> Result<()> {
     std::env::set_var("RUST_BACKTRACE", "1");
     let component = fs::read(component_path).context("wasm bindgen component missing")?;
 
-    let adapter_path = "lib/wasi_snapshot_preview1.reactor.wasm";
-    let adapter = fs::read(adapter_path).context("preview1 adapter file missing")?;
+    let adapter_path = adapter.path();
+    let adapter = fs::read(adapter_path)
+        .with_context(|| format!("preview1 adapter file missing [{adapter_path}]"))?;
 
     let mut encoder = ComponentEncoder::default()
         .validate(true)
@@ -76,6 +108,7 @@ fn transpile(component_path: &str, name: String, optimize: bool) -> Result<()> {
         no_typescript: false,
         instantiation: None,
         map: Some(import_map),
+        export_map: None,
         no_nodejs_compat: false,
         base64_cutoff: 5000_usize,
         tla_compat: true,
@@ -83,7 +116,11 @@ fn transpile(component_path: &str, name: String, optimize: bool) -> Result<()> {
         tracing: false,
         no_namespaced_exports: true,
         multi_memory: true,
+        merge_memories: false,
+        detect_multi_memory: false,
         import_bindings: Some(BindingsMode::Js),
+        import_bindings_map: None,
+        export_bindings: None,
     };
 
     let transpiled = js_component_bindgen::transpile(&adapted_component, opts)?;
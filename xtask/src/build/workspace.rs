@@ -1,5 +1,7 @@
 use xshell::{cmd, Shell};
 
+use super::jco::WasiAdapter;
+
 pub(crate) fn run(release: bool) -> anyhow::Result<()> {
     let sh = Shell::new()?;
     if release {
@@ -12,13 +14,8 @@ pub(crate) fn run(release: bool) -> anyhow::Result<()> {
         cmd!(sh, "cargo build --workspace --target wasm32-wasip1").read()?;
     }
     cmd!(sh, "node node_modules/typescript/bin/tsc -p tsconfig.json").read()?;
-    sh.copy_file(
-        "lib/wasi_snapshot_preview1.command.wasm",
-        "node_modules/@golemcloud/jco/lib",
-    )?;
-    sh.copy_file(
-        "lib/wasi_snapshot_preview1.reactor.wasm",
-        "node_modules/@golemcloud/jco/lib",
-    )?;
+    for adapter in WasiAdapter::ALL {
+        sh.copy_file(adapter.path(), "node_modules/@golemcloud/jco/lib")?;
+    }
     Ok(())
 }
@@ -31,13 +31,18 @@ pub(crate) fn run() -> Result<()> {
             no_nodejs_compat: false,
             instantiation: None,
             map: None,
+            export_map: None,
             tla_compat: false,
             valid_lifting_optimization: false,
             base64_cutoff: 0,
             tracing: false,
             no_namespaced_exports: true,
             multi_memory: false,
+            merge_memories: false,
+            detect_multi_memory: false,
             import_bindings: Some(BindingsMode::Js),
+            import_bindings_map: None,
+            export_bindings: None,
         };
 
         let files = generate_types(name, resolve, world, opts)?;